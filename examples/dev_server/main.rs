@@ -0,0 +1,34 @@
+//! The API server with every OAuth provider replaced by a local mock that signs
+//! anyone in without a password. Development only:
+//!
+//! ```sh
+//! cargo run --example dev_server --features mock-oauth
+//! ```
+
+mod mock_oauth_server;
+
+use dotenvy::dotenv;
+
+use rust_axum::config::AppConfig;
+use rust_axum::infrastructure::auth::oauth::OAuthHttpClient;
+use rust_axum::server;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    server::init_tracing();
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    });
+    tracing::debug!("Loaded configuration: {:?}", config);
+
+    let mock = mock_oauth_server::spawn()
+        .await
+        .expect("Failed to start mock OAuth server");
+    tracing::warn!("OAuth mock mode: all OAuth sign-ins go to the mock server on {}", mock.address);
+    let oauth_providers = mock.providers(&config.app.base_url, &config.oauth, &OAuthHttpClient::new(&config.oauth.http));
+
+    server::run(config, oauth_providers).await;
+}
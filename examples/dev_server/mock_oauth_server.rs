@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::json;
+use rust_axum::config::OAuthConfig;
+use rust_axum::utils::html;
+use rust_axum::infrastructure::auth::apple::{AppleClientSettings, AppleOAuthClient};
+use rust_axum::infrastructure::auth::discord::DiscordOAuthClient;
+use rust_axum::infrastructure::auth::github::GitHubOAuthClient;
+use rust_axum::infrastructure::auth::gitlab::GitLabOAuthClient;
+use rust_axum::infrastructure::auth::google::GoogleOAuthClient;
+use rust_axum::infrastructure::auth::microsoft::{MicrosoftOAuthClient, DEFAULT_TENANT};
+use rust_axum::infrastructure::auth::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthProviderRegistry};
+
+/// A fake provider account offered on the mock consent page
+struct MockAccount {
+    /// Used as the login, the authorization code and the access token
+    login: &'static str,
+    id: &'static str,
+    name: &'static str,
+    email: Option<&'static str>,
+    /// GitHub: whether the email is on the public profile or only under /user/emails.
+    /// Others: ignored.
+    public_email: bool,
+    email_verified: bool,
+    /// Google Workspace domain (`hd` claim)
+    hosted_domain: Option<&'static str>,
+    /// Microsoft: the account's tenant (`tid` claim)
+    tenant_id: Option<&'static str>,
+    /// Apple: the email is a private relay (Hide My Email) address
+    private_email: bool,
+}
+
+const GITHUB_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "octocat", id: "583231", name: "The Octocat", email: Some("octocat@example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "private-email", id: "583232", name: "Private Email", email: Some("private-email@example.com"), public_email: false, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "unverified-email", id: "583233", name: "Unverified Email", email: Some("unverified-email@example.com"), public_email: false, email_verified: false, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "no-email", id: "583234", name: "No Email", email: None, public_email: false, email_verified: false, hosted_domain: None, tenant_id: None, private_email: false },
+];
+
+const GOOGLE_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "alice", id: "100000000000000000001", name: "Alice Example", email: Some("alice@example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "unverified", id: "100000000000000000002", name: "Unverified Example", email: Some("unverified@example.com"), public_email: true, email_verified: false, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "workspace", id: "100000000000000000003", name: "Workspace Example", email: Some("workspace@corp.example.com"), public_email: true, email_verified: true, hosted_domain: Some("corp.example.com"), tenant_id: None, private_email: false },
+];
+
+const GITLAB_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "tanuki", id: "4000001", name: "Tanuki Example", email: Some("tanuki@example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "gitlab-unverified", id: "4000002", name: "Unverified GitLab", email: Some("gitlab-unverified@example.com"), public_email: true, email_verified: false, hosted_domain: None, tenant_id: None, private_email: false },
+];
+
+/// Contoso is a work tenant; the consumers tenant is shared by all personal accounts
+pub const CONTOSO_TENANT_ID: &str = "3f0c2a8e-5b7d-4e1a-9c6f-1d2e3f4a5b6c";
+const CONSUMERS_TENANT_ID: &str = "9188040d-6c67-4c5b-b112-36a304b66dad";
+const FABRIKAM_TENANT_ID: &str = "7a1b2c3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d";
+
+const MICROSOFT_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "adele", id: "0b9e2f64-1c4d-4a8b-9e3f-5d6c7b8a9f01", name: "Adele Vance", email: Some("adele@contoso.example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: Some(CONTOSO_TENANT_ID), private_email: false },
+    MockAccount { login: "personal", id: "00000000-0000-0000-66f3-3332eca7ea81", name: "Personal Example", email: Some("personal@outlook.example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: Some(CONSUMERS_TENANT_ID), private_email: false },
+    MockAccount { login: "unowned-domain", id: "5c8d9e0f-2a3b-4c5d-8e7f-6a5b4c3d2e1f", name: "Unowned Domain", email: Some("someone@example.com"), public_email: true, email_verified: false, hosted_domain: None, tenant_id: Some(FABRIKAM_TENANT_ID), private_email: false },
+];
+
+const DISCORD_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "wumpus", id: "80351110224678912", name: "Wumpus", email: Some("wumpus@example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "discord-unverified", id: "80351110224678913", name: "Unverified Discord", email: Some("discord-unverified@example.com"), public_email: true, email_verified: false, hosted_domain: None, tenant_id: None, private_email: false },
+];
+
+const APPLE_ACCOUNTS: &[MockAccount] = &[
+    MockAccount { login: "appleseed", id: "001234.5f6e7d8c9b0a4f3e2d1c0b9a8f7e6d5c.1234", name: "Johnny Appleseed", email: Some("appleseed@example.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: false },
+    MockAccount { login: "hide-my-email", id: "001234.a1b2c3d4e5f60718293a4b5c6d7e8f90.1234", name: "Hidden Email", email: Some("x7k2p9qz4m@privaterelay.appleid.com"), public_email: true, email_verified: true, hosted_domain: None, tenant_id: None, private_email: true },
+];
+
+const TOKEN_PREFIX: &str = "mock-token-";
+const REFRESH_TOKEN_PREFIX: &str = "mock-refresh-";
+/// Codes are `mock-code-<login>`, plus `.<nonce>` when the app sent one
+const CODE_PREFIX: &str = "mock-code-";
+const KEY_ID: &str = "mock-key";
+
+/// Client id the app presents to the mock server; any value is accepted
+pub const MOCK_CLIENT_ID: &str = "mock-client-id";
+/// Apple team and key ids the mock expects in client secrets
+pub const MOCK_APPLE_TEAM_ID: &str = "MOCKTEAM01";
+pub const MOCK_APPLE_KEY_ID: &str = "MOCKKEY001";
+
+/// The running mock server
+pub struct MockOAuthServer {
+    pub address: SocketAddr,
+    /// Private key for signing Apple client secrets, matching the public key the mock checks them with
+    pub apple_private_key: EncodingKey,
+}
+
+impl MockOAuthServer {
+    pub fn github_endpoints(&self) -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: format!("http://{}/github/login/oauth/authorize", self.address),
+            token_url: format!("http://{}/github/login/oauth/access_token", self.address),
+            userinfo_url: Some(format!("http://{}/github/api/user", self.address)),
+            jwks_url: None,
+        }
+    }
+
+    pub fn google_endpoints(&self) -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: format!("http://{}/google/o/oauth2/v2/auth", self.address),
+            token_url: format!("http://{}/google/token", self.address),
+            userinfo_url: None,
+            jwks_url: Some(format!("http://{}/google/oauth2/v3/certs", self.address)),
+        }
+    }
+
+    /// Stands in for a self-hosted GitLab instance
+    pub fn gitlab_base_url(&self) -> String {
+        format!("http://{}/gitlab", self.address)
+    }
+
+    /// Stands in for https://login.microsoftonline.com; any tenant works
+    pub fn microsoft_authority(&self) -> String {
+        format!("http://{}/microsoft", self.address)
+    }
+
+    pub fn discord_endpoints(&self) -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: format!("http://{}/discord/oauth2/authorize", self.address),
+            token_url: format!("http://{}/discord/api/oauth2/token", self.address),
+            userinfo_url: Some(format!("http://{}/discord/api/users/@me", self.address)),
+            jwks_url: None,
+        }
+    }
+
+    pub fn apple_endpoints(&self) -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: format!("http://{}/apple/auth/authorize", self.address),
+            token_url: format!("http://{}/apple/auth/token", self.address),
+            userinfo_url: None,
+            jwks_url: Some(format!("http://{}/apple/auth/keys", self.address)),
+        }
+    }
+
+    /// Issuer of the mock Apple id_tokens and audience of client secrets
+    pub fn apple_issuer(&self) -> String {
+        format!("http://{}/apple", self.address)
+    }
+
+    /// Every built-in provider, pointed at the mock, with callbacks under
+    /// `app_base_url`. `oauth` supplies the Google hosted domain and Microsoft tenant.
+    pub fn providers(&self, app_base_url: &str, oauth: &OAuthConfig, http: &OAuthHttpClient) -> OAuthProviderRegistry {
+        let callback = |name: &str| format!("{}/api/v1/auth/{}/callback", app_base_url.trim_end_matches('/'), name);
+        let client_id = || MOCK_CLIENT_ID.to_string();
+        let microsoft_tenant = oauth.microsoft.tenant.clone().unwrap_or_else(|| DEFAULT_TENANT.to_string());
+        let mut registry = OAuthProviderRegistry::new();
+
+        registry.register(Arc::new(GitHubOAuthClient::new(
+            client_id(),
+            String::new(),
+            callback("github"),
+            self.github_endpoints(),
+            http.clone(),
+        )));
+        registry.register(Arc::new(GoogleOAuthClient::new(
+            client_id(),
+            String::new(),
+            callback("google"),
+            self.google_endpoints(),
+            oauth.google.hosted_domain.clone(),
+            http.clone(),
+        )));
+        registry.register(Arc::new(GitLabOAuthClient::new(
+            client_id(),
+            String::new(),
+            callback("gitlab"),
+            GitLabOAuthClient::default_endpoints(&self.gitlab_base_url()),
+            http.clone(),
+        )));
+        let authority = self.microsoft_authority();
+        registry.register(Arc::new(MicrosoftOAuthClient::new(
+            client_id(),
+            String::new(),
+            callback("microsoft"),
+            MicrosoftOAuthClient::default_endpoints(&authority, &microsoft_tenant),
+            &authority,
+            microsoft_tenant,
+            http.clone(),
+        )));
+        registry.register(Arc::new(DiscordOAuthClient::new(
+            client_id(),
+            String::new(),
+            callback("discord"),
+            self.discord_endpoints(),
+            http.clone(),
+        )));
+        let apple = AppleClientSettings {
+            client_id: client_id(),
+            redirect_uri: callback("apple"),
+            endpoints: self.apple_endpoints(),
+            issuer: self.apple_issuer(),
+            team_id: MOCK_APPLE_TEAM_ID.to_string(),
+            key_id: MOCK_APPLE_KEY_ID.to_string(),
+            private_key: self.apple_private_key.clone(),
+        };
+        registry.register(Arc::new(AppleOAuthClient::new(apple, http.clone())));
+
+        registry
+    }
+}
+
+/// Signs id_tokens with a key generated at startup and published as JWKS
+struct IdTokenSigner {
+    key: EncodingKey,
+    jwks: serde_json::Value,
+}
+
+impl IdTokenSigner {
+    fn generate() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+            .expect("Failed to generate mock signing key");
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).expect("Generated key is valid PKCS#8");
+
+        Self {
+            key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            jwks: json!({
+                "keys": [{
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "alg": "EdDSA",
+                    "use": "sig",
+                    "kid": KEY_ID,
+                    "x": URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                }]
+            }),
+        }
+    }
+
+    fn sign(&self, claims: &serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(KEY_ID.to_string());
+        encode(&header, claims, &self.key).expect("Signing with a generated key cannot fail")
+    }
+}
+
+struct MockState {
+    base_url: String,
+    signer: IdTokenSigner,
+    /// Checks the ES256 client secrets the app sends to the Apple token endpoint
+    apple_client_key: DecodingKey,
+}
+
+/// The providers served at the standard token endpoint (all but GitHub)
+#[derive(Clone, Copy)]
+enum Provider {
+    Google,
+    GitLab,
+    Microsoft,
+    Discord,
+    Apple,
+}
+
+impl Provider {
+    fn accounts(self) -> &'static [MockAccount] {
+        match self {
+            Provider::Google => GOOGLE_ACCOUNTS,
+            Provider::GitLab => GITLAB_ACCOUNTS,
+            Provider::Microsoft => MICROSOFT_ACCOUNTS,
+            Provider::Discord => DISCORD_ACCOUNTS,
+            Provider::Apple => APPLE_ACCOUNTS,
+        }
+    }
+
+    /// Google and Apple keep the refresh token; GitLab, Microsoft and Discord
+    /// return a new one with every refresh
+    fn rotates_refresh_tokens(self) -> bool {
+        matches!(self, Provider::GitLab | Provider::Microsoft | Provider::Discord)
+    }
+
+    /// Claims of the id_token issued with the tokens, for OpenID Connect providers
+    fn id_token_claims(self, state: &MockState, account: &MockAccount, client_id: Option<&String>, nonce: Option<&str>) -> Option<serde_json::Value> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = match self {
+            Provider::Google => json!({
+                "iss": "https://accounts.google.com",
+                "sub": account.id,
+                "email": account.email,
+                "email_verified": account.email_verified,
+                "name": account.name,
+                "hd": account.hosted_domain,
+            }),
+            Provider::Microsoft => {
+                let tenant_id = account.tenant_id.unwrap_or(CONSUMERS_TENANT_ID);
+                json!({
+                    "iss": format!("{}/microsoft/{}/v2.0", state.base_url, tenant_id),
+                    "sub": format!("pairwise-{}", account.login),
+                    "tid": tenant_id,
+                    "oid": account.id,
+                    "email": account.email,
+                    "preferred_username": account.email,
+                    "name": account.name,
+                    "xms_edov": account.email_verified,
+                })
+            }
+            // Apple sends these booleans as strings
+            Provider::Apple => json!({
+                "iss": format!("{}/apple", state.base_url),
+                "sub": account.id,
+                "email": account.email,
+                "email_verified": account.email_verified.to_string(),
+                "is_private_email": account.private_email.to_string(),
+            }),
+            Provider::GitLab | Provider::Discord => return None,
+        };
+
+        let mut claims = claims;
+        claims["aud"] = json!(client_id);
+        claims["nonce"] = json!(nonce);
+        claims["iat"] = json!(now);
+        claims["exp"] = json!(now + 3600);
+        Some(claims)
+    }
+}
+
+/// Serves mock GitHub, Google, GitLab, Microsoft, Discord and Apple OAuth endpoints
+/// on a free port of 127.0.0.1 in the background. Tests and development only:
+/// every sign-in succeeds without a password.
+pub async fn spawn() -> std::io::Result<MockOAuthServer> {
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await?;
+    let address = listener.local_addr()?;
+
+    let rng = ring::rand::SystemRandom::new();
+    let apple_pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        .expect("Failed to generate mock Apple key");
+    let apple_key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, apple_pkcs8.as_ref(), &rng)
+        .expect("Generated key is valid PKCS#8");
+
+    let state = Arc::new(MockState {
+        base_url: format!("http://{}", address),
+        signer: IdTokenSigner::generate(),
+        apple_client_key: DecodingKey::from_ec_der(apple_key_pair.public_key().as_ref()),
+    });
+
+    let router = Router::new()
+        .route("/github/login/oauth/authorize", get(|query| authorize(GITHUB_ACCOUNTS, "GitHub", query)))
+        .route("/github/login/oauth/access_token", post(github_token))
+        .route("/github/api/user", get(github_user))
+        .route("/github/api/user/emails", get(github_emails))
+        .route("/google/o/oauth2/v2/auth", get(|query| authorize(GOOGLE_ACCOUNTS, "Google", query)))
+        .route("/google/token", post(|state, form| token(Provider::Google, state, form)))
+        .route("/google/oauth2/v3/certs", get(certs))
+        .route("/gitlab/oauth/authorize", get(|query| authorize(GITLAB_ACCOUNTS, "GitLab", query)))
+        .route("/gitlab/oauth/token", post(|state, form| token(Provider::GitLab, state, form)))
+        .route("/gitlab/oauth/userinfo", get(gitlab_userinfo))
+        .route("/microsoft/{tenant}/oauth2/v2.0/authorize", get(|query| authorize(MICROSOFT_ACCOUNTS, "Microsoft", query)))
+        .route("/microsoft/{tenant}/oauth2/v2.0/token", post(|state, form| token(Provider::Microsoft, state, form)))
+        .route("/microsoft/{tenant}/discovery/v2.0/keys", get(certs))
+        .route("/discord/oauth2/authorize", get(|query| authorize(DISCORD_ACCOUNTS, "Discord", query)))
+        .route("/discord/api/oauth2/token", post(|state, form| token(Provider::Discord, state, form)))
+        .route("/discord/api/users/@me", get(discord_user))
+        .route("/apple/auth/authorize", get(|query| authorize(APPLE_ACCOUNTS, "Apple", query)))
+        .route("/apple/auth/token", post(|state, form| token(Provider::Apple, state, form)))
+        .route("/apple/auth/keys", get(certs))
+        .with_state(state);
+
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            tracing::error!("Mock OAuth server stopped: {}", e);
+        }
+    });
+
+    Ok(MockOAuthServer {
+        address,
+        apple_private_key: EncodingKey::from_ec_der(apple_pkcs8.as_ref()),
+    })
+}
+
+/// Consent page: one choice per account, straight back to the app's callback.
+/// With `response_mode=form_post` each choice is a form POSTing the result, as Apple does.
+async fn authorize(
+    accounts: &'static [MockAccount],
+    provider: &'static str,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let Some(redirect_uri) = params.get("redirect_uri") else {
+        return (StatusCode::BAD_REQUEST, "redirect_uri is required").into_response();
+    };
+    let form_post = params.get("response_mode").map(String::as_str) == Some("form_post");
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+    let state = params.get("state");
+    let nonce = params.get("nonce").map(|nonce| format!(".{}", nonce)).unwrap_or_default();
+
+    let choices: String = accounts
+        .iter()
+        .map(|account| {
+            let code = format!("{}{}{}", CODE_PREFIX, account.login, nonce);
+            let label = format!(
+                "{} &lt;{}&gt;{}",
+                account.name,
+                account.email.unwrap_or("no email"),
+                if account.email_verified { "" } else { " (unverified)" },
+            );
+
+            if form_post {
+                let (first_name, last_name) = account.name.split_once(' ').unwrap_or((account.name, ""));
+                let user = json!({ "name": { "firstName": first_name, "lastName": last_name }, "email": account.email });
+                let state = state
+                    .map(|state| format!("<input type=\"hidden\" name=\"state\" value=\"{}\">", html::escape(state)))
+                    .unwrap_or_default();
+                format!(
+                    "<li><form method=\"post\" action=\"{}\"><input type=\"hidden\" name=\"code\" value=\"{}\">{}\
+                     <input type=\"hidden\" name=\"user\" value=\"{}\"><button>{}</button></form></li>",
+                    html::escape(redirect_uri),
+                    html::escape(&code),
+                    state,
+                    html::escape(&user.to_string()),
+                    label,
+                )
+            } else {
+                let state = state
+                    .map(|state| format!("&state={}", urlencoding::encode(state)))
+                    .unwrap_or_default();
+                let href = format!("{}{}code={}{}", redirect_uri, separator, urlencoding::encode(&code), state);
+                format!("<li><a href=\"{}\">{}</a></li>", html::escape(&href), label)
+            }
+        })
+        .collect();
+
+    Html(format!(
+        "<!doctype html><title>Mock {provider} sign-in</title><h1>Sign in to mock {provider} as</h1><ul>{choices}</ul>"
+    ))
+    .into_response()
+}
+
+/// The account a code was issued for, and the nonce it carries
+fn account_for_code<'a, 'c>(accounts: &'a [MockAccount], code: Option<&'c str>) -> Option<(&'a MockAccount, Option<&'c str>)> {
+    let code = code?.strip_prefix(CODE_PREFIX)?;
+    let (login, nonce) = match code.split_once('.') {
+        Some((login, nonce)) => (login, Some(nonce)),
+        None => (code, None),
+    };
+    accounts.iter().find(|account| account.login == login).map(|account| (account, nonce))
+}
+
+fn account_for_token<'a>(accounts: &'a [MockAccount], headers: &HeaderMap) -> Option<&'a MockAccount> {
+    let login = headers
+        .get("Authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?
+        .strip_prefix(TOKEN_PREFIX)?;
+    accounts.iter().find(|account| account.login == login)
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({ "message": "Bad credentials" }))).into_response()
+}
+
+fn token_error(error: &str) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response()
+}
+
+async fn github_token(Json(body): Json<serde_json::Value>) -> Response {
+    match account_for_code(GITHUB_ACCOUNTS, body["code"].as_str()) {
+        Some((account, _)) => Json(json!({
+            "access_token": format!("{}{}", TOKEN_PREFIX, account.login),
+            "token_type": "bearer",
+            "scope": "user:email",
+        }))
+        .into_response(),
+        // GitHub reports a bad code with 200 and an error body
+        None => Json(json!({ "error": "bad_verification_code" })).into_response(),
+    }
+}
+
+async fn github_user(headers: HeaderMap) -> Response {
+    let Some(account) = account_for_token(GITHUB_ACCOUNTS, &headers) else {
+        return unauthorized();
+    };
+    Json(json!({
+        "id": account.id.parse::<i64>().unwrap_or_default(),
+        "login": account.login,
+        "name": account.name,
+        "email": if account.public_email { account.email } else { None },
+        "avatar_url": null,
+    }))
+    .into_response()
+}
+
+async fn github_emails(headers: HeaderMap) -> Response {
+    let Some(account) = account_for_token(GITHUB_ACCOUNTS, &headers) else {
+        return unauthorized();
+    };
+    let emails: Vec<_> = account
+        .email
+        .map(|email| json!({ "email": email, "primary": true, "verified": account.email_verified }))
+        .into_iter()
+        .collect();
+    Json(emails).into_response()
+}
+
+/// Standard token endpoint: authorization codes and refresh tokens
+async fn token(
+    provider: Provider,
+    State(state): State<Arc<MockState>>,
+    axum::Form(form): axum::Form<HashMap<String, String>>,
+) -> Response {
+    if let Provider::Apple = provider {
+        if let Err(e) = check_apple_client_secret(&state, &form) {
+            tracing::warn!("Mock Apple rejected the client secret: {}", e);
+            return token_error("invalid_client");
+        }
+    }
+
+    let (account, nonce, refresh_token) = if form.get("grant_type").map(String::as_str) == Some("refresh_token") {
+        let account = form
+            .get("refresh_token")
+            .and_then(|token| token.strip_prefix(REFRESH_TOKEN_PREFIX))
+            .and_then(|login| provider.accounts().iter().find(|account| account.login == login));
+        let Some(account) = account else {
+            return token_error("invalid_grant");
+        };
+        let refresh_token = provider.rotates_refresh_tokens().then(|| format!("{}{}", REFRESH_TOKEN_PREFIX, account.login));
+        (account, None, refresh_token)
+    } else {
+        let Some((account, nonce)) = account_for_code(provider.accounts(), form.get("code").map(String::as_str)) else {
+            return token_error("invalid_grant");
+        };
+        (account, nonce, Some(format!("{}{}", REFRESH_TOKEN_PREFIX, account.login)))
+    };
+
+    let id_token = provider
+        .id_token_claims(&state, account, form.get("client_id"), nonce)
+        .map(|claims| state.signer.sign(&claims));
+
+    Json(json!({
+        "access_token": format!("{}{}", TOKEN_PREFIX, account.login),
+        "token_type": "Bearer",
+        "expires_in": 3599,
+        "refresh_token": refresh_token,
+        "id_token": id_token,
+    }))
+    .into_response()
+}
+
+/// Apple's client secret is an ES256 JWT from the team's key
+fn check_apple_client_secret(state: &MockState, form: &HashMap<String, String>) -> Result<(), String> {
+    let client_secret = form.get("client_secret").ok_or("client_secret is missing")?;
+    let header = jsonwebtoken::decode_header(client_secret).map_err(|e| e.to_string())?;
+    if header.kid.as_deref() != Some(MOCK_APPLE_KEY_ID) {
+        return Err(format!("unknown key id {:?}", header.kid));
+    }
+
+    let mut validation = Validation::new(Algorithm::ES256);
+    validation.set_audience(&[format!("{}/apple", state.base_url)]);
+    validation.set_issuer(&[MOCK_APPLE_TEAM_ID]);
+    validation.sub = form.get("client_id").cloned();
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    decode::<serde_json::Value>(client_secret, &state.apple_client_key, &validation).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn certs(State(state): State<Arc<MockState>>) -> Json<serde_json::Value> {
+    Json(state.signer.jwks.clone())
+}
+
+async fn gitlab_userinfo(headers: HeaderMap) -> Response {
+    let Some(account) = account_for_token(GITLAB_ACCOUNTS, &headers) else {
+        return unauthorized();
+    };
+    Json(json!({
+        "sub": account.id,
+        "name": account.name,
+        "nickname": account.login,
+        "email": account.email,
+        "email_verified": account.email_verified,
+        "picture": null,
+    }))
+    .into_response()
+}
+
+async fn discord_user(headers: HeaderMap) -> Response {
+    let Some(account) = account_for_token(DISCORD_ACCOUNTS, &headers) else {
+        return unauthorized();
+    };
+    Json(json!({
+        "id": account.id,
+        "username": account.login,
+        "global_name": account.name,
+        "avatar": null,
+        "email": account.email,
+        "verified": account.email_verified,
+    }))
+    .into_response()
+}
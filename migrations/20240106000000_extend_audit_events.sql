@@ -0,0 +1,21 @@
+-- Request metadata and before/after diff for audit events
+ALTER TABLE audit_events ADD COLUMN changes JSONB;
+ALTER TABLE audit_events ADD COLUMN ip_address VARCHAR(45);
+ALTER TABLE audit_events ADD COLUMN user_agent TEXT;
+ALTER TABLE audit_events ADD COLUMN request_id VARCHAR(100);
+
+CREATE INDEX idx_audit_events_action ON audit_events (action);
+CREATE INDEX idx_audit_events_created_at ON audit_events (created_at);
+
+-- Enforce append-only at the database level
+CREATE OR REPLACE FUNCTION reject_audit_event_modification()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER audit_events_append_only
+BEFORE UPDATE OR DELETE ON audit_events
+FOR EACH ROW
+EXECUTE PROCEDURE reject_audit_event_modification();
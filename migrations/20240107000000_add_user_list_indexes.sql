@@ -0,0 +1,4 @@
+-- Support filtering and sorting on GET /users
+CREATE INDEX idx_users_created_at ON users (created_at, id);
+CREATE INDEX idx_users_role ON users (role);
+CREATE INDEX idx_users_status ON users (status);
@@ -0,0 +1,5 @@
+-- Keyset pagination walks (sort_key, id); index every supported ordering
+DROP INDEX IF EXISTS idx_audit_events_created_at;
+CREATE INDEX idx_audit_events_created_at_id ON audit_events (created_at, id);
+CREATE INDEX idx_users_name_id ON users (name, id);
+CREATE INDEX idx_users_email_id ON users (email, id);
@@ -0,0 +1,15 @@
+-- Full-text and fuzzy search over users
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Emails are indexed both whole and split on '@'/'.' so "example" matches "bob@example.com"
+ALTER TABLE users ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    to_tsvector('simple',
+        coalesce(name, '') || ' ' ||
+        coalesce(email, '') || ' ' ||
+        translate(coalesce(email, ''), '@.', '  ') || ' ' ||
+        coalesce(phone, ''))
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON users USING GIN (search_vector);
+CREATE INDEX idx_users_name_trgm ON users USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_users_email_trgm ON users USING GIN (email gin_trgm_ops);
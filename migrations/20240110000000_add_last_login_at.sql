@@ -0,0 +1,2 @@
+-- Track the most recent successful sign-in (password or OAuth)
+ALTER TABLE users ADD COLUMN last_login_at TIMESTAMP WITH TIME ZONE;
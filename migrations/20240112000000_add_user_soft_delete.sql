@@ -0,0 +1,9 @@
+-- Soft delete: rows stay until the purge job anonymizes or removes them
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE users ADD COLUMN purged_at TIMESTAMP WITH TIME ZONE;
+
+-- A deleted account must not block the same email from signing up again
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+CREATE UNIQUE INDEX users_email_active_key ON users (email) WHERE deleted_at IS NULL;
+
+CREATE INDEX idx_users_deleted_at ON users (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,3 @@
+-- Blob store prefix of an avatar we host ourselves; while set, OAuth sign-in
+-- no longer overwrites avatar_url with the provider's picture
+ALTER TABLE users ADD COLUMN avatar_key TEXT;
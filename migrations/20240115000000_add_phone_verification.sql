@@ -0,0 +1,20 @@
+-- New writes store phones in E.164; a number can only be verified on one active account
+ALTER TABLE users ADD COLUMN phone_verified_at TIMESTAMP WITH TIME ZONE;
+
+CREATE UNIQUE INDEX users_phone_verified_key ON users (phone)
+    WHERE phone_verified_at IS NOT NULL AND deleted_at IS NULL;
+CREATE INDEX idx_users_phone ON users (phone) WHERE phone IS NOT NULL;
+
+-- One-time SMS codes; only the hash is stored
+CREATE TABLE IF NOT EXISTS phone_verifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone VARCHAR(20) NOT NULL,
+    code_hash VARCHAR(64) NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    verified_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_phone_verifications_user_id ON phone_verifications (user_id);
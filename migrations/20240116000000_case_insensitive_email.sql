@@ -0,0 +1,33 @@
+-- Emails are compared case-insensitively and stored lower-cased.
+-- Active accounts whose emails differ only by case cannot be merged automatically:
+-- list them and stop, so an admin can rename or delete the duplicates first.
+DO $$
+DECLARE
+    collisions TEXT;
+BEGIN
+    SELECT string_agg(format('%s (%s)', canonical, ids), E'\n' ORDER BY canonical)
+    INTO collisions
+    FROM (
+        SELECT lower(email) AS canonical,
+               string_agg(format('%s <%s>', id, email), ', ' ORDER BY created_at, id) AS ids
+        FROM users
+        WHERE deleted_at IS NULL
+        GROUP BY lower(email)
+        HAVING COUNT(*) > 1
+    ) groups;
+
+    IF collisions IS NOT NULL THEN
+        RAISE EXCEPTION 'Active users share an email that differs only by case'
+            USING DETAIL = collisions,
+                  HINT = 'Change or delete all but one account per email, then restart to rerun this migration.';
+    END IF;
+END
+$$;
+
+UPDATE users SET email = lower(email) WHERE email <> lower(email);
+UPDATE email_changes
+SET old_email = lower(old_email), new_email = lower(new_email)
+WHERE old_email <> lower(old_email) OR new_email <> lower(new_email);
+
+DROP INDEX IF EXISTS users_email_active_key;
+CREATE UNIQUE INDEX users_email_active_key ON users (lower(email)) WHERE deleted_at IS NULL;
@@ -0,0 +1,17 @@
+-- OAuth tokens kept to call providers on a user's behalf. Envelope-encrypted:
+-- each row has its own data key, stored wrapped by the master key named in key_id
+CREATE TABLE IF NOT EXISTS provider_tokens (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(20) NOT NULL,
+    key_id VARCHAR(64) NOT NULL,
+    wrapped_data_key BYTEA NOT NULL,
+    access_token BYTEA NOT NULL,
+    refresh_token BYTEA,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, provider)
+);
+
+-- Finds rows still wrapped by a retired master key
+CREATE INDEX idx_provider_tokens_key_id ON provider_tokens (key_id);
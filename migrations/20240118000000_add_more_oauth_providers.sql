@@ -0,0 +1,6 @@
+-- Account ids at the additional OAuth providers, one column per provider like github_id/google_id.
+-- Microsoft ids are "<tenant id>:<object id>"; GitLab ids are only unique per GitLab instance.
+ALTER TABLE users ADD COLUMN gitlab_id VARCHAR(255) UNIQUE;
+ALTER TABLE users ADD COLUMN microsoft_id VARCHAR(255) UNIQUE;
+ALTER TABLE users ADD COLUMN discord_id VARCHAR(255) UNIQUE;
+ALTER TABLE users ADD COLUMN apple_id VARCHAR(255) UNIQUE;
@@ -0,0 +1,11 @@
+-- One-time codes handed to the frontend after an OAuth sign-in, swapped for
+-- tokens at /auth/exchange; only code hashes are stored
+CREATE TABLE IF NOT EXISTS oauth_exchange_codes (
+    code_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(20) NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_oauth_exchange_codes_expires_at ON oauth_exchange_codes (expires_at);
@@ -0,0 +1,9 @@
+-- Keyset pagination compares (created_at, id) row values, which a NULL key
+-- never satisfies; rows written without a timestamp would drop out of listings
+UPDATE users SET created_at = COALESCE(updated_at, NOW()) WHERE created_at IS NULL;
+ALTER TABLE users ALTER COLUMN created_at SET NOT NULL;
+
+ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only;
+UPDATE audit_events SET created_at = NOW() WHERE created_at IS NULL;
+ALTER TABLE audit_events ENABLE TRIGGER audit_events_append_only;
+ALTER TABLE audit_events ALTER COLUMN created_at SET NOT NULL;
@@ -0,0 +1,15 @@
+-- A deleted account must not block its provider accounts from signing up again,
+-- like users_email_active_key does for the email
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_github_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_google_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_gitlab_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_microsoft_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_discord_id_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_apple_id_key;
+
+CREATE UNIQUE INDEX users_github_id_active_key ON users (github_id) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX users_google_id_active_key ON users (google_id) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX users_gitlab_id_active_key ON users (gitlab_id) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX users_microsoft_id_active_key ON users (microsoft_id) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX users_discord_id_active_key ON users (discord_id) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX users_apple_id_active_key ON users (apple_id) WHERE deleted_at IS NULL;
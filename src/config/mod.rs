@@ -1 +1,800 @@
 // Configuration module
+//
+// Settings are layered: built-in defaults, then an optional TOML file
+// (`APP_CONFIG_FILE`, or `./config.toml` when present), then environment variables.
+// Everything is validated once at startup and all problems are reported together.
+
+use std::env;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use axum::http::HeaderValue;
+use jsonwebtoken::EncodingKey;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::Deserialize;
+use crate::domain::repositories::user_repository::PurgeMode;
+use crate::infrastructure::auth::oauth::OAuthEndpoints;
+use crate::infrastructure::crypto::EnvelopeCipher;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::request_context::TrustedProxies;
+use crate::utils::cookie::SameSite;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// A value that must never end up in logs; `Debug` prints a placeholder
+#[derive(Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            f.write_str("\"\"")
+        } else {
+            f.write_str("\"[redacted]\"")
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    pub cors: CorsConfig,
+    pub app: AppSettings,
+    pub users: UsersConfig,
+    pub storage: StorageConfig,
+    pub phone: PhoneConfig,
+    pub oauth: OAuthConfig,
+    pub provider_tokens: ProviderTokensConfig,
+    pub session_cookies: SessionCookiesConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Reverse proxies (addresses or CIDR ranges) whose `X-Forwarded-For` is
+    /// believed; from anyone else the TCP peer is the client
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self { host: IpAddr::V4(Ipv4Addr::LOCALHOST), port: 8000, trusted_proxies: Vec::new() }
+    }
+}
+
+impl ServerConfig {
+    pub fn bind_address(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: Secret,
+    pub max_connections: u32,
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self { url: Secret::default(), max_connections: 5, acquire_timeout_secs: 3 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtConfig {
+    pub secret: Secret,
+    pub access_token_ttl_secs: u64,
+    pub refresh_token_ttl_secs: u64,
+    pub impersonation_token_ttl_secs: u64,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            secret: Secret::default(),
+            access_token_ttl_secs: 15 * 60,
+            refresh_token_ttl_secs: 7 * 24 * 60 * 60,
+            impersonation_token_ttl_secs: 10 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Exact origins (`https://app.example.com`), or `["*"]` for any
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self { allowed_origins: vec!["*".to_string()] }
+    }
+}
+
+impl CorsConfig {
+    pub fn allows_any(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppSettings {
+    /// Public base URL used to build links in outgoing email
+    pub base_url: String,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self { base_url: "http://localhost:8000".to_string() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UsersConfig {
+    /// Days a soft-deleted user is kept before the purge job handles it
+    pub retention_days: i64,
+    pub purge_mode: PurgeMode,
+}
+
+impl Default for UsersConfig {
+    fn default() -> Self {
+        Self { retention_days: 30, purge_mode: PurgeMode::default() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Where generated GDPR data exports are written
+    pub export_dir: PathBuf,
+    /// Where uploaded avatars are stored
+    pub blob_store_dir: PathBuf,
+    /// Copy provider avatars into the blob store on sign-in instead of hotlinking them
+    pub mirror_oauth_avatars: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            export_dir: PathBuf::from("./exports"),
+            blob_store_dir: PathBuf::from("./storage"),
+            mirror_oauth_avatars: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhoneConfig {
+    /// Region for numbers written without a `+` country code (ISO 3166-1 alpha-2)
+    pub default_region: String,
+    /// Reject phones already on another account, verified or not
+    pub unique: bool,
+    /// Also append outgoing SMS to this file as JSON lines
+    pub sms_log_file: Option<PathBuf>,
+}
+
+impl Default for PhoneConfig {
+    fn default() -> Self {
+        Self { default_region: "ID".to_string(), unique: false, sms_log_file: None }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OAuthConfig {
+    pub github: OAuthClientConfig,
+    pub google: OAuthClientConfig,
+    pub gitlab: OAuthClientConfig,
+    pub microsoft: OAuthClientConfig,
+    pub discord: OAuthClientConfig,
+    pub apple: OAuthClientConfig,
+    /// Frontend origins `/auth/{provider}?redirect_to=` may send the browser back to
+    pub redirect_origins: Vec<String>,
+    pub http: OAuthHttpConfig,
+}
+
+impl OAuthConfig {
+    /// Every built-in provider with its name, as used in routes and env vars
+    pub fn clients(&self) -> [(&'static str, &OAuthClientConfig); 6] {
+        [
+            ("github", &self.github),
+            ("google", &self.google),
+            ("gitlab", &self.gitlab),
+            ("microsoft", &self.microsoft),
+            ("discord", &self.discord),
+            ("apple", &self.apple),
+        ]
+    }
+
+    fn clients_mut(&mut self) -> [(&'static str, &mut OAuthClientConfig); 6] {
+        [
+            ("github", &mut self.github),
+            ("google", &mut self.google),
+            ("gitlab", &mut self.gitlab),
+            ("microsoft", &mut self.microsoft),
+            ("discord", &mut self.discord),
+            ("apple", &mut self.apple),
+        ]
+    }
+}
+
+/// Credentials of one OAuth provider; a provider with none of them set is disabled
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OAuthClientConfig {
+    pub client_id: String,
+    pub client_secret: Secret,
+    pub redirect_uri: String,
+    /// Endpoint overrides (e.g. GitHub Enterprise Server); unset means the public provider
+    pub authorize_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>,
+    pub jwks_url: Option<String>,
+    /// Google only: restrict sign-in to one Google Workspace domain
+    pub hosted_domain: Option<String>,
+    /// GitLab: the instance, for self-hosted GitLab (default https://gitlab.com).
+    /// Microsoft: the authority (default https://login.microsoftonline.com).
+    pub base_url: Option<String>,
+    /// Microsoft only: a tenant id to allow only that organization, or `common`
+    /// (default), `organizations` or `consumers`
+    pub tenant: Option<String>,
+    /// Apple only: client secrets are JWTs signed with the private key (.p8 file)
+    /// Apple issued to the team, so `client_secret` is not used
+    pub team_id: Option<String>,
+    pub key_id: Option<String>,
+    pub private_key_file: Option<PathBuf>,
+}
+
+impl OAuthClientConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.client_id.is_empty() || !self.client_secret.is_empty() || !self.redirect_uri.is_empty()
+    }
+
+    /// `defaults` with any configured overrides applied
+    pub fn endpoints(&self, defaults: OAuthEndpoints) -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: self.authorize_url.clone().unwrap_or(defaults.authorize_url),
+            token_url: self.token_url.clone().unwrap_or(defaults.token_url),
+            userinfo_url: self.userinfo_url.clone().or(defaults.userinfo_url),
+            jwks_url: self.jwks_url.clone().or(defaults.jwks_url),
+        }
+    }
+
+    /// Apple's client secret signing key, read from `private_key_file`
+    pub fn private_key(&self) -> Result<EncodingKey, String> {
+        let path = self.private_key_file.as_deref().ok_or("private_key_file is not set")?;
+        let pem = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        EncodingKey::from_ec_pem(&pem).map_err(|e| format!("{} is not a PEM EC private key: {}", path.display(), e))
+    }
+
+    /// Names of the optional settings that are set, for reporting ones the provider ignores
+    fn settings_in_use(&self) -> Vec<&'static str> {
+        [
+            ("client_secret", !self.client_secret.is_empty()),
+            ("authorize_url", self.authorize_url.is_some()),
+            ("token_url", self.token_url.is_some()),
+            ("userinfo_url", self.userinfo_url.is_some()),
+            ("jwks_url", self.jwks_url.is_some()),
+            ("hosted_domain", self.hosted_domain.is_some()),
+            ("base_url", self.base_url.is_some()),
+            ("tenant", self.tenant.is_some()),
+            ("team_id", self.team_id.is_some()),
+            ("key_id", self.key_id.is_some()),
+            ("private_key_file", self.private_key_file.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(key, set)| set.then_some(key))
+        .collect()
+    }
+}
+
+/// Optional settings each provider reads. Identities come from a profile API
+/// (`userinfo_url`) or a verified id_token (`jwks_url`), never both.
+fn supported_oauth_settings(provider: &str) -> &'static [&'static str] {
+    match provider {
+        "github" | "discord" => &["client_secret", "authorize_url", "token_url", "userinfo_url"],
+        "google" => &["client_secret", "authorize_url", "token_url", "jwks_url", "hosted_domain"],
+        "gitlab" => &["client_secret", "authorize_url", "token_url", "userinfo_url", "base_url"],
+        "microsoft" => &["client_secret", "authorize_url", "token_url", "jwks_url", "base_url", "tenant"],
+        "apple" => &["authorize_url", "token_url", "jwks_url", "team_id", "key_id", "private_key_file"],
+        _ => &[],
+    }
+}
+
+/// Settings a provider used to read and now ignores. Kept working with a
+/// warning so existing deployments don't fail to start after upgrading.
+fn deprecated_oauth_settings(provider: &str) -> &'static [(&'static str, &'static str)] {
+    match provider {
+        "google" => &[("userinfo_url", "identities now come from the verified id_token")],
+        _ => &[],
+    }
+}
+
+/// Outbound requests to OAuth providers
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OAuthHttpConfig {
+    pub connect_timeout_secs: u64,
+    /// Whole request, including reading the body
+    pub timeout_secs: u64,
+    /// Extra attempts after a transient failure
+    pub max_retries: u32,
+}
+
+impl Default for OAuthHttpConfig {
+    fn default() -> Self {
+        Self { connect_timeout_secs: 5, timeout_secs: 10, max_retries: 2 }
+    }
+}
+
+/// Opt-in delivery of tokens in HttpOnly cookies, for browser clients.
+/// Bearer tokens keep working alongside.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionCookiesConfig {
+    pub enabled: bool,
+    /// `strict`, `lax`, or `none` when the frontend is on another site
+    pub same_site: SameSite,
+}
+
+impl Default for SessionCookiesConfig {
+    fn default() -> Self {
+        Self { enabled: false, same_site: SameSite::Lax }
+    }
+}
+
+/// Storage of OAuth provider tokens for calling providers on a user's behalf
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderTokensConfig {
+    /// Master keys as `<id>:<base64 of 32 bytes>`, newest first. The first one
+    /// encrypts; older ones stay until stored tokens have been re-wrapped.
+    /// Empty means provider tokens are not stored.
+    pub encryption_keys: Vec<Secret>,
+}
+
+impl ProviderTokensConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.encryption_keys.is_empty()
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        self.encryption_keys.iter().map(Secret::expose).collect()
+    }
+}
+
+/// Every problem found while loading the configuration
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration ({} problem(s)):", self.problems.len())?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl AppConfig {
+    /// Loads defaults, the TOML file and the environment, then validates the result
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+
+        let mut config = match config_file(&mut problems) {
+            Some(path) => Self::from_file(&path).unwrap_or_else(|problem| {
+                problems.push(problem);
+                Self::default()
+            }),
+            None => Self::default(),
+        };
+
+        config.apply_env(&mut EnvLayer { problems: &mut problems });
+        config.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn apply_env(&mut self, env: &mut EnvLayer<'_>) {
+        env.parse("SERVER_HOST", &mut self.server.host);
+        env.parse("SERVER_PORT", &mut self.server.port);
+        env.list("TRUSTED_PROXIES", &mut self.server.trusted_proxies);
+
+        env.secret("DATABASE_URL", &mut self.database.url);
+        env.parse("DATABASE_MAX_CONNECTIONS", &mut self.database.max_connections);
+        env.parse("DATABASE_ACQUIRE_TIMEOUT_SECS", &mut self.database.acquire_timeout_secs);
+
+        env.secret("JWT_SECRET", &mut self.jwt.secret);
+        env.parse("JWT_ACCESS_TOKEN_TTL_SECS", &mut self.jwt.access_token_ttl_secs);
+        env.parse("JWT_REFRESH_TOKEN_TTL_SECS", &mut self.jwt.refresh_token_ttl_secs);
+        env.parse("JWT_IMPERSONATION_TOKEN_TTL_SECS", &mut self.jwt.impersonation_token_ttl_secs);
+
+        env.list("CORS_ALLOWED_ORIGINS", &mut self.cors.allowed_origins);
+
+        env.string("APP_BASE_URL", &mut self.app.base_url);
+
+        env.parse("USER_RETENTION_DAYS", &mut self.users.retention_days);
+        env.variant("USER_PURGE_MODE", &mut self.users.purge_mode);
+
+        env.parse("EXPORT_DIR", &mut self.storage.export_dir);
+        env.parse("BLOB_STORE_DIR", &mut self.storage.blob_store_dir);
+        env.flag("MIRROR_OAUTH_AVATARS", &mut self.storage.mirror_oauth_avatars);
+
+        env.string("PHONE_DEFAULT_REGION", &mut self.phone.default_region);
+        env.flag("PHONE_UNIQUE", &mut self.phone.unique);
+        env.optional("SMS_LOG_FILE", &mut self.phone.sms_log_file);
+
+        // GITHUB_CLIENT_ID, GOOGLE_CLIENT_ID, ..., APPLE_PRIVATE_KEY_FILE
+        for (name, provider) in self.oauth.clients_mut() {
+            let var = |key: &str| format!("{}_{}", name.to_uppercase(), key);
+            env.string(&var("CLIENT_ID"), &mut provider.client_id);
+            env.secret(&var("CLIENT_SECRET"), &mut provider.client_secret);
+            env.string(&var("REDIRECT_URI"), &mut provider.redirect_uri);
+            env.optional(&var("AUTHORIZE_URL"), &mut provider.authorize_url);
+            env.optional(&var("TOKEN_URL"), &mut provider.token_url);
+            env.optional(&var("USERINFO_URL"), &mut provider.userinfo_url);
+            env.optional(&var("JWKS_URL"), &mut provider.jwks_url);
+            env.optional(&var("HOSTED_DOMAIN"), &mut provider.hosted_domain);
+            env.optional(&var("BASE_URL"), &mut provider.base_url);
+            env.optional(&var("TENANT"), &mut provider.tenant);
+            env.optional(&var("TEAM_ID"), &mut provider.team_id);
+            env.optional(&var("KEY_ID"), &mut provider.key_id);
+            env.optional(&var("PRIVATE_KEY_FILE"), &mut provider.private_key_file);
+        }
+        env.list("OAUTH_REDIRECT_ORIGINS", &mut self.oauth.redirect_origins);
+        env.parse("OAUTH_CONNECT_TIMEOUT_SECS", &mut self.oauth.http.connect_timeout_secs);
+        env.parse("OAUTH_TIMEOUT_SECS", &mut self.oauth.http.timeout_secs);
+        env.parse("OAUTH_MAX_RETRIES", &mut self.oauth.http.max_retries);
+
+        env.secret_list("PROVIDER_TOKEN_KEYS", &mut self.provider_tokens.encryption_keys);
+
+        env.flag("SESSION_COOKIES_ENABLED", &mut self.session_cookies.enabled);
+        env.variant("SESSION_COOKIES_SAME_SITE", &mut self.session_cookies.same_site);
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        if let Err(e) = TrustedProxies::parse(&self.server.trusted_proxies) {
+            problems.push(format!("server.trusted_proxies: {} (TRUSTED_PROXIES)", e));
+        }
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is required (DATABASE_URL)".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+
+        if self.jwt.secret.is_empty() {
+            problems.push("jwt.secret is required (JWT_SECRET)".to_string());
+        }
+        for (key, ttl) in [
+            ("access_token_ttl_secs", self.jwt.access_token_ttl_secs),
+            ("refresh_token_ttl_secs", self.jwt.refresh_token_ttl_secs),
+            ("impersonation_token_ttl_secs", self.jwt.impersonation_token_ttl_secs),
+        ] {
+            if ttl == 0 {
+                problems.push(format!("jwt.{} must be greater than 0", key));
+            }
+        }
+        if self.jwt.refresh_token_ttl_secs <= self.jwt.access_token_ttl_secs {
+            problems.push("jwt.refresh_token_ttl_secs must be longer than jwt.access_token_ttl_secs".to_string());
+        }
+
+        if self.cors.allowed_origins.is_empty() {
+            problems.push("cors.allowed_origins must list at least one origin, or \"*\"".to_string());
+        } else if self.cors.allows_any() && self.cors.allowed_origins.len() > 1 {
+            problems.push("cors.allowed_origins cannot mix \"*\" with specific origins".to_string());
+        } else if !self.cors.allows_any() {
+            for origin in &self.cors.allowed_origins {
+                if !is_origin(origin) {
+                    problems.push(format!("cors.allowed_origins: '{}' is not an origin like https://app.example.com", origin));
+                }
+            }
+        }
+        for origin in &self.oauth.redirect_origins {
+            if !is_origin(origin) {
+                problems.push(format!("oauth.redirect_origins: '{}' is not an origin like https://app.example.com", origin));
+            }
+        }
+
+        if !is_http_url(&self.app.base_url) {
+            problems.push(format!("app.base_url: '{}' must start with http:// or https://", self.app.base_url));
+        }
+
+        if self.users.retention_days < 0 {
+            problems.push("users.retention_days cannot be negative".to_string());
+        }
+
+        if let Err(e) = PhonePolicy::new(&self.phone.default_region, self.phone.unique) {
+            problems.push(format!("phone.default_region: {}", e));
+        }
+
+        if self.provider_tokens.is_enabled() {
+            if let Err(e) = EnvelopeCipher::new(&self.provider_tokens.keys()) {
+                problems.push(format!("provider_tokens.encryption_keys: {} (PROVIDER_TOKEN_KEYS)", e));
+            }
+        }
+
+        if self.oauth.http.connect_timeout_secs == 0 || self.oauth.http.timeout_secs == 0 {
+            problems.push("oauth.http timeouts must be at least 1 second (OAUTH_CONNECT_TIMEOUT_SECS, OAUTH_TIMEOUT_SECS)".to_string());
+        }
+        if self.oauth.http.max_retries > 5 {
+            problems.push("oauth.http.max_retries must be at most 5 (OAUTH_MAX_RETRIES)".to_string());
+        }
+
+        for (name, provider) in self.oauth.clients() {
+            let env_prefix = name.to_uppercase();
+            for (key, url) in [
+                ("authorize_url", &provider.authorize_url),
+                ("token_url", &provider.token_url),
+                ("userinfo_url", &provider.userinfo_url),
+                ("jwks_url", &provider.jwks_url),
+                ("base_url", &provider.base_url),
+            ] {
+                if url.as_deref().is_some_and(|url| !is_http_url(url)) {
+                    problems.push(format!(
+                        "oauth.{}.{} must be an http(s) URL ({}_{})",
+                        name, key, env_prefix, key.to_uppercase()
+                    ));
+                }
+            }
+            let supported = supported_oauth_settings(name);
+            let deprecated = deprecated_oauth_settings(name);
+            for key in provider.settings_in_use() {
+                if let Some((_, reason)) = deprecated.iter().find(|(deprecated, _)| *deprecated == key) {
+                    tracing::warn!(
+                        "oauth.{}.{} is deprecated and ignored: {}; remove it ({}_{})",
+                        name, key, reason, env_prefix, key.to_uppercase()
+                    );
+                } else if !supported.contains(&key) {
+                    problems.push(format!("oauth.{}.{} is not used by this provider ({}_{})", name, key, env_prefix, key.to_uppercase()));
+                }
+            }
+            if let Some(tenant) = &provider.tenant {
+                let known = matches!(tenant.as_str(), "common" | "organizations" | "consumers");
+                if !known && uuid::Uuid::parse_str(tenant).is_err() {
+                    problems.push(format!(
+                        "oauth.{}.tenant must be common, organizations, consumers or a tenant id ({}_TENANT)",
+                        name, env_prefix
+                    ));
+                }
+            }
+            if !provider.is_enabled() {
+                continue;
+            }
+            if provider.client_id.is_empty() {
+                problems.push(format!("oauth.{}.client_id is required once the provider is configured ({}_CLIENT_ID)", name, env_prefix));
+            }
+            if supported.contains(&"private_key_file") {
+                for (key, value) in [("team_id", &provider.team_id), ("key_id", &provider.key_id)] {
+                    if value.as_deref().is_none_or(str::is_empty) {
+                        problems.push(format!("oauth.{}.{} is required once the provider is configured ({}_{})", name, key, env_prefix, key.to_uppercase()));
+                    }
+                }
+                if let Err(e) = provider.private_key() {
+                    problems.push(format!("oauth.{}.private_key_file: {} ({}_PRIVATE_KEY_FILE)", name, e, env_prefix));
+                }
+            } else if provider.client_secret.is_empty() {
+                problems.push(format!("oauth.{}.client_secret is required once the provider is configured ({}_CLIENT_SECRET)", name, env_prefix));
+            }
+            if !is_http_url(&provider.redirect_uri) {
+                problems.push(format!("oauth.{}.redirect_uri must be an http(s) URL ({}_REDIRECT_URI)", name, env_prefix));
+            }
+        }
+    }
+}
+
+/// `APP_CONFIG_FILE` must exist when set; the default file is optional
+fn config_file(problems: &mut Vec<String>) -> Option<PathBuf> {
+    match env::var("APP_CONFIG_FILE") {
+        Ok(path) => {
+            let path = PathBuf::from(path);
+            if path.is_file() {
+                Some(path)
+            } else {
+                problems.push(format!("APP_CONFIG_FILE: {} does not exist", path.display()));
+                None
+            }
+        }
+        Err(_) => {
+            let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+            path.is_file().then_some(path)
+        }
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+/// scheme://host[:port] with no path or trailing slash, as browsers send it
+fn is_origin(value: &str) -> bool {
+    is_http_url(value) && value.matches('/').count() == 2 && HeaderValue::from_str(value).is_ok()
+}
+
+/// Applies environment variables on top of the current values, recording bad ones
+struct EnvLayer<'a> {
+    problems: &'a mut Vec<String>,
+}
+
+impl EnvLayer<'_> {
+    fn get(&mut self, name: &str) -> Option<String> {
+        match env::var(name) {
+            Ok(value) => Some(value),
+            Err(env::VarError::NotPresent) => None,
+            Err(env::VarError::NotUnicode(_)) => {
+                self.problems.push(format!("{} is not valid UTF-8", name));
+                None
+            }
+        }
+    }
+
+    fn string(&mut self, name: &str, target: &mut String) {
+        if let Some(value) = self.get(name) {
+            *target = value;
+        }
+    }
+
+    fn secret(&mut self, name: &str, target: &mut Secret) {
+        if let Some(value) = self.get(name) {
+            *target = Secret(value);
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str, target: &mut T)
+    where
+        T::Err: fmt::Display,
+    {
+        if let Some(value) = self.get(name) {
+            match value.trim().parse() {
+                Ok(parsed) => *target = parsed,
+                Err(e) => self.problems.push(format!("{}: '{}' is invalid: {}", name, value, e)),
+            }
+        }
+    }
+
+    /// Same spelling as the TOML file, e.g. `USER_PURGE_MODE=delete`
+    fn variant<T: DeserializeOwned>(&mut self, name: &str, target: &mut T) {
+        if let Some(value) = self.get(name) {
+            match T::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(value.trim())) {
+                Ok(parsed) => *target = parsed,
+                Err(e) => self.problems.push(format!("{}: {}", name, e)),
+            }
+        }
+    }
+
+    fn flag(&mut self, name: &str, target: &mut bool) {
+        if let Some(value) = self.get(name) {
+            match value.trim().to_lowercase().as_str() {
+                "true" | "1" => *target = true,
+                "false" | "0" => *target = false,
+                _ => self.problems.push(format!("{}: '{}' must be true or false", name, value)),
+            }
+        }
+    }
+
+    fn optional<T: FromStr>(&mut self, name: &str, target: &mut Option<T>)
+    where
+        T::Err: fmt::Display,
+    {
+        if let Some(value) = self.get(name) {
+            match value.trim().parse() {
+                Ok(parsed) => *target = Some(parsed),
+                Err(e) => self.problems.push(format!("{}: '{}' is invalid: {}", name, value, e)),
+            }
+        }
+    }
+
+    /// Comma-separated list of secrets
+    fn secret_list(&mut self, name: &str, target: &mut Vec<Secret>) {
+        if let Some(value) = self.get(name) {
+            *target = value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Secret(item.to_string()))
+                .collect();
+        }
+    }
+
+    /// Comma-separated list
+    fn list(&mut self, name: &str, target: &mut Vec<String>) {
+        if let Some(value) = self.get(name) {
+            *target = value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(config: &AppConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        config.validate(&mut problems);
+        problems
+    }
+
+    fn google() -> OAuthClientConfig {
+        OAuthClientConfig {
+            client_id: "client".to_string(),
+            client_secret: Secret::new("secret"),
+            redirect_uri: "https://api.example.com/api/v1/auth/google/callback".to_string(),
+            ..OAuthClientConfig::default()
+        }
+    }
+
+    #[test]
+    fn a_legacy_google_userinfo_url_is_not_a_problem() {
+        let mut config = AppConfig::default();
+        config.oauth.google = OAuthClientConfig {
+            userinfo_url: Some("https://openidconnect.googleapis.com/v1/userinfo".to_string()),
+            ..google()
+        };
+
+        let problems = problems(&config);
+
+        assert!(!problems.iter().any(|problem| problem.contains("oauth.google")), "{:?}", problems);
+    }
+
+    #[test]
+    fn settings_a_provider_never_read_are_still_problems() {
+        let mut config = AppConfig::default();
+        config.oauth.google = OAuthClientConfig {
+            base_url: Some("https://accounts.example.com".to_string()),
+            ..google()
+        };
+
+        let problems = problems(&config);
+
+        assert!(problems.iter().any(|problem| problem.contains("oauth.google.base_url is not used")), "{:?}", problems);
+    }
+}
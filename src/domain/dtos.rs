@@ -1,16 +1,131 @@
 use serde::{Deserialize, Serialize};
-use crate::domain::entities::user::{Role, UserStatus};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use crate::domain::entities::user::{AuthProvider, Role, User, UserStatus};
+use crate::domain::repositories::user_repository::ImportMode;
 
 /// Response DTO for user data (without password)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserResponseDto {
     pub name: String,
     pub phone: Option<String>,
+    pub phone_verified_at: Option<DateTime<Utc>>,
     pub email: String,
     pub role: Role,
     pub avatar_url: Option<String>,
 }
 
+impl From<User> for UserResponseDto {
+    fn from(user: User) -> Self {
+        Self {
+            name: user.name,
+            phone: user.phone,
+            phone_verified_at: user.phone_verified_at,
+            email: user.email,
+            role: user.role,
+            avatar_url: user.avatar_url,
+        }
+    }
+}
+
+/// A self-hosted avatar; `avatar_url` is the default-size variant
+#[derive(Debug, Serialize)]
+pub struct AvatarResponseDto {
+    pub avatar_url: String,
+    pub variants: Vec<AvatarVariantDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvatarVariantDto {
+    pub size: u32,
+    pub url: String,
+}
+
+/// Detailed user view for Admin/SuperAdmin (list and detail endpoints)
+#[derive(Debug, Serialize)]
+pub struct AdminUserDto {
+    pub id: Uuid,
+    pub name: String,
+    pub phone: Option<String>,
+    pub phone_verified_at: Option<DateTime<Utc>>,
+    pub email: String,
+    pub role: Role,
+    pub status: UserStatus,
+    pub avatar_url: Option<String>,
+    pub identities: Vec<LinkedIdentityDto>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A sign-in method linked to the account
+#[derive(Debug, Serialize)]
+pub struct LinkedIdentityDto {
+    pub provider: AuthProvider,
+    /// The provider's user id (absent for password sign-in)
+    pub provider_user_id: Option<String>,
+}
+
+impl From<User> for AdminUserDto {
+    fn from(user: User) -> Self {
+        let mut identities = Vec::new();
+        if user.password_hash.is_some() {
+            identities.push(LinkedIdentityDto { provider: AuthProvider::Password, provider_user_id: None });
+        }
+        if let Some(github_id) = user.github_id {
+            identities.push(LinkedIdentityDto { provider: AuthProvider::Github, provider_user_id: Some(github_id.to_string()) });
+        }
+        let string_ids = [
+            (AuthProvider::Google, user.google_id),
+            (AuthProvider::Gitlab, user.gitlab_id),
+            (AuthProvider::Microsoft, user.microsoft_id),
+            (AuthProvider::Discord, user.discord_id),
+            (AuthProvider::Apple, user.apple_id),
+        ];
+        for (provider, provider_user_id) in string_ids {
+            if provider_user_id.is_some() {
+                identities.push(LinkedIdentityDto { provider, provider_user_id });
+            }
+        }
+
+        Self {
+            id: user.id,
+            name: user.name,
+            phone: user.phone,
+            phone_verified_at: user.phone_verified_at,
+            email: user.email,
+            role: user.role,
+            status: user.status,
+            avatar_url: user.avatar_url,
+            identities,
+            last_login_at: user.last_login_at,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+/// Response DTO for a user search hit (Admin/SuperAdmin)
+#[derive(Debug, Serialize)]
+pub struct UserSearchResultDto {
+    pub id: Uuid,
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: String,
+    pub role: Role,
+    pub avatar_url: Option<String>,
+    pub score: f32,
+    pub highlights: UserSearchHighlightsDto,
+}
+
+/// HTML-escaped fields with matched terms wrapped in `<mark>` tags
+#[derive(Debug, Serialize)]
+pub struct UserSearchHighlightsDto {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>,
+}
+
 /// Request DTO for user registration
 #[derive(Debug, Deserialize)]
 pub struct RegisterUserDto {
@@ -29,6 +144,46 @@ pub struct AuthResponseDto {
     pub expires_in: usize,
 }
 
+/// Response DTO for authentication in session cookie mode: the tokens are only
+/// in HttpOnly cookies, and `csrf_token` must be sent back in `X-CSRF-Token`
+#[derive(Debug, Serialize)]
+pub struct CookieSessionDto {
+    pub token_type: String,
+    pub expires_in: usize,
+    pub csrf_token: String,
+}
+
+/// An enabled OAuth provider; `login_url` starts the sign-in flow
+#[derive(Debug, Serialize)]
+pub struct OAuthProviderDto {
+    pub name: AuthProvider,
+    pub display_name: String,
+    pub login_url: String,
+}
+
+/// Response DTO for a signed-in device
+#[derive(Debug, Serialize)]
+pub struct SessionDto {
+    pub id: Uuid,
+    pub auth_method: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Whether this is the session of the request's own token
+    pub current: bool,
+}
+
+/// Response DTO for an impersonation session (access token only, no refresh)
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponseDto {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: usize,
+    pub impersonated_user_id: Uuid,
+    pub actor_id: Uuid,
+}
+
 /// Request DTO for creating a user (Admin/SuperAdmin)
 #[derive(Debug, Deserialize)]
 pub struct CreateUserDto {
@@ -39,6 +194,51 @@ pub struct CreateUserDto {
     pub role: Role,
 }
 
+/// One row of a bulk import; `user` holds the validated row or why it was rejected
+#[derive(Debug)]
+pub struct ImportUserRowDto {
+    /// 1-based line number in the uploaded file
+    pub line: u64,
+    pub email: Option<String>,
+    pub user: Result<CreateUserDto, String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportRowStatus {
+    /// Inserted and committed
+    Created,
+    /// Would have been inserted (dry run)
+    Valid,
+    /// Inserted, then rolled back because another row failed
+    RolledBack,
+    /// Rejected by validation or by the database
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowResultDto {
+    pub line: u64,
+    pub email: Option<String>,
+    pub status: ImportRowStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReportDto {
+    pub dry_run: bool,
+    pub mode: ImportMode,
+    /// Whether any row was actually written
+    pub committed: bool,
+    pub total: usize,
+    pub created: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowResultDto>,
+}
+
 /// Request DTO for updating a user (SuperAdmin only)
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserDto {
@@ -48,6 +248,42 @@ pub struct UpdateUserDto {
     pub role: Option<Role>,
 }
 
+/// Request DTO for a user updating their own profile
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileDto {
+    pub name: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// Request DTO for a user changing their own password
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordDto {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Request DTO for a user asking to change their own email
+#[derive(Debug, Deserialize)]
+pub struct RequestEmailChangeDto {
+    pub new_email: String,
+    pub current_password: Option<String>,
+}
+
+/// Request DTO for confirming the SMS code sent to the user's phone
+#[derive(Debug, Deserialize)]
+pub struct ConfirmPhoneVerificationDto {
+    pub code: String,
+}
+
+/// Response DTO for a freshly sent SMS code
+#[derive(Debug, Serialize)]
+pub struct PhoneVerificationSentDto {
+    pub phone: String,
+    pub expires_at: DateTime<Utc>,
+    /// Earliest time another code can be requested
+    pub resend_after: DateTime<Utc>,
+}
+
 /// Request DTO for updating user status
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserStatusDto {
@@ -81,10 +317,11 @@ pub struct GitHubEmail {
     pub verified: bool,
 }
 
-/// Google access token response
+/// Standard token endpoint response (RFC 6749 section 5.1), used by every
+/// provider except GitHub
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
-pub struct GoogleTokenResponse {
+pub struct OAuthTokenResponse {
     pub access_token: String,
     pub token_type: String,
     pub expires_in: Option<u64>,
@@ -93,12 +330,97 @@ pub struct GoogleTokenResponse {
     pub id_token: Option<String>,
 }
 
-/// Google user info from userinfo endpoint
+/// Claims of a verified Google id_token
 #[derive(Debug, Deserialize)]
-pub struct GoogleUserInfo {
-    pub id: String,
+pub struct GoogleIdTokenClaims {
+    pub sub: String,
     pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+    /// Google Workspace domain of the account, absent for consumer accounts
+    pub hd: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// GitLab OpenID Connect userinfo response
+#[derive(Debug, Deserialize)]
+pub struct GitLabUserInfo {
+    pub sub: String,
     pub name: Option<String>,
+    pub nickname: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
     pub picture: Option<String>,
-    pub verified_email: Option<bool>,
+}
+
+/// Claims of a verified Microsoft identity platform (v2.0) id_token
+#[derive(Debug, Deserialize)]
+pub struct MicrosoftIdTokenClaims {
+    pub iss: String,
+    /// Tenant the account belongs to; personal accounts share one tenant
+    pub tid: String,
+    /// Object id of the account, unique within its tenant
+    pub oid: String,
+    pub email: Option<String>,
+    /// Sign-in name; often an email but never verified
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+    /// Whether the tenant owns the email's domain, i.e. the email is verified
+    pub xms_edov: Option<bool>,
+    pub nonce: Option<String>,
+}
+
+/// Discord user from /users/@me
+#[derive(Debug, Deserialize)]
+pub struct DiscordUser {
+    pub id: String,
+    pub username: String,
+    pub global_name: Option<String>,
+    /// Avatar hash, not a URL
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    pub verified: Option<bool>,
+}
+
+/// Claims of a verified Sign in with Apple id_token
+#[derive(Debug, Deserialize)]
+pub struct AppleIdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub email_verified: Option<bool>,
+    /// The email is an @privaterelay.appleid.com address forwarding to the user
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub is_private_email: Option<bool>,
+    pub nonce: Option<String>,
+}
+
+/// The `user` form field Apple posts to the callback, only on the first sign-in
+#[derive(Debug, Deserialize)]
+pub struct AppleCallbackUser {
+    pub name: Option<AppleUserName>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppleUserName {
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+/// Apple sends some boolean claims as `"true"`/`"false"` strings
+fn lenient_bool<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Option::<BoolOrString>::deserialize(deserializer)? {
+        Some(BoolOrString::Bool(value)) => Some(value),
+        Some(BoolOrString::String(value)) => Some(value == "true"),
+        None => None,
+    })
 }
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use crate::infrastructure::request_context::RequestContext;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    ImpersonationStarted,
+    ImpersonatedRequest,
+    UserCreated,
+    UserUpdated,
+    UserDeleted,
+    UserStatusChanged,
+    LoginSucceeded,
+    LoginFailed,
+    TokenRefreshed,
+    ProfileUpdated,
+    PasswordChanged,
+    AccountDeleted,
+    EmailChangeRequested,
+    EmailChanged,
+    EmailChangeReverted,
+    UserRestored,
+    UsersPurged,
+    DataExportRequested,
+    AccountErased,
+    UsersExported,
+    PhoneVerificationRequested,
+    PhoneVerified,
+    SessionRevoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub metadata: Option<Value>,
+    pub changes: Option<Value>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl AuditEvent {
+    pub fn new(
+        actor_id: Option<Uuid>,
+        target_id: Option<Uuid>,
+        action: AuditAction,
+        metadata: Option<Value>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor_id,
+            target_id,
+            action,
+            metadata,
+            changes: None,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            created_at: None,
+        }
+    }
+
+    /// Attaches the IP, user agent and request id of the originating request
+    pub fn with_context(mut self, ctx: &RequestContext) -> Self {
+        self.ip_address = ctx.ip_address.clone();
+        self.user_agent = ctx.user_agent.clone();
+        self.request_id = ctx.request_id.clone();
+        self
+    }
+
+    /// Records only the top-level fields that differ between `before` and `after`.
+    /// Either side may be `None` for creations and deletions.
+    pub fn with_changes<T: Serialize>(mut self, before: Option<&T>, after: Option<&T>) -> Self {
+        let to_map = |value: Option<&T>| match value.map(serde_json::to_value) {
+            Some(Ok(Value::Object(map))) => map,
+            _ => Map::new(),
+        };
+        let before = to_map(before);
+        let after = to_map(after);
+
+        let mut before_diff = Map::new();
+        let mut after_diff = Map::new();
+        for key in before.keys().chain(after.keys()) {
+            let old = before.get(key).cloned().unwrap_or(Value::Null);
+            let new = after.get(key).cloned().unwrap_or(Value::Null);
+            if old != new {
+                before_diff.insert(key.clone(), old);
+                after_diff.insert(key.clone(), new);
+            }
+        }
+
+        self.changes = Some(serde_json::json!({
+            "before": before_diff,
+            "after": after_diff,
+        }));
+        self
+    }
+}
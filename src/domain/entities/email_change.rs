@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A requested email change. `users.email` is only swapped once the new
+/// address confirms; the old address can revert it until `revert_expires_at`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailChange {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub old_email: String,
+    pub new_email: String,
+    #[serde(skip_serializing)]
+    pub confirm_token_hash: String,
+    #[serde(skip_serializing)]
+    pub revert_token_hash: String,
+    pub confirm_expires_at: DateTime<Utc>,
+    pub revert_expires_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub reverted_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
@@ -1 +1,8 @@
 pub mod user;
+pub mod audit_event;
+pub mod email_change;
+pub mod data_export;
+pub mod phone_verification;
+pub mod provider_token;
+pub mod oauth_exchange_code;
+pub mod session;
@@ -0,0 +1,13 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A one-time code for a completed OAuth sign-in, redeemed for tokens by the
+/// frontend the browser was redirected to
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OAuthExchangeCode {
+    pub code_hash: String,
+    pub user_id: Uuid,
+    /// `AuthProvider::as_str`
+    pub provider: String,
+    pub expires_at: DateTime<Utc>,
+}
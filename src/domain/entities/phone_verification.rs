@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A one-time code sent by SMS to prove the user controls `phone`
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PhoneVerification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub phone: String,
+    #[serde(skip_serializing)]
+    pub code_hash: String,
+    pub attempts: i32,
+    pub expires_at: DateTime<Utc>,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
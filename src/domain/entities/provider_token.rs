@@ -0,0 +1,18 @@
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// OAuth tokens of a user's linked provider account, envelope-encrypted with
+/// `EnvelopeCipher`. Never serialized.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ProviderToken {
+    pub user_id: Uuid,
+    /// `AuthProvider::as_str`
+    pub provider: String,
+    /// Master key that wrapped `wrapped_data_key`
+    pub key_id: String,
+    pub wrapped_data_key: Vec<u8>,
+    pub access_token: Vec<u8>,
+    pub refresh_token: Option<Vec<u8>>,
+    /// When the access token stops working; `None` if it does not expire
+    pub expires_at: Option<DateTime<Utc>>,
+}
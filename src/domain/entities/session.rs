@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// A signed-in device. Started by a sign-in and seen again on every refresh;
+/// it ends when its refresh token expires or it is revoked (deleted).
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// `AuthProvider::as_str` of the sign-in that started it
+    pub auth_method: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub last_seen_at: DateTime<Utc>,
+}
@@ -11,19 +11,48 @@ pub enum Role {
     Mentor,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "varchar")]
 pub enum UserStatus {
+    #[default]
     Active,
     Suspended,
 }
 
-impl Default for UserStatus {
-    fn default() -> Self {
-        UserStatus::Active
+/// How a user is able to sign in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthProvider {
+    Password,
+    Github,
+    Google,
+    Gitlab,
+    Microsoft,
+    Discord,
+    Apple,
+}
+
+impl AuthProvider {
+    /// Name used in routes, audit metadata and API responses
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthProvider::Password => "password",
+            AuthProvider::Github => "github",
+            AuthProvider::Google => "google",
+            AuthProvider::Gitlab => "gitlab",
+            AuthProvider::Microsoft => "microsoft",
+            AuthProvider::Discord => "discord",
+            AuthProvider::Apple => "apple",
+        }
     }
 }
 
+/// The form every email is stored and compared in: trimmed and lower-cased,
+/// so `Bob@X.com` and `bob@x.com` are the same account
+pub fn canonical_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
@@ -36,7 +65,47 @@ pub struct User {
     pub status: UserStatus,
     pub github_id: Option<i64>,
     pub google_id: Option<String>,
+    pub gitlab_id: Option<String>,
+    pub microsoft_id: Option<String>,
+    pub discord_id: Option<String>,
+    pub apple_id: Option<String>,
     pub avatar_url: Option<String>,
+    /// Set when `avatar_url` points at our own blob store
+    #[serde(skip_serializing)]
+    pub avatar_key: Option<String>,
+    /// Cleared whenever `phone` changes
+    pub phone_verified_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl User {
+    /// A user not yet stored, with no sign-in method linked; the database
+    /// fills in the timestamps
+    pub fn new(name: String, email: &str, role: Role) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            phone: None,
+            email: canonical_email(email),
+            password_hash: None,
+            role,
+            status: UserStatus::default(),
+            github_id: None,
+            google_id: None,
+            gitlab_id: None,
+            microsoft_id: None,
+            discord_id: None,
+            apple_id: None,
+            avatar_url: None,
+            avatar_key: None,
+            phone_verified_at: None,
+            created_at: None,
+            updated_at: None,
+            last_login_at: None,
+            deleted_at: None,
+        }
+    }
 }
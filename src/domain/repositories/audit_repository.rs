@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use super::super::entities::audit_event::{AuditAction, AuditEvent};
+use super::pagination::{Cursor, KeysetPage, PageParams};
+use crate::infrastructure::errors::AppError;
+
+/// Audit events are always listed newest first by this field
+pub const AUDIT_SORT_FIELD: &str = "created_at";
+
+/// Optional criteria for listing audit events; `None` fields are not filtered on
+#[derive(Debug, Default)]
+pub struct AuditEventFilter {
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Audit events are append-only: there is intentionally no update or delete.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn create(&self, event: &AuditEvent) -> Result<AuditEvent, AppError>;
+    async fn find_page(&self, filter: &AuditEventFilter, page: PageParams) -> Result<Vec<AuditEvent>, AppError>;
+    async fn find_keyset(&self, filter: &AuditEventFilter, cursor: Option<&Cursor>, limit: i64) -> Result<KeysetPage<AuditEvent>, AppError>;
+    /// Every event targeting the user, oldest first. Events where the user only acted
+    /// on someone else are left out: their details belong to that other user.
+    async fn find_by_subject(&self, user_id: Uuid) -> Result<Vec<AuditEvent>, AppError>;
+    async fn count(&self, filter: &AuditEventFilter) -> Result<i64, AppError>;
+}
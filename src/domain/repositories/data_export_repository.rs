@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use super::super::entities::data_export::DataExport;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait DataExportRepository: Send + Sync {
+    async fn create(&self, user_id: Uuid) -> Result<DataExport, AppError>;
+    async fn find_for_user(&self, id: Uuid, user_id: Uuid) -> Result<Option<DataExport>, AppError>;
+    async fn find_all_for_user(&self, user_id: Uuid) -> Result<Vec<DataExport>, AppError>;
+    /// Atomically moves the oldest pending export to `Processing`, safe across instances
+    async fn claim_next_pending(&self) -> Result<Option<DataExport>, AppError>;
+    async fn mark_ready(&self, id: Uuid, file_path: &str, expires_at: DateTime<Utc>) -> Result<(), AppError>;
+    async fn mark_failed(&self, id: Uuid, error: &str) -> Result<(), AppError>;
+    /// Ready exports whose download window has closed
+    async fn find_expired(&self) -> Result<Vec<DataExport>, AppError>;
+    async fn mark_expired(&self, id: Uuid) -> Result<(), AppError>;
+    /// Removes every export of the user, returning the rows so their files can be deleted
+    async fn delete_for_user(&self, user_id: Uuid) -> Result<Vec<DataExport>, AppError>;
+}
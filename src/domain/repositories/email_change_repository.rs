@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use super::super::entities::email_change::EmailChange;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait EmailChangeRepository: Send + Sync {
+    async fn create(&self, change: &EmailChange) -> Result<EmailChange, AppError>;
+    /// Unconfirmed, unreverted and unexpired change for this confirmation token
+    async fn find_confirmable(&self, confirm_token_hash: &str) -> Result<Option<EmailChange>, AppError>;
+    /// Unreverted change whose revert window is still open
+    async fn find_revertable(&self, revert_token_hash: &str) -> Result<Option<EmailChange>, AppError>;
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<EmailChange>, AppError>;
+    async fn mark_confirmed(&self, id: Uuid) -> Result<(), AppError>;
+    /// Marks `change` and every later change of the same user reverted, so no
+    /// link from a later change can undo the revert
+    async fn mark_reverted_since(&self, change: &EmailChange) -> Result<(), AppError>;
+    /// Drops any still-unconfirmed request so only the latest link works
+    async fn delete_pending_for_user(&self, user_id: Uuid) -> Result<(), AppError>;
+    /// Drops the full history, old and new addresses included; used on erasure
+    async fn delete_all_for_user(&self, user_id: Uuid) -> Result<(), AppError>;
+}
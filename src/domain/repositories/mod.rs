@@ -1 +1,9 @@
 pub mod user_repository;
+pub mod audit_repository;
+pub mod pagination;
+pub mod email_change_repository;
+pub mod data_export_repository;
+pub mod phone_verification_repository;
+pub mod provider_token_repository;
+pub mod oauth_exchange_code_repository;
+pub mod session_repository;
@@ -0,0 +1,12 @@
+use async_trait::async_trait;
+use super::super::entities::oauth_exchange_code::OAuthExchangeCode;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait OAuthExchangeCodeRepository: Send + Sync {
+    async fn create(&self, code: &OAuthExchangeCode) -> Result<(), AppError>;
+    /// Deletes and returns the unexpired code, so it can only be redeemed once
+    async fn consume(&self, code_hash: &str) -> Result<Option<OAuthExchangeCode>, AppError>;
+    /// Drops codes that expired without being redeemed
+    async fn delete_expired(&self) -> Result<(), AppError>;
+}
@@ -0,0 +1,160 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::infrastructure::errors::AppError;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+/// Deeper offset pages cost a scan of every row before them; use a cursor instead
+pub const MAX_PAGE: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+
+    pub fn reverse(&self) -> Self {
+        match self {
+            SortDirection::Asc => SortDirection::Desc,
+            SortDirection::Desc => SortDirection::Asc,
+        }
+    }
+}
+
+/// 1-based page number and page size, clamped to sane bounds
+#[derive(Debug, Clone, Copy)]
+pub struct PageParams {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl PageParams {
+    pub fn new(page: Option<i64>, per_page: Option<i64>) -> Self {
+        Self {
+            page: page.unwrap_or(1).clamp(1, MAX_PAGE),
+            per_page: per_page.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+}
+
+/// Clamps a keyset page size to the same bounds as offset pages
+pub fn keyset_limit(per_page: Option<i64>) -> i64 {
+    per_page.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Sort-key value captured in a cursor; tagged so timestamps never decode as text
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "t", content = "v", rename_all = "lowercase")]
+pub enum CursorKey {
+    Timestamp(DateTime<Utc>),
+    Text(String),
+}
+
+/// Opaque keyset position: the sort key and id of the row at a page boundary.
+/// Clients only ever see it as a base64url string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Name of the sort field the cursor was produced for
+    pub sort: String,
+    pub order: SortDirection,
+    pub key: CursorKey,
+    pub id: Uuid,
+    /// `true` when walking towards the start of the result set (prev page)
+    pub backward: bool,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(value: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::ValidationError("Invalid cursor".to_string()))
+    }
+
+    /// Decodes an optional cursor and rejects one minted for a different ordering
+    pub fn decode_for(value: Option<&str>, sort: &str, order: SortDirection) -> Result<Option<Self>, AppError> {
+        let Some(value) = value.filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        let cursor = Self::decode(value)?;
+        if cursor.sort != sort || cursor.order != order {
+            return Err(AppError::ValidationError("Cursor does not match the requested sort order".to_string()));
+        }
+        Ok(Some(cursor))
+    }
+}
+
+/// One page of a keyset-paginated listing
+#[derive(Debug)]
+pub struct KeysetPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> KeysetPage<T> {
+    /// Builds a page from rows fetched with `limit + 1` in query order.
+    /// The extra row only signals that another page exists in the walking direction.
+    pub fn from_rows(
+        mut rows: Vec<T>,
+        limit: i64,
+        sort: &str,
+        order: SortDirection,
+        cursor: Option<&Cursor>,
+        key_of: impl Fn(&T) -> (CursorKey, Uuid),
+    ) -> Self {
+        let backward = cursor.is_some_and(|c| c.backward);
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit.max(0) as usize);
+        if backward {
+            rows.reverse();
+        }
+
+        let make = |row: Option<&T>, backward: bool| {
+            row.map(|row| {
+                let (key, id) = key_of(row);
+                Cursor { sort: sort.to_string(), order, key, id, backward }.encode()
+            })
+        };
+
+        // Forward: more rows ahead means a next page; having a cursor means a prev page.
+        // Backward: the mirror image.
+        let (has_next, has_prev) = if backward {
+            (true, has_more)
+        } else {
+            (has_more, cursor.is_some())
+        };
+
+        Self {
+            next_cursor: if has_next { make(rows.last(), false) } else { None },
+            prev_cursor: if has_prev { make(rows.first(), true) } else { None },
+            items: rows,
+        }
+    }
+}
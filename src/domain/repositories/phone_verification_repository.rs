@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use super::super::entities::phone_verification::PhoneVerification;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait PhoneVerificationRepository: Send + Sync {
+    async fn create(&self, verification: &PhoneVerification) -> Result<PhoneVerification, AppError>;
+    /// Most recent unverified code of the user, expired or not
+    async fn find_latest_pending(&self, user_id: Uuid) -> Result<Option<PhoneVerification>, AppError>;
+    /// Uses up one attempt at the code; false once `max_attempts` have been used
+    async fn increment_attempts(&self, id: Uuid, max_attempts: i32) -> Result<bool, AppError>;
+    async fn mark_verified(&self, id: Uuid) -> Result<(), AppError>;
+    /// Drops unverified codes so only the latest one works
+    async fn delete_pending_for_user(&self, user_id: Uuid) -> Result<(), AppError>;
+}
@@ -0,0 +1,23 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+use super::super::entities::provider_token::ProviderToken;
+use super::super::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait ProviderTokenRepository: Send + Sync {
+    /// Replaces the user's tokens for the provider
+    async fn upsert(&self, token: &ProviderToken) -> Result<(), AppError>;
+    async fn find(&self, user_id: Uuid, provider: AuthProvider) -> Result<Option<ProviderToken>, AppError>;
+    async fn delete(&self, user_id: Uuid, provider: AuthProvider) -> Result<(), AppError>;
+    /// Rows whose data key is wrapped by a master key other than `key_id`
+    async fn find_not_wrapped_by(&self, key_id: &str, limit: i64) -> Result<Vec<ProviderToken>, AppError>;
+    /// Stores a re-wrapped data key, unless the row changed since `token` was read;
+    /// returns whether it was updated
+    async fn update_wrapped_key(
+        &self,
+        token: &ProviderToken,
+        new_key_id: &str,
+        wrapped_data_key: &[u8],
+    ) -> Result<bool, AppError>;
+}
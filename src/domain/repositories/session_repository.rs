@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use super::super::entities::session::Session;
+use crate::infrastructure::errors::AppError;
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn create(&self, session: &Session) -> Result<Session, AppError>;
+    /// Records a refresh of the user's session from this address and device;
+    /// returns whether the session still exists
+    async fn touch(&self, id: Uuid, user_id: Uuid, ip_address: Option<&str>, user_agent: Option<&str>) -> Result<bool, AppError>;
+    async fn find_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>, AppError>;
+    /// Every session of the user, expired ones included, most recently seen first
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Session>, AppError>;
+    /// Sessions seen since `seen_since`, most recently seen first
+    async fn find_active_by_user(&self, user_id: Uuid, seen_since: DateTime<Utc>) -> Result<Vec<Session>, AppError>;
+    /// Revokes one of the user's sessions; returns whether it existed
+    async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<bool, AppError>;
+    /// Signs the user out everywhere but `except`; returns how many sessions ended
+    async fn delete_all_for_user(&self, user_id: Uuid, except: Option<Uuid>) -> Result<u64, AppError>;
+    /// Drops the user's sessions whose refresh tokens have expired
+    async fn delete_stale_for_user(&self, user_id: Uuid, seen_before: DateTime<Utc>) -> Result<(), AppError>;
+}
@@ -1,19 +1,125 @@
 use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use super::super::entities::user::User;
+use super::super::entities::user::{AuthProvider, Role, User, UserStatus};
+use super::pagination::{Cursor, KeysetPage, PageParams, SortDirection};
 use crate::infrastructure::errors::AppError;
 
+/// Optional criteria for listing users; `None` fields are not filtered on
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UserFilter {
+    pub role: Option<Role>,
+    pub status: Option<UserStatus>,
+    pub provider: Option<AuthProvider>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    /// Case-insensitive substring match over name and email
+    pub search: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    Name,
+    Email,
+}
+
+impl UserSortField {
+    /// Field name as used in query strings, cursors and the `users` table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserSortField::CreatedAt => "created_at",
+            UserSortField::Name => "name",
+            UserSortField::Email => "email",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserSort {
+    pub field: UserSortField,
+    pub direction: SortDirection,
+}
+
+/// A user matched by `UserRepository::search`, best matches first.
+/// Highlights are HTML-escaped, with matched terms wrapped in `<mark>` tags.
+#[derive(Debug, sqlx::FromRow)]
+pub struct UserSearchHit {
+    #[sqlx(flatten)]
+    pub user: User,
+    pub rank: f32,
+    pub name_highlight: String,
+    pub email_highlight: String,
+    pub phone_highlight: Option<String>,
+}
+
+/// What the purge job does with soft-deleted users past the retention window
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PurgeMode {
+    /// Scrub personal data but keep the row for referential integrity
+    #[default]
+    Anonymize,
+    /// Remove the row entirely
+    Delete,
+}
+
+/// How `UserRepository::create_many` treats rows that fail to insert
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// A single failed row rolls back the whole batch
+    #[default]
+    AllOrNothing,
+    /// Failed rows are skipped, the rest are committed
+    BestEffort,
+}
+
+/// All lookups and listings exclude soft-deleted users unless stated otherwise.
 #[async_trait]
 pub trait UserRepository: Send + Sync {
     async fn create(&self, user: &User) -> Result<User, AppError>;
+    /// Inserts users in one transaction with a savepoint per row, returning one result per
+    /// input. With `rollback` set nothing is committed, whatever the outcome.
+    async fn create_many(&self, users: &[User], mode: ImportMode, rollback: bool) -> Result<Vec<Result<User, AppError>>, AppError>;
+    /// Case-insensitive, matching the unique index on `lower(email)`
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError>;
+    /// Active user holding `phone` (E.164), preferring the one who verified it
+    async fn find_by_phone(&self, phone: &str) -> Result<Option<User>, AppError>;
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, AppError>;
-    async fn find_all(&self) -> Result<Vec<User>, AppError>;
+    async fn find_page(&self, filter: &UserFilter, sort: UserSort, page: PageParams) -> Result<Vec<User>, AppError>;
+    async fn find_keyset(&self, filter: &UserFilter, sort: UserSort, cursor: Option<&Cursor>, limit: i64) -> Result<KeysetPage<User>, AppError>;
+    async fn count(&self, filter: &UserFilter) -> Result<i64, AppError>;
+    /// Every matching user in `sort` order, read row by row so memory stays bounded
+    fn stream(&self, filter: UserFilter, sort: UserSort) -> BoxStream<'static, Result<User, AppError>>;
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<UserSearchHit>, AppError>;
     async fn update(&self, id: Uuid, user: &User) -> Result<User, AppError>;
+    /// Soft delete: sets `deleted_at`
     async fn delete(&self, id: Uuid) -> Result<(), AppError>;
-    async fn update_status(&self, id: Uuid, status: crate::domain::entities::user::UserStatus) -> Result<User, AppError>;
-    async fn find_by_github_id(&self, github_id: i64) -> Result<Option<User>, AppError>;
-    async fn upsert_github_user(&self, user: &User) -> Result<User, AppError>;
-    async fn find_by_google_id(&self, google_id: &str) -> Result<Option<User>, AppError>;
-    async fn upsert_google_user(&self, user: &User) -> Result<User, AppError>;
+    /// Soft-deleted (but not yet purged) user
+    async fn find_deleted_by_id(&self, id: Uuid) -> Result<Option<User>, AppError>;
+    async fn restore(&self, id: Uuid) -> Result<User, AppError>;
+    /// Right-to-erasure: scrubs personal data immediately and marks the user deleted
+    async fn anonymize(&self, id: Uuid) -> Result<(), AppError>;
+    /// Purges users deleted before `cutoff`; returns the ids that were affected
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>, mode: PurgeMode) -> Result<Vec<Uuid>, AppError>;
+    async fn record_login(&self, id: Uuid) -> Result<(), AppError>;
+    async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<(), AppError>;
+    /// Sets or clears the avatar; `avatar_key` is the blob store prefix of a self-hosted one
+    async fn update_avatar(&self, id: Uuid, avatar_url: Option<&str>, avatar_key: Option<&str>) -> Result<User, AppError>;
+    /// Marks `phone` as verified, provided it is still the user's current number
+    async fn mark_phone_verified(&self, id: Uuid, phone: &str) -> Result<User, AppError>;
+    async fn update_status(&self, id: Uuid, status: UserStatus) -> Result<User, AppError>;
+    /// User linked to the given account at an OAuth provider
+    async fn find_by_identity(&self, provider: AuthProvider, provider_user_id: &str) -> Result<Option<User>, AppError>;
+    /// Links a provider account to an existing user; a provider avatar replaces the
+    /// current one unless the user has a self-hosted avatar
+    async fn link_identity(&self, id: Uuid, provider: AuthProvider, provider_user_id: &str, avatar_url: Option<&str>) -> Result<User, AppError>;
+    /// Creates a user signed up through a provider, or refreshes name and avatar if
+    /// a concurrent sign-in already created it
+    async fn upsert_identity_user(&self, provider: AuthProvider, provider_user_id: &str, user: &User) -> Result<User, AppError>;
 }
@@ -0,0 +1,61 @@
+use axum::{extract::{State, Query, OriginalUri}, response::IntoResponse};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::AppState;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::auth::middleware::AuthUser;
+use crate::domain::entities::audit_event::AuditAction;
+use crate::domain::repositories::audit_repository::AuditEventFilter;
+use crate::domain::repositories::pagination::{keyset_limit, PageParams};
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::usecases::audit::ListAuditEventsUseCase;
+use crate::utils::response::{paginated_response, Pagination};
+
+#[derive(serde::Deserialize)]
+pub struct AuditEventsQuery {
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub action: Option<AuditAction>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// Switches to keyset pagination; pass an empty value for the first page
+    pub cursor: Option<String>,
+}
+
+/// GET /api/v1/audit-events - List audit events (SuperAdmin only)
+pub async fn get_audit_events(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<AuditEventsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let filter = AuditEventFilter {
+        actor_id: query.actor_id,
+        target_id: query.target_id,
+        action: query.action,
+        from: query.from,
+        to: query.to,
+    };
+
+    let usecase = ListAuditEventsUseCase::new(state.audit_repository.clone());
+
+    if let Some(cursor) = query.cursor.as_deref() {
+        let limit = keyset_limit(query.per_page);
+        let page = usecase.execute_keyset(requester.role, filter, Some(cursor), limit).await?;
+        let pagination = Pagination::cursor(&page, limit, &uri);
+        return Ok(paginated_response(page.items, "success", pagination));
+    }
+
+    let page = PageParams::new(query.page, query.per_page);
+    let (events, total) = usecase.execute(requester.role, filter, page).await?;
+
+    Ok(paginated_response(events, "success", Pagination::new(total, page, &uri)))
+}
@@ -1,16 +1,29 @@
-use axum::{extract::{State, Query}, response::{IntoResponse, Redirect}, Json};
+use std::sync::Arc;
+use axum::{extract::{State, Query}, http::{header::SET_COOKIE, HeaderMap, HeaderValue}, response::{AppendHeaders, IntoResponse, Redirect, Response}, Extension, Form, Json};
+use reqwest::Url;
 use validator::Validate;
 use crate::infrastructure::errors::AppError;
-use crate::domain::dtos::RegisterUserDto;
-use crate::usecases::auth::{RegisterUseCase, LoginUseCase, RefreshTokenUseCase, GitHubCallbackUseCase, GoogleCallbackUseCase};
-use crate::utils::{response::success_response, validation::validate_request};
+use crate::domain::dtos::{AuthResponseDto, CookieSessionDto, OAuthProviderDto, RegisterUserDto};
+use crate::usecases::auth::{RegisterUseCase, LoginUseCase, RefreshTokenUseCase, OAuthCallbackUseCase, OAuthExchangeUseCase};
+use crate::infrastructure::auth::oauth::{provider_error, OAuthProvider};
+use crate::usecases::avatar::MirrorAvatarUseCase;
+use crate::usecases::provider_tokens::ProviderTokenStore;
+use crate::usecases::sessions::{RevokeSessionUseCase, SessionIssuer};
+use crate::infrastructure::repositories::postgres_provider_token_repository::PostgresProviderTokenRepository;
+use crate::infrastructure::repositories::postgres_session_repository::PostgresSessionRepository;
+use crate::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+use crate::infrastructure::storage::local_blob_store::LocalBlobStore;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::auth::token::generate_token;
+use crate::infrastructure::auth::session_cookies;
+use crate::utils::{cookie::{self, SameSite}, response::success_response, validation::validate_request};
 use crate::AppState;
 
 #[derive(serde::Deserialize, Validate)]
 pub struct RegisterRequest {
     #[validate(length(min = 1, message = "Name is required"))]
     pub name: String,
-    #[validate(length(min = 10, message = "Phone must be at least 10 characters"))]
+    /// Any common format; normalized to E.164 using `PHONE_DEFAULT_REGION`
     pub phone: Option<String>,
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
@@ -32,10 +45,28 @@ pub struct RefreshRequest {
 }
 
 #[derive(serde::Deserialize)]
-pub struct OAuthCallbackQuery {
+pub struct OAuthLoginQuery {
+    /// Frontend page to send the browser back to, on an `OAUTH_REDIRECT_ORIGINS` origin
+    pub redirect_to: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct OAuthExchangeRequest {
     pub code: String,
 }
 
+#[derive(serde::Deserialize)]
+pub struct OAuthCallbackQuery {
+    pub code: Option<String>,
+    /// Set instead of `code` when the provider refused, e.g. `access_denied`
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+    /// Echo of the state `/auth/{provider}` sent
+    pub state: Option<String>,
+    /// Sign in with Apple: the user's name, as JSON, on their first sign-in only
+    pub user: Option<String>,
+}
+
 pub async fn sign_up(
     State(state): State<AppState>,
     Json(payload): Json<RegisterRequest>,
@@ -49,7 +80,7 @@ pub async fn sign_up(
         password: payload.password,
     };
 
-    let usecase = RegisterUseCase::new(state.user_repository.clone());
+    let usecase = RegisterUseCase::new(state.user_repository.clone(), state.phone_policy.clone());
     let user = usecase.execute(dto).await?;
 
     Ok(success_response(user, "User registered successfully"))
@@ -57,70 +88,335 @@ pub async fn sign_up(
 
 pub async fn sign_in(
     State(state): State<AppState>,
+    ctx: RequestContext,
     Json(payload): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     validate_request(&payload)?;
 
-    let usecase = LoginUseCase::new(state.user_repository.clone(), state.jwt_service.clone());
-    let tokens = usecase.execute(&payload.email, &payload.password).await?;
+    let usecase = LoginUseCase::new(
+        state.user_repository.clone(),
+        state.audit_repository.clone(),
+        state.session_repository.clone(),
+        state.jwt_service.clone(),
+    );
+    let tokens = usecase.execute(&payload.email, &payload.password, &ctx).await?;
 
-    Ok(success_response(tokens, "success"))
+    Ok(session_response(&state, tokens, "success"))
 }
 
+/// Bearer clients send the refresh token in the body and get the new tokens back.
+/// In session cookie mode a request without a body uses the refresh token cookie
+/// and, like any cookie-authenticated POST, needs the CSRF token.
 pub async fn refresh(
     State(state): State<AppState>,
-    Json(payload): Json<RefreshRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    let usecase = RefreshTokenUseCase::new(state.user_repository.clone(), state.jwt_service.clone());
-    let tokens = usecase.execute(&payload.refresh_token).await?;
+    ctx: RequestContext,
+    headers: HeaderMap,
+    payload: Option<Json<RefreshRequest>>,
+) -> Result<Response, AppError> {
+    let usecase = RefreshTokenUseCase::new(
+        state.user_repository.clone(),
+        state.audit_repository.clone(),
+        state.session_repository.clone(),
+        state.jwt_service.clone(),
+    );
+
+    if let Some(Json(payload)) = payload {
+        let tokens = usecase.execute(&payload.refresh_token, &ctx).await?;
+        return Ok(success_response(tokens, "success").into_response());
+    }
 
-    Ok(success_response(tokens, "success"))
+    if state.session_cookies.is_none() {
+        return Err(AppError::ValidationError("refresh_token is required".to_string()));
+    }
+    let refresh_token = session_cookies::refresh_token(&headers).ok_or(AppError::InvalidToken)?;
+    let access_token = session_cookies::access_token(&headers).ok_or(AppError::CsrfTokenInvalid)?;
+    session_cookies::verify_csrf(&state.jwt_service, &headers, access_token)?;
+
+    let tokens = usecase.execute(refresh_token, &ctx).await?;
+    Ok(session_response(&state, tokens, "success"))
 }
 
-/// Redirects the user to GitHub's authorization page
-pub async fn github_login(
+/// POST /api/v1/auth/sign-out - Ends the session of the refresh token, sent in
+/// the body or (in session cookie mode) the cookie, and deletes the session cookies
+pub async fn sign_out(
     State(state): State<AppState>,
-) -> Redirect {
-    let authorize_url = state.github_oauth.get_authorize_url();
-    Redirect::temporary(&authorize_url)
+    ctx: RequestContext,
+    headers: HeaderMap,
+    payload: Option<Json<RefreshRequest>>,
+) -> Result<Response, AppError> {
+    let refresh_token = match &payload {
+        Some(Json(payload)) => Some(payload.refresh_token.as_str()),
+        None if state.session_cookies.is_some() => {
+            if let Some(access_token) = session_cookies::access_token(&headers) {
+                session_cookies::verify_csrf(&state.jwt_service, &headers, access_token)?;
+            }
+            session_cookies::refresh_token(&headers)
+        }
+        None => None,
+    };
+
+    // An expired or already revoked session is as good as signed out
+    let session = refresh_token
+        .and_then(|token| state.jwt_service.verify_token(token).ok())
+        .filter(|token| token.claims.token_type == "refresh")
+        .and_then(|token| Some((token.claims.sub, token.claims.sid?)));
+    if let Some((user_id, session_id)) = session {
+        let usecase = RevokeSessionUseCase::new(
+            state.user_repository.clone(),
+            state.session_repository.clone(),
+            state.audit_repository.clone(),
+        );
+        match usecase.execute(user_id, session_id, &ctx).await {
+            Ok(()) | Err(AppError::SessionNotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let Some(cookies) = &state.session_cookies else {
+        return Ok(success_response((), "Signed out").into_response());
+    };
+    Ok((
+        AppendHeaders(cookies.clear().map(|cookie| (SET_COOKIE, cookie))),
+        success_response((), "Signed out"),
+    ).into_response())
+}
+
+/// The tokens in the body, or in session cookie mode only in HttpOnly cookies,
+/// with the CSRF token the frontend must echo in the body instead
+fn session_response(state: &AppState, tokens: AuthResponseDto, message: impl Into<String>) -> Response {
+    let Some(cookies) = &state.session_cookies else {
+        return success_response(tokens, message).into_response();
+    };
+
+    let csrf_token = state.jwt_service.csrf_token(&tokens.access_token);
+    let set_cookies = cookies.set(&tokens.access_token, &tokens.refresh_token, &csrf_token);
+    let session = CookieSessionDto {
+        token_type: "Cookie".to_string(),
+        expires_in: tokens.expires_in,
+        csrf_token,
+    };
+
+    (AppendHeaders(set_cookies.map(|cookie| (SET_COOKIE, cookie))), success_response(session, message)).into_response()
 }
 
-/// Handles the GitHub OAuth callback
-pub async fn github_callback(
+/// Copies provider avatars into our blob store when `MIRROR_OAUTH_AVATARS` is enabled
+fn avatar_mirror(state: &AppState) -> Option<MirrorAvatarUseCase<PostgresUserRepository, LocalBlobStore>> {
+    state.mirror_oauth_avatars.then(|| {
+        MirrorAvatarUseCase::new(state.user_repository.clone(), state.blob_store.clone(), state.app_base_url.clone())
+    })
+}
+
+fn session_issuer(state: &AppState) -> SessionIssuer<PostgresSessionRepository> {
+    SessionIssuer::new(state.session_repository.clone(), state.jwt_service.clone())
+}
+
+/// Keeps provider tokens for later API calls when `PROVIDER_TOKEN_KEYS` is set
+fn provider_token_store(state: &AppState) -> Option<ProviderTokenStore<PostgresProviderTokenRepository>> {
+    state.token_cipher.as_ref().map(|cipher| {
+        ProviderTokenStore::new(state.provider_token_repository.clone(), cipher.clone(), state.oauth_providers.clone())
+    })
+}
+
+/// GET /api/v1/auth/providers - Enabled OAuth providers, for rendering login buttons
+pub async fn get_oauth_providers(
     State(state): State<AppState>,
-    Query(query): Query<OAuthCallbackQuery>,
+) -> impl IntoResponse {
+    let providers: Vec<OAuthProviderDto> = state.oauth_providers
+        .iter()
+        .map(|provider| OAuthProviderDto {
+            name: provider.provider(),
+            display_name: provider.display_name().to_string(),
+            login_url: format!("{}/api/v1/auth/{}", state.app_base_url, provider.provider().as_str()),
+        })
+        .collect();
+
+    success_response(providers, "success")
+}
+
+/// Cookie binding a sign-in to the browser that started it
+const OAUTH_NONCE_COOKIE: &str = "oauth_nonce";
+const OAUTH_NONCE_TTL_SECS: i64 = 10 * 60;
+
+/// Scopes the nonce cookie to the provider's login and callback routes
+fn oauth_cookie_path(provider: &dyn OAuthProvider) -> String {
+    format!("/api/v1/auth/{}", provider.provider().as_str())
+}
+
+/// A provider that POSTs the callback from its own site only gets the cookie back with SameSite=None
+fn oauth_nonce_cookie(state: &AppState, provider: &dyn OAuthProvider, value: &str, max_age_secs: i64) -> HeaderValue {
+    let same_site = if provider.uses_form_post() { SameSite::None } else { SameSite::Lax };
+    cookie::set(
+        OAUTH_NONCE_COOKIE,
+        value,
+        &oauth_cookie_path(provider),
+        max_age_secs,
+        state.app_base_url.starts_with("https://"),
+        same_site,
+    )
+}
+
+/// `redirect_to` as a URL, if it is on an allowlisted frontend origin
+fn allowed_redirect(state: &AppState, redirect_to: &str) -> Result<Url, AppError> {
+    let url = Url::parse(redirect_to)
+        .map_err(|_| AppError::ValidationError("redirect_to must be an absolute URL".to_string()))?;
+    let origin = url.origin().ascii_serialization();
+    let allowed = state.oauth_redirect_origins
+        .iter()
+        .any(|allowed| Url::parse(allowed).is_ok_and(|allowed| allowed.origin().ascii_serialization() == origin));
+    if !allowed {
+        return Err(AppError::ValidationError("redirect_to is not on an allowed origin".to_string()));
+    }
+    Ok(url)
+}
+
+/// Redirects the user to the provider's authorization page.
+/// Mounted once per enabled provider, which is passed in as an extension.
+pub async fn oauth_login(
+    State(state): State<AppState>,
+    Extension(provider): Extension<Arc<dyn OAuthProvider>>,
+    Query(query): Query<OAuthLoginQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    let usecase = GitHubCallbackUseCase::new(
+    if let Some(redirect_to) = &query.redirect_to {
+        allowed_redirect(&state, redirect_to)?;
+    }
+
+    let nonce = generate_token();
+    let oauth_state = state.jwt_service.oauth_state(&nonce, query.redirect_to.as_deref());
+    let cookie = oauth_nonce_cookie(&state, provider.as_ref(), &nonce, OAUTH_NONCE_TTL_SECS);
+
+    Ok(([(SET_COOKIE, cookie)], Redirect::temporary(&provider.authorize_url(&oauth_state, &nonce))))
+}
+
+/// Handles the provider's OAuth callback
+pub async fn oauth_callback(
+    State(state): State<AppState>,
+    Extension(provider): Extension<Arc<dyn OAuthProvider>>,
+    ctx: RequestContext,
+    headers: HeaderMap,
+    Query(query): Query<OAuthCallbackQuery>,
+) -> Result<Response, AppError> {
+    complete_oauth_sign_in(state, provider, ctx, headers, query).await
+}
+
+/// Handles the callback of providers that POST it as a form (`response_mode=form_post`)
+pub async fn oauth_callback_form(
+    State(state): State<AppState>,
+    Extension(provider): Extension<Arc<dyn OAuthProvider>>,
+    ctx: RequestContext,
+    headers: HeaderMap,
+    Form(query): Form<OAuthCallbackQuery>,
+) -> Result<Response, AppError> {
+    complete_oauth_sign_in(state, provider, ctx, headers, query).await
+}
+
+/// Signs the user in, then either answers with the session (JSON), or when the
+/// sign-in was started with `redirect_to`, redirects there with `?code=` for
+/// `/auth/exchange` or `?error=`
+async fn complete_oauth_sign_in(
+    state: AppState,
+    provider: Arc<dyn OAuthProvider>,
+    ctx: RequestContext,
+    headers: HeaderMap,
+    query: OAuthCallbackQuery,
+) -> Result<Response, AppError> {
+    // The nonce is single-use
+    let clear_nonce = AppendHeaders([(SET_COOKIE, oauth_nonce_cookie(&state, provider.as_ref(), "", 0))]);
+
+    // The state must have been issued to this browser, which stops sign-ins it did
+    // not start. Without it there is no trusted place to redirect to either.
+    let nonce = cookie::get(&headers, OAUTH_NONCE_COOKIE).ok_or(AppError::OAuthStateInvalid)?;
+    let oauth_state = query.state.as_deref().ok_or(AppError::OAuthStateInvalid)?;
+    let redirect_to = state.jwt_service
+        .verify_oauth_state(nonce, oauth_state)?
+        .map(|redirect_to| allowed_redirect(&state, &redirect_to))
+        .transpose()?;
+
+    let name_hint = query.user.as_deref().and_then(|user| provider.callback_name(user));
+    let display_name = provider.display_name();
+    let provider_kind = provider.provider();
+    let usecase = OAuthCallbackUseCase::new(
         state.user_repository.clone(),
+        state.audit_repository.clone(),
+        provider,
+        avatar_mirror(&state),
+        provider_token_store(&state),
+    );
+
+    let Some(mut redirect_to) = redirect_to else {
+        let code = callback_code(display_name, &query)?;
+        let user = usecase.execute(code, Some(nonce), name_hint, &ctx).await?;
+        let tokens = session_issuer(&state).start(&user, provider_kind.as_str(), &ctx).await?;
+        let message = format!("{} login successful", display_name);
+        return Ok((clear_nonce, session_response(&state, tokens, message)).into_response());
+    };
+
+    let exchange = OAuthExchangeUseCase::new(
+        state.user_repository.clone(),
+        state.oauth_exchange_code_repository.clone(),
+        state.session_repository.clone(),
         state.jwt_service.clone(),
-        state.github_oauth.clone(),
     );
+    let signed_in = async {
+        let code = callback_code(display_name, &query)?;
+        let user = usecase.execute(code, Some(nonce), name_hint, &ctx).await?;
+        exchange.issue(&user, provider_kind).await
+    }.await;
 
-    let tokens = usecase.execute(&query.code).await?;
+    match signed_in {
+        Ok(code) => {
+            redirect_to.query_pairs_mut().append_pair("code", &code);
+        }
+        Err(e) => {
+            tracing::warn!("{} sign-in failed: {:?}", display_name, e);
+            let mut params = redirect_to.query_pairs_mut();
+            params.append_pair("error", redirect_error_code(&e));
+            // Our own, user-facing explanation, e.g. an unverified email
+            if let AppError::OAuthError(description) = &e {
+                params.append_pair("error_description", description);
+            }
+        }
+    }
 
-    Ok(success_response(tokens, "GitHub login successful"))
+    // 303 so a form_post callback is followed with a GET
+    Ok((clear_nonce, Redirect::to(redirect_to.as_str())).into_response())
 }
 
-/// Redirects the user to Google's authorization page
-pub async fn google_login(
-    State(state): State<AppState>,
-) -> Redirect {
-    let authorize_url = state.google_oauth.get_authorize_url();
-    Redirect::temporary(&authorize_url)
+/// The authorization code, unless the provider redirected back with an error instead
+fn callback_code<'a>(display_name: &str, query: &'a OAuthCallbackQuery) -> Result<&'a str, AppError> {
+    if let Some(error) = &query.error {
+        return Err(provider_error(display_name, error, query.error_description.as_deref()));
+    }
+    query.code
+        .as_deref()
+        .ok_or_else(|| AppError::ValidationError("Missing authorization code".to_string()))
+}
+
+/// The `?error=` a failed sign-in redirects back to the frontend with
+fn redirect_error_code(error: &AppError) -> &'static str {
+    match error {
+        AppError::OAuthAccessDenied => "access_denied",
+        AppError::OAuthInvalidCode => "invalid_code",
+        AppError::ValidationError(_) => "invalid_request",
+        AppError::OAuthError(_) | AppError::IdentityAlreadyLinked => "sign_in_failed",
+        AppError::OAuthProviderUnavailable(_) | AppError::OAuthProviderTimeout(_) => "provider_unavailable",
+        _ => "server_error",
+    }
 }
 
-/// Handles the Google OAuth callback
-pub async fn google_callback(
+/// POST /api/v1/auth/exchange - Swaps the one-time code an OAuth sign-in
+/// redirected to the frontend with for a session
+pub async fn oauth_exchange(
     State(state): State<AppState>,
-    Query(query): Query<OAuthCallbackQuery>,
-) -> Result<impl IntoResponse, AppError> {
-    let usecase = GoogleCallbackUseCase::new(
+    ctx: RequestContext,
+    Json(payload): Json<OAuthExchangeRequest>,
+) -> Result<Response, AppError> {
+    let usecase = OAuthExchangeUseCase::new(
         state.user_repository.clone(),
+        state.oauth_exchange_code_repository.clone(),
+        state.session_repository.clone(),
         state.jwt_service.clone(),
-        state.google_oauth.clone(),
     );
+    let tokens = usecase.redeem(&payload.code, &ctx).await?;
 
-    let tokens = usecase.execute(&query.code).await?;
-
-    Ok(success_response(tokens, "Google login successful"))
+    Ok(session_response(&state, tokens, "success"))
 }
@@ -0,0 +1,92 @@
+use axum::{
+    extract::{Multipart, State, Path},
+    http::header,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+use crate::AppState;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::auth::middleware::AuthUser;
+use crate::infrastructure::images::AVATAR_SIZES;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::storage::{is_valid_key, BlobStore};
+use crate::usecases::avatar::{avatar_dir, UploadAvatarUseCase, RemoveAvatarUseCase};
+use crate::utils::response::success_response;
+
+const AVATAR_CONTENT_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+/// PUT /api/v1/me/avatar - multipart upload, file in the `avatar` field
+pub async fn upload_avatar(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    let invalid_body = |_| AppError::ValidationError("Invalid multipart body".to_string());
+
+    let mut bytes = None;
+    while let Some(field) = multipart.next_field().await.map_err(invalid_body)? {
+        if field.name() != Some("avatar") {
+            continue;
+        }
+        // Only a first filter: the image format is sniffed again from the bytes
+        if !field.content_type().is_some_and(|ct| AVATAR_CONTENT_TYPES.contains(&ct)) {
+            return Err(AppError::ValidationError("Avatar must be a JPEG, PNG or WebP image".to_string()));
+        }
+        bytes = Some(field.bytes().await.map_err(invalid_body)?.to_vec());
+        break;
+    }
+    let bytes = bytes.ok_or_else(|| AppError::ValidationError("Missing avatar file".to_string()))?;
+
+    let usecase = UploadAvatarUseCase::new(
+        state.user_repository.clone(),
+        state.audit_repository.clone(),
+        state.blob_store.clone(),
+        state.app_base_url.clone(),
+    );
+    let avatar = usecase.execute(auth_user.claims.claims.sub, bytes, &ctx).await?;
+
+    Ok(success_response(avatar, "Avatar updated successfully"))
+}
+
+/// DELETE /api/v1/me/avatar - Remove the avatar
+pub async fn delete_avatar(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, AppError> {
+    let usecase = RemoveAvatarUseCase::new(
+        state.user_repository.clone(),
+        state.audit_repository.clone(),
+        state.blob_store.clone(),
+    );
+    usecase.execute(auth_user.claims.claims.sub, &ctx).await?;
+
+    Ok(success_response((), "Avatar removed successfully"))
+}
+
+/// GET /api/v1/avatars/{user_id}/{version}/{size}.jpg - Public, immutable avatar files
+pub async fn get_avatar(
+    State(state): State<AppState>,
+    Path((user_id, version, file)): Path<(Uuid, String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let known_size = file
+        .strip_suffix(".jpg")
+        .and_then(|size| size.parse::<u32>().ok())
+        .is_some_and(|size| AVATAR_SIZES.contains(&size));
+    let key = format!("{}/{}/{}", avatar_dir(user_id), version, file);
+    if !known_size || !is_valid_key(&key) {
+        return Err(AppError::FileNotFound);
+    }
+
+    let bytes = state.blob_store.get(&key).await?.ok_or(AppError::FileNotFound)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg"),
+            // Every upload gets a new version, so a given URL never changes
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        bytes,
+    ))
+}
@@ -0,0 +1,97 @@
+use axum::{
+    extract::{State, Path},
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use uuid::Uuid;
+use crate::AppState;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::auth::middleware::AuthUser;
+use crate::infrastructure::request_context::RequestContext;
+use crate::usecases::data_privacy::{RequestDataExportUseCase, GetDataExportUseCase, EraseAccountUseCase};
+use crate::utils::response::success_response;
+
+#[derive(serde::Deserialize, Default)]
+pub struct EraseAccountRequest {
+    pub password: Option<String>,
+}
+
+/// POST /api/v1/me/data-exports - Queue a JSON archive of everything held about the user
+pub async fn request_data_export(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let usecase = RequestDataExportUseCase::new(state.data_export_repository.clone(), state.audit_repository.clone());
+    let export = usecase.execute(auth_user.claims.claims.sub, &ctx).await?;
+
+    Ok((StatusCode::ACCEPTED, success_response(export, "Data export queued")))
+}
+
+/// GET /api/v1/me/data-exports - Own exports, newest first
+pub async fn get_data_exports(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    let usecase = GetDataExportUseCase::new(state.data_export_repository.clone());
+    let exports = usecase.execute_all(auth_user.claims.claims.sub).await?;
+
+    Ok(success_response(exports, "success"))
+}
+
+/// GET /api/v1/me/data-exports/{id} - Poll export status
+pub async fn get_data_export(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let usecase = GetDataExportUseCase::new(state.data_export_repository.clone());
+    let export = usecase.execute(auth_user.claims.claims.sub, id).await?;
+
+    Ok(success_response(export, "success"))
+}
+
+/// GET /api/v1/me/data-exports/{id}/download - The archive itself (not allowed while impersonating)
+pub async fn download_data_export(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let usecase = GetDataExportUseCase::new(state.data_export_repository.clone());
+    let body = usecase.download(auth_user.claims.claims.sub, id).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"data-export-{}.json\"", id)),
+        ],
+        body,
+    ))
+}
+
+/// POST /api/v1/me/erasure - Anonymize the account permanently (not allowed while impersonating)
+pub async fn erase_me(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    payload: Option<axum::Json<EraseAccountRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let payload = payload.map(|axum::Json(p)| p).unwrap_or_default();
+
+    let usecase = EraseAccountUseCase::new(
+        state.user_repository.clone(),
+        state.data_export_repository.clone(),
+        state.email_change_repository.clone(),
+        state.audit_repository.clone(),
+        state.blob_store.clone(),
+    );
+    usecase.execute(auth_user.claims.claims.sub, payload.password, &ctx).await?;
+
+    Ok(success_response((), "Account erased successfully"))
+}
@@ -1,3 +1,8 @@
+pub mod audit;
 pub mod auth;
+pub mod avatars;
+pub mod data_privacy;
+pub mod profile;
+pub mod sessions;
 pub mod users;
 pub mod user_management;
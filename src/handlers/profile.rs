@@ -0,0 +1,281 @@
+use axum::{
+    extract::{Form, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use validator::Validate;
+use crate::AppState;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::auth::middleware::AuthUser;
+use crate::infrastructure::request_context::RequestContext;
+use crate::domain::dtos::{ChangePasswordDto, ConfirmPhoneVerificationDto, RequestEmailChangeDto, UpdateProfileDto};
+use crate::usecases::profile::{
+    GetProfileUseCase, UpdateProfileUseCase, ChangePasswordUseCase, DeleteAccountUseCase,
+};
+use crate::usecases::email_change::{
+    RequestEmailChangeUseCase, ConfirmEmailChangeUseCase, RevertEmailChangeUseCase,
+};
+use crate::usecases::phone::{RequestPhoneVerificationUseCase, ConfirmPhoneVerificationUseCase};
+use crate::utils::{html, response::success_response, validation::validate_request};
+
+#[derive(serde::Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    /// Any common format; normalized to E.164, blank clears it
+    pub phone: Option<String>,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub new_email: String,
+    pub current_password: Option<String>,
+}
+
+#[derive(serde::Deserialize, Validate)]
+pub struct ConfirmPhoneRequest {
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EmailChangeTokenQuery {
+    pub token: String,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct DeleteAccountRequest {
+    pub password: Option<String>,
+}
+
+/// GET /api/v1/me - Current user's profile
+pub async fn get_me(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    let usecase = GetProfileUseCase::new(state.user_repository.clone());
+    let user = usecase.execute(auth_user.claims.claims.sub).await?;
+
+    Ok(success_response(user, "success"))
+}
+
+/// PATCH /api/v1/me - Update name or phone; avatars are uploaded (PUT /me/avatar)
+pub async fn update_me(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    axum::Json(payload): axum::Json<UpdateProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    validate_request(&payload)?;
+
+    let dto = UpdateProfileDto {
+        name: payload.name,
+        phone: payload.phone,
+    };
+
+    let usecase = UpdateProfileUseCase::new(state.user_repository.clone(), state.audit_repository.clone(), state.phone_policy.clone());
+    let user = usecase.execute(auth_user.claims.claims.sub, dto, &ctx).await?;
+
+    Ok(success_response(user, "Profile updated successfully"))
+}
+
+/// PUT /api/v1/me/password - Change password and sign out other sessions (not allowed while impersonating)
+pub async fn change_my_password(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    axum::Json(payload): axum::Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+    validate_request(&payload)?;
+
+    let dto = ChangePasswordDto {
+        current_password: payload.current_password,
+        new_password: payload.new_password,
+    };
+
+    let usecase = ChangePasswordUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    usecase.execute(auth_user.claims.claims.sub, auth_user.session_id(), dto, &ctx).await?;
+
+    Ok(success_response((), "Password changed successfully"))
+}
+
+/// DELETE /api/v1/me - Delete own account (not allowed while impersonating)
+pub async fn delete_me(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    payload: Option<axum::Json<DeleteAccountRequest>>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let payload = payload.map(|axum::Json(p)| p).unwrap_or_default();
+
+    let usecase = DeleteAccountUseCase::new(state.user_repository.clone(), state.audit_repository.clone());
+    usecase.execute(auth_user.claims.claims.sub, payload.password, &ctx).await?;
+
+    Ok(success_response((), "Account deleted successfully"))
+}
+
+/// POST /api/v1/me/email - Start an email change (not allowed while impersonating).
+/// Users without a password must have signed in within the last few minutes.
+pub async fn request_email_change(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    axum::Json(payload): axum::Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+    validate_request(&payload)?;
+
+    let dto = RequestEmailChangeDto {
+        new_email: payload.new_email,
+        current_password: payload.current_password,
+    };
+
+    let usecase = RequestEmailChangeUseCase::new(
+        state.user_repository.clone(),
+        state.email_change_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+        state.mailer.clone(),
+        state.app_base_url.clone(),
+    );
+    usecase.execute(auth_user.claims.claims.sub, auth_user.session_id(), dto, &ctx).await?;
+
+    Ok(success_response((), "Confirmation link sent to the new email address"))
+}
+
+/// Page behind an emailed link. Opening it changes nothing: mail scanners follow
+/// links, so only its button, which posts the token back, acts on it.
+fn email_change_page(message: &str, form: Option<(&str, &str)>) -> Html<String> {
+    let form = form
+        .map(|(button, token)| format!(
+            "<form method=\"post\"><input type=\"hidden\" name=\"token\" value=\"{}\"><button type=\"submit\">{}</button></form>",
+            html::escape(token),
+            html::escape(button),
+        ))
+        .unwrap_or_default();
+    Html(format!(
+        "<!doctype html><meta charset=\"utf-8\"><meta name=\"referrer\" content=\"no-referrer\"><title>Email change</title><p>{}</p>{}",
+        html::escape(message),
+        form,
+    ))
+}
+
+/// Outcome of a posted link as a page; other errors keep their JSON response
+fn email_change_outcome(result: Result<(), AppError>, done: &str) -> Response {
+    match result {
+        Ok(()) => email_change_page(done, None).into_response(),
+        Err(AppError::InvalidToken) => {
+            (StatusCode::BAD_REQUEST, email_change_page("This link is invalid, has expired or was already used.", None)).into_response()
+        }
+        Err(AppError::EmailAlreadyExists) => {
+            (StatusCode::CONFLICT, email_change_page("That email address now belongs to another account.", None)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// GET /api/v1/auth/email-change/confirm?token= - Link sent to the new address
+pub async fn confirm_email_change_page(Query(query): Query<EmailChangeTokenQuery>) -> Html<String> {
+    email_change_page("Use this email address for your account?", Some(("Confirm email address", &query.token)))
+}
+
+/// POST /api/v1/auth/email-change/confirm - The confirmation page's button
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    Form(form): Form<EmailChangeTokenQuery>,
+) -> Response {
+    let usecase = ConfirmEmailChangeUseCase::new(
+        state.user_repository.clone(),
+        state.email_change_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    let result = usecase.execute(&form.token, &ctx).await.map(|_| ());
+
+    email_change_outcome(result, "Your email address has been changed.")
+}
+
+/// GET /api/v1/auth/email-change/revert?token= - Link sent to the old address
+pub async fn revert_email_change_page(Query(query): Query<EmailChangeTokenQuery>) -> Html<String> {
+    email_change_page(
+        "Undo the change of your account email? This restores your previous address and signs your account out on every device.",
+        Some(("Undo email change", &query.token)),
+    )
+}
+
+/// POST /api/v1/auth/email-change/revert - The revert page's button
+pub async fn revert_email_change(
+    State(state): State<AppState>,
+    ctx: RequestContext,
+    Form(form): Form<EmailChangeTokenQuery>,
+) -> Response {
+    let usecase = RevertEmailChangeUseCase::new(
+        state.user_repository.clone(),
+        state.email_change_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    let result = usecase.execute(&form.token, &ctx).await;
+
+    email_change_outcome(
+        result,
+        "The email change was undone and your account was signed out everywhere. Sign in again and consider changing your password.",
+    )
+}
+
+/// POST /api/v1/me/phone/verification - Text a one-time code to the current phone
+pub async fn request_phone_verification(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let usecase = RequestPhoneVerificationUseCase::new(
+        state.user_repository.clone(),
+        state.phone_verification_repository.clone(),
+        state.audit_repository.clone(),
+        state.sms_sender.clone(),
+    );
+    let sent = usecase.execute(auth_user.claims.claims.sub, &ctx).await?;
+
+    Ok(success_response(sent, "Verification code sent"))
+}
+
+/// POST /api/v1/me/phone/verification/confirm - Submit the code received by SMS
+pub async fn confirm_phone_verification(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    axum::Json(payload): axum::Json<ConfirmPhoneRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+    validate_request(&payload)?;
+
+    let dto = ConfirmPhoneVerificationDto { code: payload.code };
+
+    let usecase = ConfirmPhoneVerificationUseCase::new(
+        state.user_repository.clone(),
+        state.phone_verification_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    let user = usecase.execute(auth_user.claims.claims.sub, dto, &ctx).await?;
+
+    Ok(success_response(user, "Phone number verified"))
+}
@@ -0,0 +1,108 @@
+use axum::{
+    extract::{State, Path},
+    response::IntoResponse,
+};
+use uuid::Uuid;
+use crate::AppState;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::auth::middleware::AuthUser;
+use crate::infrastructure::request_context::RequestContext;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::usecases::sessions::{GetSessionsUseCase, RevokeSessionUseCase};
+use crate::utils::response::success_response;
+
+/// GET /api/v1/me/sessions - Where the user is signed in, most recently seen first
+pub async fn get_my_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<impl IntoResponse, AppError> {
+    let usecase = GetSessionsUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.jwt_service.clone(),
+    );
+    let sessions = usecase.execute(auth_user.claims.claims.sub, auth_user.session_id()).await?;
+
+    Ok(success_response(sessions, "success"))
+}
+
+/// DELETE /api/v1/me/sessions/{id} - Sign a device out (not allowed while impersonating)
+pub async fn revoke_my_session(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Path(session_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let usecase = RevokeSessionUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    usecase.execute(auth_user.claims.claims.sub, session_id, &ctx).await?;
+
+    Ok(success_response((), "Session revoked"))
+}
+
+/// DELETE /api/v1/me/sessions - Sign every other device out (not allowed while impersonating)
+pub async fn revoke_my_other_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, AppError> {
+    auth_user.ensure_not_impersonated()?;
+
+    let usecase = RevokeSessionUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    usecase.execute_others(auth_user.claims.claims.sub, auth_user.session_id(), &ctx).await?;
+
+    Ok(success_response((), "Other sessions revoked"))
+}
+
+/// GET /api/v1/users/{id}/sessions - A user's sessions (Admin + SuperAdmin)
+pub async fn get_user_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester = state.user_repository
+        .find_by_id(auth_user.claims.claims.sub)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = GetSessionsUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.jwt_service.clone(),
+    );
+    let sessions = usecase.execute_for_user(requester.role, user_id).await?;
+
+    Ok(success_response(sessions, "success"))
+}
+
+/// DELETE /api/v1/users/{id}/sessions/{session_id} - Sign a user's device out (Admin + SuperAdmin)
+pub async fn revoke_user_session(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Path((user_id, session_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = RevokeSessionUseCase::new(
+        state.user_repository.clone(),
+        state.session_repository.clone(),
+        state.audit_repository.clone(),
+    );
+    usecase.execute_for_user(requester_id, requester.role, user_id, session_id, &ctx).await?;
+
+    Ok(success_response((), "Session revoked"))
+}
@@ -1,13 +1,21 @@
-use axum::{extract::{State, Path}, response::IntoResponse};
+use axum::{
+    extract::{State, Path, Query},
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
 use validator::Validate;
 use uuid::Uuid;
 use crate::AppState;
 use crate::infrastructure::errors::AppError;
 use crate::infrastructure::auth::middleware::AuthUser;
-use crate::domain::repositories::user_repository::UserRepository;
-use crate::domain::dtos::{CreateUserDto, UpdateUserDto, UpdateUserStatusDto};
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::auth::token::generate_token;
+use crate::domain::entities::user::Role;
+use crate::domain::repositories::user_repository::{ImportMode, UserRepository};
+use crate::domain::dtos::{CreateUserDto, ImportUserRowDto, UpdateUserDto, UpdateUserStatusDto};
 use crate::usecases::user_management::{
-    CreateUserUseCase, UpdateUserUseCase, DeleteUserUseCase, UpdateUserStatusUseCase
+    CreateUserUseCase, UpdateUserUseCase, DeleteUserUseCase, UpdateUserStatusUseCase,
+    ImpersonateUserUseCase, RestoreUserUseCase, ImportUsersUseCase,
 };
 use crate::utils::{response::success_response, validation::validate_request};
 
@@ -23,6 +31,91 @@ pub struct CreateUserRequest {
     pub role: crate::domain::entities::user::Role,
 }
 
+/// A CSV record or NDJSON line of `POST /users/import`
+#[derive(serde::Deserialize)]
+pub struct ImportUserRecord {
+    pub name: String,
+    pub email: String,
+    pub phone: Option<String>,
+    pub role: Role,
+    /// Optional; without one the user signs in through an OAuth provider, which links by email
+    pub password: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ImportUsersQuery {
+    #[serde(default)]
+    pub mode: ImportMode,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl ImportUserRecord {
+    /// Runs the record through the same validation as `POST /users`
+    fn into_dto(self) -> Result<CreateUserDto, String> {
+        let request = CreateUserRequest {
+            name: self.name,
+            phone: self.phone,
+            email: self.email,
+            password: self.password.unwrap_or_else(generate_token),
+            role: self.role,
+        };
+        validate_request(&request).map_err(|e| e.to_string())?;
+
+        Ok(CreateUserDto {
+            name: request.name,
+            phone: request.phone,
+            email: request.email,
+            password: request.password,
+            role: request.role,
+        })
+    }
+}
+
+fn parse_csv(body: &str) -> Result<Vec<ImportUserRowDto>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::ValidationError(format!("Invalid CSV header: {}", e)))?
+        .clone();
+    let email_index = headers.iter().position(|h| h == "email");
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::ValidationError(format!("Invalid CSV: {}", e)))?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let email = email_index.and_then(|i| record.get(i)).map(str::to_string);
+        let user = record
+            .deserialize::<ImportUserRecord>(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(ImportUserRecord::into_dto);
+        rows.push(ImportUserRowDto { line, email, user });
+    }
+
+    Ok(rows)
+}
+
+fn parse_ndjson(body: &str) -> Vec<ImportUserRowDto> {
+    body.lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| {
+            let value = serde_json::from_str::<serde_json::Value>(text);
+            let email = value.as_ref().ok()
+                .and_then(|v| v.get("email"))
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+            let user = value
+                .and_then(serde_json::from_value::<ImportUserRecord>)
+                .map_err(|e| e.to_string())
+                .and_then(ImportUserRecord::into_dto);
+            ImportUserRowDto { line: index as u64 + 1, email, user }
+        })
+        .collect()
+}
+
 #[derive(serde::Deserialize)]
 pub struct UpdateUserRequest {
     pub name: Option<String>,
@@ -41,6 +134,7 @@ pub struct UpdateUserStatusRequest {
 pub async fn create_user(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    ctx: RequestContext,
     axum::Json(payload): axum::Json<CreateUserRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     validate_request(&payload)?;
@@ -59,16 +153,51 @@ pub async fn create_user(
         role: payload.role,
     };
 
-    let usecase = CreateUserUseCase::new(state.user_repository.clone());
-    let user = usecase.execute(requester.role, dto).await?;
+    let usecase = CreateUserUseCase::new(state.user_repository.clone(), state.audit_repository.clone(), state.phone_policy.clone());
+    let user = usecase.execute(requester_id, requester.role, dto, &ctx).await?;
 
     Ok(success_response(user, "User created successfully"))
 }
 
+/// POST /api/v1/users/import?mode=&dry_run= - Bulk create from CSV or NDJSON (Admin + SuperAdmin)
+pub async fn import_users(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Query(query): Query<ImportUsersQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, AppError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let rows = match content_type.split(';').next().unwrap_or_default().trim() {
+        "text/csv" => parse_csv(&body)?,
+        "application/x-ndjson" | "application/jsonl" => parse_ndjson(&body),
+        _ => return Err(AppError::ValidationError(
+            "Content-Type must be text/csv or application/x-ndjson".to_string(),
+        )),
+    };
+
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = ImportUsersUseCase::new(state.user_repository.clone(), state.audit_repository.clone(), state.phone_policy.clone());
+    let report = usecase.execute(requester_id, requester.role, rows, query.mode, query.dry_run, &ctx).await?;
+
+    let message = if report.dry_run { "Import validated" } else { "Import finished" };
+    Ok(success_response(report, message))
+}
+
 /// PUT /api/v1/users/:id - Update user (SuperAdmin only)
 pub async fn update_user(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    ctx: RequestContext,
     Path(user_id): Path<Uuid>,
     axum::Json(payload): axum::Json<UpdateUserRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -85,8 +214,8 @@ pub async fn update_user(
         role: payload.role,
     };
 
-    let usecase = UpdateUserUseCase::new(state.user_repository.clone());
-    let user = usecase.execute(requester.role, user_id, dto).await?;
+    let usecase = UpdateUserUseCase::new(state.user_repository.clone(), state.audit_repository.clone(), state.phone_policy.clone());
+    let user = usecase.execute(requester_id, requester.role, user_id, dto, &ctx).await?;
 
     Ok(success_response(user, "User updated successfully"))
 }
@@ -95,6 +224,7 @@ pub async fn update_user(
 pub async fn delete_user(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    ctx: RequestContext,
     Path(user_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, AppError> {
     let requester_id = auth_user.claims.claims.sub;
@@ -103,8 +233,8 @@ pub async fn delete_user(
         .await?
         .ok_or(AppError::UserNotFound)?;
 
-    let usecase = DeleteUserUseCase::new(state.user_repository.clone());
-    usecase.execute(requester_id, requester.role, user_id).await?;
+    let usecase = DeleteUserUseCase::new(state.user_repository.clone(), state.audit_repository.clone());
+    usecase.execute(requester_id, requester.role, user_id, &ctx).await?;
 
     Ok(success_response((), "User deleted successfully"))
 }
@@ -113,6 +243,7 @@ pub async fn delete_user(
 pub async fn update_user_status(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    ctx: RequestContext,
     Path(user_id): Path<Uuid>,
     axum::Json(payload): axum::Json<UpdateUserStatusRequest>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -126,8 +257,53 @@ pub async fn update_user_status(
         status: payload.status,
     };
 
-    let usecase = UpdateUserStatusUseCase::new(state.user_repository.clone());
-    let user = usecase.execute(requester.role, user_id, dto).await?;
+    let usecase = UpdateUserStatusUseCase::new(state.user_repository.clone(), state.audit_repository.clone());
+    let user = usecase.execute(requester_id, requester.role, user_id, dto, &ctx).await?;
 
     Ok(success_response(user, "User status updated successfully"))
 }
+
+/// POST /api/v1/users/:id/impersonate - Issue a short-lived token acting as the user (SuperAdmin only)
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    // An impersonated session cannot start another impersonation
+    auth_user.ensure_not_impersonated()?;
+
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = ImpersonateUserUseCase::new(
+        state.user_repository.clone(),
+        state.audit_repository.clone(),
+        state.jwt_service.clone(),
+    );
+    let session = usecase.execute(requester_id, requester.role, user_id, &ctx).await?;
+
+    Ok(success_response(session, "Impersonation started"))
+}
+
+/// POST /api/v1/users/:id/restore - Undo a soft delete (SuperAdmin only)
+pub async fn restore_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = RestoreUserUseCase::new(state.user_repository.clone(), state.audit_repository.clone());
+    let user = usecase.execute(requester_id, requester.role, user_id, &ctx).await?;
+
+    Ok(success_response(user, "User restored successfully"))
+}
@@ -1,26 +1,314 @@
-use axum::{extract::State, response::IntoResponse};
+use axum::{
+    body::{Body, Bytes},
+    extract::{State, Query, Path, OriginalUri},
+    http::header,
+    response::IntoResponse,
+};
+use futures_util::stream::{self, StreamExt};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
 use crate::AppState;
+use crate::domain::dtos::AdminUserDto;
 use crate::infrastructure::errors::AppError;
 use crate::infrastructure::auth::middleware::AuthUser;
-use crate::usecases::users::GetUsersUseCase;
-use crate::domain::repositories::user_repository::UserRepository;
-use crate::utils::response::success_response;
+use crate::infrastructure::request_context::RequestContext;
+use crate::usecases::users::{GetUsersUseCase, GetUserUseCase, SearchUsersUseCase, ExportUsersUseCase};
+use crate::domain::entities::user::{AuthProvider, Role, UserStatus};
+use crate::domain::repositories::pagination::{keyset_limit, PageParams, SortDirection};
+use crate::domain::repositories::user_repository::{UserFilter, UserRepository, UserSort, UserSortField};
+use crate::utils::response::{paginated_response, success_response, Pagination};
+
+#[derive(serde::Deserialize)]
+pub struct UsersQuery {
+    pub role: Option<Role>,
+    pub status: Option<UserStatus>,
+    pub provider: Option<AuthProvider>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub q: Option<String>,
+    pub sort: Option<UserSortField>,
+    pub order: Option<SortDirection>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    /// Switches to keyset pagination; pass an empty value for the first page
+    pub cursor: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExportUsersQuery {
+    pub format: ExportFormat,
+    pub role: Option<Role>,
+    pub status: Option<UserStatus>,
+    pub provider: Option<AuthProvider>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub q: Option<String>,
+    pub sort: Option<UserSortField>,
+    pub order: Option<SortDirection>,
+}
+
+const EXPORT_CSV_COLUMNS: [&str; 11] = [
+    "id", "name", "email", "phone", "role", "status", "providers",
+    "avatar_url", "last_login_at", "created_at", "updated_at",
+];
+
+/// Spreadsheets evaluate cells starting with these as formulas; plain phone numbers are left alone
+fn csv_safe(value: String) -> String {
+    let is_number = value.strip_prefix('+').is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()));
+    if !is_number && value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+fn encode_csv_record(fields: &[String]) -> Result<Bytes, AppError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields).map_err(|_| AppError::InternalServerError)?;
+    let bytes = writer.into_inner().map_err(|_| AppError::InternalServerError)?;
+    Ok(Bytes::from(bytes))
+}
+
+fn encode_user(format: ExportFormat, user: AdminUserDto) -> Result<Bytes, AppError> {
+    match format {
+        ExportFormat::Ndjson => {
+            let mut line = serde_json::to_vec(&user).map_err(|_| AppError::InternalServerError)?;
+            line.push(b'\n');
+            Ok(Bytes::from(line))
+        }
+        ExportFormat::Csv => {
+            let to_text = |value: serde_json::Value| match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            let providers = user.identities
+                .iter()
+                .map(|identity| to_text(serde_json::json!(identity.provider)))
+                .collect::<Vec<_>>()
+                .join(";");
+            let timestamp = |value: Option<DateTime<Utc>>| value.map(|t| t.to_rfc3339()).unwrap_or_default();
+
+            encode_csv_record(&[
+                user.id.to_string(),
+                csv_safe(user.name),
+                csv_safe(user.email),
+                csv_safe(user.phone.unwrap_or_default()),
+                to_text(serde_json::json!(user.role)),
+                to_text(serde_json::json!(user.status)),
+                providers,
+                csv_safe(user.avatar_url.unwrap_or_default()),
+                timestamp(user.last_login_at),
+                timestamp(user.created_at),
+                timestamp(user.updated_at),
+            ])
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct SearchUsersQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
 
 /// Handler for GET /users - restricted to Admin and SuperAdmin only
 pub async fn get_users(
     State(state): State<AppState>,
     auth_user: AuthUser,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<UsersQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     let user_repository = state.user_repository.clone();
     let requester_id = auth_user.claims.claims.sub;
-    
+
     let requester = user_repository
         .find_by_id(requester_id)
         .await?
         .ok_or(AppError::UserNotFound)?;
-        
+
+    let filter = UserFilter {
+        role: query.role,
+        status: query.status,
+        provider: query.provider,
+        created_from: query.created_from,
+        created_to: query.created_to,
+        search: query.q,
+    };
+    let sort = UserSort {
+        field: query.sort.unwrap_or_default(),
+        direction: query.order.unwrap_or_default(),
+    };
     let usecase = GetUsersUseCase::new(user_repository);
-    let users = usecase.execute(requester.role).await?;
 
-    Ok(success_response(users, "success"))
+    if let Some(cursor) = query.cursor.as_deref() {
+        let limit = keyset_limit(query.per_page);
+        let page = usecase.execute_keyset(requester.role, filter, sort, Some(cursor), limit).await?;
+        let pagination = Pagination::cursor(&page, limit, &uri);
+        return Ok(paginated_response(page.items, "success", pagination));
+    }
+
+    let page = PageParams::new(query.page, query.per_page);
+    let (users, total) = usecase.execute(requester.role, filter, sort, page).await?;
+
+    Ok(paginated_response(users, "success", Pagination::new(total, page, &uri)))
+}
+
+/// Handler for GET /users/export?format=csv|ndjson - streams every matching user (Admin + SuperAdmin)
+pub async fn export_users(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    ctx: RequestContext,
+    Query(query): Query<ExportUsersQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let filter = UserFilter {
+        role: query.role,
+        status: query.status,
+        provider: query.provider,
+        created_from: query.created_from,
+        created_to: query.created_to,
+        search: query.q,
+    };
+    let sort = UserSort {
+        field: query.sort.unwrap_or_default(),
+        direction: query.order.unwrap_or_default(),
+    };
+    let format = query.format;
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+
+    let usecase = ExportUsersUseCase::new(state.user_repository.clone(), state.audit_repository.clone());
+    let users = usecase.execute(requester_id, requester.role, filter, sort, extension, &ctx).await?;
+
+    let header_row = match format {
+        ExportFormat::Csv => Some(encode_csv_record(&EXPORT_CSV_COLUMNS.map(String::from))),
+        ExportFormat::Ndjson => None,
+    };
+    let rows = users.map(move |row| {
+        row.and_then(|user| encode_user(format, user)).inspect_err(|e| {
+            // Headers are already sent, so the only option left is to cut the body short
+            tracing::error!("User export aborted: {:?}", e);
+        })
+    });
+    let body = Body::from_stream(stream::iter(header_row).chain(rows));
+
+    let filename = format!("users-{}.{}", Utc::now().format("%Y%m%d%H%M%S"), extension);
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    ))
+}
+
+/// Handler for GET /users/search - ranked, typo-tolerant search (Admin + SuperAdmin)
+pub async fn search_users(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(query): Query<SearchUsersQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = SearchUsersUseCase::new(state.user_repository.clone());
+    let results = usecase.execute(requester.role, &query.q, PageParams::new(None, query.limit).limit()).await?;
+
+    Ok(success_response(results, "success"))
+}
+
+/// Handler for GET /users/:id - detailed admin view of one user (Admin + SuperAdmin)
+pub async fn get_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let requester_id = auth_user.claims.claims.sub;
+    let requester = state.user_repository
+        .find_by_id(requester_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+
+    let usecase = GetUserUseCase::new(state.user_repository.clone());
+    let user = usecase.execute(requester.role, user_id).await?;
+
+    Ok(success_response(user, "success"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::dtos::LinkedIdentityDto;
+
+    fn user(name: &str, phone: Option<&str>) -> AdminUserDto {
+        AdminUserDto {
+            id: Uuid::nil(),
+            name: name.to_string(),
+            phone: phone.map(str::to_string),
+            phone_verified_at: None,
+            email: "user@example.com".to_string(),
+            role: Role::User,
+            status: UserStatus::Active,
+            avatar_url: None,
+            identities: vec![
+                LinkedIdentityDto { provider: AuthProvider::Password, provider_user_id: None },
+                LinkedIdentityDto { provider: AuthProvider::Github, provider_user_id: Some("1".to_string()) },
+            ],
+            last_login_at: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn formula_cells_are_quoted() {
+        for value in ["=1+1", "+cmd", "-2", "@SUM(A1)", "\tx", "\rx", "+"] {
+            assert_eq!(csv_safe(value.to_string()), format!("'{}", value));
+        }
+    }
+
+    #[test]
+    fn phone_numbers_and_plain_text_are_kept() {
+        for value in ["+6281234567890", "Alice", "a=b", ""] {
+            assert_eq!(csv_safe(value.to_string()), value);
+        }
+    }
+
+    #[test]
+    fn csv_rows_escape_formulas_and_list_providers() {
+        let row = encode_user(ExportFormat::Csv, user("=HYPERLINK(\"http://evil.test\")", Some("+6281234567890"))).unwrap();
+
+        assert_eq!(
+            String::from_utf8(row.to_vec()).unwrap(),
+            "00000000-0000-0000-0000-000000000000,\"'=HYPERLINK(\"\"http://evil.test\"\")\",user@example.com,\
+             +6281234567890,User,Active,password;github,,,,\n",
+        );
+    }
+
+    #[test]
+    fn ndjson_lines_keep_values_as_they_are() {
+        let line = encode_user(ExportFormat::Ndjson, user("=1+1", None)).unwrap();
+
+        assert!(line.ends_with(b"\n"));
+        let value: serde_json::Value = serde_json::from_slice(&line).unwrap();
+        assert_eq!(value["name"], "=1+1");
+        assert_eq!(value["identities"][1]["provider"], "github");
+    }
 }
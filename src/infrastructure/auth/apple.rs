@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::Serialize;
+use crate::domain::dtos::{AppleCallbackUser, AppleIdTokenClaims, OAuthTokenResponse};
+use crate::domain::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+use super::jwks::JwksCache;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
+use super::oidc;
+
+pub const APPLE_ISSUER: &str = "https://appleid.apple.com";
+
+/// Lifetime of a generated client secret; Apple accepts up to six months
+const CLIENT_SECRET_TTL_SECS: i64 = 5 * 60;
+
+/// Sign in with Apple. Instead of a client secret, Apple issues a private key
+/// (`.p8` file) that the app signs short-lived client-secret JWTs with.
+pub struct AppleOAuthClient {
+    /// The Services ID
+    client_id: String,
+    redirect_uri: String,
+    endpoints: OAuthEndpoints,
+    issuer: String,
+    team_id: String,
+    key_id: String,
+    private_key: EncodingKey,
+    http: OAuthHttpClient,
+    jwks: JwksCache,
+}
+
+/// What `AppleOAuthClient::new` needs besides the HTTP client
+pub struct AppleClientSettings {
+    /// The Services ID
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub endpoints: OAuthEndpoints,
+    /// Both the id_token issuer and the client secret audience: `APPLE_ISSUER`
+    /// except against a mock server
+    pub issuer: String,
+    pub team_id: String,
+    pub key_id: String,
+    /// Signs the client secrets
+    pub private_key: EncodingKey,
+}
+
+#[derive(Serialize)]
+struct ClientSecretClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+impl AppleOAuthClient {
+    /// The public Apple endpoints
+    pub fn default_endpoints() -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: "https://appleid.apple.com/auth/authorize".to_string(),
+            token_url: "https://appleid.apple.com/auth/token".to_string(),
+            userinfo_url: None,
+            jwks_url: Some("https://appleid.apple.com/auth/keys".to_string()),
+        }
+    }
+
+    pub fn new(settings: AppleClientSettings, http: OAuthHttpClient) -> Self {
+        let jwks_url = settings.endpoints.jwks_url
+            .clone()
+            .expect("Apple endpoints always include jwks_url");
+        Self {
+            client_id: settings.client_id,
+            redirect_uri: settings.redirect_uri,
+            endpoints: settings.endpoints,
+            issuer: settings.issuer,
+            team_id: settings.team_id,
+            key_id: settings.key_id,
+            private_key: settings.private_key,
+            jwks: JwksCache::new("Apple", jwks_url, http.clone()),
+            http,
+        }
+    }
+
+    /// Returns the Apple authorization URL to redirect the user to. Asking for
+    /// name or email requires Apple to POST the result back (`form_post`).
+    fn get_authorize_url(&self, state: &str, nonce: &str) -> String {
+        format!(
+            "{}?client_id={}&redirect_uri={}&response_type=code&response_mode=form_post&scope=name%20email&state={}&nonce={}",
+            self.endpoints.authorize_url,
+            self.client_id,
+            urlencoding::encode(&self.redirect_uri),
+            urlencoding::encode(state),
+            urlencoding::encode(nonce),
+        )
+    }
+
+    /// ES256 JWT standing in for the client secret
+    fn client_secret(&self) -> Result<String, AppError> {
+        let now = Utc::now().timestamp();
+        let claims = ClientSecretClaims {
+            iss: &self.team_id,
+            sub: &self.client_id,
+            aud: &self.issuer,
+            iat: now,
+            exp: now + CLIENT_SECRET_TTL_SECS,
+        };
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.key_id.clone());
+
+        encode(&header, &claims, &self.private_key)
+            .map_err(|e| AppError::OAuthMisconfigured(format!("Apple client secret could not be signed: {}", e)))
+    }
+
+    /// Exchange the authorization code for tokens
+    async fn exchange_token(&self, code: &str) -> Result<OAuthTokenResponse, AppError> {
+        let client_secret = self.client_secret()?;
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "authorization_code"),
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Exchange a refresh token for a new access token; Apple keeps the refresh token
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<OAuthTokenResponse, AppError> {
+        let client_secret = self.client_secret()?;
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("grant_type", "refresh_token"),
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::Idempotent).await
+    }
+
+    /// Checks the id_token's signature against Apple's published keys, then
+    /// its audience, issuer, expiry and nonce
+    async fn verify_id_token(&self, id_token: &str, nonce: Option<&str>) -> Result<AppleIdTokenClaims, AppError> {
+        let claims: AppleIdTokenClaims = oidc::verify_id_token(
+            &self.jwks,
+            self.display_name(),
+            id_token,
+            &self.client_id,
+            Some(&[self.issuer.as_str()]),
+        ).await?;
+        oidc::check_nonce(self.display_name(), claims.nonce.as_deref(), nonce)?;
+
+        Ok(claims)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for AppleOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Apple
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Apple"
+    }
+
+    fn authorize_url(&self, state: &str, nonce: &str) -> String {
+        self.get_authorize_url(state, nonce)
+    }
+
+    fn uses_form_post(&self) -> bool {
+        true
+    }
+
+    /// Apple never puts the name in the id_token; it posts it with the callback,
+    /// and only the first time the user signs in to the app
+    fn callback_name(&self, user: &str) -> Option<String> {
+        let name = serde_json::from_str::<AppleCallbackUser>(user).ok()?.name?;
+        let full_name = [name.first_name, name.last_name]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!full_name.is_empty()).then_some(full_name)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_token(code).await?.into())
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let id_token = tokens.id_token
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthError("Apple did not return an id_token".to_string()))?;
+        let claims = self.verify_id_token(id_token, nonce).await?;
+
+        // A private relay address (Hide My Email) forwards to the user's verified
+        // Apple ID email, so it is as good as verified. Being unique to this app,
+        // it never matches, and so never links to, an existing account.
+        let email_verified = claims.email_verified == Some(true) || claims.is_private_email == Some(true);
+
+        Ok(OAuthIdentity {
+            provider_user_id: claims.sub,
+            email: claims.email,
+            email_verified,
+            name: None,
+            avatar_url: None,
+        })
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_refresh_token(refresh_token).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, timestamp, JwksServer, SigningKey};
+
+    const CLIENT_ID: &str = "com.example.web";
+    const TEAM_ID: &str = "TEAM123456";
+    const KEY_ID: &str = "KEY1234567";
+    const NONCE: &str = "nonce-from-the-browser";
+
+    struct Fixture {
+        key: SigningKey,
+        /// Checks the client secrets the client signs
+        client_key: DecodingKey,
+        client: AppleOAuthClient,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let key = SigningKey::rsa("apple-key");
+            let jwks = JwksServer::spawn(vec![key.jwk()]).await;
+
+            let rng = ring::rand::SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).expect("generate P-256 key");
+            let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).expect("valid PKCS#8");
+
+            let settings = AppleClientSettings {
+                client_id: CLIENT_ID.to_string(),
+                redirect_uri: "https://api.example.com/api/v1/auth/apple/callback".to_string(),
+                endpoints: OAuthEndpoints { jwks_url: Some(jwks.url.clone()), ..AppleOAuthClient::default_endpoints() },
+                issuer: APPLE_ISSUER.to_string(),
+                team_id: TEAM_ID.to_string(),
+                key_id: KEY_ID.to_string(),
+                private_key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+            };
+
+            Self {
+                client: AppleOAuthClient::new(settings, http_client()),
+                client_key: DecodingKey::from_ec_der(key_pair.public_key().as_ref()),
+                key,
+            }
+        }
+
+        async fn identity(&self, claims: &Value) -> Result<OAuthIdentity, AppError> {
+            let tokens = OAuthTokens {
+                access_token: "access-token".to_string(),
+                refresh_token: None,
+                expires_in: None,
+                id_token: Some(self.key.sign(claims)),
+            };
+            self.client.fetch_identity(&tokens, Some(NONCE)).await
+        }
+    }
+
+    /// Apple sends the booleans as strings
+    fn claims() -> Value {
+        json!({
+            "iss": APPLE_ISSUER,
+            "aud": CLIENT_ID,
+            "sub": "001234.5f6e7d8c9b0a4f3e2d1c0b9a8f7e6d5c.1234",
+            "email": "appleseed@example.com",
+            "email_verified": "true",
+            "is_private_email": "false",
+            "nonce": NONCE,
+            "iat": timestamp(0),
+            "exp": timestamp(600),
+        })
+    }
+
+    fn with(mut claims: Value, key: &str, value: Value) -> Value {
+        claims[key] = value;
+        claims
+    }
+
+    #[tokio::test]
+    async fn signs_the_client_secret_with_the_team_key() {
+        let fixture = Fixture::new().await;
+
+        let secret = fixture.client.client_secret().unwrap();
+
+        let header = decode_header(&secret).unwrap();
+        assert_eq!(header.alg, Algorithm::ES256);
+        assert_eq!(header.kid.as_deref(), Some(KEY_ID));
+        let mut validation = Validation::new(Algorithm::ES256);
+        validation.set_audience(&[APPLE_ISSUER]);
+        validation.set_issuer(&[TEAM_ID]);
+        validation.sub = Some(CLIENT_ID.to_string());
+        let claims = decode::<Value>(&secret, &fixture.client_key, &validation).unwrap().claims;
+        let lifetime = claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap();
+        assert_eq!(lifetime, CLIENT_SECRET_TTL_SECS);
+    }
+
+    #[tokio::test]
+    async fn reads_string_booleans() {
+        let fixture = Fixture::new().await;
+
+        let identity = fixture.identity(&claims()).await.unwrap();
+        assert_eq!(identity.email.as_deref(), Some("appleseed@example.com"));
+        assert!(identity.email_verified);
+
+        let identity = fixture.identity(&with(claims(), "email_verified", json!("false"))).await.unwrap();
+        assert!(!identity.email_verified);
+    }
+
+    #[tokio::test]
+    async fn treats_a_private_relay_email_as_verified() {
+        let fixture = Fixture::new().await;
+        let relay = with(claims(), "email", json!("x7k2p9qz4m@privaterelay.appleid.com"));
+        let relay = with(with(relay, "email_verified", json!("false")), "is_private_email", json!("true"));
+
+        let identity = fixture.identity(&relay).await.unwrap();
+
+        assert_eq!(identity.email.as_deref(), Some("x7k2p9qz4m@privaterelay.appleid.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name, None);
+    }
+
+    #[tokio::test]
+    async fn rejects_another_issuer_and_a_nonce_mismatch() {
+        let fixture = Fixture::new().await;
+
+        for (claims, reason) in [
+            (with(claims(), "iss", json!("https://appleid.apple.com.evil.example")), "InvalidIssuer"),
+            (with(claims(), "nonce", json!("another-nonce")), "nonce mismatch"),
+        ] {
+            match fixture.identity(&claims).await {
+                Err(AppError::OAuthError(message)) => assert!(message.contains(reason), "{}", message),
+                other => panic!("expected a rejection mentioning {:?}, got {:?}", reason, other.map(|identity| identity.provider_user_id)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn takes_the_name_from_the_posted_user() {
+        let fixture = Fixture::new().await;
+        let name = |user: Value| fixture.client.callback_name(&user.to_string());
+
+        assert_eq!(name(json!({ "name": { "firstName": "Johnny", "lastName": "Appleseed" } })).as_deref(), Some("Johnny Appleseed"));
+        assert_eq!(name(json!({ "name": { "firstName": "Johnny", "lastName": " " } })).as_deref(), Some("Johnny"));
+        assert_eq!(name(json!({ "name": { "firstName": "", "lastName": "" } })), None);
+        assert_eq!(name(json!({ "email": "appleseed@example.com" })), None);
+        assert_eq!(fixture.client.callback_name("not json"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use async_trait::async_trait;
+use crate::domain::dtos::{DiscordUser, OAuthTokenResponse};
+use crate::domain::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
+
+pub struct DiscordOAuthClient {
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    endpoints: OAuthEndpoints,
+    http: OAuthHttpClient,
+}
+
+const AVATAR_CDN: &str = "https://cdn.discordapp.com/avatars";
+
+impl DiscordOAuthClient {
+    /// The public Discord endpoints
+    pub fn default_endpoints() -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: "https://discord.com/oauth2/authorize".to_string(),
+            token_url: "https://discord.com/api/oauth2/token".to_string(),
+            userinfo_url: Some("https://discord.com/api/users/@me".to_string()),
+            jwks_url: None,
+        }
+    }
+
+    pub fn new(client_id: String, client_secret: String, redirect_uri: String, endpoints: OAuthEndpoints, http: OAuthHttpClient) -> Self {
+        Self {
+            client_id,
+            client_secret,
+            redirect_uri,
+            endpoints,
+            http,
+        }
+    }
+
+    /// Returns the Discord authorization URL to redirect the user to
+    fn get_authorize_url(&self, state: &str) -> String {
+        format!(
+            "{}?client_id={}&redirect_uri={}&response_type=code&scope=identify%20email&state={}",
+            self.endpoints.authorize_url,
+            self.client_id,
+            urlencoding::encode(&self.redirect_uri),
+            urlencoding::encode(state),
+        )
+    }
+
+    /// Exchange the authorization code for tokens
+    async fn exchange_token(&self, code: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "authorization_code"),
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Exchange a refresh token for new tokens; Discord rotates refresh tokens
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("grant_type", "refresh_token"),
+            ]);
+
+        // Not retried: a refresh that reached Discord has already used up the old token
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Fetch the signed-in Discord user
+    async fn get_user(&self, access_token: &str) -> Result<DiscordUser, AppError> {
+        let userinfo_url = self.endpoints.userinfo_url
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthMisconfigured("Discord userinfo_url is not set".to_string()))?;
+        let request = self
+            .http
+            .get(userinfo_url)
+            .header("Authorization", format!("Bearer {}", access_token));
+
+        self.http.send_json(self.display_name(), request, Retry::Idempotent).await
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for DiscordOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Discord
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Discord"
+    }
+
+    /// Discord is not OpenID Connect, so the nonce is not used
+    fn authorize_url(&self, state: &str, _nonce: &str) -> String {
+        self.get_authorize_url(state)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_token(code).await?.into())
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, _nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let user = self.get_user(&tokens.access_token).await?;
+
+        // Discord hands out whatever address was typed at sign-up, confirmed or not,
+        // so an unverified one is refused rather than used to create an account
+        if user.email.is_some() && user.verified != Some(true) {
+            return Err(AppError::OAuthError("Verify your email address with Discord, then sign in again".to_string()));
+        }
+
+        Ok(OAuthIdentity {
+            avatar_url: user.avatar.map(|hash| format!("{}/{}/{}.png", AVATAR_CDN, user.id, hash)),
+            provider_user_id: user.id,
+            email_verified: user.email.is_some(),
+            email: user.email,
+            name: Some(user.global_name.unwrap_or(user.username)),
+        })
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_refresh_token(refresh_token).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, serve};
+
+    /// A client whose userinfo endpoint returns `user`
+    async fn client_returning(user: Value) -> DiscordOAuthClient {
+        let base_url = serve(Router::new().route("/api/users/@me", get(move || async move { Json(user) }))).await;
+        DiscordOAuthClient::new(
+            "client-id".to_string(),
+            "client-secret".to_string(),
+            "https://api.example.com/api/v1/auth/discord/callback".to_string(),
+            OAuthEndpoints { userinfo_url: Some(format!("{}/api/users/@me", base_url)), ..DiscordOAuthClient::default_endpoints() },
+            http_client(),
+        )
+    }
+
+    async fn identity(user: Value) -> Result<OAuthIdentity, AppError> {
+        let tokens = OAuthTokens { access_token: "access-token".to_string(), refresh_token: None, expires_in: None, id_token: None };
+        client_returning(user).await.fetch_identity(&tokens, None).await
+    }
+
+    fn user() -> Value {
+        json!({
+            "id": "80351110224678912",
+            "username": "wumpus",
+            "global_name": "Wumpus",
+            "avatar": "8342729096ea3675442027381ff50dfe",
+            "email": "wumpus@example.com",
+            "verified": true,
+        })
+    }
+
+    fn with(mut user: Value, key: &str, value: Value) -> Value {
+        user[key] = value;
+        user
+    }
+
+    #[tokio::test]
+    async fn builds_the_identity_from_the_user() {
+        let identity = identity(user()).await.unwrap();
+
+        assert_eq!(identity.provider_user_id, "80351110224678912");
+        assert_eq!(identity.email.as_deref(), Some("wumpus@example.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("Wumpus"));
+        assert_eq!(
+            identity.avatar_url.as_deref(),
+            Some("https://cdn.discordapp.com/avatars/80351110224678912/8342729096ea3675442027381ff50dfe.png"),
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_username_without_a_display_name_or_avatar() {
+        let identity = identity(with(with(user(), "global_name", Value::Null), "avatar", Value::Null)).await.unwrap();
+
+        assert_eq!(identity.name.as_deref(), Some("wumpus"));
+        assert_eq!(identity.avatar_url, None);
+    }
+
+    #[tokio::test]
+    async fn refuses_an_unverified_email() {
+        for verified in [json!(false), Value::Null] {
+            match identity(with(user(), "verified", verified)).await {
+                Err(AppError::OAuthError(message)) => assert!(message.contains("Verify your email address with Discord"), "{}", message),
+                other => panic!("expected a refusal, got {:?}", other.map(|identity| identity.provider_user_id)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_on_a_missing_email_unverified() {
+        let identity = identity(with(with(user(), "email", Value::Null), "verified", Value::Null)).await.unwrap();
+
+        assert_eq!(identity.email, None);
+        assert!(!identity.email_verified);
+    }
+}
@@ -1,84 +1,86 @@
-use reqwest::Client;
+use async_trait::async_trait;
 use crate::domain::dtos::{GitHubTokenResponse, GitHubUserInfo, GitHubEmail};
+use crate::domain::entities::user::AuthProvider;
 use crate::infrastructure::errors::AppError;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
 
 pub struct GitHubOAuthClient {
     client_id: String,
     client_secret: String,
     redirect_uri: String,
-    http_client: Client,
+    endpoints: OAuthEndpoints,
+    http: OAuthHttpClient,
 }
 
 impl GitHubOAuthClient {
-    pub fn new(client_id: String, client_secret: String, redirect_uri: String) -> Self {
+    /// The public GitHub endpoints
+    pub fn default_endpoints() -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: "https://github.com/login/oauth/authorize".to_string(),
+            token_url: "https://github.com/login/oauth/access_token".to_string(),
+            userinfo_url: Some("https://api.github.com/user".to_string()),
+            jwks_url: None,
+        }
+    }
+
+    pub fn new(client_id: String, client_secret: String, redirect_uri: String, endpoints: OAuthEndpoints, http: OAuthHttpClient) -> Self {
         Self {
             client_id,
             client_secret,
             redirect_uri,
-            http_client: Client::new(),
+            endpoints,
+            http,
         }
     }
 
     /// Returns the GitHub authorization URL to redirect the user to
-    pub fn get_authorize_url(&self) -> String {
+    fn get_authorize_url(&self, state: &str) -> String {
         let encoded_redirect_uri = urlencoding::encode(&self.redirect_uri);
         format!(
-            "https://github.com/login/oauth/authorize?client_id={}&redirect_uri={}&scope=user:email",
-            self.client_id, encoded_redirect_uri
+            "{}?client_id={}&redirect_uri={}&scope=user:email&state={}",
+            self.endpoints.authorize_url, self.client_id, encoded_redirect_uri, urlencoding::encode(state)
         )
     }
 
     /// Exchange the authorization code for an access token
-    pub async fn exchange_code(&self, code: &str) -> Result<String, AppError> {
-        let response = self
-            .http_client
-            .post("https://github.com/login/oauth/access_token")
+    async fn exchange_access_token(&self, code: &str) -> Result<String, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
             .header("Accept", "application/json")
             .json(&serde_json::json!({
                 "client_id": self.client_id,
                 "client_secret": self.client_secret,
                 "code": code,
                 "redirect_uri": self.redirect_uri,
-            }))
-            .send()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to exchange code: {}", e)))?;
+            }));
 
-        let token_response: GitHubTokenResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to parse token response: {}", e)))?;
+        let token_response: GitHubTokenResponse = self
+            .http
+            .send_json(self.display_name(), request, Retry::ConnectFailuresOnly)
+            .await?;
 
         Ok(token_response.access_token)
     }
 
     /// Fetch the authenticated GitHub user's profile
-    pub async fn get_user_info(&self, access_token: &str) -> Result<GitHubUserInfo, AppError> {
-        let mut user_info: GitHubUserInfo = self
-            .http_client
-            .get("https://api.github.com/user")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("User-Agent", "rust-axum-app")
-            .send()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to fetch user info: {}", e)))?
-            .json()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to parse user info: {}", e)))?;
+    async fn get_user_info(&self, access_token: &str) -> Result<GitHubUserInfo, AppError> {
+        let userinfo_url = self.endpoints.userinfo_url
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthMisconfigured("GitHub userinfo_url is not set".to_string()))?;
+        let request = self
+            .http
+            .get(userinfo_url)
+            .header("Authorization", format!("Bearer {}", access_token));
+        let mut user_info: GitHubUserInfo = self.http.send_json(self.display_name(), request, Retry::Idempotent).await?;
 
         // If email is not public, fetch from /user/emails endpoint
         if user_info.email.is_none() {
-            let emails: Vec<GitHubEmail> = self
-                .http_client
-                .get("https://api.github.com/user/emails")
-                .header("Authorization", format!("Bearer {}", access_token))
-                .header("User-Agent", "rust-axum-app")
-                .send()
-                .await
-                .map_err(|e| AppError::OAuthError(format!("Failed to fetch emails: {}", e)))?
-                .json()
-                .await
-                .map_err(|e| AppError::OAuthError(format!("Failed to parse emails: {}", e)))?;
+            let request = self
+                .http
+                .get(&format!("{}/emails", userinfo_url))
+                .header("Authorization", format!("Bearer {}", access_token));
+            let emails: Vec<GitHubEmail> = self.http.send_json(self.display_name(), request, Retry::Idempotent).await?;
 
             // Find the primary verified email
             user_info.email = emails
@@ -90,3 +92,40 @@ impl GitHubOAuthClient {
         Ok(user_info)
     }
 }
+
+#[async_trait]
+impl OAuthProvider for GitHubOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Github
+    }
+
+    fn display_name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    /// GitHub is not OpenID Connect, so the nonce is not used
+    fn authorize_url(&self, state: &str, _nonce: &str) -> String {
+        self.get_authorize_url(state)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        let access_token = self.exchange_access_token(code).await?;
+
+        // GitHub OAuth app tokens do not expire and come without a refresh token
+        Ok(OAuthTokens { access_token, refresh_token: None, expires_in: None, id_token: None })
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, _nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let user = self.get_user_info(&tokens.access_token).await?;
+
+        Ok(OAuthIdentity {
+            provider_user_id: user.id.to_string(),
+            // GitHub only shows verified addresses as the public email,
+            // and the fallback above picks the primary verified one
+            email_verified: user.email.is_some(),
+            email: user.email,
+            name: Some(user.name.unwrap_or(user.login)),
+            avatar_url: user.avatar_url,
+        })
+    }
+}
@@ -0,0 +1,196 @@
+use async_trait::async_trait;
+use crate::domain::dtos::{GitLabUserInfo, OAuthTokenResponse};
+use crate::domain::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
+
+/// GitLab.com or a self-hosted GitLab instance
+pub struct GitLabOAuthClient {
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    endpoints: OAuthEndpoints,
+    http: OAuthHttpClient,
+}
+
+pub const GITLAB_COM: &str = "https://gitlab.com";
+
+impl GitLabOAuthClient {
+    /// The endpoints of the GitLab instance at `base_url`, e.g. `https://gitlab.example.com`
+    pub fn default_endpoints(base_url: &str) -> OAuthEndpoints {
+        let base_url = base_url.trim_end_matches('/');
+        OAuthEndpoints {
+            authorize_url: format!("{}/oauth/authorize", base_url),
+            token_url: format!("{}/oauth/token", base_url),
+            userinfo_url: Some(format!("{}/oauth/userinfo", base_url)),
+            jwks_url: None,
+        }
+    }
+
+    pub fn new(client_id: String, client_secret: String, redirect_uri: String, endpoints: OAuthEndpoints, http: OAuthHttpClient) -> Self {
+        Self {
+            client_id,
+            client_secret,
+            redirect_uri,
+            endpoints,
+            http,
+        }
+    }
+
+    /// Returns the GitLab authorization URL to redirect the user to
+    fn get_authorize_url(&self, state: &str) -> String {
+        format!(
+            "{}?client_id={}&redirect_uri={}&response_type=code&scope=openid%20profile%20email&state={}",
+            self.endpoints.authorize_url,
+            self.client_id,
+            urlencoding::encode(&self.redirect_uri),
+            urlencoding::encode(state),
+        )
+    }
+
+    /// Exchange the authorization code for tokens
+    async fn exchange_token(&self, code: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "authorization_code"),
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Exchange a refresh token for new tokens. GitLab rotates refresh tokens,
+    /// so the old one stops working once this succeeds.
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "refresh_token"),
+            ]);
+
+        // Not retried: a refresh that reached GitLab has already used up the old token
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Fetch the signed-in user from the OpenID Connect userinfo endpoint
+    async fn get_user_info(&self, access_token: &str) -> Result<GitLabUserInfo, AppError> {
+        let userinfo_url = self.endpoints.userinfo_url
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthMisconfigured("GitLab userinfo_url is not set".to_string()))?;
+        let request = self
+            .http
+            .get(userinfo_url)
+            .header("Authorization", format!("Bearer {}", access_token));
+
+        self.http.send_json(self.display_name(), request, Retry::Idempotent).await
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GitLabOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Gitlab
+    }
+
+    fn display_name(&self) -> &'static str {
+        "GitLab"
+    }
+
+    /// The identity comes from the userinfo endpoint rather than the id_token,
+    /// so the nonce is not used
+    fn authorize_url(&self, state: &str, _nonce: &str) -> String {
+        self.get_authorize_url(state)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_token(code).await?.into())
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, _nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let user = self.get_user_info(&tokens.access_token).await?;
+
+        Ok(OAuthIdentity {
+            provider_user_id: user.sub,
+            email: user.email,
+            email_verified: user.email_verified.unwrap_or(false),
+            name: user.name.or(user.nickname),
+            avatar_url: user.picture,
+        })
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_refresh_token(refresh_token).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::get, Json, Router};
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, serve};
+
+    /// A client of a GitLab instance under `/gitlab` whose userinfo endpoint returns `user_info`
+    async fn client_returning(user_info: Value) -> GitLabOAuthClient {
+        let base_url = serve(Router::new().route("/gitlab/oauth/userinfo", get(move || async move { Json(user_info) }))).await;
+        GitLabOAuthClient::new(
+            "client-id".to_string(),
+            "client-secret".to_string(),
+            "https://api.example.com/api/v1/auth/gitlab/callback".to_string(),
+            GitLabOAuthClient::default_endpoints(&format!("{}/gitlab/", base_url)),
+            http_client(),
+        )
+    }
+
+    async fn identity(user_info: Value) -> OAuthIdentity {
+        let tokens = OAuthTokens { access_token: "access-token".to_string(), refresh_token: None, expires_in: None, id_token: None };
+        client_returning(user_info).await.fetch_identity(&tokens, None).await.unwrap()
+    }
+
+    #[test]
+    fn points_at_a_self_hosted_instance() {
+        let endpoints = GitLabOAuthClient::default_endpoints("https://git.example.com/gitlab/");
+
+        assert_eq!(endpoints.authorize_url, "https://git.example.com/gitlab/oauth/authorize");
+        assert_eq!(endpoints.token_url, "https://git.example.com/gitlab/oauth/token");
+        assert_eq!(endpoints.userinfo_url.as_deref(), Some("https://git.example.com/gitlab/oauth/userinfo"));
+        assert_eq!(GitLabOAuthClient::default_endpoints(GITLAB_COM).authorize_url, "https://gitlab.com/oauth/authorize");
+    }
+
+    #[tokio::test]
+    async fn builds_the_identity_from_userinfo() {
+        let identity = identity(json!({
+            "sub": "4000001",
+            "name": "Tanuki Example",
+            "nickname": "tanuki",
+            "email": "tanuki@example.com",
+            "email_verified": true,
+            "picture": "https://git.example.com/uploads/-/system/user/avatar/4000001/avatar.png",
+        }))
+        .await;
+
+        assert_eq!(identity.provider_user_id, "4000001");
+        assert_eq!(identity.email.as_deref(), Some("tanuki@example.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("Tanuki Example"));
+        assert_eq!(identity.avatar_url.as_deref(), Some("https://git.example.com/uploads/-/system/user/avatar/4000001/avatar.png"));
+    }
+
+    #[tokio::test]
+    async fn treats_a_missing_verification_as_unverified() {
+        let identity = identity(json!({ "sub": "4000002", "nickname": "tanuki", "email": "tanuki@example.com" })).await;
+
+        assert!(!identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("tanuki"));
+    }
+}
@@ -1,70 +1,327 @@
-use reqwest::Client;
-use crate::domain::dtos::{GoogleTokenResponse, GoogleUserInfo};
+use async_trait::async_trait;
+use crate::domain::dtos::{GoogleIdTokenClaims, OAuthTokenResponse};
+use crate::domain::entities::user::AuthProvider;
 use crate::infrastructure::errors::AppError;
+use super::jwks::JwksCache;
+use super::oidc;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
 
 pub struct GoogleOAuthClient {
     client_id: String,
     client_secret: String,
     redirect_uri: String,
-    http_client: Client,
+    endpoints: OAuthEndpoints,
+    hosted_domain: Option<String>,
+    http: OAuthHttpClient,
+    jwks: JwksCache,
 }
 
+/// Google signs id_tokens with either spelling of its issuer
+const ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+
 impl GoogleOAuthClient {
-    pub fn new(client_id: String, client_secret: String, redirect_uri: String) -> Self {
+    /// The public Google endpoints
+    pub fn default_endpoints() -> OAuthEndpoints {
+        OAuthEndpoints {
+            authorize_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token_url: "https://oauth2.googleapis.com/token".to_string(),
+            userinfo_url: None,
+            jwks_url: Some("https://www.googleapis.com/oauth2/v3/certs".to_string()),
+        }
+    }
+
+    pub fn new(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        endpoints: OAuthEndpoints,
+        hosted_domain: Option<String>,
+        http: OAuthHttpClient,
+    ) -> Self {
+        let jwks_url = endpoints.jwks_url
+            .clone()
+            .expect("Google endpoints always include jwks_url");
         Self {
             client_id,
             client_secret,
             redirect_uri,
-            http_client: Client::new(),
+            endpoints,
+            hosted_domain,
+            jwks: JwksCache::new("Google", jwks_url, http.clone()),
+            http,
         }
     }
 
     /// Returns the Google authorization URL to redirect the user to
-    pub fn get_authorize_url(&self) -> String {
-        let encoded_redirect_uri = urlencoding::encode(&self.redirect_uri);
-        format!(
-            "https://accounts.google.com/o/oauth2/v2/auth?client_id={}&redirect_uri={}&response_type=code&scope=openid%20email%20profile&access_type=offline",
-            self.client_id, encoded_redirect_uri
-        )
+    fn get_authorize_url(&self, state: &str, nonce: &str) -> String {
+        let mut url = format!(
+            "{}?client_id={}&redirect_uri={}&response_type=code&scope=openid%20email%20profile&access_type=offline&state={}&nonce={}",
+            self.endpoints.authorize_url,
+            self.client_id,
+            urlencoding::encode(&self.redirect_uri),
+            urlencoding::encode(state),
+            urlencoding::encode(nonce),
+        );
+        // Only preselects the account; the hd claim is what is enforced
+        if let Some(domain) = &self.hosted_domain {
+            url.push_str(&format!("&hd={}", urlencoding::encode(domain)));
+        }
+        url
     }
 
     /// Exchange the authorization code for tokens
-    pub async fn exchange_code(&self, code: &str) -> Result<GoogleTokenResponse, AppError> {
-        let response = self
-            .http_client
-            .post("https://oauth2.googleapis.com/token")
+    async fn exchange_token(&self, code: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
             .form(&[
                 ("client_id", self.client_id.as_str()),
                 ("client_secret", self.client_secret.as_str()),
                 ("code", code),
                 ("redirect_uri", self.redirect_uri.as_str()),
                 ("grant_type", "authorization_code"),
-            ])
-            .send()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to exchange code: {}", e)))?;
-
-        let token_response: GoogleTokenResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to parse token response: {}", e)))?;
-
-        Ok(token_response)
-    }
-
-    /// Fetch the authenticated Google user's profile
-    pub async fn get_user_info(&self, access_token: &str) -> Result<GoogleUserInfo, AppError> {
-        let user_info: GoogleUserInfo = self
-            .http_client
-            .get("https://www.googleapis.com/oauth2/v2/userinfo")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .send()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to fetch user info: {}", e)))?
-            .json()
-            .await
-            .map_err(|e| AppError::OAuthError(format!("Failed to parse user info: {}", e)))?;
-
-        Ok(user_info)
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Exchange a refresh token for a new access token
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("grant_type", "refresh_token"),
+            ]);
+
+        // Refreshing is repeatable, unlike redeeming a code
+        self.http.send_json(self.display_name(), request, Retry::Idempotent).await
+    }
+
+    /// Checks the id_token's signature against Google's published keys, then
+    /// its audience, issuer, expiry, nonce and hosted domain
+    async fn verify_id_token(&self, id_token: &str, nonce: Option<&str>) -> Result<GoogleIdTokenClaims, AppError> {
+        let claims: GoogleIdTokenClaims = oidc::verify_id_token(
+            &self.jwks,
+            self.display_name(),
+            id_token,
+            &self.client_id,
+            Some(&ISSUERS),
+        ).await?;
+        oidc::check_nonce(self.display_name(), claims.nonce.as_deref(), nonce)?;
+
+        if let Some(domain) = &self.hosted_domain {
+            if claims.hd.as_deref() != Some(domain.as_str()) {
+                return Err(AppError::OAuthError(format!("Only {} Google accounts can sign in", domain)));
+            }
+        }
+
+        Ok(claims)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GoogleOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Google
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Google"
+    }
+
+    fn authorize_url(&self, state: &str, nonce: &str) -> String {
+        self.get_authorize_url(state, nonce)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_token(code).await?.into())
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let id_token = tokens.id_token
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthError("Google did not return an id_token".to_string()))?;
+        let claims = self.verify_id_token(id_token, nonce).await?;
+
+        Ok(OAuthIdentity {
+            provider_user_id: claims.sub,
+            email: claims.email,
+            email_verified: claims.email_verified.unwrap_or(false),
+            name: claims.name,
+            avatar_url: claims.picture,
+        })
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_refresh_token(refresh_token).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, sign_hs256, timestamp, JwksServer, SigningKey};
+
+    const CLIENT_ID: &str = "client-id.apps.googleusercontent.com";
+    const NONCE: &str = "nonce-from-the-browser";
+
+    struct Fixture {
+        key: SigningKey,
+        jwks: JwksServer,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let key = SigningKey::rsa("google-key");
+            let jwks = JwksServer::spawn(vec![key.jwk()]).await;
+            Self { key, jwks }
+        }
+
+        fn client(&self, hosted_domain: Option<&str>) -> GoogleOAuthClient {
+            GoogleOAuthClient::new(
+                CLIENT_ID.to_string(),
+                "client-secret".to_string(),
+                "https://api.example.com/api/v1/auth/google/callback".to_string(),
+                OAuthEndpoints { jwks_url: Some(self.jwks.url.clone()), ..GoogleOAuthClient::default_endpoints() },
+                hosted_domain.map(str::to_string),
+                http_client(),
+            )
+        }
+
+        async fn verify(&self, claims: &Value) -> Result<GoogleIdTokenClaims, AppError> {
+            self.client(None).verify_id_token(&self.key.sign(claims), Some(NONCE)).await
+        }
+    }
+
+    fn claims() -> Value {
+        json!({
+            "iss": "https://accounts.google.com",
+            "aud": CLIENT_ID,
+            "sub": "100000000000000000001",
+            "email": "alice@example.com",
+            "email_verified": true,
+            "nonce": NONCE,
+            "iat": timestamp(0),
+            "exp": timestamp(600),
+        })
+    }
+
+    fn with(mut claims: Value, key: &str, value: Value) -> Value {
+        claims[key] = value;
+        claims
+    }
+
+    fn assert_rejected(result: Result<GoogleIdTokenClaims, AppError>, reason: &str) {
+        match result {
+            Err(AppError::OAuthError(message)) => assert!(message.contains(reason), "{}", message),
+            other => panic!("expected a rejection mentioning {:?}, got {:?}", reason, other.map(|claims| claims.sub)),
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_a_valid_token() {
+        let fixture = Fixture::new().await;
+
+        let claims = fixture.verify(&claims()).await.unwrap();
+
+        assert_eq!(claims.sub, "100000000000000000001");
+        assert_eq!(claims.email.as_deref(), Some("alice@example.com"));
+    }
+
+    #[tokio::test]
+    async fn accepts_both_issuer_spellings() {
+        let fixture = Fixture::new().await;
+
+        for issuer in ISSUERS {
+            fixture.verify(&with(claims(), "iss", json!(issuer))).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_another_issuer() {
+        let fixture = Fixture::new().await;
+
+        for issuer in ["https://accounts.google.com.evil.example", "http://accounts.google.com", "https://evil.example"] {
+            assert_rejected(fixture.verify(&with(claims(), "iss", json!(issuer))).await, "InvalidIssuer");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_another_audience() {
+        let fixture = Fixture::new().await;
+
+        let result = fixture.verify(&with(claims(), "aud", json!("someone-else.apps.googleusercontent.com"))).await;
+
+        assert_rejected(result, "InvalidAudience");
+    }
+
+    #[tokio::test]
+    async fn rejects_an_expired_token() {
+        let fixture = Fixture::new().await;
+
+        let result = fixture.verify(&with(claims(), "exp", json!(timestamp(-3600)))).await;
+
+        assert_rejected(result, "ExpiredSignature");
+    }
+
+    #[tokio::test]
+    async fn rejects_a_nonce_mismatch() {
+        let fixture = Fixture::new().await;
+
+        assert_rejected(fixture.verify(&with(claims(), "nonce", json!("another-nonce"))).await, "nonce mismatch");
+        assert_rejected(fixture.verify(&with(claims(), "nonce", Value::Null)).await, "nonce mismatch");
+    }
+
+    #[tokio::test]
+    async fn rejects_a_token_when_the_browser_has_no_nonce() {
+        let fixture = Fixture::new().await;
+
+        let result = fixture.client(None).verify_id_token(&fixture.key.sign(&claims()), None).await;
+
+        assert_rejected(result, "sign-in session expired");
+    }
+
+    #[tokio::test]
+    async fn enforces_the_hosted_domain() {
+        let fixture = Fixture::new().await;
+        let client = fixture.client(Some("corp.example.com"));
+
+        let workspace = with(claims(), "hd", json!("corp.example.com"));
+        client.verify_id_token(&fixture.key.sign(&workspace), Some(NONCE)).await.unwrap();
+
+        for hd in [json!("other.example.com"), Value::Null] {
+            let result = client.verify_id_token(&fixture.key.sign(&with(claims(), "hd", hd)), Some(NONCE)).await;
+            assert_rejected(result, "Only corp.example.com Google accounts");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_symmetrically_signed_token() {
+        let fixture = Fixture::new().await;
+        // HS256 keyed with the public modulus, the classic algorithm confusion attack
+        let secret = match fixture.key.jwk().algorithm {
+            jsonwebtoken::jwk::AlgorithmParameters::RSA(rsa) => rsa.n.into_bytes(),
+            _ => unreachable!(),
+        };
+        let token = sign_hs256(&fixture.key.kid, &secret, &claims());
+
+        let result = fixture.client(None).verify_id_token(&token, Some(NONCE)).await;
+
+        assert_rejected(result, "unexpected algorithm");
+    }
+
+    #[tokio::test]
+    async fn rejects_a_token_signed_by_an_unpublished_key() {
+        let fixture = Fixture::new().await;
+        let token = SigningKey::rsa("not-published").sign(&claims());
+
+        let result = fixture.client(None).verify_id_token(&token, Some(NONCE)).await;
+
+        assert_rejected(result, "unknown key");
     }
 }
@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey};
+use tokio::sync::RwLock;
+use crate::infrastructure::errors::AppError;
+use super::oauth::{OAuthHttpClient, Retry};
+
+/// How long fetched keys are trusted before they are fetched again
+const KEYS_TTL: Duration = Duration::from_secs(60 * 60);
+/// Unknown `kid`s trigger a refetch, but at most this often, so forged tokens
+/// cannot make us hammer the provider
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// A provider's published signing keys (JWKS), fetched lazily and cached.
+/// Refetched when they expire or when a token names a key we don't have,
+/// which is how key rotation shows up.
+pub struct JwksCache {
+    provider: &'static str,
+    url: String,
+    http: OAuthHttpClient,
+    cached: RwLock<Option<CachedKeys>>,
+}
+
+impl JwksCache {
+    pub fn new(provider: &'static str, url: String, http: OAuthHttpClient) -> Self {
+        Self { provider, url, http, cached: RwLock::new(None) }
+    }
+
+    /// The verification key and algorithm for `kid`
+    pub async fn key(&self, kid: &str) -> Result<(DecodingKey, Algorithm), AppError> {
+        {
+            let cached = self.cached.read().await;
+            if let Some(cached) = cached.as_ref().filter(|cached| cached.fetched_at.elapsed() < KEYS_TTL) {
+                if let Some(key) = self.find(&cached.keys, kid)? {
+                    return Ok(key);
+                }
+            }
+        }
+
+        let mut cached = self.cached.write().await;
+        // Another request may have refreshed while we waited for the lock
+        let stale = cached.as_ref().is_none_or(|cached| {
+            cached.fetched_at.elapsed() >= KEYS_TTL
+                || (cached.keys.find(kid).is_none() && cached.fetched_at.elapsed() >= MIN_REFRESH_INTERVAL)
+        });
+        if stale {
+            let keys: JwkSet = self.http.send_json(self.provider, self.http.get(&self.url), Retry::Idempotent).await?;
+            *cached = Some(CachedKeys { keys, fetched_at: Instant::now() });
+        }
+
+        let keys = &cached.as_ref().expect("keys were just fetched").keys;
+        self.find(keys, kid)?
+            .ok_or_else(|| AppError::OAuthError(format!("{} id_token is signed with an unknown key", self.provider)))
+    }
+
+    fn find(&self, keys: &JwkSet, kid: &str) -> Result<Option<(DecodingKey, Algorithm)>, AppError> {
+        let Some(jwk) = keys.find(kid) else {
+            return Ok(None);
+        };
+
+        // Only asymmetric keys; a published HMAC key would let anyone mint tokens
+        let algorithm = match &jwk.algorithm {
+            AlgorithmParameters::RSA(_) => Algorithm::RS256,
+            AlgorithmParameters::EllipticCurve(_) => Algorithm::ES256,
+            AlgorithmParameters::OctetKeyPair(_) => Algorithm::EdDSA,
+            AlgorithmParameters::OctetKey(_) => {
+                return Err(AppError::OAuthError(format!("{} published a symmetric signing key", self.provider)));
+            }
+        };
+
+        let key = DecodingKey::from_jwk(jwk)
+            .map_err(|e| AppError::OAuthError(format!("{} published an invalid signing key: {}", self.provider, e)))?;
+
+        Ok(Some((key, algorithm)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::jwk::{CommonParameters, Jwk, OctetKeyParameters, OctetKeyType};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, JwksServer, SigningKey};
+
+    /// Pretends the keys were fetched `age` ago
+    async fn age_keys(cache: &JwksCache, age: Duration) {
+        let mut cached = cache.cached.write().await;
+        let cached = cached.as_mut().expect("keys were fetched");
+        cached.fetched_at = Instant::now().checked_sub(age).expect("clock is past the key age");
+    }
+
+    #[tokio::test]
+    async fn caches_keys_between_lookups() {
+        let key = SigningKey::rsa("current");
+        let server = JwksServer::spawn(vec![key.jwk()]).await;
+        let cache = JwksCache::new("Test", server.url.clone(), http_client());
+
+        let (_, algorithm) = cache.key("current").await.unwrap();
+        cache.key("current").await.unwrap();
+
+        assert_eq!(algorithm, Algorithm::RS256);
+        assert_eq!(server.fetches(), 1);
+    }
+
+    #[tokio::test]
+    async fn refetches_expired_keys() {
+        let server = JwksServer::spawn(vec![SigningKey::rsa("current").jwk()]).await;
+        let cache = JwksCache::new("Test", server.url.clone(), http_client());
+        cache.key("current").await.unwrap();
+
+        age_keys(&cache, KEYS_TTL).await;
+        cache.key("current").await.unwrap();
+
+        assert_eq!(server.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_kid_refetches_at_most_once_per_interval() {
+        let server = JwksServer::spawn(vec![SigningKey::rsa("old").jwk()]).await;
+        let cache = JwksCache::new("Test", server.url.clone(), http_client());
+        cache.key("old").await.unwrap();
+
+        // The provider rotates, but the keys were fetched too recently to refetch
+        server.publish(vec![SigningKey::rsa("old").jwk(), SigningKey::rsa("new").jwk()]);
+        assert!(cache.key("new").await.is_err());
+        assert!(cache.key("new").await.is_err());
+        assert_eq!(server.fetches(), 1);
+
+        age_keys(&cache, MIN_REFRESH_INTERVAL).await;
+        cache.key("new").await.unwrap();
+        assert_eq!(server.fetches(), 2);
+
+        // A kid nobody published doesn't cause another fetch right away
+        assert!(cache.key("forged").await.is_err());
+        assert_eq!(server.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_published_symmetric_key() {
+        let octet = Jwk {
+            common: CommonParameters { key_id: Some("shared".to_string()), ..CommonParameters::default() },
+            algorithm: AlgorithmParameters::OctetKey(OctetKeyParameters {
+                key_type: OctetKeyType::Octet,
+                value: "c2VjcmV0".to_string(),
+            }),
+        };
+        let server = JwksServer::spawn(vec![octet]).await;
+        let cache = JwksCache::new("Test", server.url.clone(), http_client());
+
+        let error = cache.key("shared").await.err().expect("symmetric key accepted");
+
+        assert!(matches!(&error, AppError::OAuthError(message) if message.contains("symmetric")), "{:?}", error);
+    }
+}
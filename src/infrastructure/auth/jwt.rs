@@ -1,7 +1,10 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
+use ring::hmac;
 use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey, TokenData};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::config::JwtConfig;
 use crate::infrastructure::errors::AppError;
 
 use crate::domain::entities::user::{User, Role};
@@ -17,23 +20,99 @@ pub struct Claims {
     pub exp: usize,
     pub iat: usize,
     pub token_type: String, // "access" or "refresh"
+    /// Present only on impersonation tokens: the admin acting as `sub` (RFC 8693)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaim>,
+    /// The session the token belongs to; absent on impersonation tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActorClaim {
+    pub sub: Uuid,
 }
 
 pub struct JwtService {
     secret: String,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
+    impersonation_token_ttl: Duration,
 }
 
 impl JwtService {
-    pub fn new(secret: String) -> Self {
-        Self { secret }
+    pub fn new(config: &JwtConfig) -> Self {
+        Self {
+            secret: config.secret.expose().to_string(),
+            access_token_ttl: Duration::seconds(config.access_token_ttl_secs as i64),
+            refresh_token_ttl: Duration::seconds(config.refresh_token_ttl_secs as i64),
+            impersonation_token_ttl: Duration::seconds(config.impersonation_token_ttl_secs as i64),
+        }
+    }
+
+    /// Lifetime of access tokens in seconds, as reported in `expires_in`
+    pub fn access_token_ttl_secs(&self) -> usize {
+        self.access_token_ttl.num_seconds() as usize
     }
 
-    pub fn generate_tokens(&self, user: &User) -> Result<(String, String), AppError> {
+    pub fn impersonation_token_ttl_secs(&self) -> usize {
+        self.impersonation_token_ttl.num_seconds() as usize
+    }
+
+    pub fn refresh_token_ttl_secs(&self) -> usize {
+        self.refresh_token_ttl.num_seconds() as usize
+    }
+
+    /// CSRF token for a cookie session: an HMAC of its access token, so it cannot
+    /// be forged or planted without the (HttpOnly) token itself
+    pub fn csrf_token(&self, access_token: &str) -> String {
+        let tag = hmac::sign(&self.derived_key(b"csrf"), access_token.as_bytes());
+        URL_SAFE_NO_PAD.encode(tag.as_ref())
+    }
+
+    /// Constant-time check of a token from `csrf_token`
+    pub fn verify_csrf_token(&self, access_token: &str, csrf_token: &str) -> bool {
+        URL_SAFE_NO_PAD
+            .decode(csrf_token)
+            .is_ok_and(|tag| hmac::verify(&self.derived_key(b"csrf"), access_token.as_bytes(), &tag).is_ok())
+    }
+
+    /// `state` parameter of an OAuth sign-in: the page to return to, signed
+    /// together with the sign-in's nonce, so the callback only completes in the
+    /// browser that started it and the destination cannot be swapped
+    pub fn oauth_state(&self, nonce: &str, redirect_to: Option<&str>) -> String {
+        let redirect_to = redirect_to.unwrap_or_default();
+        let tag = hmac::sign(&self.derived_key(b"oauth_state"), oauth_state_message(nonce, redirect_to).as_bytes());
+        format!("{}.{}", URL_SAFE_NO_PAD.encode(redirect_to), URL_SAFE_NO_PAD.encode(tag.as_ref()))
+    }
+
+    /// The `redirect_to` a state from `oauth_state` carries, if it was issued for `nonce`
+    pub fn verify_oauth_state(&self, nonce: &str, state: &str) -> Result<Option<String>, AppError> {
+        let (redirect_to, tag) = state.split_once('.').ok_or(AppError::OAuthStateInvalid)?;
+        let redirect_to = URL_SAFE_NO_PAD
+            .decode(redirect_to)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(AppError::OAuthStateInvalid)?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).map_err(|_| AppError::OAuthStateInvalid)?;
+        hmac::verify(&self.derived_key(b"oauth_state"), oauth_state_message(nonce, &redirect_to).as_bytes(), &tag)
+            .map_err(|_| AppError::OAuthStateInvalid)?;
+
+        Ok((!redirect_to.is_empty()).then_some(redirect_to))
+    }
+
+    /// A key per purpose, separate from the signing key, so these tags are never
+    /// valid JWT signatures or valid for one another
+    fn derived_key(&self, purpose: &[u8]) -> hmac::Key {
+        let derived = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, self.secret.as_bytes()), purpose);
+        hmac::Key::new(hmac::HMAC_SHA256, derived.as_ref())
+    }
+
+    pub fn generate_tokens(&self, user: &User, session_id: Uuid) -> Result<(String, String), AppError> {
         let now = Utc::now();
         let iat = now.timestamp() as usize;
 
-        // Access Token (15 minutes)
-        let exp_access = (now + Duration::minutes(15)).timestamp() as usize;
+        let exp_access = (now + self.access_token_ttl).timestamp() as usize;
         let access_claims = Claims {
             sub: user.id,
             name: user.name.clone(),
@@ -44,6 +123,8 @@ impl JwtService {
             exp: exp_access,
             iat,
             token_type: "access".to_string(),
+            act: None,
+            sid: Some(session_id),
         };
         let access_token = encode(
             &Header::default(),
@@ -51,8 +132,7 @@ impl JwtService {
             &EncodingKey::from_secret(self.secret.as_bytes()),
         ).map_err(|_| AppError::TokenCreationError)?;
 
-        // Refresh Token (7 days)
-        let exp_refresh = (now + Duration::days(7)).timestamp() as usize;
+        let exp_refresh = (now + self.refresh_token_ttl).timestamp() as usize;
         let refresh_claims = Claims {
             sub: user.id,
             name: user.name.clone(),
@@ -63,6 +143,8 @@ impl JwtService {
             exp: exp_refresh,
             iat,
             token_type: "refresh".to_string(),
+            act: None,
+            sid: Some(session_id),
         };
         let refresh_token = encode(
             &Header::default(),
@@ -73,6 +155,32 @@ impl JwtService {
         Ok((access_token, refresh_token))
     }
 
+    /// Issues a short-lived access token for `user` on behalf of `actor_id`.
+    /// No refresh token is issued, so the session ends when this one expires.
+    pub fn generate_impersonation_token(&self, user: &User, actor_id: Uuid) -> Result<String, AppError> {
+        let now = Utc::now();
+
+        let claims = Claims {
+            sub: user.id,
+            name: user.name.clone(),
+            email: user.email.clone(),
+            phone: user.phone.clone(),
+            role: user.role.clone(),
+            avatar_url: user.avatar_url.clone(),
+            exp: (now + self.impersonation_token_ttl).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            token_type: "access".to_string(),
+            act: Some(ActorClaim { sub: actor_id }),
+            sid: None,
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.secret.as_bytes()),
+        ).map_err(|_| AppError::TokenCreationError)
+    }
+
     pub fn verify_token(&self, token: &str) -> Result<TokenData<Claims>, AppError> {
         decode::<Claims>(
             token,
@@ -84,3 +192,8 @@ impl JwtService {
         })
     }
 }
+
+/// Nonces are base64url, so the first `.` always ends the nonce
+fn oauth_state_message(nonce: &str, redirect_to: &str) -> String {
+    format!("{}.{}", nonce, redirect_to)
+}
@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use crate::domain::dtos::{MicrosoftIdTokenClaims, OAuthTokenResponse};
+use crate::domain::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+use super::jwks::JwksCache;
+use super::oauth::{OAuthEndpoints, OAuthHttpClient, OAuthIdentity, OAuthProvider, OAuthTokens, Retry};
+use super::oidc;
+
+pub const MICROSOFT_AUTHORITY: &str = "https://login.microsoftonline.com";
+pub const DEFAULT_TENANT: &str = "common";
+
+/// Tenant all personal Microsoft accounts (outlook.com, live.com, ...) belong to
+const CONSUMERS_TENANT_ID: &str = "9188040d-6c67-4c5b-b112-36a304b66dad";
+
+/// Microsoft identity platform (Entra ID and personal Microsoft accounts)
+pub struct MicrosoftOAuthClient {
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    endpoints: OAuthEndpoints,
+    authority: String,
+    tenant: String,
+    http: OAuthHttpClient,
+    jwks: JwksCache,
+}
+
+impl MicrosoftOAuthClient {
+    /// The endpoints for `tenant` at `authority`. `tenant` is a tenant id, or
+    /// `common` (any account), `organizations` (work and school accounts only)
+    /// or `consumers` (personal accounts only).
+    pub fn default_endpoints(authority: &str, tenant: &str) -> OAuthEndpoints {
+        let base = format!("{}/{}", authority.trim_end_matches('/'), tenant);
+        OAuthEndpoints {
+            authorize_url: format!("{}/oauth2/v2.0/authorize", base),
+            token_url: format!("{}/oauth2/v2.0/token", base),
+            userinfo_url: None,
+            jwks_url: Some(format!("{}/discovery/v2.0/keys", base)),
+        }
+    }
+
+    pub fn new(
+        client_id: String,
+        client_secret: String,
+        redirect_uri: String,
+        endpoints: OAuthEndpoints,
+        authority: &str,
+        tenant: String,
+        http: OAuthHttpClient,
+    ) -> Self {
+        let jwks_url = endpoints.jwks_url
+            .clone()
+            .expect("Microsoft endpoints always include jwks_url");
+        Self {
+            client_id,
+            client_secret,
+            redirect_uri,
+            endpoints,
+            authority: authority.trim_end_matches('/').to_string(),
+            tenant,
+            jwks: JwksCache::new("Microsoft", jwks_url, http.clone()),
+            http,
+        }
+    }
+
+    /// Returns the Microsoft authorization URL to redirect the user to
+    fn get_authorize_url(&self, state: &str, nonce: &str) -> String {
+        format!(
+            "{}?client_id={}&redirect_uri={}&response_type=code&response_mode=query&scope=openid%20profile%20email%20offline_access&state={}&nonce={}",
+            self.endpoints.authorize_url,
+            self.client_id,
+            urlencoding::encode(&self.redirect_uri),
+            urlencoding::encode(state),
+            urlencoding::encode(nonce),
+        )
+    }
+
+    /// Exchange the authorization code for tokens
+    async fn exchange_token(&self, code: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("grant_type", "authorization_code"),
+            ]);
+
+        self.http.send_json(self.display_name(), request, Retry::ConnectFailuresOnly).await
+    }
+
+    /// Exchange a refresh token for new tokens
+    async fn exchange_refresh_token(&self, refresh_token: &str) -> Result<OAuthTokenResponse, AppError> {
+        let request = self
+            .http
+            .post(&self.endpoints.token_url)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("refresh_token", refresh_token),
+                ("grant_type", "refresh_token"),
+            ]);
+
+        // Microsoft rotates refresh tokens, but the old one stays valid, so a retry is safe
+        self.http.send_json(self.display_name(), request, Retry::Idempotent).await
+    }
+
+    /// Checks the id_token's signature, audience, expiry and nonce, then that it
+    /// was issued by the account's own tenant and that the tenant is allowed
+    async fn verify_id_token(&self, id_token: &str, nonce: Option<&str>) -> Result<MicrosoftIdTokenClaims, AppError> {
+        // With `common` and friends every tenant has its own issuer, so it is checked below
+        let claims: MicrosoftIdTokenClaims = oidc::verify_id_token(
+            &self.jwks,
+            self.display_name(),
+            id_token,
+            &self.client_id,
+            None,
+        ).await?;
+        oidc::check_nonce(self.display_name(), claims.nonce.as_deref(), nonce)?;
+
+        if claims.iss != format!("{}/{}/v2.0", self.authority, claims.tid) {
+            return Err(oidc::invalid_id_token(self.display_name(), "issuer does not match the tenant"));
+        }
+
+        let allowed = match self.tenant.as_str() {
+            "common" => true,
+            "organizations" => claims.tid != CONSUMERS_TENANT_ID,
+            "consumers" => claims.tid == CONSUMERS_TENANT_ID,
+            tenant_id => claims.tid.eq_ignore_ascii_case(tenant_id),
+        };
+        if !allowed {
+            return Err(AppError::OAuthError("This Microsoft account cannot sign in to this app".to_string()));
+        }
+
+        Ok(claims)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for MicrosoftOAuthClient {
+    fn provider(&self) -> AuthProvider {
+        AuthProvider::Microsoft
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Microsoft"
+    }
+
+    fn authorize_url(&self, state: &str, nonce: &str) -> String {
+        self.get_authorize_url(state, nonce)
+    }
+
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_token(code).await?.into())
+    }
+
+    async fn fetch_identity(&self, tokens: &OAuthTokens, nonce: Option<&str>) -> Result<OAuthIdentity, AppError> {
+        let id_token = tokens.id_token
+            .as_deref()
+            .ok_or_else(|| AppError::OAuthError("Microsoft did not return an id_token".to_string()))?;
+        let claims = self.verify_id_token(id_token, nonce).await?;
+
+        // Tenant admins can set any email on their accounts, so it only counts as
+        // verified when Microsoft says the tenant owns the domain
+        let email_verified = claims.email.is_some() && claims.xms_edov == Some(true);
+        let email = claims.email.or_else(|| claims.preferred_username.filter(|username| username.contains('@')));
+
+        Ok(OAuthIdentity {
+            // oid is only unique within a tenant
+            provider_user_id: format!("{}:{}", claims.tid, claims.oid),
+            email,
+            email_verified,
+            name: claims.name,
+            avatar_url: None,
+        })
+    }
+
+    async fn refresh_tokens(&self, refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Ok(self.exchange_refresh_token(refresh_token).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::{http_client, timestamp, JwksServer, SigningKey};
+
+    const CLIENT_ID: &str = "5f1b2c3d-microsoft-client-id";
+    const NONCE: &str = "nonce-from-the-browser";
+    const CONTOSO_TENANT_ID: &str = "3f0c2a8e-5b7d-4e1a-9c6f-1d2e3f4a5b6c";
+
+    struct Fixture {
+        key: SigningKey,
+        jwks: JwksServer,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let key = SigningKey::rsa("microsoft-key");
+            let jwks = JwksServer::spawn(vec![key.jwk()]).await;
+            Self { key, jwks }
+        }
+
+        fn client(&self, tenant: &str) -> MicrosoftOAuthClient {
+            let endpoints = MicrosoftOAuthClient::default_endpoints(MICROSOFT_AUTHORITY, tenant);
+            MicrosoftOAuthClient::new(
+                CLIENT_ID.to_string(),
+                "client-secret".to_string(),
+                "https://api.example.com/api/v1/auth/microsoft/callback".to_string(),
+                OAuthEndpoints { jwks_url: Some(self.jwks.url.clone()), ..endpoints },
+                MICROSOFT_AUTHORITY,
+                tenant.to_string(),
+                http_client(),
+            )
+        }
+
+        async fn identity(&self, tenant: &str, claims: &Value) -> Result<OAuthIdentity, AppError> {
+            let tokens = OAuthTokens {
+                access_token: "access-token".to_string(),
+                refresh_token: None,
+                expires_in: None,
+                id_token: Some(self.key.sign(claims)),
+            };
+            self.client(tenant).fetch_identity(&tokens, Some(NONCE)).await
+        }
+    }
+
+    /// An id_token of a work account in `tid`, issued by that tenant
+    fn claims(tid: &str) -> Value {
+        json!({
+            "iss": format!("{}/{}/v2.0", MICROSOFT_AUTHORITY, tid),
+            "aud": CLIENT_ID,
+            "sub": "pairwise-subject-for-this-app",
+            "tid": tid,
+            "oid": "0b9e2f64-1c4d-4a8b-9e3f-5d6c7b8a9f01",
+            "email": "adele@contoso.example.com",
+            "name": "Adele Vance",
+            "xms_edov": true,
+            "nonce": NONCE,
+            "iat": timestamp(0),
+            "exp": timestamp(600),
+        })
+    }
+
+    fn with(mut claims: Value, key: &str, value: Value) -> Value {
+        claims[key] = value;
+        claims
+    }
+
+    fn assert_rejected(result: Result<OAuthIdentity, AppError>, reason: &str) {
+        match result {
+            Err(AppError::OAuthError(message)) => assert!(message.contains(reason), "{}", message),
+            other => panic!("expected a rejection mentioning {:?}, got {:?}", reason, other.map(|identity| identity.provider_user_id)),
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_the_identity_by_tenant_and_object_id() {
+        let fixture = Fixture::new().await;
+
+        let identity = fixture.identity(DEFAULT_TENANT, &claims(CONTOSO_TENANT_ID)).await.unwrap();
+
+        assert_eq!(identity.provider_user_id, format!("{}:0b9e2f64-1c4d-4a8b-9e3f-5d6c7b8a9f01", CONTOSO_TENANT_ID));
+        assert_eq!(identity.email.as_deref(), Some("adele@contoso.example.com"));
+        assert!(identity.email_verified);
+        assert_eq!(identity.name.as_deref(), Some("Adele Vance"));
+    }
+
+    #[tokio::test]
+    async fn rejects_an_issuer_of_another_tenant() {
+        let fixture = Fixture::new().await;
+        // A token from the attacker's own tenant, claiming to belong to Contoso
+        let forged = with(claims(CONTOSO_TENANT_ID), "iss", json!(format!("{}/{}/v2.0", MICROSOFT_AUTHORITY, CONSUMERS_TENANT_ID)));
+
+        assert_rejected(fixture.identity(DEFAULT_TENANT, &forged).await, "issuer does not match the tenant");
+    }
+
+    #[tokio::test]
+    async fn enforces_the_configured_tenant() {
+        let fixture = Fixture::new().await;
+        let work = claims(CONTOSO_TENANT_ID);
+        let personal = claims(CONSUMERS_TENANT_ID);
+        let refused = "cannot sign in to this app";
+
+        fixture.identity("organizations", &work).await.unwrap();
+        assert_rejected(fixture.identity("organizations", &personal).await, refused);
+
+        fixture.identity("consumers", &personal).await.unwrap();
+        assert_rejected(fixture.identity("consumers", &work).await, refused);
+
+        fixture.identity(&CONTOSO_TENANT_ID.to_uppercase(), &work).await.unwrap();
+        assert_rejected(fixture.identity(CONTOSO_TENANT_ID, &personal).await, refused);
+    }
+
+    #[tokio::test]
+    async fn trusts_the_email_only_when_the_tenant_owns_its_domain() {
+        let fixture = Fixture::new().await;
+
+        for xms_edov in [json!(false), Value::Null] {
+            let identity = fixture.identity(DEFAULT_TENANT, &with(claims(CONTOSO_TENANT_ID), "xms_edov", xms_edov)).await.unwrap();
+            assert_eq!(identity.email.as_deref(), Some("adele@contoso.example.com"));
+            assert!(!identity.email_verified);
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_an_unverified_sign_in_name() {
+        let fixture = Fixture::new().await;
+        let without_email = with(claims(CONTOSO_TENANT_ID), "email", Value::Null);
+
+        let identity = fixture.identity(DEFAULT_TENANT, &with(without_email.clone(), "preferred_username", json!("adele@contoso.example.com"))).await.unwrap();
+        assert_eq!(identity.email.as_deref(), Some("adele@contoso.example.com"));
+        assert!(!identity.email_verified);
+
+        let identity = fixture.identity(DEFAULT_TENANT, &with(without_email, "preferred_username", json!("adele"))).await.unwrap();
+        assert_eq!(identity.email, None);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_nonce_mismatch() {
+        let fixture = Fixture::new().await;
+
+        let result = fixture.identity(DEFAULT_TENANT, &with(claims(CONTOSO_TENANT_ID), "nonce", json!("another-nonce"))).await;
+
+        assert_rejected(result, "nonce mismatch");
+    }
+}
@@ -1,22 +1,65 @@
 use axum::{
-    extract::{FromRequestParts, FromRef},
-    http::request::Parts,
-    RequestPartsExt,
+    extract::{FromRequestParts, FromRef, Request, State},
+    http::{request::Parts, HeaderMap},
+    middleware::Next,
+    response::Response,
 };
 
-use axum_extra::{
-    headers::{authorization::Bearer, Authorization},
-    TypedHeader,
-};
+use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
 use jsonwebtoken::TokenData;
+use serde_json::json;
+use uuid::Uuid;
 use crate::AppState;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::repositories::audit_repository::AuditRepository;
 use crate::infrastructure::auth::jwt::Claims;
+use crate::infrastructure::auth::session_cookies;
 use crate::infrastructure::errors::AppError;
+use crate::infrastructure::request_context::RequestContext;
 
 pub struct AuthUser {
     pub claims: TokenData<Claims>,
 }
 
+impl AuthUser {
+    /// Returns the id of the admin impersonating this user, if any
+    pub fn actor_id(&self) -> Option<Uuid> {
+        self.claims.claims.act.as_ref().map(|act| act.sub)
+    }
+
+    /// The session the token belongs to; `None` for impersonation tokens
+    pub fn session_id(&self) -> Option<Uuid> {
+        self.claims.claims.sid
+    }
+
+    /// Rejects impersonated sessions for sensitive operations (password, MFA, ...)
+    pub fn ensure_not_impersonated(&self) -> Result<(), AppError> {
+        if self.actor_id().is_some() {
+            return Err(AppError::ImpersonationNotAllowed);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum TokenSource {
+    Header,
+    Cookie,
+}
+
+/// The request's access token, not yet verified. The Authorization header wins;
+/// the session cookie is the fallback in cookie mode.
+fn request_token(state: &AppState, headers: &HeaderMap) -> Option<(String, TokenSource)> {
+    if let Some(Authorization(bearer)) = headers.typed_get::<Authorization<Bearer>>() {
+        return Some((bearer.token().to_string(), TokenSource::Header));
+    }
+
+    state.session_cookies
+        .as_ref()
+        .and_then(|_| session_cookies::access_token(headers))
+        .map(|token| (token.to_string(), TokenSource::Cookie))
+}
+
 // Manual implementation to avoid lifetime issues with async_trait
 impl<S> FromRequestParts<S> for AuthUser
 where
@@ -27,19 +70,16 @@ where
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let state = AppState::from_ref(state);
-        
-        // Extract the token from the Authorization header
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|e| {
-                tracing::error!("Auth Header Extraction Error: {:?}", e);
-                AppError::InvalidToken
-            })?;
+
+        let (token, source) = request_token(&state, &parts.headers).ok_or(AppError::InvalidToken)?;
+        // Browsers attach cookies to cross-site requests too
+        if source == TokenSource::Cookie && !parts.method.is_safe() {
+            session_cookies::verify_csrf(&state.jwt_service, &parts.headers, &token)?;
+        }
 
         // Verify the token
-        let token_data = state.jwt_service.verify_token(bearer.token())?;
-        
+        let token_data = state.jwt_service.verify_token(&token)?;
+
         // Ensure it is an access token
         if token_data.claims.token_type != "access" {
             return Err(AppError::InvalidToken);
@@ -48,3 +88,43 @@ where
         Ok(AuthUser { claims: token_data })
     }
 }
+
+/// Records every request made with an impersonation token in the audit log
+pub async fn audit_impersonation(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let impersonation = request_token(&state, request.headers())
+        .and_then(|(token, _)| state.jwt_service.verify_token(&token).ok())
+        .and_then(|token_data| {
+            let claims = token_data.claims;
+            claims.act.map(|act| (act.sub, claims.sub))
+        });
+
+    let ctx = RequestContext::from_http(request.headers(), request.extensions());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+
+    if let Some((actor_id, target_id)) = impersonation {
+        let event = AuditEvent::new(
+            Some(actor_id),
+            Some(target_id),
+            AuditAction::ImpersonatedRequest,
+            Some(json!({
+                "method": method,
+                "path": path,
+                "status": response.status().as_u16(),
+            })),
+        )
+        .with_context(&ctx);
+
+        if let Err(e) = state.audit_repository.create(&event).await {
+            tracing::error!("Failed to record impersonated request: {:?}", e);
+        }
+    }
+
+    response
+}
@@ -3,3 +3,14 @@ pub mod password;
 pub mod middleware;
 pub mod github;
 pub mod google;
+pub mod gitlab;
+pub mod microsoft;
+pub mod discord;
+pub mod apple;
+pub mod token;
+pub mod oauth;
+pub mod jwks;
+pub mod oidc;
+pub mod session_cookies;
+#[cfg(test)]
+pub(crate) mod test_support;
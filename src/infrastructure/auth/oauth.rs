@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use crate::config::OAuthHttpConfig;
+use crate::domain::dtos::OAuthTokenResponse;
+use crate::domain::entities::user::AuthProvider;
+use crate::infrastructure::errors::AppError;
+
+/// Where a provider's OAuth endpoints live; configurable for self-hosted
+/// installations and local mock servers
+#[derive(Debug, Clone)]
+pub struct OAuthEndpoints {
+    pub authorize_url: String,
+    pub token_url: String,
+    /// Profile endpoint, for providers that are not OpenID Connect
+    pub userinfo_url: Option<String>,
+    /// Signing keys for id_tokens, for OpenID Connect providers
+    pub jwks_url: Option<String>,
+}
+
+/// Tokens returned by a provider's token endpoint
+#[derive(Debug, Clone)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_in: Option<u64>,
+    pub id_token: Option<String>,
+}
+
+impl From<OAuthTokenResponse> for OAuthTokens {
+    fn from(response: OAuthTokenResponse) -> Self {
+        Self {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_in: response.expires_in,
+            id_token: response.id_token,
+        }
+    }
+}
+
+/// The signed-in account as reported by the provider, normalized across providers
+#[derive(Debug, Clone)]
+pub struct OAuthIdentity {
+    /// Stable account id at the provider (never the email)
+    pub provider_user_id: String,
+    pub email: Option<String>,
+    /// Only verified emails are used to link an existing account
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// An OAuth 2.0 sign-in provider
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+    fn provider(&self) -> AuthProvider;
+
+    /// Human-readable name for login buttons
+    fn display_name(&self) -> &'static str;
+
+    /// Where to send the user's browser to start signing in. `state` is echoed
+    /// back to the callback as is. `nonce` is bound to the browser and must come
+    /// back in the id_token of OpenID Connect providers.
+    fn authorize_url(&self, state: &str, nonce: &str) -> String;
+
+    /// Whether the provider POSTs the callback as a form (`response_mode=form_post`)
+    /// instead of redirecting with a query string
+    fn uses_form_post(&self) -> bool {
+        false
+    }
+
+    /// Display name from the `user` field some providers send with the callback
+    /// itself rather than in the identity
+    fn callback_name(&self, _user: &str) -> Option<String> {
+        None
+    }
+
+    /// Exchanges the authorization code from the callback for tokens
+    async fn exchange_code(&self, code: &str) -> Result<OAuthTokens, AppError>;
+
+    /// `nonce` is the value sent with `authorize_url`, if the browser still has it
+    async fn fetch_identity(&self, tokens: &OAuthTokens, nonce: Option<&str>) -> Result<OAuthIdentity, AppError>;
+
+    /// Gets a new access token. The response may omit the refresh token, in which
+    /// case the old one stays valid.
+    async fn refresh_tokens(&self, _refresh_token: &str) -> Result<OAuthTokens, AppError> {
+        Err(AppError::OAuthError(format!("{} tokens cannot be refreshed", self.display_name())))
+    }
+}
+
+/// The enabled providers, keyed by name (`github`, `google`, ...)
+#[derive(Default)]
+pub struct OAuthProviderRegistry {
+    providers: BTreeMap<&'static str, Arc<dyn OAuthProvider>>,
+}
+
+impl OAuthProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, provider: Arc<dyn OAuthProvider>) {
+        self.providers.insert(provider.provider().as_str(), provider);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn OAuthProvider>> {
+        self.providers.get(name).cloned()
+    }
+
+    /// Providers in name order
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn OAuthProvider>> {
+        self.providers.values()
+    }
+}
+
+/// Whether a request may be sent again after it might have reached the provider.
+/// Authorization codes are single-use, so a code exchange is only retried when
+/// the connection could not be made at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retry {
+    Idempotent,
+    ConnectFailuresOnly,
+}
+
+/// HTTP client shared by the OAuth providers: timeouts, bounded retries with
+/// backoff, and provider errors turned into specific `AppError`s
+#[derive(Clone)]
+pub struct OAuthHttpClient {
+    client: Client,
+    max_retries: u32,
+}
+
+/// RFC 6749 error body; GitHub sends it with status 200
+#[derive(Deserialize)]
+struct ProviderErrorBody {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl OAuthHttpClient {
+    pub fn new(config: &OAuthHttpConfig) -> Self {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent("rust-axum-app")
+            .build()
+            .expect("Failed to build OAuth HTTP client");
+
+        Self { client, max_retries: config.max_retries }
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends `request` and decodes a successful JSON response. `provider` names
+    /// the provider in errors.
+    pub async fn send_json<T: DeserializeOwned>(&self, provider: &str, request: RequestBuilder, retry: Retry) -> Result<T, AppError> {
+        let mut attempt = 0;
+        loop {
+            let outcome = request
+                .try_clone()
+                .ok_or(AppError::InternalServerError)?
+                .send()
+                .await;
+
+            let transient = match &outcome {
+                Err(e) => e.is_connect() || (retry == Retry::Idempotent && e.is_timeout()),
+                Ok(response) => {
+                    retry == Retry::Idempotent
+                        && (response.status().is_server_error() || response.status() == StatusCode::TOO_MANY_REQUESTS)
+                }
+            };
+
+            if transient && attempt < self.max_retries {
+                attempt += 1;
+                tracing::warn!("{} request failed, retrying ({}/{})", provider, attempt, self.max_retries);
+                tokio::time::sleep(backoff(attempt)).await;
+                continue;
+            }
+
+            return match outcome {
+                Ok(response) => decode(provider, response).await,
+                Err(e) => Err(transport_error(provider, e)),
+            };
+        }
+    }
+}
+
+/// 250ms, 500ms, 1s, ... plus up to 100ms of jitter
+fn backoff(attempt: u32) -> Duration {
+    let base = 250u64 << (attempt - 1).min(4);
+    Duration::from_millis(base + rand::thread_rng().gen_range(0..100))
+}
+
+fn transport_error(provider: &str, e: reqwest::Error) -> AppError {
+    tracing::warn!("{} request failed: {}", provider, e);
+    if e.is_timeout() {
+        AppError::OAuthProviderTimeout(provider.to_string())
+    } else {
+        AppError::OAuthProviderUnavailable(provider.to_string())
+    }
+}
+
+async fn decode<T: DeserializeOwned>(provider: &str, response: Response) -> Result<T, AppError> {
+    let status = response.status();
+    let body = response.bytes().await.map_err(|e| transport_error(provider, e))?;
+
+    if let Ok(error) = serde_json::from_slice::<ProviderErrorBody>(&body) {
+        return Err(provider_error(provider, &error.error, error.error_description.as_deref()));
+    }
+
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        tracing::warn!("{} responded with {}", provider, status);
+        return Err(AppError::OAuthProviderUnavailable(provider.to_string()));
+    }
+    if !status.is_success() {
+        return Err(AppError::OAuthError(format!("{} responded with {}", provider, status)));
+    }
+
+    serde_json::from_slice(&body)
+        .map_err(|e| AppError::OAuthError(format!("Unexpected response from {}: {}", provider, e)))
+}
+
+/// Maps an OAuth error code, from a provider response or the callback query, to an `AppError`
+pub fn provider_error(provider: &str, error: &str, description: Option<&str>) -> AppError {
+    let detail = match description {
+        Some(description) => format!("{} returned {}: {}", provider, error, description),
+        None => format!("{} returned {}", provider, error),
+    };
+
+    match error {
+        // GitHub says bad_verification_code for expired and reused codes alike
+        "invalid_grant" | "bad_verification_code" => AppError::OAuthInvalidCode,
+        "access_denied" => AppError::OAuthAccessDenied,
+        "invalid_client" | "unauthorized_client" | "incorrect_client_credentials" | "redirect_uri_mismatch" | "invalid_scope" => {
+            AppError::OAuthMisconfigured(detail)
+        }
+        "server_error" | "temporarily_unavailable" => {
+            tracing::warn!("{}", detail);
+            AppError::OAuthProviderUnavailable(provider.to_string())
+        }
+        _ => AppError::OAuthError(detail),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use axum::{http::StatusCode as HttpStatus, routing::post, Json, Router};
+    use serde_json::{json, Value};
+    use super::*;
+    use crate::infrastructure::auth::test_support::serve;
+
+    /// An endpoint answering each request with the next of `responses` (the
+    /// last one repeats), and a count of the requests it got
+    async fn endpoint(responses: Vec<(u16, Value)>) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let router = Router::new().route("/token", post(move || async move {
+            let hit = counter.fetch_add(1, Ordering::SeqCst);
+            let (status, body) = responses[hit.min(responses.len() - 1)].clone();
+            (HttpStatus::from_u16(status).unwrap(), Json(body))
+        }));
+        (format!("{}/token", serve(router).await), hits)
+    }
+
+    async fn send(responses: Vec<(u16, Value)>, retry: Retry) -> (Result<Value, AppError>, usize) {
+        let (url, hits) = endpoint(responses).await;
+        let client = OAuthHttpClient::new(&OAuthHttpConfig { max_retries: 2, ..OAuthHttpConfig::default() });
+
+        let result = client.send_json::<Value>("Example", client.post(&url), retry).await;
+        (result, hits.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn an_error_body_with_status_200_is_an_error() {
+        // GitHub reports a used code this way
+        let (result, _) = send(vec![(200, json!({ "error": "bad_verification_code", "error_description": "expired" }))], Retry::ConnectFailuresOnly).await;
+
+        assert!(matches!(result, Err(AppError::OAuthInvalidCode)));
+    }
+
+    #[tokio::test]
+    async fn decodes_a_successful_response() {
+        let (result, hits) = send(vec![(200, json!({ "access_token": "token" }))], Retry::Idempotent).await;
+
+        assert_eq!(result.unwrap(), json!({ "access_token": "token" }));
+        assert_eq!(hits, 1);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_for_idempotent_calls() {
+        let responses = vec![(503, json!({})), (429, json!({})), (200, json!({ "id": 1 }))];
+
+        let (result, hits) = send(responses, Retry::Idempotent).await;
+
+        assert_eq!(result.unwrap(), json!({ "id": 1 }));
+        assert_eq!(hits, 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (result, hits) = send(vec![(502, json!({}))], Retry::Idempotent).await;
+
+        assert!(matches!(result, Err(AppError::OAuthProviderUnavailable(provider)) if provider == "Example"));
+        assert_eq!(hits, 3);
+    }
+
+    #[tokio::test]
+    async fn a_code_exchange_is_not_retried_after_a_server_error() {
+        let responses = vec![(500, json!({})), (200, json!({ "access_token": "token" }))];
+
+        let (result, hits) = send(responses, Retry::ConnectFailuresOnly).await;
+
+        assert!(matches!(result, Err(AppError::OAuthProviderUnavailable(_))));
+        assert_eq!(hits, 1);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_never_retried() {
+        let (result, hits) = send(vec![(400, json!({ "error": "invalid_client" }))], Retry::Idempotent).await;
+        assert!(matches!(result, Err(AppError::OAuthMisconfigured(_))));
+        assert_eq!(hits, 1);
+
+        let (result, hits) = send(vec![(404, json!({ "message": "Not Found" }))], Retry::Idempotent).await;
+        assert!(matches!(result, Err(AppError::OAuthError(detail)) if detail.contains("404")));
+        assert_eq!(hits, 1);
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_provider_is_unavailable() {
+        // Nothing listens on a port just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        drop(listener);
+        let client = OAuthHttpClient::new(&OAuthHttpConfig { max_retries: 0, ..OAuthHttpConfig::default() });
+
+        let result = client.send_json::<Value>("Example", client.post(&url), Retry::ConnectFailuresOnly).await;
+
+        assert!(matches!(result, Err(AppError::OAuthProviderUnavailable(_))));
+    }
+
+    #[test]
+    fn maps_provider_error_codes() {
+        assert!(matches!(provider_error("Example", "invalid_grant", None), AppError::OAuthInvalidCode));
+        assert!(matches!(provider_error("Example", "access_denied", None), AppError::OAuthAccessDenied));
+        assert!(matches!(provider_error("Example", "redirect_uri_mismatch", None), AppError::OAuthMisconfigured(_)));
+        assert!(matches!(provider_error("Example", "temporarily_unavailable", None), AppError::OAuthProviderUnavailable(_)));
+        assert!(matches!(
+            provider_error("Example", "something_new", Some("details")),
+            AppError::OAuthError(detail) if detail == "Example returned something_new: details"
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+use jsonwebtoken::{decode, decode_header, Validation};
+use serde::de::DeserializeOwned;
+use crate::infrastructure::errors::AppError;
+use super::jwks::JwksCache;
+
+/// Verifies an OpenID Connect id_token: the signature against the provider's
+/// published keys, then audience and expiry, and the issuer when `issuers` is
+/// given (providers with per-tenant issuers check it on the claims instead)
+pub async fn verify_id_token<C: DeserializeOwned>(
+    jwks: &JwksCache,
+    provider: &str,
+    id_token: &str,
+    audience: &str,
+    issuers: Option<&[&str]>,
+) -> Result<C, AppError> {
+    let header = decode_header(id_token).map_err(|e| invalid_id_token(provider, e))?;
+    let kid = header.kid.ok_or_else(|| invalid_id_token(provider, "missing kid"))?;
+    let (key, algorithm) = jwks.key(&kid).await?;
+    if header.alg != algorithm {
+        return Err(invalid_id_token(provider, format!("unexpected algorithm {:?}", header.alg)));
+    }
+
+    let mut validation = Validation::new(algorithm);
+    validation.set_audience(&[audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    if let Some(issuers) = issuers {
+        validation.set_issuer(issuers);
+    }
+
+    decode::<C>(id_token, &key, &validation)
+        .map(|data| data.claims)
+        .map_err(|e| invalid_id_token(provider, e))
+}
+
+/// The id_token must carry the nonce stored in the browser that started the sign-in;
+/// without it a token from another sign-in could be replayed
+pub fn check_nonce(provider: &str, claimed: Option<&str>, expected: Option<&str>) -> Result<(), AppError> {
+    let expected = expected.ok_or_else(|| invalid_id_token(provider, "sign-in session expired, start again"))?;
+    if claimed != Some(expected) {
+        return Err(invalid_id_token(provider, "nonce mismatch"));
+    }
+    Ok(())
+}
+
+pub fn invalid_id_token(provider: &str, reason: impl std::fmt::Display) -> AppError {
+    AppError::OAuthError(format!("{} id_token is invalid: {}", provider, reason))
+}
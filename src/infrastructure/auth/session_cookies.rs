@@ -0,0 +1,71 @@
+use axum::http::{HeaderMap, HeaderValue};
+use crate::infrastructure::auth::jwt::JwtService;
+use crate::infrastructure::errors::AppError;
+use crate::utils::cookie::{self, SameSite};
+
+pub const ACCESS_TOKEN_COOKIE: &str = "access_token";
+pub const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+/// Readable by the frontend, which echoes it in `CSRF_HEADER`
+pub const CSRF_TOKEN_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// The refresh token is only sent to the auth routes (refresh and sign-out)
+const REFRESH_TOKEN_PATH: &str = "/api/v1/auth";
+
+/// Session cookie mode: tokens travel in HttpOnly cookies instead of response
+/// bodies, and cookie-authenticated unsafe requests need a CSRF token
+/// (signed double-submit: the token is an HMAC of the access token).
+#[derive(Clone)]
+pub struct SessionCookies {
+    same_site: SameSite,
+    /// Every cookie lives as long as the refresh token. An expired access token
+    /// is still sent, so the CSRF token of a refresh can be checked against it.
+    max_age_secs: i64,
+    /// Whether the app is served over https
+    secure: bool,
+}
+
+impl SessionCookies {
+    pub fn new(same_site: SameSite, refresh_token_ttl_secs: usize, secure: bool) -> Self {
+        Self { same_site, max_age_secs: refresh_token_ttl_secs as i64, secure }
+    }
+
+    /// `Set-Cookie` values for a new or refreshed session
+    pub fn set(&self, access_token: &str, refresh_token: &str, csrf_token: &str) -> [HeaderValue; 3] {
+        [
+            cookie::set(ACCESS_TOKEN_COOKIE, access_token, "/", self.max_age_secs, self.secure, self.same_site),
+            cookie::set(REFRESH_TOKEN_COOKIE, refresh_token, REFRESH_TOKEN_PATH, self.max_age_secs, self.secure, self.same_site),
+            cookie::set_readable(CSRF_TOKEN_COOKIE, csrf_token, "/", self.max_age_secs, self.secure, self.same_site),
+        ]
+    }
+
+    /// `Set-Cookie` values that delete the session cookies
+    pub fn clear(&self) -> [HeaderValue; 3] {
+        [
+            cookie::set(ACCESS_TOKEN_COOKIE, "", "/", 0, self.secure, self.same_site),
+            cookie::set(REFRESH_TOKEN_COOKIE, "", REFRESH_TOKEN_PATH, 0, self.secure, self.same_site),
+            cookie::set_readable(CSRF_TOKEN_COOKIE, "", "/", 0, self.secure, self.same_site),
+        ]
+    }
+}
+
+pub fn access_token(headers: &HeaderMap) -> Option<&str> {
+    cookie::get(headers, ACCESS_TOKEN_COOKIE).filter(|token| !token.is_empty())
+}
+
+pub fn refresh_token(headers: &HeaderMap) -> Option<&str> {
+    cookie::get(headers, REFRESH_TOKEN_COOKIE).filter(|token| !token.is_empty())
+}
+
+/// A cross-site page can make the browser send our cookies, but cannot read the
+/// CSRF token to put in the header
+pub fn verify_csrf(jwt_service: &JwtService, headers: &HeaderMap, access_token: &str) -> Result<(), AppError> {
+    let csrf_token = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppError::CsrfTokenInvalid)?;
+    if !jwt_service.verify_csrf_token(access_token, csrf_token) {
+        return Err(AppError::CsrfTokenInvalid);
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! Fakes shared by the OAuth client tests: provider endpoints served locally
+//! and an RSA key to sign id_tokens with
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use axum::{extract::State, routing::get, Json, Router};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk::{AlgorithmParameters, CommonParameters, Jwk, JwkSet, RSAKeyParameters, RSAKeyType};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde::Serialize;
+use crate::config::OAuthHttpConfig;
+use super::oauth::OAuthHttpClient;
+
+/// Serves `router` on a free local port and returns its base URL
+pub async fn serve(router: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let address = listener.local_addr().expect("mock server address");
+    tokio::spawn(async move { axum::serve(listener, router).await.expect("mock server failed") });
+    format!("http://{}", address)
+}
+
+/// An HTTP client that fails fast instead of retrying
+pub fn http_client() -> OAuthHttpClient {
+    OAuthHttpClient::new(&OAuthHttpConfig { max_retries: 0, ..OAuthHttpConfig::default() })
+}
+
+/// Generating RSA keys is slow, so every test signs with the same one
+fn rsa_key() -> &'static RsaPrivateKey {
+    static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+    KEY.get_or_init(|| RsaPrivateKey::new(&mut rand::thread_rng(), 2048).expect("generate RSA key"))
+}
+
+/// An RS256 signing key published under `kid`
+pub struct SigningKey {
+    pub kid: String,
+    encoding_key: EncodingKey,
+}
+
+impl SigningKey {
+    pub fn rsa(kid: &str) -> Self {
+        let der = rsa_key().to_pkcs1_der().expect("encode RSA key");
+        Self { kid: kid.to_string(), encoding_key: EncodingKey::from_rsa_der(der.as_bytes()) }
+    }
+
+    /// The public key as a JWKS entry
+    pub fn jwk(&self) -> Jwk {
+        let key = rsa_key();
+        Jwk {
+            common: CommonParameters { key_id: Some(self.kid.clone()), ..CommonParameters::default() },
+            algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }),
+        }
+    }
+
+    pub fn sign(&self, claims: &impl Serialize) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(self.kid.clone());
+        encode(&header, claims, &self.encoding_key).expect("sign token")
+    }
+}
+
+/// Signs `claims` with a shared secret (HS256) under `kid`, as a forger who
+/// knows a public key's bytes might
+pub fn sign_hs256(kid: &str, secret: &[u8], claims: &impl Serialize) -> String {
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some(kid.to_string());
+    encode(&header, claims, &EncodingKey::from_secret(secret)).expect("sign token")
+}
+
+#[derive(Clone, Default)]
+struct Published {
+    keys: Arc<Mutex<Vec<Jwk>>>,
+    fetches: Arc<AtomicUsize>,
+}
+
+/// A provider's JWKS endpoint, counting how often it is fetched
+pub struct JwksServer {
+    pub url: String,
+    published: Published,
+}
+
+impl JwksServer {
+    pub async fn spawn(keys: Vec<Jwk>) -> Self {
+        let published = Published::default();
+        *published.keys.lock().unwrap() = keys;
+
+        let router = Router::new()
+            .route("/jwks", get(|State(published): State<Published>| async move {
+                published.fetches.fetch_add(1, Ordering::SeqCst);
+                Json(JwkSet { keys: published.keys.lock().unwrap().clone() })
+            }))
+            .with_state(published.clone());
+
+        Self { url: format!("{}/jwks", serve(router).await), published }
+    }
+
+    /// Replaces the published keys, as a provider rotating its keys would
+    pub fn publish(&self, keys: Vec<Jwk>) {
+        *self.published.keys.lock().unwrap() = keys;
+    }
+
+    pub fn fetches(&self) -> usize {
+        self.published.fetches.load(Ordering::SeqCst)
+    }
+}
+
+/// Seconds since the epoch, offset by `offset`
+pub fn timestamp(offset: i64) -> i64 {
+    chrono::Utc::now().timestamp() + offset
+}
@@ -0,0 +1,20 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+/// Generates a URL-safe random token (256 bits) for one-time links and codes
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hashes a token for storage so a database leak does not expose live links
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Generates a 6-digit numeric code for channels where a link cannot be clicked (SMS)
+pub fn generate_otp() -> String {
+    format!("{:06}", OsRng.gen_range(0..1_000_000))
+}
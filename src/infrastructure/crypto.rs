@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::infrastructure::errors::AppError;
+
+const KEY_LEN: usize = 32;
+
+/// Values encrypted by `EnvelopeCipher::seal`: a fresh data key, wrapped by the
+/// master key `key_id`, and the values encrypted with that data key
+pub struct Sealed {
+    pub key_id: String,
+    pub wrapped_data_key: Vec<u8>,
+    pub ciphertexts: Vec<Vec<u8>>,
+}
+
+/// Envelope encryption with rotatable master keys.
+///
+/// Every record gets its own random AES-256-GCM data key. Only the wrapped
+/// (encrypted) data key is stored, next to the id of the master key that wrapped
+/// it. Rotating the master key therefore means re-wrapping one small key per
+/// record; the data itself is never re-encrypted.
+pub struct EnvelopeCipher {
+    active_key_id: String,
+    master_keys: HashMap<String, LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl EnvelopeCipher {
+    /// `keys` are `<id>:<base64 of 32 random bytes>`; the first one encrypts,
+    /// the others are only kept to decrypt records not yet rotated
+    pub fn new<S: AsRef<str>>(keys: &[S]) -> Result<Self, String> {
+        let mut master_keys = HashMap::new();
+        let mut active_key_id = None;
+
+        for key in keys {
+            let (id, material) = key.as_ref()
+                .split_once(':')
+                .ok_or_else(|| "keys must look like <id>:<base64 key>".to_string())?;
+            let id = id.trim();
+            if id.is_empty() {
+                return Err("key ids must not be empty".to_string());
+            }
+            let bytes = STANDARD.decode(material.trim())
+                .map_err(|_| format!("key '{}' is not valid base64", id))?;
+            if bytes.len() != KEY_LEN {
+                return Err(format!("key '{}' must be {} bytes, got {}", id, KEY_LEN, bytes.len()));
+            }
+            let unbound = UnboundKey::new(&AES_256_GCM, &bytes).expect("key length is checked above");
+            if master_keys.insert(id.to_string(), LessSafeKey::new(unbound)).is_some() {
+                return Err(format!("key id '{}' is used twice", id));
+            }
+            active_key_id.get_or_insert_with(|| id.to_string());
+        }
+
+        let active_key_id = active_key_id.ok_or_else(|| "at least one key is required".to_string())?;
+        Ok(Self { active_key_id, master_keys, rng: SystemRandom::new() })
+    }
+
+    pub fn active_key_id(&self) -> &str {
+        &self.active_key_id
+    }
+
+    /// Encrypts `values` under a new data key. `context` is authenticated but not
+    /// stored, so a sealed record only opens for the same context (e.g. its owner).
+    pub fn seal(&self, context: &str, values: &[&str]) -> Result<Sealed, AppError> {
+        let mut data_key = [0u8; KEY_LEN];
+        self.rng.fill(&mut data_key).map_err(|_| AppError::InternalServerError)?;
+
+        let master_key = &self.master_keys[&self.active_key_id];
+        let wrapped_data_key = self.encrypt(master_key, context, &data_key)?;
+
+        let data_key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &data_key).expect("data keys are 32 bytes"));
+        let ciphertexts = values
+            .iter()
+            .map(|value| self.encrypt(&data_key, context, value.as_bytes()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Sealed { key_id: self.active_key_id.clone(), wrapped_data_key, ciphertexts })
+    }
+
+    /// Decrypts values sealed with the same `context`
+    pub fn open(&self, context: &str, key_id: &str, wrapped_data_key: &[u8], ciphertexts: &[&[u8]]) -> Result<Vec<String>, AppError> {
+        let data_key = self.unwrap_data_key(context, key_id, wrapped_data_key)?;
+        let data_key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &data_key).map_err(|_| AppError::InternalServerError)?);
+
+        ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                let plaintext = decrypt(&data_key, context, ciphertext)?;
+                String::from_utf8(plaintext).map_err(|_| AppError::InternalServerError)
+            })
+            .collect()
+    }
+
+    /// Wraps the data key again with the active master key
+    pub fn rewrap(&self, context: &str, key_id: &str, wrapped_data_key: &[u8]) -> Result<Vec<u8>, AppError> {
+        let data_key = self.unwrap_data_key(context, key_id, wrapped_data_key)?;
+        self.encrypt(&self.master_keys[&self.active_key_id], context, &data_key)
+    }
+
+    fn unwrap_data_key(&self, context: &str, key_id: &str, wrapped_data_key: &[u8]) -> Result<Vec<u8>, AppError> {
+        let master_key = self.master_keys.get(key_id).ok_or_else(|| {
+            tracing::error!("Master key '{}' is not configured; it is still needed to decrypt stored data", key_id);
+            AppError::InternalServerError
+        })?;
+        decrypt(master_key, context, wrapped_data_key)
+    }
+
+    /// nonce || ciphertext || tag
+    fn encrypt(&self, key: &LessSafeKey, context: &str, plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce).map_err(|_| AppError::InternalServerError)?;
+
+        let mut in_out = plaintext.to_vec();
+        key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(context.as_bytes()), &mut in_out)
+            .map_err(|_| AppError::InternalServerError)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&in_out);
+        Ok(sealed)
+    }
+}
+
+fn decrypt(key: &LessSafeKey, context: &str, sealed: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(AppError::InternalServerError);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| AppError::InternalServerError)?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(context.as_bytes()), &mut in_out)
+        .map_err(|_| {
+            tracing::error!("Failed to decrypt stored data: wrong key or tampered record");
+            AppError::InternalServerError
+        })?;
+
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str, byte: u8) -> String {
+        format!("{}:{}", id, STANDARD.encode([byte; KEY_LEN]))
+    }
+
+    #[test]
+    fn opens_what_it_sealed() {
+        let cipher = EnvelopeCipher::new(&[key("2024-01", 1)]).unwrap();
+
+        let sealed = cipher.seal("provider_tokens:alice:github", &["access", "refresh"]).unwrap();
+        let ciphertexts: Vec<&[u8]> = sealed.ciphertexts.iter().map(Vec::as_slice).collect();
+
+        assert_eq!(sealed.key_id, "2024-01");
+        assert!(!sealed.ciphertexts[0].windows(6).any(|window| window == b"access"));
+        let values = cipher.open("provider_tokens:alice:github", &sealed.key_id, &sealed.wrapped_data_key, &ciphertexts).unwrap();
+        assert_eq!(values, vec!["access", "refresh"]);
+    }
+
+    #[test]
+    fn does_not_open_under_another_context() {
+        let cipher = EnvelopeCipher::new(&[key("2024-01", 1)]).unwrap();
+
+        let sealed = cipher.seal("provider_tokens:alice:github", &["access"]).unwrap();
+        let ciphertexts: Vec<&[u8]> = sealed.ciphertexts.iter().map(Vec::as_slice).collect();
+
+        let opened = cipher.open("provider_tokens:mallory:github", &sealed.key_id, &sealed.wrapped_data_key, &ciphertexts);
+        assert!(opened.is_err());
+    }
+
+    #[test]
+    fn rewraps_with_the_rotated_key() {
+        let old = EnvelopeCipher::new(&[key("2024-01", 1)]).unwrap();
+        let rotated = EnvelopeCipher::new(&[key("2024-06", 2), key("2024-01", 1)]).unwrap();
+        let new_only = EnvelopeCipher::new(&[key("2024-06", 2)]).unwrap();
+
+        let sealed = old.seal("provider_tokens:alice:google", &["access"]).unwrap();
+        let ciphertexts: Vec<&[u8]> = sealed.ciphertexts.iter().map(Vec::as_slice).collect();
+        let rewrapped = rotated.rewrap("provider_tokens:alice:google", &sealed.key_id, &sealed.wrapped_data_key).unwrap();
+
+        // The ciphertexts are untouched; only the data key moved to the new master key
+        let values = new_only.open("provider_tokens:alice:google", "2024-06", &rewrapped, &ciphertexts).unwrap();
+        assert_eq!(values, vec!["access"]);
+        assert!(old.open("provider_tokens:alice:google", "2024-06", &rewrapped, &ciphertexts).is_err());
+        assert!(new_only.open("provider_tokens:alice:google", "2024-01", &sealed.wrapped_data_key, &ciphertexts).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        assert!(EnvelopeCipher::new::<&str>(&[]).is_err());
+        assert!(EnvelopeCipher::new(&["no-separator"]).is_err());
+        assert!(EnvelopeCipher::new(&[format!("short:{}", STANDARD.encode([1u8; 16]))]).is_err());
+        assert!(EnvelopeCipher::new(&[key("same", 1), key("same", 2)]).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use sqlx::postgres::{PgPoolOptions, PgPool};
 use std::time::Duration;
+use crate::config::DatabaseConfig;
 use crate::infrastructure::errors::AppError;
 
 pub struct Database {
@@ -7,11 +8,11 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn new(connection_string: &str) -> Result<Self, AppError> {
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, AppError> {
         let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(3))
-            .connect(connection_string)
+            .max_connections(config.max_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+            .connect(config.url.expose())
             .await
             .map_err(AppError::DatabaseError)?;
 
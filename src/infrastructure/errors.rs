@@ -30,8 +30,48 @@ pub enum AppError {
     Forbidden,
     #[error("Cannot delete your own account")]
     CannotDeleteSelf,
+    #[error("Cannot impersonate yourself")]
+    CannotImpersonateSelf,
+    #[error("Not allowed during impersonation")]
+    ImpersonationNotAllowed,
+    #[error("Data export not found")]
+    DataExportNotFound,
+    #[error("Data export is not ready")]
+    DataExportNotReady,
+    #[error("File not found")]
+    FileNotFound,
+    #[error("Phone number already exists")]
+    PhoneAlreadyExists,
+    #[error("Provider account is already linked to another user")]
+    IdentityAlreadyLinked,
+    #[error("Invalid or expired verification code")]
+    InvalidVerificationCode,
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
     #[error("OAuth error: {0}")]
     OAuthError(String),
+    #[error("Authorization code is invalid or expired")]
+    OAuthInvalidCode,
+    #[error("Sign-in was denied at the provider")]
+    OAuthAccessDenied,
+    #[error("OAuth provider is misconfigured: {0}")]
+    OAuthMisconfigured(String),
+    #[error("{0} is unavailable")]
+    OAuthProviderUnavailable(String),
+    #[error("{0} did not respond in time")]
+    OAuthProviderTimeout(String),
+    #[error("{0} account is not connected")]
+    ProviderNotConnected(String),
+    #[error("Missing or invalid CSRF token")]
+    CsrfTokenInvalid,
+    #[error("OAuth state does not match this browser")]
+    OAuthStateInvalid,
+    #[error("Sign-in code is invalid, expired or already used")]
+    OAuthExchangeCodeInvalid,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Sign in again to continue")]
+    ReauthenticationRequired,
 }
 
 impl IntoResponse for AppError {
@@ -51,10 +91,33 @@ impl IntoResponse for AppError {
             AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()),
             AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden".to_string()),
             AppError::CannotDeleteSelf => (StatusCode::BAD_REQUEST, "Cannot delete your own account".to_string()),
+            AppError::CannotImpersonateSelf => (StatusCode::BAD_REQUEST, "Cannot impersonate yourself".to_string()),
+            AppError::ImpersonationNotAllowed => (StatusCode::FORBIDDEN, "Not allowed during impersonation".to_string()),
+            AppError::CsrfTokenInvalid => (StatusCode::FORBIDDEN, "Missing or invalid CSRF token".to_string()),
+            AppError::DataExportNotFound => (StatusCode::NOT_FOUND, "Data export not found".to_string()),
+            AppError::DataExportNotReady => (StatusCode::CONFLICT, "Data export is not ready".to_string()),
+            AppError::FileNotFound => (StatusCode::NOT_FOUND, "File not found".to_string()),
+            AppError::PhoneAlreadyExists => (StatusCode::CONFLICT, "Phone number already exists".to_string()),
+            AppError::IdentityAlreadyLinked => (StatusCode::CONFLICT, "This provider account is already linked to another user".to_string()),
+            AppError::InvalidVerificationCode => (StatusCode::BAD_REQUEST, "Invalid or expired verification code".to_string()),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
             AppError::OAuthError(msg) => {
                 tracing::error!("OAuth error: {}", msg);
                 (StatusCode::BAD_REQUEST, format!("OAuth error: {}", msg))
             }
+            AppError::OAuthInvalidCode => (StatusCode::BAD_REQUEST, "Authorization code is invalid or expired, please sign in again".to_string()),
+            AppError::OAuthAccessDenied => (StatusCode::FORBIDDEN, "Sign-in was denied at the provider".to_string()),
+            AppError::OAuthMisconfigured(msg) => {
+                tracing::error!("OAuth provider is misconfigured: {}", msg);
+                (StatusCode::INTERNAL_SERVER_ERROR, "Sign-in is not available".to_string())
+            }
+            AppError::OAuthProviderUnavailable(provider) => (StatusCode::BAD_GATEWAY, format!("{} is unavailable, please try again later", provider)),
+            AppError::OAuthProviderTimeout(provider) => (StatusCode::GATEWAY_TIMEOUT, format!("{} did not respond in time, please try again later", provider)),
+            AppError::OAuthStateInvalid => (StatusCode::BAD_REQUEST, "Sign-in could not be verified in this browser, please sign in again".to_string()),
+            AppError::OAuthExchangeCodeInvalid => (StatusCode::BAD_REQUEST, "Sign-in code is invalid, expired or already used".to_string()),
+            AppError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found".to_string()),
+            AppError::ReauthenticationRequired => (StatusCode::UNAUTHORIZED, "Sign in again to continue".to_string()),
+            AppError::ProviderNotConnected(provider) => (StatusCode::CONFLICT, format!("{0} account is not connected or access was revoked, sign in with {0} again", provider)),
         };
 
         let body = Json(json!({
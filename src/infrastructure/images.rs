@@ -0,0 +1,79 @@
+use std::io::Cursor;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, Rgb, RgbImage};
+use crate::infrastructure::errors::AppError;
+
+/// Largest accepted upload
+pub const AVATAR_MAX_BYTES: usize = 5 * 1024 * 1024;
+/// Square edge lengths of the generated variants; the middle one is the default `avatar_url`
+pub const AVATAR_SIZES: [u32; 3] = [64, 256, 512];
+pub const AVATAR_DEFAULT_SIZE: u32 = 256;
+/// Bigger sources are rejected before decoding to keep memory use predictable
+const AVATAR_MAX_DIMENSION: u32 = 5000;
+const AVATAR_JPEG_QUALITY: u8 = 85;
+
+/// A resized avatar, JPEG-encoded
+pub struct AvatarVariant {
+    pub size: u32,
+    pub bytes: Vec<u8>,
+}
+
+fn invalid_image() -> AppError {
+    AppError::ValidationError("File must be a JPEG, PNG or WebP image".to_string())
+}
+
+/// Decodes an uploaded avatar and re-encodes it as square JPEG variants.
+/// The format is sniffed from the bytes, never trusted from the client, and
+/// re-encoding drops EXIF and any other metadata once orientation is applied.
+/// CPU-bound: call from `spawn_blocking`.
+pub fn process_avatar(bytes: &[u8]) -> Result<Vec<AvatarVariant>, AppError> {
+    if bytes.len() > AVATAR_MAX_BYTES {
+        return Err(AppError::ValidationError(format!(
+            "Avatar must be at most {} MB", AVATAR_MAX_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|_| invalid_image())?;
+    match reader.format() {
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP) => {}
+        _ => return Err(invalid_image()),
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(AVATAR_MAX_DIMENSION);
+    limits.max_image_height = Some(AVATAR_MAX_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|_| invalid_image())?;
+    let orientation = decoder.orientation().map_err(|_| invalid_image())?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|_| invalid_image())?;
+    image.apply_orientation(orientation);
+
+    AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let resized = image.resize_to_fill(size, size, FilterType::Lanczos3);
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, AVATAR_JPEG_QUALITY)
+                .encode_image(&flatten(&resized))
+                .map_err(|e| {
+                    tracing::error!("Failed to encode avatar: {:?}", e);
+                    AppError::InternalServerError
+                })?;
+            Ok(AvatarVariant { size, bytes })
+        })
+        .collect()
+}
+
+/// JPEG has no alpha channel, so transparent pixels are blended onto white
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+use crate::infrastructure::errors::AppError;
+use super::{EmailMessage, Mailer};
+
+/// Development mailer: writes outgoing email to the log instead of sending it
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), AppError> {
+        tracing::info!(
+            to = %message.to,
+            subject = %message.subject,
+            "Outgoing email:\n{}",
+            message.body
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+pub mod log_mailer;
+
+use async_trait::async_trait;
+use crate::infrastructure::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> Result<(), AppError>;
+}
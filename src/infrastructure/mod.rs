@@ -1,4 +1,11 @@
 pub mod auth;
+pub mod crypto;
 pub mod database;
 pub mod errors;
+pub mod images;
+pub mod mail;
+pub mod phone;
 pub mod repositories;
+pub mod request_context;
+pub mod sms;
+pub mod storage;
@@ -0,0 +1,37 @@
+use phonenumber::{country, Mode};
+use crate::infrastructure::errors::AppError;
+
+/// How phone numbers are read and which ones may be stored
+pub struct PhonePolicy {
+    /// Region used for numbers written without a `+` country code
+    default_region: country::Id,
+    /// Reject numbers already on another active account, verified or not
+    unique: bool,
+}
+
+impl PhonePolicy {
+    pub fn new(default_region: &str, unique: bool) -> Result<Self, String> {
+        let default_region = default_region
+            .to_uppercase()
+            .parse::<country::Id>()
+            .map_err(|_| format!("Unknown phone region '{}'", default_region))?;
+
+        Ok(Self { default_region, unique })
+    }
+
+    pub fn requires_unique(&self) -> bool {
+        self.unique
+    }
+
+    /// Parses `raw` (any common formatting) into E.164, e.g. "0812-3456-789" -> "+628123456789"
+    pub fn normalize(&self, raw: &str) -> Result<String, AppError> {
+        let invalid = || AppError::ValidationError(format!("Invalid phone number: {}", raw));
+
+        let number = phonenumber::parse(Some(self.default_region), raw.trim()).map_err(|_| invalid())?;
+        if !phonenumber::is_valid(&number) {
+            return Err(invalid());
+        }
+
+        Ok(number.format().mode(Mode::E164).to_string())
+    }
+}
@@ -0,0 +1,36 @@
+use sqlx::{Postgres, QueryBuilder};
+use crate::domain::repositories::pagination::{Cursor, CursorKey, SortDirection};
+
+/// Appends the keyset predicate, ordering and limit for a `(sort_column, id)` walk.
+///
+/// Expects `builder` to already end in a `WHERE` clause. Fetches `limit + 1` rows so
+/// `KeysetPage::from_rows` can tell whether another page exists. Rows inserted while a
+/// client is paging never shift existing positions, because the boundary is a value
+/// rather than a row count; ties on the sort key are broken by `id`.
+pub fn push_keyset(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    sort_column: &str,
+    order: SortDirection,
+    cursor: Option<&Cursor>,
+    limit: i64,
+) {
+    // Walking backward reads the result set in reverse, then the page is flipped back
+    let direction = if cursor.is_some_and(|c| c.backward) { order.reverse() } else { order };
+
+    if let Some(cursor) = cursor {
+        let op = match direction {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        };
+        builder.push(format!(" AND ({}, id) {} (", sort_column, op));
+        match &cursor.key {
+            CursorKey::Timestamp(ts) => builder.push_bind(*ts),
+            CursorKey::Text(text) => builder.push_bind(text.clone()),
+        };
+        builder.push(", ").push_bind(cursor.id).push(")");
+    }
+
+    builder
+        .push(format!(" ORDER BY {0} {1}, id {1} LIMIT ", sort_column, direction.as_sql()))
+        .push_bind(limit + 1);
+}
@@ -1 +1,9 @@
 pub mod postgres_user_repository;
+pub mod postgres_audit_repository;
+pub mod keyset;
+pub mod postgres_email_change_repository;
+pub mod postgres_data_export_repository;
+pub mod postgres_phone_verification_repository;
+pub mod postgres_provider_token_repository;
+pub mod postgres_oauth_exchange_code_repository;
+pub mod postgres_session_repository;
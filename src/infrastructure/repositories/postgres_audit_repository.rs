@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+use crate::domain::entities::audit_event::AuditEvent;
+use crate::domain::repositories::audit_repository::{AuditEventFilter, AuditRepository, AUDIT_SORT_FIELD};
+use crate::domain::repositories::pagination::{Cursor, CursorKey, KeysetPage, PageParams, SortDirection};
+use crate::infrastructure::repositories::keyset::push_keyset;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresAuditRepository {
+    pool: PgPool,
+}
+
+impl PostgresAuditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const AUDIT_COLUMNS: &str = "id, actor_id, target_id, action, metadata, changes, ip_address, user_agent, request_id, created_at";
+
+/// Appends a `WHERE` clause for every filter that is set
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &AuditEventFilter) {
+    builder.push(" WHERE TRUE");
+    if let Some(actor_id) = filter.actor_id {
+        builder.push(" AND actor_id = ").push_bind(actor_id);
+    }
+    if let Some(target_id) = filter.target_id {
+        builder.push(" AND target_id = ").push_bind(target_id);
+    }
+    if let Some(action) = &filter.action {
+        builder.push(" AND action = ").push_bind(action.clone());
+    }
+    if let Some(from) = filter.from {
+        builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        builder.push(" AND created_at < ").push_bind(to);
+    }
+}
+
+#[async_trait]
+impl AuditRepository for PostgresAuditRepository {
+    async fn create(&self, event: &AuditEvent) -> Result<AuditEvent, AppError> {
+        let query = format!(
+            "INSERT INTO audit_events (id, actor_id, target_id, action, metadata, changes, ip_address, user_agent, request_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING {}", AUDIT_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, AuditEvent>(&query)
+            .bind(event.id)
+            .bind(event.actor_id)
+            .bind(event.target_id)
+            .bind(&event.action)
+            .bind(&event.metadata)
+            .bind(&event.changes)
+            .bind(&event.ip_address)
+            .bind(&event.user_agent)
+            .bind(&event.request_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_page(&self, filter: &AuditEventFilter, page: PageParams) -> Result<Vec<AuditEvent>, AppError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM audit_events", AUDIT_COLUMNS));
+        push_filter(&mut builder, filter);
+        builder
+            .push(" ORDER BY created_at DESC, id DESC LIMIT ")
+            .push_bind(page.limit())
+            .push(" OFFSET ")
+            .push_bind(page.offset());
+
+        let rec = builder
+            .build_query_as::<AuditEvent>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_keyset(&self, filter: &AuditEventFilter, cursor: Option<&Cursor>, limit: i64) -> Result<KeysetPage<AuditEvent>, AppError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM audit_events", AUDIT_COLUMNS));
+        push_filter(&mut builder, filter);
+        push_keyset(&mut builder, AUDIT_SORT_FIELD, SortDirection::Desc, cursor, limit);
+
+        let rows = builder
+            .build_query_as::<AuditEvent>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(KeysetPage::from_rows(rows, limit, AUDIT_SORT_FIELD, SortDirection::Desc, cursor, |event| {
+            (CursorKey::Timestamp(event.created_at.unwrap_or_default()), event.id)
+        }))
+    }
+
+    async fn find_by_subject(&self, user_id: Uuid) -> Result<Vec<AuditEvent>, AppError> {
+        let query = format!(
+            "SELECT {} FROM audit_events WHERE target_id = $1 ORDER BY created_at, id",
+            AUDIT_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, AuditEvent>(&query)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn count(&self, filter: &AuditEventFilter) -> Result<i64, AppError> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM audit_events");
+        push_filter(&mut builder, filter);
+
+        let total: i64 = builder
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(total)
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::domain::entities::data_export::DataExport;
+use crate::domain::repositories::data_export_repository::DataExportRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresDataExportRepository {
+    pool: PgPool,
+}
+
+impl PostgresDataExportRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const DATA_EXPORT_COLUMNS: &str = "id, user_id, status, file_path, error, expires_at, created_at, completed_at";
+
+#[async_trait]
+impl DataExportRepository for PostgresDataExportRepository {
+    async fn create(&self, user_id: Uuid) -> Result<DataExport, AppError> {
+        let query = format!(
+            "INSERT INTO data_exports (user_id) VALUES ($1) RETURNING {}", DATA_EXPORT_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_for_user(&self, id: Uuid, user_id: Uuid) -> Result<Option<DataExport>, AppError> {
+        let query = format!("SELECT {} FROM data_exports WHERE id = $1 AND user_id = $2", DATA_EXPORT_COLUMNS);
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_all_for_user(&self, user_id: Uuid) -> Result<Vec<DataExport>, AppError> {
+        let query = format!("SELECT {} FROM data_exports WHERE user_id = $1 ORDER BY created_at DESC", DATA_EXPORT_COLUMNS);
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn claim_next_pending(&self) -> Result<Option<DataExport>, AppError> {
+        let query = format!(
+            "UPDATE data_exports SET status = 'Processing'
+             WHERE id = (
+                 SELECT id FROM data_exports WHERE status = 'Pending'
+                 ORDER BY created_at LIMIT 1 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING {}", DATA_EXPORT_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn mark_ready(&self, id: Uuid, file_path: &str, expires_at: DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query("UPDATE data_exports SET status = 'Ready', file_path = $1, expires_at = $2, completed_at = NOW() WHERE id = $3")
+            .bind(file_path)
+            .bind(expires_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn mark_failed(&self, id: Uuid, error: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE data_exports SET status = 'Failed', error = $1, completed_at = NOW() WHERE id = $2")
+            .bind(error)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn find_expired(&self) -> Result<Vec<DataExport>, AppError> {
+        let query = format!("SELECT {} FROM data_exports WHERE status = 'Ready' AND expires_at <= NOW()", DATA_EXPORT_COLUMNS);
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn mark_expired(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE data_exports SET status = 'Expired', file_path = NULL WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid) -> Result<Vec<DataExport>, AppError> {
+        let query = format!("DELETE FROM data_exports WHERE user_id = $1 RETURNING {}", DATA_EXPORT_COLUMNS);
+        let rec = sqlx::query_as::<_, DataExport>(&query)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::domain::entities::email_change::EmailChange;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresEmailChangeRepository {
+    pool: PgPool,
+}
+
+impl PostgresEmailChangeRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const EMAIL_CHANGE_COLUMNS: &str = "id, user_id, old_email, new_email, confirm_token_hash, revert_token_hash, confirm_expires_at, revert_expires_at, confirmed_at, reverted_at, created_at";
+
+#[async_trait]
+impl EmailChangeRepository for PostgresEmailChangeRepository {
+    async fn create(&self, change: &EmailChange) -> Result<EmailChange, AppError> {
+        let query = format!(
+            "INSERT INTO email_changes (id, user_id, old_email, new_email, confirm_token_hash, revert_token_hash, confirm_expires_at, revert_expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+             RETURNING {}", EMAIL_CHANGE_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, EmailChange>(&query)
+            .bind(change.id)
+            .bind(change.user_id)
+            .bind(&change.old_email)
+            .bind(&change.new_email)
+            .bind(&change.confirm_token_hash)
+            .bind(&change.revert_token_hash)
+            .bind(change.confirm_expires_at)
+            .bind(change.revert_expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_confirmable(&self, confirm_token_hash: &str) -> Result<Option<EmailChange>, AppError> {
+        let query = format!(
+            "SELECT {} FROM email_changes
+             WHERE confirm_token_hash = $1 AND confirmed_at IS NULL AND reverted_at IS NULL AND confirm_expires_at > NOW()",
+            EMAIL_CHANGE_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, EmailChange>(&query)
+            .bind(confirm_token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_revertable(&self, revert_token_hash: &str) -> Result<Option<EmailChange>, AppError> {
+        let query = format!(
+            "SELECT {} FROM email_changes
+             WHERE revert_token_hash = $1 AND reverted_at IS NULL AND revert_expires_at > NOW()",
+            EMAIL_CHANGE_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, EmailChange>(&query)
+            .bind(revert_token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<EmailChange>, AppError> {
+        let query = format!("SELECT {} FROM email_changes WHERE user_id = $1 ORDER BY created_at", EMAIL_CHANGE_COLUMNS);
+        let rec = sqlx::query_as::<_, EmailChange>(&query)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn mark_confirmed(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE email_changes SET confirmed_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn mark_reverted_since(&self, change: &EmailChange) -> Result<(), AppError> {
+        sqlx::query(
+            "UPDATE email_changes SET reverted_at = NOW()
+             WHERE user_id = $1 AND reverted_at IS NULL AND (id = $2 OR created_at >= $3)"
+        )
+            .bind(change.user_id)
+            .bind(change.id)
+            .bind(change.created_at)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn delete_pending_for_user(&self, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM email_changes WHERE user_id = $1 AND confirmed_at IS NULL AND reverted_at IS NULL")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn delete_all_for_user(&self, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM email_changes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
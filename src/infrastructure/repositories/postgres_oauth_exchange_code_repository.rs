@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use crate::domain::entities::oauth_exchange_code::OAuthExchangeCode;
+use crate::domain::repositories::oauth_exchange_code_repository::OAuthExchangeCodeRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresOAuthExchangeCodeRepository {
+    pool: PgPool,
+}
+
+impl PostgresOAuthExchangeCodeRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const OAUTH_EXCHANGE_CODE_COLUMNS: &str = "code_hash, user_id, provider, expires_at";
+
+#[async_trait]
+impl OAuthExchangeCodeRepository for PostgresOAuthExchangeCodeRepository {
+    async fn create(&self, code: &OAuthExchangeCode) -> Result<(), AppError> {
+        sqlx::query("INSERT INTO oauth_exchange_codes (code_hash, user_id, provider, expires_at) VALUES ($1, $2, $3, $4)")
+            .bind(&code.code_hash)
+            .bind(code.user_id)
+            .bind(&code.provider)
+            .bind(code.expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn consume(&self, code_hash: &str) -> Result<Option<OAuthExchangeCode>, AppError> {
+        let query = format!(
+            "DELETE FROM oauth_exchange_codes WHERE code_hash = $1 AND expires_at > NOW() RETURNING {}",
+            OAUTH_EXCHANGE_CODE_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, OAuthExchangeCode>(&query)
+            .bind(code_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn delete_expired(&self) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM oauth_exchange_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
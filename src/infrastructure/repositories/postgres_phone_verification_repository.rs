@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::domain::entities::phone_verification::PhoneVerification;
+use crate::domain::repositories::phone_verification_repository::PhoneVerificationRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresPhoneVerificationRepository {
+    pool: PgPool,
+}
+
+impl PostgresPhoneVerificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const PHONE_VERIFICATION_COLUMNS: &str = "id, user_id, phone, code_hash, attempts, expires_at, verified_at, created_at";
+
+#[async_trait]
+impl PhoneVerificationRepository for PostgresPhoneVerificationRepository {
+    async fn create(&self, verification: &PhoneVerification) -> Result<PhoneVerification, AppError> {
+        let query = format!(
+            "INSERT INTO phone_verifications (id, user_id, phone, code_hash, expires_at)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING {}", PHONE_VERIFICATION_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, PhoneVerification>(&query)
+            .bind(verification.id)
+            .bind(verification.user_id)
+            .bind(&verification.phone)
+            .bind(&verification.code_hash)
+            .bind(verification.expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_latest_pending(&self, user_id: Uuid) -> Result<Option<PhoneVerification>, AppError> {
+        let query = format!(
+            "SELECT {} FROM phone_verifications
+             WHERE user_id = $1 AND verified_at IS NULL
+             ORDER BY created_at DESC LIMIT 1",
+            PHONE_VERIFICATION_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, PhoneVerification>(&query)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn increment_attempts(&self, id: Uuid, max_attempts: i32) -> Result<bool, AppError> {
+        // Checked and counted in one statement so concurrent guesses cannot exceed the limit
+        let rec = sqlx::query_scalar::<_, i32>(
+            "UPDATE phone_verifications SET attempts = attempts + 1
+             WHERE id = $1 AND attempts < $2 RETURNING attempts",
+        )
+        .bind(id)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rec.is_some())
+    }
+
+    async fn mark_verified(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE phone_verifications SET verified_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn delete_pending_for_user(&self, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM phone_verifications WHERE user_id = $1 AND verified_at IS NULL")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
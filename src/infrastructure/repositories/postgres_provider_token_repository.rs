@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::domain::entities::provider_token::ProviderToken;
+use crate::domain::entities::user::AuthProvider;
+use crate::domain::repositories::provider_token_repository::ProviderTokenRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresProviderTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresProviderTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const PROVIDER_TOKEN_COLUMNS: &str =
+    "user_id, provider, key_id, wrapped_data_key, access_token, refresh_token, expires_at";
+
+#[async_trait]
+impl ProviderTokenRepository for PostgresProviderTokenRepository {
+    async fn upsert(&self, token: &ProviderToken) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO provider_tokens (user_id, provider, key_id, wrapped_data_key, access_token, refresh_token, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (user_id, provider) DO UPDATE SET
+                key_id = EXCLUDED.key_id,
+                wrapped_data_key = EXCLUDED.wrapped_data_key,
+                access_token = EXCLUDED.access_token,
+                refresh_token = EXCLUDED.refresh_token,
+                expires_at = EXCLUDED.expires_at,
+                updated_at = NOW()",
+        )
+        .bind(token.user_id)
+        .bind(&token.provider)
+        .bind(&token.key_id)
+        .bind(&token.wrapped_data_key)
+        .bind(&token.access_token)
+        .bind(&token.refresh_token)
+        .bind(token.expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn find(&self, user_id: Uuid, provider: AuthProvider) -> Result<Option<ProviderToken>, AppError> {
+        let query = format!(
+            "SELECT {} FROM provider_tokens WHERE user_id = $1 AND provider = $2",
+            PROVIDER_TOKEN_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, ProviderToken>(&query)
+            .bind(user_id)
+            .bind(provider.as_str())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn delete(&self, user_id: Uuid, provider: AuthProvider) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM provider_tokens WHERE user_id = $1 AND provider = $2")
+            .bind(user_id)
+            .bind(provider.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn find_not_wrapped_by(&self, key_id: &str, limit: i64) -> Result<Vec<ProviderToken>, AppError> {
+        let query = format!(
+            "SELECT {} FROM provider_tokens WHERE key_id <> $1 ORDER BY user_id, provider LIMIT $2",
+            PROVIDER_TOKEN_COLUMNS
+        );
+        let recs = sqlx::query_as::<_, ProviderToken>(&query)
+            .bind(key_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(recs)
+    }
+
+    async fn update_wrapped_key(
+        &self,
+        token: &ProviderToken,
+        new_key_id: &str,
+        wrapped_data_key: &[u8],
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE provider_tokens SET key_id = $4, wrapped_data_key = $5
+             WHERE user_id = $1 AND provider = $2 AND key_id = $3 AND wrapped_data_key = $6",
+        )
+        .bind(token.user_id)
+        .bind(&token.provider)
+        .bind(&token.key_id)
+        .bind(new_key_id)
+        .bind(wrapped_data_key)
+        .bind(&token.wrapped_data_key)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,128 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::domain::entities::session::Session;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::infrastructure::errors::AppError;
+
+pub struct PostgresSessionRepository {
+    pool: PgPool,
+}
+
+impl PostgresSessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const SESSION_COLUMNS: &str = "id, user_id, auth_method, user_agent, ip_address, created_at, last_seen_at";
+
+#[async_trait]
+impl SessionRepository for PostgresSessionRepository {
+    async fn create(&self, session: &Session) -> Result<Session, AppError> {
+        let query = format!(
+            "INSERT INTO sessions (id, user_id, auth_method, user_agent, ip_address)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING {}", SESSION_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, Session>(&query)
+            .bind(session.id)
+            .bind(session.user_id)
+            .bind(&session.auth_method)
+            .bind(&session.user_agent)
+            .bind(&session.ip_address)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn touch(&self, id: Uuid, user_id: Uuid, ip_address: Option<&str>, user_agent: Option<&str>) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE sessions SET last_seen_at = NOW(), ip_address = $3, user_agent = $4
+             WHERE id = $1 AND user_id = $2"
+        )
+            .bind(id)
+            .bind(user_id)
+            .bind(ip_address)
+            .bind(user_agent)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn find_by_id(&self, id: Uuid, user_id: Uuid) -> Result<Option<Session>, AppError> {
+        let query = format!("SELECT {} FROM sessions WHERE id = $1 AND user_id = $2", SESSION_COLUMNS);
+        let rec = sqlx::query_as::<_, Session>(&query)
+            .bind(id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Session>, AppError> {
+        let query = format!("SELECT {} FROM sessions WHERE user_id = $1 ORDER BY last_seen_at DESC", SESSION_COLUMNS);
+        let rec = sqlx::query_as::<_, Session>(&query)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn find_active_by_user(&self, user_id: Uuid, seen_since: DateTime<Utc>) -> Result<Vec<Session>, AppError> {
+        let query = format!(
+            "SELECT {} FROM sessions WHERE user_id = $1 AND last_seen_at > $2 ORDER BY last_seen_at DESC",
+            SESSION_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, Session>(&query)
+            .bind(user_id)
+            .bind(seen_since)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn delete(&self, id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM sessions WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_all_for_user(&self, user_id: Uuid, except: Option<Uuid>) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND id IS DISTINCT FROM $2")
+            .bind(user_id)
+            .bind(except)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_stale_for_user(&self, user_id: Uuid, seen_before: DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND last_seen_at <= $2")
+            .bind(user_id)
+            .bind(seen_before)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
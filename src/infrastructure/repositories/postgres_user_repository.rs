@@ -1,9 +1,15 @@
 use async_trait::async_trait;
-use sqlx::PgPool;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use tokio::sync::mpsc;
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, PgExecutor, PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
-use crate::domain::entities::user::User;
-use crate::domain::repositories::user_repository::UserRepository;
+use crate::domain::entities::user::{AuthProvider, User, UserStatus};
+use crate::domain::repositories::pagination::{Cursor, CursorKey, KeysetPage, PageParams};
+use crate::domain::repositories::user_repository::{ImportMode, PurgeMode, UserFilter, UserRepository, UserSearchHit, UserSort, UserSortField};
+use crate::infrastructure::repositories::keyset::push_keyset;
 use crate::infrastructure::errors::AppError;
+use crate::utils::html;
 
 pub struct PostgresUserRepository {
     pool: PgPool,
@@ -15,42 +21,172 @@ impl PostgresUserRepository {
     }
 }
 
-const USER_COLUMNS: &str = "id, name, phone, email, password_hash, role, status, github_id, google_id, avatar_url, created_at, updated_at";
+/// Rows buffered between the database and a slow `stream` consumer
+const STREAM_BUFFER: usize = 256;
+
+const USER_COLUMNS: &str = "id, name, phone, email, password_hash, role, status, github_id, google_id, gitlab_id, microsoft_id, discord_id, apple_id, avatar_url, avatar_key, phone_verified_at, created_at, updated_at, last_login_at, deleted_at";
+
+/// Scrubs every personal field but keeps the row, so anything referencing its id
+/// (audit events, exports) stays valid. Callers also drop the user's SMS codes, which hold the phone.
+const ANONYMIZE_SET: &str = "name = 'Deleted user', email = 'deleted-' || id || '@invalid', phone = NULL, phone_verified_at = NULL, \
+    password_hash = NULL, github_id = NULL, google_id = NULL, gitlab_id = NULL, microsoft_id = NULL, discord_id = NULL, apple_id = NULL, \
+    avatar_url = NULL, avatar_key = NULL, purged_at = NOW()";
+
+/// Appends a `WHERE` clause for every filter that is set
+fn push_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &UserFilter) {
+    builder.push(" WHERE deleted_at IS NULL");
+    if let Some(role) = &filter.role {
+        builder.push(" AND role = ").push_bind(role.clone());
+    }
+    if let Some(status) = &filter.status {
+        builder.push(" AND status = ").push_bind(status.clone());
+    }
+    match filter.provider {
+        Some(AuthProvider::Password) => { builder.push(" AND password_hash IS NOT NULL"); }
+        Some(AuthProvider::Github) => { builder.push(" AND github_id IS NOT NULL"); }
+        Some(AuthProvider::Google) => { builder.push(" AND google_id IS NOT NULL"); }
+        Some(AuthProvider::Gitlab) => { builder.push(" AND gitlab_id IS NOT NULL"); }
+        Some(AuthProvider::Microsoft) => { builder.push(" AND microsoft_id IS NOT NULL"); }
+        Some(AuthProvider::Discord) => { builder.push(" AND discord_id IS NOT NULL"); }
+        Some(AuthProvider::Apple) => { builder.push(" AND apple_id IS NOT NULL"); }
+        None => {}
+    }
+    if let Some(from) = filter.created_from {
+        builder.push(" AND created_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.created_to {
+        builder.push(" AND created_at < ").push_bind(to);
+    }
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        let escaped = search.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let pattern = format!("%{}%", escaped);
+        builder
+            .push(" AND (name ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR email ILIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+}
+
+/// Maps a unique violation to `PhoneAlreadyExists` (verified phones) or `EmailAlreadyExists`
+fn map_unique_violation(e: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(db_err) = &e {
+        if db_err.code().unwrap_or_default() == "23505" {
+            if db_err.constraint() == Some("users_phone_verified_key") {
+                return AppError::PhoneAlreadyExists;
+            }
+            // users_github_id_active_key and the other providers' ids
+            if db_err.constraint().is_some_and(|name| name.ends_with("_id_active_key")) {
+                return AppError::IdentityAlreadyLinked;
+            }
+            return AppError::EmailAlreadyExists;
+        }
+    }
+    AppError::DatabaseError(e)
+}
+
+async fn insert_user<'e>(executor: impl PgExecutor<'e>, user: &User) -> Result<User, AppError> {
+    let query = format!(
+        "INSERT INTO users (name, phone, email, password_hash, role, status, github_id, google_id, avatar_url)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING {}", USER_COLUMNS
+    );
+    sqlx::query_as::<_, User>(&query)
+        .bind(&user.name)
+        .bind(&user.phone)
+        .bind(&user.email)
+        .bind(&user.password_hash)
+        .bind(&user.role)
+        .bind(&user.status)
+        .bind(user.github_id)
+        .bind(&user.google_id)
+        .bind(&user.avatar_url)
+        .fetch_one(executor)
+        .await
+        .map_err(map_unique_violation)
+}
+
+/// Column holding a provider's account id, and the SQL type to cast the id to
+fn identity_column(provider: AuthProvider) -> Result<(&'static str, &'static str), AppError> {
+    match provider {
+        AuthProvider::Github => Ok(("github_id", "bigint")),
+        AuthProvider::Google => Ok(("google_id", "text")),
+        AuthProvider::Gitlab => Ok(("gitlab_id", "text")),
+        AuthProvider::Microsoft => Ok(("microsoft_id", "text")),
+        AuthProvider::Discord => Ok(("discord_id", "text")),
+        AuthProvider::Apple => Ok(("apple_id", "text")),
+        AuthProvider::Password => Err(AppError::InternalServerError),
+    }
+}
+
+/// Turns free text into a prefix `tsquery` ("daf ema" -> "daf:* & ema:*").
+/// Only alphanumeric tokens survive, so user input can never inject tsquery syntax.
+fn to_prefix_tsquery(query: &str) -> String {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| format!("{}:*", token.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Marks `ts_headline` puts around matches. The column is highlighted as plain
+/// text and only escaped afterwards, so user data can never supply markup;
+/// the markers themselves are stripped from the column first.
+const HIGHLIGHT_START: char = '\u{1}';
+const HIGHLIGHT_STOP: char = '\u{2}';
+
+fn highlight_sql(column: &str) -> String {
+    format!(
+        "ts_headline('simple', translate({column}, E'\\x01\\x02', ''), q.ts, E'StartSel=\\x01, StopSel=\\x02, HighlightAll=true')"
+    )
+}
+
+/// HTML-escapes a highlight and turns its markers into `<mark>` tags
+fn highlight_html(highlight: &str) -> String {
+    html::escape(highlight)
+        .replace(HIGHLIGHT_START, "<mark>")
+        .replace(HIGHLIGHT_STOP, "</mark>")
+}
 
 #[async_trait]
 impl UserRepository for PostgresUserRepository {
     async fn create(&self, user: &User) -> Result<User, AppError> {
-        let query = format!(
-            "INSERT INTO users (name, phone, email, password_hash, role, status, github_id, google_id, avatar_url)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-             RETURNING {}", USER_COLUMNS
-        );
-        let rec = sqlx::query_as::<_, User>(&query)
-            .bind(&user.name)
-            .bind(&user.phone)
-            .bind(&user.email)
-            .bind(&user.password_hash)
-            .bind(&user.role)
-            .bind(&user.status)
-            .bind(&user.github_id)
-            .bind(&user.google_id)
-            .bind(&user.avatar_url)
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| {
-                if let sqlx::Error::Database(db_err) = &e {
-                    if db_err.code().unwrap_or_default() == "23505" {
-                        return AppError::EmailAlreadyExists;
-                    }
+        insert_user(&self.pool, user).await
+    }
+
+    async fn create_many(&self, users: &[User], mode: ImportMode, rollback: bool) -> Result<Vec<Result<User, AppError>>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        let mut results = Vec::with_capacity(users.len());
+
+        for user in users {
+            // A failed statement aborts the whole transaction unless it ran inside a savepoint
+            let mut savepoint = tx.begin().await.map_err(AppError::DatabaseError)?;
+            match insert_user(&mut *savepoint, user).await {
+                Ok(created) => {
+                    savepoint.commit().await.map_err(AppError::DatabaseError)?;
+                    results.push(Ok(created));
                 }
-                AppError::DatabaseError(e)
-            })?;
+                Err(e) => {
+                    savepoint.rollback().await.map_err(AppError::DatabaseError)?;
+                    results.push(Err(e));
+                }
+            }
+        }
 
-        Ok(rec)
+        let any_failed = results.iter().any(Result::is_err);
+        if rollback || (mode == ImportMode::AllOrNothing && any_failed) {
+            tx.rollback().await.map_err(AppError::DatabaseError)?;
+        } else {
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+        }
+
+        Ok(results)
     }
 
     async fn find_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
-        let query = format!("SELECT {} FROM users WHERE email = $1", USER_COLUMNS);
+        let query = format!("SELECT {} FROM users WHERE lower(email) = lower($1) AND deleted_at IS NULL", USER_COLUMNS);
         let rec = sqlx::query_as::<_, User>(&query)
             .bind(email)
             .fetch_optional(&self.pool)
@@ -60,8 +196,22 @@ impl UserRepository for PostgresUserRepository {
         Ok(rec)
     }
 
+    async fn find_by_phone(&self, phone: &str) -> Result<Option<User>, AppError> {
+        let query = format!(
+            "SELECT {} FROM users WHERE phone = $1 AND deleted_at IS NULL ORDER BY phone_verified_at IS NULL, created_at LIMIT 1",
+            USER_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, User>(&query)
+            .bind(phone)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
-        let query = format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS);
+        let query = format!("SELECT {} FROM users WHERE id = $1 AND deleted_at IS NULL", USER_COLUMNS);
         let rec = sqlx::query_as::<_, User>(&query)
             .bind(id)
             .fetch_optional(&self.pool)
@@ -71,9 +221,19 @@ impl UserRepository for PostgresUserRepository {
         Ok(rec)
     }
 
-    async fn find_all(&self) -> Result<Vec<User>, AppError> {
-        let query = format!("SELECT {} FROM users", USER_COLUMNS);
-        let rec = sqlx::query_as::<_, User>(&query)
+    async fn find_page(&self, filter: &UserFilter, sort: UserSort, page: PageParams) -> Result<Vec<User>, AppError> {
+        let sort_column = sort.field.as_str();
+
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+        push_filter(&mut builder, filter);
+        builder
+            .push(format!(" ORDER BY {0} {1}, id {1} LIMIT ", sort_column, sort.direction.as_sql()))
+            .push_bind(page.limit())
+            .push(" OFFSET ")
+            .push_bind(page.offset());
+
+        let rec = builder
+            .build_query_as::<User>()
             .fetch_all(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
@@ -81,36 +241,136 @@ impl UserRepository for PostgresUserRepository {
         Ok(rec)
     }
 
+    fn stream(&self, filter: UserFilter, sort: UserSort) -> BoxStream<'static, Result<User, AppError>> {
+        let pool = self.pool.clone();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        // The row stream borrows the query, so it runs on its own task and hands rows over
+        // a bounded channel; a full channel stops reading from the socket until the consumer catches up
+        tokio::spawn(async move {
+            let mut builder = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+            push_filter(&mut builder, &filter);
+            builder.push(format!(" ORDER BY {0} {1}, id {1}", sort.field.as_str(), sort.direction.as_sql()));
+
+            let mut rows = builder.build_query_as::<User>().fetch(&pool);
+            while let Some(row) = rows.next().await {
+                let failed = row.is_err();
+                // The receiver is gone once the client disconnects
+                if tx.send(row.map_err(AppError::DatabaseError)).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|row| (row, rx)) }).boxed()
+    }
+
+    async fn find_keyset(&self, filter: &UserFilter, sort: UserSort, cursor: Option<&Cursor>, limit: i64) -> Result<KeysetPage<User>, AppError> {
+        let mut builder = QueryBuilder::new(format!("SELECT {} FROM users", USER_COLUMNS));
+        push_filter(&mut builder, filter);
+        push_keyset(&mut builder, sort.field.as_str(), sort.direction, cursor, limit);
+
+        let rows = builder
+            .build_query_as::<User>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(KeysetPage::from_rows(rows, limit, sort.field.as_str(), sort.direction, cursor, |user| {
+            let key = match sort.field {
+                // Only unset on users not yet inserted; the column is NOT NULL
+                UserSortField::CreatedAt => CursorKey::Timestamp(user.created_at.unwrap_or_default()),
+                UserSortField::Name => CursorKey::Text(user.name.clone()),
+                UserSortField::Email => CursorKey::Text(user.email.clone()),
+            };
+            (key, user.id)
+        }))
+    }
+
+    async fn count(&self, filter: &UserFilter) -> Result<i64, AppError> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM users");
+        push_filter(&mut builder, filter);
+
+        let total: i64 = builder
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(total)
+    }
+
+    async fn search(&self, query: &str, limit: i64) -> Result<Vec<UserSearchHit>, AppError> {
+        let query = query.trim();
+        let digits: String = query.chars().filter(char::is_ascii_digit).collect();
+
+        // Prefix full-text matches rank first; trigram word similarity tolerates typos
+        let sql = format!(
+            "WITH q AS (SELECT to_tsquery('simple', $1) AS ts)
+             SELECT {},
+                 (ts_rank(search_vector, q.ts) + GREATEST(word_similarity($2, name), word_similarity($2, email)))::real AS rank,
+                 {highlight_name} AS name_highlight,
+                 {highlight_email} AS email_highlight,
+                 {highlight_phone} AS phone_highlight
+             FROM users, q
+             WHERE deleted_at IS NULL
+               AND (search_vector @@ q.ts
+                OR $2 <% name
+                OR $2 <% email
+                OR (length($3) >= 3 AND regexp_replace(coalesce(phone, ''), '\\D', '', 'g') LIKE '%' || $3 || '%'))
+             ORDER BY rank DESC, id
+             LIMIT $4",
+            USER_COLUMNS,
+            highlight_name = highlight_sql("name"),
+            highlight_email = highlight_sql("email"),
+            highlight_phone = highlight_sql("phone"),
+        );
+        let mut rec = sqlx::query_as::<_, UserSearchHit>(&sql)
+            .bind(to_prefix_tsquery(query))
+            .bind(query)
+            .bind(digits)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        for hit in &mut rec {
+            hit.name_highlight = highlight_html(&hit.name_highlight);
+            hit.email_highlight = highlight_html(&hit.email_highlight);
+            hit.phone_highlight = hit.phone_highlight.as_deref().map(highlight_html);
+        }
+
+        Ok(rec)
+    }
+
     async fn update(&self, id: Uuid, user: &User) -> Result<User, AppError> {
         let query = format!(
-            "UPDATE users SET name = $1, phone = $2, email = $3, role = $4, github_id = $5, google_id = $6, avatar_url = $7, updated_at = NOW()
-             WHERE id = $8 RETURNING {}", USER_COLUMNS
+            "UPDATE users SET name = $1, phone = $2, email = $3, role = $4, github_id = $5, google_id = $6, avatar_url = $7,
+                 phone_verified_at = CASE WHEN phone IS DISTINCT FROM $2 THEN NULL ELSE phone_verified_at END,
+                 updated_at = NOW()
+             WHERE id = $8 AND deleted_at IS NULL RETURNING {}", USER_COLUMNS
         );
         let rec = sqlx::query_as::<_, User>(&query)
             .bind(&user.name)
             .bind(&user.phone)
             .bind(&user.email)
             .bind(&user.role)
-            .bind(&user.github_id)
+            .bind(user.github_id)
             .bind(&user.google_id)
             .bind(&user.avatar_url)
             .bind(id)
             .fetch_one(&self.pool)
             .await
-            .map_err(|e| {
-                if let sqlx::Error::Database(db_err) = &e {
-                    if db_err.code().unwrap_or_default() == "23505" {
-                        return AppError::EmailAlreadyExists;
-                    }
-                }
-                AppError::DatabaseError(e)
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => AppError::UserNotFound,
+                e => map_unique_violation(e),
             })?;
 
         Ok(rec)
     }
 
     async fn delete(&self, id: Uuid) -> Result<(), AppError> {
-        sqlx::query("DELETE FROM users WHERE id = $1")
+        sqlx::query("UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
             .bind(id)
             .execute(&self.pool)
             .await
@@ -119,61 +379,149 @@ impl UserRepository for PostgresUserRepository {
         Ok(())
     }
 
-    async fn update_status(&self, id: Uuid, status: crate::domain::entities::user::UserStatus) -> Result<User, AppError> {
+    async fn find_deleted_by_id(&self, id: Uuid) -> Result<Option<User>, AppError> {
+        let query = format!("SELECT {} FROM users WHERE id = $1 AND deleted_at IS NOT NULL AND purged_at IS NULL", USER_COLUMNS);
+        let rec = sqlx::query_as::<_, User>(&query)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rec)
+    }
+
+    async fn restore(&self, id: Uuid) -> Result<User, AppError> {
         let query = format!(
-            "UPDATE users SET status = $1, updated_at = NOW() WHERE id = $2 RETURNING {}", USER_COLUMNS
+            "UPDATE users SET deleted_at = NULL, updated_at = NOW()
+             WHERE id = $1 AND deleted_at IS NOT NULL AND purged_at IS NULL
+             RETURNING {}", USER_COLUMNS
         );
         let rec = sqlx::query_as::<_, User>(&query)
-            .bind(status)
             .bind(id)
-            .fetch_one(&self.pool)
+            .fetch_optional(&self.pool)
+            .await
+            // Someone else took the email, verified the phone or linked a provider
+            // account while this account was deleted
+            .map_err(map_unique_violation)?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(rec)
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>, mode: PurgeMode) -> Result<Vec<Uuid>, AppError> {
+        let query = match mode {
+            PurgeMode::Anonymize => format!(
+                "WITH purged AS (UPDATE users SET {} WHERE deleted_at < $1 AND purged_at IS NULL RETURNING id),
+                      codes AS (DELETE FROM phone_verifications WHERE user_id IN (SELECT id FROM purged)),
+                      tokens AS (DELETE FROM provider_tokens WHERE user_id IN (SELECT id FROM purged)),
+                      signed_in AS (DELETE FROM sessions WHERE user_id IN (SELECT id FROM purged))
+                 SELECT id FROM purged",
+                ANONYMIZE_SET
+            ),
+            PurgeMode::Delete => "DELETE FROM users WHERE deleted_at < $1 RETURNING id".to_string(),
+        };
+        let ids = sqlx::query_scalar::<_, Uuid>(&query)
+            .bind(cutoff)
+            .fetch_all(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
 
+        Ok(ids)
+    }
+
+    async fn anonymize(&self, id: Uuid) -> Result<(), AppError> {
+        let query = format!(
+            "WITH codes AS (DELETE FROM phone_verifications WHERE user_id = $1),
+                  tokens AS (DELETE FROM provider_tokens WHERE user_id = $1),
+                  signed_in AS (DELETE FROM sessions WHERE user_id = $1)
+             UPDATE users SET {}, deleted_at = COALESCE(deleted_at, NOW()) WHERE id = $1 AND purged_at IS NULL",
+            ANONYMIZE_SET
+        );
+        sqlx::query(&query)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn record_login(&self, id: Uuid) -> Result<(), AppError> {
+        sqlx::query("UPDATE users SET last_login_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn update_avatar(&self, id: Uuid, avatar_url: Option<&str>, avatar_key: Option<&str>) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET avatar_url = $1, avatar_key = $2, updated_at = NOW() WHERE id = $3 AND deleted_at IS NULL RETURNING {}", USER_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, User>(&query)
+            .bind(avatar_url)
+            .bind(avatar_key)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?
+            .ok_or(AppError::UserNotFound)?;
+
         Ok(rec)
     }
 
-    async fn find_by_github_id(&self, github_id: i64) -> Result<Option<User>, AppError> {
-        let query = format!("SELECT {} FROM users WHERE github_id = $1", USER_COLUMNS);
+    async fn mark_phone_verified(&self, id: Uuid, phone: &str) -> Result<User, AppError> {
+        let query = format!(
+            "UPDATE users SET phone_verified_at = NOW(), updated_at = NOW()
+             WHERE id = $1 AND phone = $2 AND deleted_at IS NULL RETURNING {}", USER_COLUMNS
+        );
         let rec = sqlx::query_as::<_, User>(&query)
-            .bind(github_id)
+            .bind(id)
+            .bind(phone)
             .fetch_optional(&self.pool)
             .await
-            .map_err(AppError::DatabaseError)?;
+            .map_err(map_unique_violation)?
+            .ok_or(AppError::InvalidVerificationCode)?;
 
         Ok(rec)
     }
 
-    async fn upsert_github_user(&self, user: &User) -> Result<User, AppError> {
+    async fn update_status(&self, id: Uuid, status: UserStatus) -> Result<User, AppError> {
         let query = format!(
-            "INSERT INTO users (id, name, email, github_id, avatar_url, role, status)
-             VALUES ($1, $2, $3, $4, $5, $6, $7)
-             ON CONFLICT (github_id) DO UPDATE
-             SET name = EXCLUDED.name, avatar_url = EXCLUDED.avatar_url, updated_at = NOW()
-             RETURNING {}", USER_COLUMNS
+            "UPDATE users SET status = $1, updated_at = NOW() WHERE id = $2 AND deleted_at IS NULL RETURNING {}", USER_COLUMNS
         );
         let rec = sqlx::query_as::<_, User>(&query)
-            .bind(&user.id)
-            .bind(&user.name)
-            .bind(&user.email)
-            .bind(&user.github_id)
-            .bind(&user.avatar_url)
-            .bind(&user.role)
-            .bind(&user.status)
-            .fetch_one(&self.pool)
+            .bind(status)
+            .bind(id)
+            .fetch_optional(&self.pool)
             .await
-            .map_err(|e| {
-                tracing::error!("GitHub user upsert error: {:?}", e);
-                AppError::DatabaseError(e)
-            })?;
+            .map_err(AppError::DatabaseError)?
+            .ok_or(AppError::UserNotFound)?;
 
         Ok(rec)
     }
 
-    async fn find_by_google_id(&self, google_id: &str) -> Result<Option<User>, AppError> {
-        let query = format!("SELECT {} FROM users WHERE google_id = $1", USER_COLUMNS);
+    async fn find_by_identity(&self, provider: AuthProvider, provider_user_id: &str) -> Result<Option<User>, AppError> {
+        let (column, sql_type) = identity_column(provider)?;
+        let query = format!(
+            "SELECT {} FROM users WHERE {} = $1::{} AND deleted_at IS NULL",
+            USER_COLUMNS, column, sql_type
+        );
         let rec = sqlx::query_as::<_, User>(&query)
-            .bind(google_id)
+            .bind(provider_user_id)
             .fetch_optional(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
@@ -181,27 +529,55 @@ impl UserRepository for PostgresUserRepository {
         Ok(rec)
     }
 
-    async fn upsert_google_user(&self, user: &User) -> Result<User, AppError> {
+    async fn link_identity(&self, id: Uuid, provider: AuthProvider, provider_user_id: &str, avatar_url: Option<&str>) -> Result<User, AppError> {
+        let (column, sql_type) = identity_column(provider)?;
         let query = format!(
-            "INSERT INTO users (id, name, email, google_id, avatar_url, role, status)
-             VALUES ($1, $2, $3, $4, $5, $6, $7)
-             ON CONFLICT (google_id) DO UPDATE
-             SET name = EXCLUDED.name, avatar_url = EXCLUDED.avatar_url, updated_at = NOW()
-             RETURNING {}", USER_COLUMNS
+            "UPDATE users SET {} = $1::{},
+                 avatar_url = CASE WHEN avatar_key IS NULL THEN $2 ELSE avatar_url END,
+                 updated_at = NOW()
+             WHERE id = $3 AND deleted_at IS NULL RETURNING {}",
+            column, sql_type, USER_COLUMNS
         );
         let rec = sqlx::query_as::<_, User>(&query)
-            .bind(&user.id)
+            .bind(provider_user_id)
+            .bind(avatar_url)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            // Another account got linked to the same provider account meanwhile
+            .map_err(map_unique_violation)?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(rec)
+    }
+
+    async fn upsert_identity_user(&self, provider: AuthProvider, provider_user_id: &str, user: &User) -> Result<User, AppError> {
+        let (column, sql_type) = identity_column(provider)?;
+        let query = format!(
+            "INSERT INTO users (id, name, email, {column}, avatar_url, role, status)
+             VALUES ($1, $2, $3, $4::{sql_type}, $5, $6, $7)
+             ON CONFLICT ({column}) WHERE deleted_at IS NULL DO UPDATE
+             SET name = EXCLUDED.name,
+                 avatar_url = CASE WHEN users.avatar_key IS NULL THEN EXCLUDED.avatar_url ELSE users.avatar_url END,
+                 updated_at = NOW()
+             RETURNING {columns}",
+            column = column,
+            sql_type = sql_type,
+            columns = USER_COLUMNS
+        );
+        let rec = sqlx::query_as::<_, User>(&query)
+            .bind(user.id)
             .bind(&user.name)
             .bind(&user.email)
-            .bind(&user.google_id)
+            .bind(provider_user_id)
             .bind(&user.avatar_url)
             .bind(&user.role)
             .bind(&user.status)
             .fetch_one(&self.pool)
             .await
             .map_err(|e| {
-                tracing::error!("Google user upsert error: {:?}", e);
-                AppError::DatabaseError(e)
+                tracing::error!("{} user upsert error: {:?}", provider.as_str(), e);
+                map_unique_violation(e)
             })?;
 
         Ok(rec)
@@ -0,0 +1,187 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, Extensions, HeaderMap},
+};
+
+/// Longest request id kept; longer ones are client-supplied junk
+const MAX_REQUEST_ID_LEN: usize = 100;
+
+/// Where a request came from, recorded alongside audit events
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl RequestContext {
+    /// The client address is the TCP peer, unless the peer is a trusted proxy
+    /// (a `TrustedProxies` request extension), in which case `X-Forwarded-For`
+    /// is followed right to left up to the first hop that is not one
+    pub fn from_http(headers: &HeaderMap, extensions: &Extensions) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let peer = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_canonical());
+        let ip_address = peer.map(|peer| match extensions.get::<Arc<TrustedProxies>>() {
+            Some(proxies) => proxies.client_ip(peer, headers),
+            None => peer,
+        });
+
+        Self {
+            ip_address: ip_address.map(|ip| ip.to_string()),
+            user_agent: header(USER_AGENT.as_str()),
+            request_id: header("x-request-id").filter(|id| id.len() <= MAX_REQUEST_ID_LEN),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for RequestContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_http(&parts.headers, &parts.extensions))
+    }
+}
+
+/// Reverse proxies whose `X-Forwarded-For` is believed (`TRUSTED_PROXIES`)
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Addresses (`10.0.0.5`) or CIDR ranges (`10.0.0.0/8`, `fd00::/8`)
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let ranges = entries
+            .iter()
+            .map(|entry| {
+                let (address, prefix) = match entry.split_once('/') {
+                    Some((address, prefix)) => (address, Some(prefix)),
+                    None => (entry.as_str(), None),
+                };
+                let address: IpAddr = address
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("'{}' is not an IP address or CIDR range", entry))?;
+                let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .trim()
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|prefix| *prefix <= max_prefix)
+                        .ok_or_else(|| format!("'{}' has an invalid prefix length", entry))?,
+                    None => max_prefix,
+                };
+                Ok((address, prefix))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { ranges })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|&(range, prefix)| match (range, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+                u32::from(range) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+                u128::from(range) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
+    }
+
+    /// The right-most address that is not a trusted proxy. A hop that is not
+    /// an IP address stops the walk at the proxy that reported it.
+    fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+
+        let mut client = peer;
+        for hop in forwarded.into_iter().rev() {
+            if !self.contains(client) {
+                break;
+            }
+            match hop.parse::<IpAddr>() {
+                Ok(ip) => client = ip.to_canonical(),
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(peer: &str, forwarded_for: Option<&str>, proxies: &[&str]) -> RequestContext {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = forwarded_for {
+            headers.insert("x-forwarded-for", value.parse().unwrap());
+        }
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)));
+        let proxies: Vec<String> = proxies.iter().map(|proxy| proxy.to_string()).collect();
+        extensions.insert(Arc::new(TrustedProxies::parse(&proxies).unwrap()));
+        RequestContext::from_http(&headers, &extensions)
+    }
+
+    #[test]
+    fn forwarded_for_from_an_untrusted_peer_is_ignored() {
+        let ctx = context("203.0.113.7", Some("198.51.100.1"), &[]);
+
+        assert_eq!(ctx.ip_address.as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn takes_the_right_most_hop_that_is_not_a_trusted_proxy() {
+        // The client made up the first entry; our proxies appended the rest
+        let ctx = context("10.0.0.2", Some("1.1.1.1, 198.51.100.1, 10.0.0.9"), &["10.0.0.0/8"]);
+
+        assert_eq!(ctx.ip_address.as_deref(), Some("198.51.100.1"));
+    }
+
+    #[test]
+    fn a_hop_that_is_not_an_ip_address_is_not_recorded() {
+        let junk = "x".repeat(200);
+        let ctx = context("10.0.0.2", Some(&junk), &["10.0.0.2"]);
+
+        assert_eq!(ctx.ip_address.as_deref(), Some("10.0.0.2"));
+    }
+
+    #[test]
+    fn matches_ipv6_ranges_and_mapped_ipv4_peers() {
+        let ctx = context("::ffff:10.0.0.2", Some("2001:db8::1"), &["10.0.0.0/24"]);
+        assert_eq!(ctx.ip_address.as_deref(), Some("2001:db8::1"));
+
+        let ctx = context("fd00::1", Some("2001:db8::1"), &["fd00::/8"]);
+        assert_eq!(ctx.ip_address.as_deref(), Some("2001:db8::1"));
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        assert!(TrustedProxies::parse(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(TrustedProxies::parse(&["proxy.internal".to_string()]).is_err());
+    }
+}
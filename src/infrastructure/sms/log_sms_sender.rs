@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use crate::infrastructure::errors::AppError;
+use super::{SmsMessage, SmsSender};
+
+/// Development SMS sender: logs outgoing messages instead of sending them and,
+/// when `file_path` is set, also appends each one as a JSON line so tests can read the codes
+pub struct LogSmsSender {
+    file_path: Option<PathBuf>,
+}
+
+impl LogSmsSender {
+    pub fn new(file_path: Option<PathBuf>) -> Self {
+        Self { file_path }
+    }
+}
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, message: &SmsMessage) -> Result<(), AppError> {
+        tracing::info!(to = %message.to, "Outgoing SMS: {}", message.body);
+
+        if let Some(file_path) = &self.file_path {
+            let mut line = json!({
+                "to": message.to,
+                "body": message.body,
+                "sent_at": Utc::now(),
+            })
+            .to_string();
+            line.push('\n');
+
+            let io_error = |e: std::io::Error| {
+                tracing::error!("Failed to write SMS to {}: {:?}", file_path.display(), e);
+                AppError::InternalServerError
+            };
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file_path)
+                .await
+                .map_err(io_error)?;
+            file.write_all(line.as_bytes()).await.map_err(io_error)?;
+        }
+
+        Ok(())
+    }
+}
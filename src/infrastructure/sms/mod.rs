@@ -0,0 +1,16 @@
+pub mod log_sms_sender;
+
+use async_trait::async_trait;
+use crate::infrastructure::errors::AppError;
+
+#[derive(Debug, Clone)]
+pub struct SmsMessage {
+    /// E.164 number
+    pub to: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send(&self, message: &SmsMessage) -> Result<(), AppError>;
+}
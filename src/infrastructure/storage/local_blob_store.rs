@@ -0,0 +1,69 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use async_trait::async_trait;
+use crate::infrastructure::errors::AppError;
+use super::{is_valid_key, BlobStore};
+
+/// Stores blobs as files under `root`, one file per key
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, AppError> {
+        if !is_valid_key(key) {
+            tracing::error!("Rejected blob key: {:?}", key);
+            return Err(AppError::InternalServerError);
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+fn io_error(e: std::io::Error) -> AppError {
+    tracing::error!("Blob store error: {:?}", e);
+    AppError::InternalServerError
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+
+        // Write then rename so readers never see a half-written file
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await.map_err(io_error)?;
+        tokio::fs::rename(&tmp, &path).await.map_err(io_error)?;
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, AppError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), AppError> {
+        let path = self.path(prefix)?;
+        let result = match tokio::fs::metadata(&path).await {
+            Ok(meta) if meta.is_dir() => tokio::fs::remove_dir_all(&path).await,
+            Ok(_) => tokio::fs::remove_file(&path).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+}
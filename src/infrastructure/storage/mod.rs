@@ -0,0 +1,24 @@
+pub mod local_blob_store;
+
+use async_trait::async_trait;
+use crate::infrastructure::errors::AppError;
+
+/// Opaque byte storage addressed by `/`-separated keys such as `avatars/{user_id}/{version}/256.jpg`
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: Vec<u8>) -> Result<(), AppError>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, AppError>;
+    /// Removes `prefix` and every key below it
+    async fn delete_prefix(&self, prefix: &str) -> Result<(), AppError>;
+}
+
+/// Keys are plain path segments: no empty, `.` or `..` parts and no unusual characters
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::data_export_repository::DataExportRepository;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::usecases::data_privacy::GenerateDataExportUseCase;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Builds queued data exports and removes archives past their download window
+pub fn spawn<R, E, C, S, A>(
+    user_repository: Arc<R>,
+    data_export_repository: Arc<E>,
+    email_change_repository: Arc<C>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+    export_dir: PathBuf,
+)
+where
+    R: UserRepository + 'static,
+    E: DataExportRepository + 'static,
+    C: EmailChangeRepository + 'static,
+    S: SessionRepository + 'static,
+    A: AuditRepository + 'static,
+{
+    tokio::spawn(async move {
+        let usecase = GenerateDataExportUseCase::new(
+            user_repository,
+            data_export_repository,
+            email_change_repository,
+            session_repository,
+            audit_repository,
+            export_dir,
+        );
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            // Drain the queue before waiting for the next tick
+            loop {
+                match usecase.execute_next().await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        tracing::error!("Data export job failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+
+            match usecase.expire().await {
+                Ok(0) => {}
+                Ok(expired) => tracing::info!("Expired {} data export(s)", expired),
+                Err(e) => tracing::error!("Data export expiry failed: {:?}", e),
+            }
+        }
+    });
+}
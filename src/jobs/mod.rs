@@ -0,0 +1,3 @@
+pub mod user_purge;
+pub mod data_export;
+pub mod provider_token_rotation;
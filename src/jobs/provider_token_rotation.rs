@@ -0,0 +1,19 @@
+use tokio::task::JoinHandle;
+use crate::domain::repositories::provider_token_repository::ProviderTokenRepository;
+use crate::usecases::provider_tokens::ProviderTokenStore;
+
+/// Re-wraps stored provider tokens with the active master key once at startup,
+/// after which retired keys can be removed from `PROVIDER_TOKEN_KEYS`. The
+/// handle resolves once the pass is over.
+pub fn spawn<P>(token_store: ProviderTokenStore<P>) -> JoinHandle<()>
+where
+    P: ProviderTokenRepository + 'static,
+{
+    tokio::spawn(async move {
+        match token_store.rotate_keys().await {
+            Ok(0) => {}
+            Ok(rotated) => tracing::info!("Re-wrapped {} provider token(s) with the active master key", rotated),
+            Err(e) => tracing::error!("Provider token key rotation failed: {:?}", e),
+        }
+    })
+}
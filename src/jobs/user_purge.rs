@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::data_export_repository::DataExportRepository;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::domain::repositories::user_repository::{PurgeMode, UserRepository};
+use crate::infrastructure::storage::BlobStore;
+use crate::usecases::user_management::PurgeDeletedUsersUseCase;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically purges users soft-deleted more than `retention_days` ago
+pub fn spawn<R, E, C, A, B>(
+    user_repository: Arc<R>,
+    data_export_repository: Arc<E>,
+    email_change_repository: Arc<C>,
+    audit_repository: Arc<A>,
+    blob_store: Arc<B>,
+    retention_days: i64,
+    mode: PurgeMode,
+)
+where
+    R: UserRepository + 'static,
+    E: DataExportRepository + 'static,
+    C: EmailChangeRepository + 'static,
+    A: AuditRepository + 'static,
+    B: BlobStore + 'static,
+{
+    tokio::spawn(async move {
+        let usecase = PurgeDeletedUsersUseCase::new(
+            user_repository,
+            data_export_repository,
+            email_change_repository,
+            audit_repository,
+            blob_store,
+        );
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match usecase.execute(retention_days, mode).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {} deleted user(s) ({:?})", purged, mode),
+                Err(e) => tracing::error!("User purge failed: {:?}", e),
+            }
+        }
+    });
+}
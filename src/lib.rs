@@ -0,0 +1,79 @@
+pub mod config;
+pub mod domain;
+pub mod handlers;
+pub mod infrastructure;
+pub mod jobs;
+pub mod routes;
+pub mod server;
+pub mod usecases;
+pub mod utils;
+
+use std::sync::Arc;
+use tower_http::cors::CorsLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+
+use crate::infrastructure::auth::jwt::JwtService;
+use crate::infrastructure::auth::oauth::OAuthProviderRegistry;
+use crate::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+use crate::infrastructure::repositories::postgres_audit_repository::PostgresAuditRepository;
+use crate::infrastructure::repositories::postgres_email_change_repository::PostgresEmailChangeRepository;
+use crate::infrastructure::repositories::postgres_data_export_repository::PostgresDataExportRepository;
+use crate::infrastructure::repositories::postgres_phone_verification_repository::PostgresPhoneVerificationRepository;
+use crate::infrastructure::repositories::postgres_provider_token_repository::PostgresProviderTokenRepository;
+use crate::infrastructure::repositories::postgres_oauth_exchange_code_repository::PostgresOAuthExchangeCodeRepository;
+use crate::infrastructure::repositories::postgres_session_repository::PostgresSessionRepository;
+use crate::infrastructure::crypto::EnvelopeCipher;
+use crate::infrastructure::mail::log_mailer::LogMailer;
+use crate::infrastructure::sms::log_sms_sender::LogSmsSender;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::storage::local_blob_store::LocalBlobStore;
+use crate::infrastructure::auth::middleware::audit_impersonation;
+use crate::infrastructure::auth::session_cookies::SessionCookies;
+use crate::infrastructure::request_context::TrustedProxies;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub user_repository: Arc<PostgresUserRepository>,
+    pub audit_repository: Arc<PostgresAuditRepository>,
+    pub email_change_repository: Arc<PostgresEmailChangeRepository>,
+    pub data_export_repository: Arc<PostgresDataExportRepository>,
+    pub phone_verification_repository: Arc<PostgresPhoneVerificationRepository>,
+    pub provider_token_repository: Arc<PostgresProviderTokenRepository>,
+    pub oauth_exchange_code_repository: Arc<PostgresOAuthExchangeCodeRepository>,
+    pub session_repository: Arc<PostgresSessionRepository>,
+    pub jwt_service: Arc<JwtService>,
+    /// OAuth providers that have credentials configured
+    pub oauth_providers: Arc<OAuthProviderRegistry>,
+    /// Frontend origins OAuth sign-ins may redirect back to (`OAUTH_REDIRECT_ORIGINS`)
+    pub oauth_redirect_origins: Arc<Vec<String>>,
+    /// Set when tokens are delivered in cookies (`SESSION_COOKIES_ENABLED`)
+    pub session_cookies: Option<SessionCookies>,
+    /// Encrypts stored provider tokens; `None` when they are not kept
+    pub token_cipher: Option<Arc<EnvelopeCipher>>,
+    pub mailer: Arc<LogMailer>,
+    pub sms_sender: Arc<LogSmsSender>,
+    pub phone_policy: Arc<PhonePolicy>,
+    pub blob_store: Arc<LocalBlobStore>,
+    /// Copy provider avatars into `blob_store` on sign-in instead of hotlinking them
+    pub mirror_oauth_avatars: bool,
+    /// Public base URL used to build links in outgoing email
+    pub app_base_url: String,
+    /// Proxies whose `X-Forwarded-For` decides the client address (`TRUSTED_PROXIES`)
+    pub trusted_proxies: Arc<TrustedProxies>,
+}
+
+/// The API under `/api/v1` with the middleware every request goes through
+pub fn app(state: AppState, cors: CorsLayer) -> axum::Router {
+    let api_routes = routes::api::create_router(&state.oauth_providers);
+
+    axum::Router::new()
+        .nest("/api/v1", api_routes)
+        .layer(axum::middleware::from_fn_with_state(state.clone(), audit_impersonation))
+        .layer(axum::Extension(state.trusted_proxies.clone()))
+        .layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state)
+}
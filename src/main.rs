@@ -1,98 +1,107 @@
-mod config;
-mod domain;
-mod handlers;
-mod infrastructure;
-mod routes;
-mod usecases;
-mod utils;
-
-use axum::http::Method;
 use dotenvy::dotenv;
-use std::env;
-use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-
-use crate::infrastructure::auth::jwt::JwtService;
-use crate::infrastructure::auth::github::GitHubOAuthClient;
-use crate::infrastructure::auth::google::GoogleOAuthClient;
-use crate::infrastructure::database::postgres::Database;
-use crate::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
 
-#[derive(Clone)]
-pub struct AppState {
-    pub user_repository: Arc<PostgresUserRepository>,
-    pub jwt_service: Arc<JwtService>,
-    pub github_oauth: Arc<GitHubOAuthClient>,
-    pub google_oauth: Arc<GoogleOAuthClient>,
-}
+use rust_axum::config::AppConfig;
+use rust_axum::infrastructure::auth::oauth::{OAuthHttpClient, OAuthProviderRegistry};
+use rust_axum::server;
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
+    server::init_tracing();
 
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rust_axum=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    let github_client_id = env::var("GITHUB_CLIENT_ID").expect("GITHUB_CLIENT_ID must be set");
-    let github_client_secret = env::var("GITHUB_CLIENT_SECRET").expect("GITHUB_CLIENT_SECRET must be set");
-    let github_redirect_uri = env::var("GITHUB_REDIRECT_URI").expect("GITHUB_REDIRECT_URI must be set");
-    let google_client_id = env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set");
-    let google_client_secret = env::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET must be set");
-    let google_redirect_uri = env::var("GOOGLE_REDIRECT_URI").expect("GOOGLE_REDIRECT_URI must be set");
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    });
+    tracing::debug!("Loaded configuration: {:?}", config);
 
-    let db = Database::new(&database_url).await.expect("Failed to connect to database");
-
-    sqlx::migrate!("./migrations")
-        .run(&db.pool)
-        .await
-        .expect("Failed to run migrations");
-
-    let user_repository = Arc::new(PostgresUserRepository::new(db.pool.clone()));
-    let jwt_service = Arc::new(JwtService::new(jwt_secret));
-    let github_oauth = Arc::new(GitHubOAuthClient::new(
-        github_client_id,
-        github_client_secret,
-        github_redirect_uri,
-    ));
-    let google_oauth = Arc::new(GoogleOAuthClient::new(
-        google_client_id,
-        google_client_secret,
-        google_redirect_uri,
-    ));
+    let oauth_providers = oauth_providers(&config);
+    server::run(config, oauth_providers).await;
+}
 
-    let state = AppState {
-        user_repository,
-        jwt_service,
-        github_oauth,
-        google_oauth,
-    };
+/// The OAuth providers with credentials configured
+fn oauth_providers(config: &AppConfig) -> OAuthProviderRegistry {
+    use rust_axum::infrastructure::auth::github::GitHubOAuthClient;
+    use rust_axum::infrastructure::auth::google::GoogleOAuthClient;
+    use rust_axum::infrastructure::auth::gitlab::{GitLabOAuthClient, GITLAB_COM};
+    use rust_axum::infrastructure::auth::microsoft::{MicrosoftOAuthClient, DEFAULT_TENANT, MICROSOFT_AUTHORITY};
+    use rust_axum::infrastructure::auth::discord::DiscordOAuthClient;
+    use rust_axum::infrastructure::auth::apple::{AppleClientSettings, AppleOAuthClient, APPLE_ISSUER};
 
-    let api_routes = routes::api::create_router();
-    
-    let app = axum::Router::new()
-        .nest("/api/v1", api_routes)
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-                .allow_headers(Any),
-        )
-        .layer(TraceLayer::new_for_http())
-        .with_state(state);
+    let oauth = &config.oauth;
+    let http = OAuthHttpClient::new(&oauth.http);
+    let mut registry = OAuthProviderRegistry::new();
+    let microsoft_tenant = oauth.microsoft.tenant.clone().unwrap_or_else(|| DEFAULT_TENANT.to_string());
 
-    let address = SocketAddr::from(([127, 0, 0, 1], 8000));
-    tracing::info!("Server listening on {}", address);
+    let github = &oauth.github;
+    if github.is_enabled() {
+        registry.register(Arc::new(GitHubOAuthClient::new(
+            github.client_id.clone(),
+            github.client_secret.expose().to_string(),
+            github.redirect_uri.clone(),
+            github.endpoints(GitHubOAuthClient::default_endpoints()),
+            http.clone(),
+        )));
+    }
+    let google = &oauth.google;
+    if google.is_enabled() {
+        registry.register(Arc::new(GoogleOAuthClient::new(
+            google.client_id.clone(),
+            google.client_secret.expose().to_string(),
+            google.redirect_uri.clone(),
+            google.endpoints(GoogleOAuthClient::default_endpoints()),
+            google.hosted_domain.clone(),
+            http.clone(),
+        )));
+    }
+    let gitlab = &oauth.gitlab;
+    if gitlab.is_enabled() {
+        let base_url = gitlab.base_url.as_deref().unwrap_or(GITLAB_COM);
+        registry.register(Arc::new(GitLabOAuthClient::new(
+            gitlab.client_id.clone(),
+            gitlab.client_secret.expose().to_string(),
+            gitlab.redirect_uri.clone(),
+            gitlab.endpoints(GitLabOAuthClient::default_endpoints(base_url)),
+            http.clone(),
+        )));
+    }
+    let microsoft = &oauth.microsoft;
+    if microsoft.is_enabled() {
+        let authority = microsoft.base_url.as_deref().unwrap_or(MICROSOFT_AUTHORITY);
+        registry.register(Arc::new(MicrosoftOAuthClient::new(
+            microsoft.client_id.clone(),
+            microsoft.client_secret.expose().to_string(),
+            microsoft.redirect_uri.clone(),
+            microsoft.endpoints(MicrosoftOAuthClient::default_endpoints(authority, &microsoft_tenant)),
+            authority,
+            microsoft_tenant,
+            http.clone(),
+        )));
+    }
+    let discord = &oauth.discord;
+    if discord.is_enabled() {
+        registry.register(Arc::new(DiscordOAuthClient::new(
+            discord.client_id.clone(),
+            discord.client_secret.expose().to_string(),
+            discord.redirect_uri.clone(),
+            discord.endpoints(DiscordOAuthClient::default_endpoints()),
+            http.clone(),
+        )));
+    }
+    let apple = &oauth.apple;
+    if apple.is_enabled() {
+        let settings = AppleClientSettings {
+            client_id: apple.client_id.clone(),
+            redirect_uri: apple.redirect_uri.clone(),
+            endpoints: apple.endpoints(AppleOAuthClient::default_endpoints()),
+            issuer: APPLE_ISSUER.to_string(),
+            team_id: apple.team_id.clone().unwrap_or_default(),
+            key_id: apple.key_id.clone().unwrap_or_default(),
+            private_key: apple.private_key().expect("oauth.apple.private_key_file is validated by AppConfig::load"),
+        };
+        registry.register(Arc::new(AppleOAuthClient::new(settings, http)));
+    }
 
-    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    registry
 }
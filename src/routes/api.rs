@@ -1,22 +1,73 @@
 use axum::{
-    routing::{post, get, put, patch},
-    Router,
+    extract::DefaultBodyLimit,
+    routing::{post, get, put, patch, delete},
+    Extension, Router,
 };
-use crate::handlers::auth::{sign_up, sign_in, refresh, github_login, github_callback, google_login, google_callback};
-use crate::handlers::users::get_users;
-use crate::handlers::user_management::{create_user, update_user, delete_user, update_user_status};
+use crate::handlers::auth::{sign_up, sign_in, refresh, sign_out, oauth_exchange, get_oauth_providers, oauth_login, oauth_callback, oauth_callback_form};
+use crate::handlers::users::{get_users, get_user, search_users, export_users};
+use crate::handlers::audit::get_audit_events;
+use crate::handlers::sessions::{get_my_sessions, revoke_my_session, revoke_my_other_sessions, get_user_sessions, revoke_user_session};
+use crate::handlers::avatars::{upload_avatar, delete_avatar, get_avatar};
+use crate::infrastructure::images::AVATAR_MAX_BYTES;
+use crate::handlers::profile::{
+    get_me, update_me, change_my_password, delete_me,
+    request_email_change, confirm_email_change_page, confirm_email_change, revert_email_change_page, revert_email_change,
+    request_phone_verification, confirm_phone_verification,
+};
+use crate::handlers::data_privacy::{
+    request_data_export, get_data_exports, get_data_export, download_data_export, erase_me,
+};
+use crate::handlers::user_management::{create_user, update_user, delete_user, update_user_status, impersonate_user, restore_user, import_users};
+use crate::infrastructure::auth::oauth::OAuthProviderRegistry;
 use crate::AppState;
 
-pub fn create_router() -> Router<AppState> {
-    Router::new()
+pub fn create_router(oauth_providers: &OAuthProviderRegistry) -> Router<AppState> {
+    let mut router = Router::new()
         .route("/auth/sign-up", post(sign_up))
         .route("/auth/sign-in", post(sign_in))
         .route("/auth/refresh", post(refresh))
-        .route("/auth/github", get(github_login))
-        .route("/auth/github/callback", get(github_callback))
-        .route("/auth/google", get(google_login))
-        .route("/auth/google/callback", get(google_callback))
+        .route("/auth/sign-out", post(sign_out))
+        .route("/auth/exchange", post(oauth_exchange))
+        .route("/auth/providers", get(get_oauth_providers));
+
+    // Only enabled providers get routes; the handlers receive the provider as an extension
+    for provider in oauth_providers.iter() {
+        let name = provider.provider().as_str();
+        let callback = if provider.uses_form_post() { post(oauth_callback_form) } else { get(oauth_callback) };
+        router = router
+            .route(&format!("/auth/{}", name), get(oauth_login).layer(Extension(provider.clone())))
+            .route(&format!("/auth/{}/callback", name), callback.layer(Extension(provider.clone())));
+    }
+
+    router
+        .route("/me", get(get_me).patch(update_me).delete(delete_me))
+        .route("/me/password", put(change_my_password))
+        .route("/me/email", post(request_email_change))
+        .route("/me/phone/verification", post(request_phone_verification))
+        .route("/me/phone/verification/confirm", post(confirm_phone_verification))
+        .route(
+            "/me/avatar",
+            // Room for the multipart framing around the file itself
+            put(upload_avatar).delete(delete_avatar).layer(DefaultBodyLimit::max(AVATAR_MAX_BYTES + 64 * 1024)),
+        )
+        .route("/avatars/{user_id}/{version}/{file}", get(get_avatar))
+        .route("/me/data-exports", get(get_data_exports).post(request_data_export))
+        .route("/me/data-exports/{id}", get(get_data_export))
+        .route("/me/data-exports/{id}/download", get(download_data_export))
+        .route("/me/erasure", post(erase_me))
+        .route("/me/sessions", get(get_my_sessions).delete(revoke_my_other_sessions))
+        .route("/me/sessions/{id}", delete(revoke_my_session))
+        .route("/auth/email-change/confirm", get(confirm_email_change_page).post(confirm_email_change))
+        .route("/auth/email-change/revert", get(revert_email_change_page).post(revert_email_change))
         .route("/users", get(get_users).post(create_user))
-        .route("/users/{id}", put(update_user).delete(delete_user))
+        .route("/users/search", get(search_users))
+        .route("/users/import", post(import_users))
+        .route("/users/export", get(export_users))
+        .route("/users/{id}", get(get_user).put(update_user).delete(delete_user))
         .route("/users/{id}/status", patch(update_user_status))
+        .route("/users/{id}/impersonate", post(impersonate_user))
+        .route("/users/{id}/restore", post(restore_user))
+        .route("/users/{id}/sessions", get(get_user_sessions))
+        .route("/users/{id}/sessions/{session_id}", delete(revoke_user_session))
+        .route("/audit-events", get(get_audit_events))
 }
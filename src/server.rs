@@ -0,0 +1,155 @@
+//! Starting the API server: database, background jobs, state and listener
+
+use axum::http::{HeaderValue, Method};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_http::cors::{AllowHeaders, AllowOrigin, Any, CorsLayer};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::{jobs, AppState};
+use crate::config::AppConfig;
+use crate::infrastructure::auth::jwt::JwtService;
+use crate::infrastructure::auth::oauth::OAuthProviderRegistry;
+use crate::infrastructure::database::postgres::Database;
+use crate::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+use crate::infrastructure::repositories::postgres_audit_repository::PostgresAuditRepository;
+use crate::infrastructure::repositories::postgres_email_change_repository::PostgresEmailChangeRepository;
+use crate::infrastructure::repositories::postgres_data_export_repository::PostgresDataExportRepository;
+use crate::infrastructure::repositories::postgres_phone_verification_repository::PostgresPhoneVerificationRepository;
+use crate::infrastructure::repositories::postgres_provider_token_repository::PostgresProviderTokenRepository;
+use crate::infrastructure::repositories::postgres_oauth_exchange_code_repository::PostgresOAuthExchangeCodeRepository;
+use crate::infrastructure::repositories::postgres_session_repository::PostgresSessionRepository;
+use crate::infrastructure::crypto::EnvelopeCipher;
+use crate::usecases::provider_tokens::ProviderTokenStore;
+use crate::infrastructure::mail::log_mailer::LogMailer;
+use crate::infrastructure::sms::log_sms_sender::LogSmsSender;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::storage::local_blob_store::LocalBlobStore;
+use crate::infrastructure::auth::session_cookies::SessionCookies;
+use crate::infrastructure::request_context::TrustedProxies;
+
+/// Logs to stdout, filtered by `RUST_LOG`
+pub fn init_tracing() {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "rust_axum=debug,tower_http=debug".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+}
+
+/// Migrates the database, starts the background jobs and serves the API until
+/// the process ends. `oauth_providers` are the providers users can sign in with.
+pub async fn run(config: AppConfig, oauth_providers: OAuthProviderRegistry) {
+    let db = Database::new(&config.database).await.expect("Failed to connect to database");
+
+    sqlx::migrate!("./migrations")
+        .run(&db.pool)
+        .await
+        .expect("Failed to run migrations");
+
+    let user_repository = Arc::new(PostgresUserRepository::new(db.pool.clone()));
+    let audit_repository = Arc::new(PostgresAuditRepository::new(db.pool.clone()));
+    let email_change_repository = Arc::new(PostgresEmailChangeRepository::new(db.pool.clone()));
+    let data_export_repository = Arc::new(PostgresDataExportRepository::new(db.pool.clone()));
+    let phone_verification_repository = Arc::new(PostgresPhoneVerificationRepository::new(db.pool.clone()));
+    let provider_token_repository = Arc::new(PostgresProviderTokenRepository::new(db.pool.clone()));
+    let oauth_exchange_code_repository = Arc::new(PostgresOAuthExchangeCodeRepository::new(db.pool.clone()));
+    let session_repository = Arc::new(PostgresSessionRepository::new(db.pool.clone()));
+    let blob_store = Arc::new(LocalBlobStore::new(config.storage.blob_store_dir.clone()));
+    let jwt_service = Arc::new(JwtService::new(&config.jwt));
+    let oauth_providers = Arc::new(oauth_providers);
+    let token_cipher = config.provider_tokens.is_enabled().then(|| {
+        Arc::new(EnvelopeCipher::new(&config.provider_tokens.keys()).expect("provider_tokens.encryption_keys are validated by AppConfig::load"))
+    });
+    let trusted_proxies = TrustedProxies::parse(&config.server.trusted_proxies)
+        .expect("server.trusted_proxies is validated by AppConfig::load");
+    let phone_policy = PhonePolicy::new(&config.phone.default_region, config.phone.unique)
+        .expect("phone.default_region is validated by AppConfig::load");
+
+    jobs::user_purge::spawn(
+        user_repository.clone(),
+        data_export_repository.clone(),
+        email_change_repository.clone(),
+        audit_repository.clone(),
+        blob_store.clone(),
+        config.users.retention_days,
+        config.users.purge_mode,
+    );
+    jobs::data_export::spawn(
+        user_repository.clone(),
+        data_export_repository.clone(),
+        email_change_repository.clone(),
+        session_repository.clone(),
+        audit_repository.clone(),
+        config.storage.export_dir.clone(),
+    );
+    if let Some(cipher) = &token_cipher {
+        jobs::provider_token_rotation::spawn(ProviderTokenStore::new(
+            provider_token_repository.clone(),
+            cipher.clone(),
+            oauth_providers.clone(),
+        ));
+    }
+
+    let session_cookies = config.session_cookies.enabled
+        .then(|| SessionCookies::new(
+            config.session_cookies.same_site,
+            jwt_service.refresh_token_ttl_secs(),
+            config.app.base_url.starts_with("https://"),
+        ));
+
+    let state = AppState {
+        user_repository,
+        audit_repository,
+        email_change_repository,
+        data_export_repository,
+        phone_verification_repository,
+        provider_token_repository,
+        oauth_exchange_code_repository,
+        session_repository,
+        jwt_service,
+        oauth_providers,
+        oauth_redirect_origins: Arc::new(config.oauth.redirect_origins.clone()),
+        session_cookies,
+        token_cipher,
+        mailer: Arc::new(LogMailer),
+        sms_sender: Arc::new(LogSmsSender::new(config.phone.sms_log_file.clone())),
+        phone_policy: Arc::new(phone_policy),
+        blob_store,
+        mirror_oauth_avatars: config.storage.mirror_oauth_avatars,
+        app_base_url: config.app.base_url.clone(),
+        trusted_proxies: Arc::new(trusted_proxies),
+    };
+
+    // Browsers only send cookies cross-origin with credentials allowed, which in
+    // turn rules out wildcards; with any origin allowed, cookie sessions work
+    // same-origin only
+    let cors = if config.cors.allows_any() {
+        CorsLayer::new().allow_origin(Any).allow_headers(Any)
+    } else {
+        let origins = config.cors.allowed_origins
+            .iter()
+            .map(|origin| origin.parse::<HeaderValue>().expect("cors.allowed_origins is validated by AppConfig::load"));
+        let cors = CorsLayer::new().allow_origin(AllowOrigin::list(origins));
+        if config.session_cookies.enabled {
+            cors.allow_credentials(true).allow_headers(AllowHeaders::mirror_request())
+        } else {
+            cors.allow_headers(Any)
+        }
+    };
+
+    let app = crate::app(
+        state,
+        cors.allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE]),
+    );
+
+    let address = config.server.bind_address();
+    tracing::info!("Server listening on {}", address);
+
+    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use crate::domain::entities::audit_event::AuditEvent;
+use crate::domain::entities::user::Role;
+use crate::domain::repositories::audit_repository::{AuditEventFilter, AuditRepository, AUDIT_SORT_FIELD};
+use crate::domain::repositories::pagination::{Cursor, KeysetPage, PageParams, SortDirection};
+use crate::infrastructure::errors::AppError;
+
+/// List Audit Events Use Case - SuperAdmin only
+pub struct ListAuditEventsUseCase<A: AuditRepository> {
+    audit_repository: Arc<A>,
+}
+
+impl<A: AuditRepository> ListAuditEventsUseCase<A> {
+    pub fn new(audit_repository: Arc<A>) -> Self {
+        Self { audit_repository }
+    }
+
+    /// Returns one page of events (newest first) plus the total number of matches
+    pub async fn execute(
+        &self,
+        requester_role: Role,
+        filter: AuditEventFilter,
+        page: PageParams,
+    ) -> Result<(Vec<AuditEvent>, i64), AppError> {
+        // Check permissions: Only SuperAdmin can read the audit log
+        if requester_role != Role::SuperAdmin {
+            return Err(AppError::Forbidden);
+        }
+
+        let events = self.audit_repository.find_page(&filter, page).await?;
+        let total = self.audit_repository.count(&filter).await?;
+
+        Ok((events, total))
+    }
+
+    /// Returns one keyset page of events (newest first)
+    pub async fn execute_keyset(
+        &self,
+        requester_role: Role,
+        filter: AuditEventFilter,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<KeysetPage<AuditEvent>, AppError> {
+        if requester_role != Role::SuperAdmin {
+            return Err(AppError::Forbidden);
+        }
+
+        let cursor = Cursor::decode_for(cursor, AUDIT_SORT_FIELD, SortDirection::Desc)?;
+        self.audit_repository.find_keyset(&filter, cursor.as_ref(), limit).await
+    }
+}
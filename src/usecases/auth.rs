@@ -2,103 +2,128 @@ use crate::domain::dtos::{RegisterUserDto, AuthResponseDto, UserResponseDto};
 use crate::infrastructure::errors::AppError;
 use std::sync::Arc;
 use uuid::Uuid;
-use crate::domain::entities::user::{User, Role};
+use crate::domain::entities::user::{canonical_email, AuthProvider, User, Role};
 use crate::domain::repositories::user_repository::UserRepository;
 use crate::infrastructure::auth::jwt::JwtService;
 use crate::infrastructure::auth::password::{hash_password, verify_password};
-use crate::infrastructure::auth::github::GitHubOAuthClient;
-use crate::infrastructure::auth::google::GoogleOAuthClient;
-use crate::domain::dtos::GitHubUserInfo;
-
-const ACCESS_TOKEN_EXPIRY_SECONDS: usize = 900; // 15 minutes
+use crate::infrastructure::auth::oauth::OAuthProvider;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::provider_token_repository::ProviderTokenRepository;
+use crate::domain::repositories::oauth_exchange_code_repository::OAuthExchangeCodeRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::entities::oauth_exchange_code::OAuthExchangeCode;
+use crate::infrastructure::auth::token::{generate_token, hash_token};
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::storage::BlobStore;
+use crate::usecases::avatar::MirrorAvatarUseCase;
+use crate::usecases::phone::prepare_phone;
+use crate::usecases::provider_tokens::ProviderTokenStore;
+use crate::usecases::sessions::SessionIssuer;
+use serde_json::json;
+use chrono::{Duration, Utc};
 
 // Register Use Case
 pub struct RegisterUseCase<R: UserRepository> {
     user_repository: Arc<R>,
+    phone_policy: Arc<PhonePolicy>,
 }
 
 impl<R: UserRepository> RegisterUseCase<R> {
-    pub fn new(user_repository: Arc<R>) -> Self {
-        Self { user_repository }
+    pub fn new(user_repository: Arc<R>, phone_policy: Arc<PhonePolicy>) -> Self {
+        Self { user_repository, phone_policy }
     }
 
     pub async fn execute(&self, dto: RegisterUserDto) -> Result<UserResponseDto, AppError> {
+        let phone = match dto.phone.as_deref() {
+            Some(raw) => prepare_phone(self.user_repository.as_ref(), &self.phone_policy, raw, None).await?,
+            None => None,
+        };
         let password_hash = hash_password(&dto.password)?;
 
         let user = User {
-            id: Uuid::new_v4(),
-            name: dto.name,
-            phone: dto.phone,
-            email: dto.email,
+            phone,
             password_hash: Some(password_hash),
-            role: Role::User,
-            status: crate::domain::entities::user::UserStatus::default(),
-            github_id: None,
-            google_id: None,
-            avatar_url: None,
-            created_at: None,
-            updated_at: None,
+            ..User::new(dto.name, &dto.email, Role::User)
         };
 
         let created_user = self.user_repository.create(&user).await?;
 
-        Ok(UserResponseDto {
-            name: created_user.name,
-            phone: created_user.phone,
-            email: created_user.email,
-            role: created_user.role,
-            avatar_url: created_user.avatar_url,
-        })
+        Ok(UserResponseDto::from(created_user))
     }
 }
 
 // Login Use Case
-pub struct LoginUseCase<R: UserRepository> {
+pub struct LoginUseCase<R: UserRepository, A: AuditRepository, S: SessionRepository> {
     user_repository: Arc<R>,
-    jwt_service: Arc<JwtService>,
+    audit_repository: Arc<A>,
+    sessions: SessionIssuer<S>,
 }
 
-impl<R: UserRepository> LoginUseCase<R> {
-    pub fn new(user_repository: Arc<R>, jwt_service: Arc<JwtService>) -> Self {
-        Self { user_repository, jwt_service }
+impl<R: UserRepository, A: AuditRepository, S: SessionRepository> LoginUseCase<R, A, S> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, session_repository: Arc<S>, jwt_service: Arc<JwtService>) -> Self {
+        Self { user_repository, audit_repository, sessions: SessionIssuer::new(session_repository, jwt_service) }
     }
 
-    pub async fn execute(&self, email: &str, password: &str) -> Result<AuthResponseDto, AppError> {
-        let user = self.user_repository.find_by_email(email)
-            .await?
-            .ok_or(AppError::InvalidCredentials)?;
+    pub async fn execute(&self, email: &str, password: &str, ctx: &RequestContext) -> Result<AuthResponseDto, AppError> {
+        let user = match self.user_repository.find_by_email(email).await? {
+            Some(user) => user,
+            None => return Err(self.record_failure(None, email, "unknown_email", ctx).await?),
+        };
 
         // OAuth-only users cannot sign in with password
-        let password_hash = user.password_hash.as_ref()
-            .ok_or(AppError::InvalidCredentials)?;
+        let Some(password_hash) = user.password_hash.as_ref() else {
+            return Err(self.record_failure(Some(user.id), email, "no_password", ctx).await?);
+        };
 
         if !verify_password(password_hash, password)? {
-            return Err(AppError::InvalidCredentials);
+            return Err(self.record_failure(Some(user.id), email, "wrong_password", ctx).await?);
         }
 
-        let (access_token, refresh_token) = self.jwt_service.generate_tokens(&user)?;
+        let tokens = self.sessions.start(&user, AuthProvider::Password.as_str(), ctx).await?;
 
-        Ok(AuthResponseDto {
-            access_token,
-            refresh_token,
-            token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
-        })
+        self.user_repository.record_login(user.id).await?;
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user.id), Some(user.id), AuditAction::LoginSucceeded, Some(json!({ "method": "password" })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(tokens)
+    }
+}
+
+impl<R: UserRepository, A: AuditRepository, S: SessionRepository> LoginUseCase<R, A, S> {
+    /// Records a failed sign-in and returns the error to surface to the client
+    async fn record_failure(&self, user_id: Option<Uuid>, email: &str, reason: &str, ctx: &RequestContext) -> Result<AppError, AppError> {
+        self.audit_repository.create(
+            &AuditEvent::new(None, user_id, AuditAction::LoginFailed, Some(json!({ "email": email, "reason": reason })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(AppError::InvalidCredentials)
     }
 }
 
 // Refresh Token Use Case
-pub struct RefreshTokenUseCase<R: UserRepository> {
+pub struct RefreshTokenUseCase<R: UserRepository, A: AuditRepository, S: SessionRepository> {
     user_repository: Arc<R>,
+    audit_repository: Arc<A>,
     jwt_service: Arc<JwtService>,
+    sessions: SessionIssuer<S>,
 }
 
-impl<R: UserRepository> RefreshTokenUseCase<R> {
-    pub fn new(user_repository: Arc<R>, jwt_service: Arc<JwtService>) -> Self {
-        Self { user_repository, jwt_service }
+impl<R: UserRepository, A: AuditRepository, S: SessionRepository> RefreshTokenUseCase<R, A, S> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, session_repository: Arc<S>, jwt_service: Arc<JwtService>) -> Self {
+        Self {
+            user_repository,
+            audit_repository,
+            sessions: SessionIssuer::new(session_repository, jwt_service.clone()),
+            jwt_service,
+        }
     }
 
-    pub async fn execute(&self, refresh_token: &str) -> Result<AuthResponseDto, AppError> {
+    pub async fn execute(&self, refresh_token: &str, ctx: &RequestContext) -> Result<AuthResponseDto, AppError> {
         let claims = self.jwt_service.verify_token(refresh_token)?;
         
         if claims.claims.token_type != "refresh" {
@@ -109,158 +134,152 @@ impl<R: UserRepository> RefreshTokenUseCase<R> {
             .await?
             .ok_or(AppError::UserNotFound)?;
 
-        let (access_token, refresh_token) = self.jwt_service.generate_tokens(&user)?;
+        // Tokens from before sessions were recorded cannot be revoked, so they
+        // need a fresh sign-in
+        let session_id = claims.claims.sid.ok_or(AppError::InvalidToken)?;
+        let tokens = self.sessions.resume(&user, session_id, ctx).await?;
 
-        Ok(AuthResponseDto {
-            access_token,
-            refresh_token,
-            token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
-        })
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user.id), Some(user.id), AuditAction::TokenRefreshed, None)
+                .with_context(ctx),
+        ).await?;
+
+        Ok(tokens)
     }
 }
 
-// GitHub OAuth Callback Use Case
-pub struct GitHubCallbackUseCase<R: UserRepository> {
+/// OAuth Callback Use Case - signs in through any registered provider: an account
+/// already linked to the provider identity, else the account with the same verified
+/// email (which gets linked), else a new account
+pub struct OAuthCallbackUseCase<R: UserRepository, A: AuditRepository, B: BlobStore, P: ProviderTokenRepository> {
     user_repository: Arc<R>,
-    jwt_service: Arc<JwtService>,
-    github_client: Arc<GitHubOAuthClient>,
+    audit_repository: Arc<A>,
+    provider: Arc<dyn OAuthProvider>,
+    avatar_mirror: Option<MirrorAvatarUseCase<R, B>>,
+    token_store: Option<ProviderTokenStore<P>>,
 }
 
-impl<R: UserRepository> GitHubCallbackUseCase<R> {
+impl<R: UserRepository, A: AuditRepository, B: BlobStore, P: ProviderTokenRepository> OAuthCallbackUseCase<R, A, B, P> {
     pub fn new(
         user_repository: Arc<R>,
-        jwt_service: Arc<JwtService>,
-        github_client: Arc<GitHubOAuthClient>,
+        audit_repository: Arc<A>,
+        provider: Arc<dyn OAuthProvider>,
+        avatar_mirror: Option<MirrorAvatarUseCase<R, B>>,
+        token_store: Option<ProviderTokenStore<P>>,
     ) -> Self {
         Self {
             user_repository,
-            jwt_service,
-            github_client,
+            audit_repository,
+            provider,
+            avatar_mirror,
+            token_store,
         }
     }
 
-    pub async fn execute(&self, code: &str) -> Result<AuthResponseDto, AppError> {
-        // 1. Exchange code for access token
-        let access_token = self.github_client.exchange_code(code).await?;
+    /// Signs the user in and returns them; the caller starts the session, now or
+    /// when the frontend redeems a one-time code.
+    /// `nonce` is the one the sign-in was started with, read back from the browser.
+    /// `name_hint` is a name the provider sent outside the identity (Apple), used
+    /// when the identity has none.
+    pub async fn execute(&self, code: &str, nonce: Option<&str>, name_hint: Option<String>, ctx: &RequestContext) -> Result<User, AppError> {
+        let provider = self.provider.provider();
 
-        // 2. Fetch GitHub user info
-        let github_user: GitHubUserInfo = self.github_client.get_user_info(&access_token).await?;
+        // 1. Exchange code for tokens
+        let tokens = self.provider.exchange_code(code).await?;
 
-        let email = github_user.email
-            .ok_or_else(|| AppError::OAuthError("GitHub account has no email".to_string()))?;
+        // 2. Fetch the provider's view of the account
+        let identity = self.provider.fetch_identity(&tokens, nonce).await?;
 
-        let name = github_user.name.unwrap_or(github_user.login);
+        let email = identity.email
+            .as_deref()
+            .map(canonical_email)
+            .ok_or_else(|| AppError::OAuthError(format!("{} account has no email", self.provider.display_name())))?;
 
-        // 3. Check if user already exists by github_id
-        let user = if let Some(existing_user) = self.user_repository.find_by_github_id(github_user.id).await? {
+        // 3. Find the user by provider identity, link by email, or create
+        let user = if let Some(existing_user) = self.user_repository.find_by_identity(provider, &identity.provider_user_id).await? {
             existing_user
-        } else {
-            // Check if a user with this email already exists (link accounts)
-            if let Some(mut existing_user) = self.user_repository.find_by_email(&email).await? {
-                existing_user.github_id = Some(github_user.id);
-                existing_user.avatar_url = github_user.avatar_url;
-                self.user_repository.update(existing_user.id, &existing_user).await?;
-                self.user_repository.find_by_github_id(github_user.id).await?
-                    .ok_or(AppError::InternalServerError)?
-            } else {
-                let new_user = User {
-                    id: Uuid::new_v4(),
-                    name,
-                    phone: None,
-                    email,
-                    password_hash: None,
-                    role: Role::User,
-                    status: crate::domain::entities::user::UserStatus::default(),
-                    github_id: Some(github_user.id),
-                    google_id: None,
-                    avatar_url: github_user.avatar_url,
-                    created_at: None,
-                    updated_at: None,
-                };
-                self.user_repository.upsert_github_user(&new_user).await?
+        } else if let Some(existing_user) = self.user_repository.find_by_email(&email).await? {
+            // Linking on an unverified email would let anyone claim the account
+            if !identity.email_verified {
+                return Err(AppError::OAuthError(format!("{} email is not verified", self.provider.display_name())));
             }
+            self.user_repository
+                .link_identity(existing_user.id, provider, &identity.provider_user_id, identity.avatar_url.as_deref())
+                .await?
+        } else {
+            let new_user = User {
+                avatar_url: identity.avatar_url.clone(),
+                ..User::new(identity.name.clone().or(name_hint).unwrap_or_else(|| email.clone()), &email, Role::User)
+            };
+            self.user_repository.upsert_identity_user(provider, &identity.provider_user_id, &new_user).await?
         };
 
-        let (jwt_access_token, jwt_refresh_token) = self.jwt_service.generate_tokens(&user)?;
+        // A failed mirror keeps the provider URL; it is retried on the next sign-in
+        if let Some(avatar_mirror) = &self.avatar_mirror {
+            if let Err(e) = avatar_mirror.execute(&user, identity.avatar_url.as_deref()).await {
+                tracing::warn!("Avatar mirroring failed for user {}: {:?}", user.id, e);
+            }
+        }
 
-        Ok(AuthResponseDto {
-            access_token: jwt_access_token,
-            refresh_token: jwt_refresh_token,
-            token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
-        })
+        // Sign-in does not depend on it; features needing the tokens ask the user to sign in again
+        if let Some(token_store) = &self.token_store {
+            if let Err(e) = token_store.store(user.id, provider, &tokens).await {
+                tracing::warn!("Storing {} tokens failed for user {}: {:?}", provider.as_str(), user.id, e);
+            }
+        }
+
+        self.user_repository.record_login(user.id).await?;
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user.id), Some(user.id), AuditAction::LoginSucceeded, Some(json!({ "method": provider.as_str() })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(user)
     }
 }
 
-// Google OAuth Callback Use Case
-pub struct GoogleCallbackUseCase<R: UserRepository> {
+/// How long the frontend has to redeem the code it was redirected with
+const OAUTH_EXCHANGE_CODE_TTL_SECS: i64 = 60;
+
+/// OAuth Exchange Use Case - the one-time code an OAuth callback redirects the
+/// browser back to the frontend with, so tokens never appear in a URL
+pub struct OAuthExchangeUseCase<R: UserRepository, C: OAuthExchangeCodeRepository, S: SessionRepository> {
     user_repository: Arc<R>,
-    jwt_service: Arc<JwtService>,
-    google_client: Arc<GoogleOAuthClient>,
+    exchange_code_repository: Arc<C>,
+    sessions: SessionIssuer<S>,
 }
 
-impl<R: UserRepository> GoogleCallbackUseCase<R> {
-    pub fn new(
-        user_repository: Arc<R>,
-        jwt_service: Arc<JwtService>,
-        google_client: Arc<GoogleOAuthClient>,
-    ) -> Self {
-        Self {
-            user_repository,
-            jwt_service,
-            google_client,
-        }
+impl<R: UserRepository, C: OAuthExchangeCodeRepository, S: SessionRepository> OAuthExchangeUseCase<R, C, S> {
+    pub fn new(user_repository: Arc<R>, exchange_code_repository: Arc<C>, session_repository: Arc<S>, jwt_service: Arc<JwtService>) -> Self {
+        Self { user_repository, exchange_code_repository, sessions: SessionIssuer::new(session_repository, jwt_service) }
     }
 
-    pub async fn execute(&self, code: &str) -> Result<AuthResponseDto, AppError> {
-        // 1. Exchange code for tokens
-        let token_response = self.google_client.exchange_code(code).await?;
-
-        // 2. Fetch Google user info
-        let google_user = self.google_client.get_user_info(&token_response.access_token).await?;
+    /// Issues a code for a user who just signed in with `provider`
+    pub async fn issue(&self, user: &User, provider: AuthProvider) -> Result<String, AppError> {
+        self.exchange_code_repository.delete_expired().await?;
 
-        let email = google_user.email
-            .ok_or_else(|| AppError::OAuthError("Google account has no email".to_string()))?;
+        let code = generate_token();
+        self.exchange_code_repository.create(&OAuthExchangeCode {
+            code_hash: hash_token(&code),
+            user_id: user.id,
+            provider: provider.as_str().to_string(),
+            expires_at: Utc::now() + Duration::seconds(OAUTH_EXCHANGE_CODE_TTL_SECS),
+        }).await?;
 
-        let name = google_user.name.unwrap_or_else(|| email.clone());
+        Ok(code)
+    }
 
-        // 3. Check if user already exists by google_id
-        let user = if let Some(existing_user) = self.user_repository.find_by_google_id(&google_user.id).await? {
-            existing_user
-        } else {
-            if let Some(mut existing_user) = self.user_repository.find_by_email(&email).await? {
-                existing_user.google_id = Some(google_user.id.clone());
-                existing_user.avatar_url = google_user.picture.clone();
-                self.user_repository.update(existing_user.id, &existing_user).await?;
-                self.user_repository.find_by_google_id(&google_user.id).await?
-                    .ok_or(AppError::InternalServerError)?
-            } else {
-                let new_user = User {
-                    id: Uuid::new_v4(),
-                    name,
-                    phone: None,
-                    email,
-                    password_hash: None,
-                    role: Role::User,
-                    status: crate::domain::entities::user::UserStatus::default(),
-                    github_id: None,
-                    google_id: Some(google_user.id),
-                    avatar_url: google_user.picture,
-                    created_at: None,
-                    updated_at: None,
-                };
-                self.user_repository.upsert_google_user(&new_user).await?
-            }
-        };
+    /// Swaps a code for tokens of a new session; each code works once
+    pub async fn redeem(&self, code: &str, ctx: &RequestContext) -> Result<AuthResponseDto, AppError> {
+        let exchange_code = self.exchange_code_repository
+            .consume(&hash_token(code))
+            .await?
+            .ok_or(AppError::OAuthExchangeCodeInvalid)?;
 
-        let (jwt_access_token, jwt_refresh_token) = self.jwt_service.generate_tokens(&user)?;
+        let user = self.user_repository.find_by_id(exchange_code.user_id)
+            .await?
+            .ok_or(AppError::OAuthExchangeCodeInvalid)?;
 
-        Ok(AuthResponseDto {
-            access_token: jwt_access_token,
-            refresh_token: jwt_refresh_token,
-            token_type: "Bearer".to_string(),
-            expires_in: ACCESS_TOKEN_EXPIRY_SECONDS,
-        })
+        self.sessions.start(&user, &exchange_code.provider, ctx).await
     }
 }
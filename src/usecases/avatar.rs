@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use crate::domain::dtos::{AvatarResponseDto, AvatarVariantDto};
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::user::User;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::images::{process_avatar, AVATAR_DEFAULT_SIZE, AVATAR_MAX_BYTES, AVATAR_SIZES};
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::storage::BlobStore;
+
+const MIRROR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Blob store directory holding every avatar version of a user
+pub fn avatar_dir(user_id: Uuid) -> String {
+    format!("avatars/{}", user_id)
+}
+
+/// Public URL of one variant, served by `GET /api/v1/avatars/...`
+fn variant_url(app_base_url: &str, avatar_key: &str, size: u32) -> String {
+    format!("{}/api/v1/{}/{}.jpg", app_base_url.trim_end_matches('/'), avatar_key, size)
+}
+
+fn to_dto(app_base_url: &str, avatar_key: &str) -> AvatarResponseDto {
+    AvatarResponseDto {
+        avatar_url: variant_url(app_base_url, avatar_key, AVATAR_DEFAULT_SIZE),
+        variants: AVATAR_SIZES
+            .iter()
+            .map(|&size| AvatarVariantDto { size, url: variant_url(app_base_url, avatar_key, size) })
+            .collect(),
+    }
+}
+
+/// Resizes `bytes` and writes every variant under a fresh version, returning its key.
+/// Versioned keys make the URLs immutable, so they can be cached forever.
+async fn store_avatar<B: BlobStore>(blob_store: &B, user_id: Uuid, bytes: Vec<u8>) -> Result<String, AppError> {
+    let variants = tokio::task::spawn_blocking(move || process_avatar(&bytes))
+        .await
+        .map_err(|_| AppError::InternalServerError)??;
+
+    let avatar_key = format!("{}/{}", avatar_dir(user_id), Uuid::new_v4().simple());
+    for variant in variants {
+        blob_store.put(&format!("{}/{}.jpg", avatar_key, variant.size), variant.bytes).await?;
+    }
+
+    Ok(avatar_key)
+}
+
+/// Points the user at `avatar_key` (or clears the avatar) and drops the previous version
+async fn replace_avatar<R: UserRepository, B: BlobStore>(
+    user_repository: &R,
+    blob_store: &B,
+    user: &User,
+    avatar: Option<(&str, &str)>,
+) -> Result<User, AppError> {
+    let (avatar_url, avatar_key) = avatar.unzip();
+    let updated_user = match user_repository.update_avatar(user.id, avatar_url, avatar_key).await {
+        Ok(updated_user) => updated_user,
+        Err(e) => {
+            if let Some(avatar_key) = avatar_key {
+                let _ = blob_store.delete_prefix(avatar_key).await;
+            }
+            return Err(e);
+        }
+    };
+
+    if let Some(old_key) = &user.avatar_key {
+        if let Err(e) = blob_store.delete_prefix(old_key).await {
+            tracing::error!("Failed to remove old avatar {}: {:?}", old_key, e);
+        }
+    }
+
+    Ok(updated_user)
+}
+
+/// Upload Avatar Use Case - own account only
+pub struct UploadAvatarUseCase<R: UserRepository, A: AuditRepository, B: BlobStore> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    blob_store: Arc<B>,
+    app_base_url: String,
+}
+
+impl<R: UserRepository, A: AuditRepository, B: BlobStore> UploadAvatarUseCase<R, A, B> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, blob_store: Arc<B>, app_base_url: String) -> Self {
+        Self { user_repository, audit_repository, blob_store, app_base_url }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, bytes: Vec<u8>, ctx: &RequestContext) -> Result<AvatarResponseDto, AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let avatar_key = store_avatar(self.blob_store.as_ref(), user_id, bytes).await?;
+        let avatar = to_dto(&self.app_base_url, &avatar_key);
+
+        let updated_user = replace_avatar(
+            self.user_repository.as_ref(),
+            self.blob_store.as_ref(),
+            &user,
+            Some((&avatar.avatar_url, &avatar_key)),
+        ).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::ProfileUpdated, None)
+                .with_changes(Some(&user), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(avatar)
+    }
+}
+
+/// Remove Avatar Use Case - own account only
+pub struct RemoveAvatarUseCase<R: UserRepository, A: AuditRepository, B: BlobStore> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    blob_store: Arc<B>,
+}
+
+impl<R: UserRepository, A: AuditRepository, B: BlobStore> RemoveAvatarUseCase<R, A, B> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, blob_store: Arc<B>) -> Self {
+        Self { user_repository, audit_repository, blob_store }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, ctx: &RequestContext) -> Result<(), AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let updated_user = replace_avatar(self.user_repository.as_ref(), self.blob_store.as_ref(), &user, None).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::ProfileUpdated, None)
+                .with_changes(Some(&user), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
+
+/// Mirror Avatar Use Case - copies an OAuth provider's picture into our store on sign-in,
+/// so we stop hotlinking it. Runs once per user: afterwards `avatar_key` is set.
+/// Only a URL the provider just returned is fetched, never the stored `avatar_url`.
+pub struct MirrorAvatarUseCase<R: UserRepository, B: BlobStore> {
+    user_repository: Arc<R>,
+    blob_store: Arc<B>,
+    app_base_url: String,
+    client: reqwest::Client,
+}
+
+impl<R: UserRepository, B: BlobStore> MirrorAvatarUseCase<R, B> {
+    pub fn new(user_repository: Arc<R>, blob_store: Arc<B>, app_base_url: String) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(MIRROR_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { user_repository, blob_store, app_base_url, client }
+    }
+
+    /// `provider_avatar_url` is the picture in the identity the provider returned for
+    /// this sign-in. It is mirrored only while it is still the user's avatar.
+    pub async fn execute(&self, user: &User, provider_avatar_url: Option<&str>) -> Result<(), AppError> {
+        let Some(source_url) = provider_avatar_url else {
+            return Ok(());
+        };
+        if user.avatar_key.is_some() || user.avatar_url.as_deref() != Some(source_url) {
+            return Ok(());
+        }
+
+        let bytes = self.download(source_url).await?;
+        let avatar_key = store_avatar(self.blob_store.as_ref(), user.id, bytes).await?;
+        let avatar_url = variant_url(&self.app_base_url, &avatar_key, AVATAR_DEFAULT_SIZE);
+
+        replace_avatar(self.user_repository.as_ref(), self.blob_store.as_ref(), user, Some((&avatar_url, &avatar_key))).await?;
+
+        Ok(())
+    }
+
+    /// Fetches at most `AVATAR_MAX_BYTES`, whatever the server claims in its headers
+    async fn download(&self, url: &str) -> Result<Vec<u8>, AppError> {
+        let fetch_error = |e: reqwest::Error| AppError::OAuthError(format!("Failed to fetch avatar: {}", e));
+
+        let mut response = self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(fetch_error)?;
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(fetch_error)? {
+            if bytes.len() + chunk.len() > AVATAR_MAX_BYTES {
+                return Err(AppError::OAuthError("Provider avatar is too large".to_string()));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::dtos::AdminUserDto;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::data_export::{DataExport, DataExportStatus};
+use crate::domain::entities::user::Role;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::data_export_repository::DataExportRepository;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::infrastructure::auth::password::verify_password;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::storage::BlobStore;
+use crate::usecases::avatar::avatar_dir;
+
+/// How long a generated archive stays downloadable
+pub const DATA_EXPORT_TTL_DAYS: i64 = 7;
+
+/// Request Data Export Use Case - queues an archive for the background job
+pub struct RequestDataExportUseCase<E: DataExportRepository, A: AuditRepository> {
+    data_export_repository: Arc<E>,
+    audit_repository: Arc<A>,
+}
+
+impl<E: DataExportRepository, A: AuditRepository> RequestDataExportUseCase<E, A> {
+    pub fn new(data_export_repository: Arc<E>, audit_repository: Arc<A>) -> Self {
+        Self { data_export_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, ctx: &RequestContext) -> Result<DataExport, AppError> {
+        let export = self.data_export_repository.create(user_id).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::DataExportRequested, Some(json!({ "export_id": export.id })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(export)
+    }
+}
+
+/// Get Data Export Use Case - own exports only
+pub struct GetDataExportUseCase<E: DataExportRepository> {
+    data_export_repository: Arc<E>,
+}
+
+impl<E: DataExportRepository> GetDataExportUseCase<E> {
+    pub fn new(data_export_repository: Arc<E>) -> Self {
+        Self { data_export_repository }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, export_id: Uuid) -> Result<DataExport, AppError> {
+        self.data_export_repository
+            .find_for_user(export_id, user_id)
+            .await?
+            .ok_or(AppError::DataExportNotFound)
+    }
+
+    pub async fn execute_all(&self, user_id: Uuid) -> Result<Vec<DataExport>, AppError> {
+        self.data_export_repository.find_all_for_user(user_id).await
+    }
+
+    /// Returns the archive contents of a ready export
+    pub async fn download(&self, user_id: Uuid, export_id: Uuid) -> Result<Vec<u8>, AppError> {
+        let export = self.execute(user_id, export_id).await?;
+
+        let file_path = match (&export.status, &export.file_path) {
+            (DataExportStatus::Ready, Some(file_path)) => file_path,
+            (DataExportStatus::Expired, _) => return Err(AppError::DataExportNotFound),
+            _ => return Err(AppError::DataExportNotReady),
+        };
+
+        tokio::fs::read(file_path).await.map_err(|e| {
+            tracing::error!("Failed to read data export {}: {:?}", export.id, e);
+            AppError::InternalServerError
+        })
+    }
+}
+
+/// Generate Data Export Use Case - run by the background job, never from a request
+pub struct GenerateDataExportUseCase<R, E, C, S, A>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    S: SessionRepository,
+    A: AuditRepository,
+{
+    user_repository: Arc<R>,
+    data_export_repository: Arc<E>,
+    email_change_repository: Arc<C>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+    export_dir: PathBuf,
+}
+
+impl<R, E, C, S, A> GenerateDataExportUseCase<R, E, C, S, A>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    S: SessionRepository,
+    A: AuditRepository,
+{
+    pub fn new(
+        user_repository: Arc<R>,
+        data_export_repository: Arc<E>,
+        email_change_repository: Arc<C>,
+        session_repository: Arc<S>,
+        audit_repository: Arc<A>,
+        export_dir: PathBuf,
+    ) -> Self {
+        Self { user_repository, data_export_repository, email_change_repository, session_repository, audit_repository, export_dir }
+    }
+
+    /// Claims and builds the oldest pending export; returns `false` when the queue is empty
+    pub async fn execute_next(&self) -> Result<bool, AppError> {
+        let Some(export) = self.data_export_repository.claim_next_pending().await? else {
+            return Ok(false);
+        };
+
+        match self.write_archive(&export).await {
+            Ok(file_path) => {
+                let expires_at = Utc::now() + Duration::days(DATA_EXPORT_TTL_DAYS);
+                self.data_export_repository.mark_ready(export.id, &file_path, expires_at).await?;
+            }
+            Err(e) => {
+                tracing::error!("Data export {} failed: {:?}", export.id, e);
+                self.data_export_repository.mark_failed(export.id, &e.to_string()).await?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Deletes archives past their download window
+    pub async fn expire(&self) -> Result<usize, AppError> {
+        let expired = self.data_export_repository.find_expired().await?;
+        for export in &expired {
+            if let Some(file_path) = &export.file_path {
+                remove_archive(file_path).await;
+            }
+            self.data_export_repository.mark_expired(export.id).await?;
+        }
+
+        Ok(expired.len())
+    }
+
+    async fn write_archive(&self, export: &DataExport) -> Result<String, AppError> {
+        let user = self.user_repository
+            .find_by_id(export.user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+        let email_changes = self.email_change_repository.find_by_user(user.id).await?;
+        let sessions = self.session_repository.find_by_user(user.id).await?;
+        let audit_events = self.audit_repository.find_by_subject(user.id).await?;
+
+        let archive = json!({
+            "generated_at": Utc::now(),
+            "profile": AdminUserDto::from(user),
+            "email_changes": email_changes,
+            "sessions": sessions,
+            "audit_events": audit_events,
+        });
+        let body = serde_json::to_vec_pretty(&archive).map_err(|_| AppError::InternalServerError)?;
+
+        let file_path = self.export_dir.join(format!("{}.json", export.id));
+        let io_error = |e: std::io::Error| {
+            tracing::error!("Failed to write data export {}: {:?}", export.id, e);
+            AppError::InternalServerError
+        };
+        tokio::fs::create_dir_all(&self.export_dir).await.map_err(io_error)?;
+        tokio::fs::write(&file_path, body).await.map_err(io_error)?;
+
+        Ok(file_path.to_string_lossy().into_owned())
+    }
+}
+
+/// Erase Account Use Case - anonymizes the user row in place so foreign keys
+/// and the audit trail stay intact, then drops exports, avatars and email history
+pub struct EraseAccountUseCase<R, E, C, A, B>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    A: AuditRepository,
+    B: BlobStore,
+{
+    user_repository: Arc<R>,
+    data_export_repository: Arc<E>,
+    email_change_repository: Arc<C>,
+    audit_repository: Arc<A>,
+    blob_store: Arc<B>,
+}
+
+impl<R, E, C, A, B> EraseAccountUseCase<R, E, C, A, B>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    A: AuditRepository,
+    B: BlobStore,
+{
+    pub fn new(
+        user_repository: Arc<R>,
+        data_export_repository: Arc<E>,
+        email_change_repository: Arc<C>,
+        audit_repository: Arc<A>,
+        blob_store: Arc<B>,
+    ) -> Self {
+        Self { user_repository, data_export_repository, email_change_repository, audit_repository, blob_store }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, password: Option<String>, ctx: &RequestContext) -> Result<(), AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        if user.role == Role::SuperAdmin {
+            return Err(AppError::CannotDeleteSelf);
+        }
+
+        if let Some(password_hash) = user.password_hash.as_ref() {
+            let password = password
+                .ok_or_else(|| AppError::ValidationError("Password is required".to_string()))?;
+            if !verify_password(password_hash, &password)? {
+                return Err(AppError::InvalidCredentials);
+            }
+        }
+
+        self.user_repository.anonymize(user_id).await?;
+        self.email_change_repository.delete_all_for_user(user_id).await?;
+        self.blob_store.delete_prefix(&avatar_dir(user_id)).await?;
+        for export in self.data_export_repository.delete_for_user(user_id).await? {
+            if let Some(file_path) = &export.file_path {
+                remove_archive(file_path).await;
+            }
+        }
+
+        // No before/after diff here: it would copy the erased data into the audit log
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::AccountErased, None)
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
+
+/// Deletes an export archive; one that is already gone is not an error
+pub(crate) async fn remove_archive(file_path: &str) {
+    if let Err(e) = tokio::fs::remove_file(Path::new(file_path)).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::error!("Failed to remove data export {}: {:?}", file_path, e);
+        }
+    }
+}
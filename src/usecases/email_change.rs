@@ -0,0 +1,237 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::email_change::EmailChange;
+use crate::domain::entities::user::canonical_email;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::domain::dtos::{RequestEmailChangeDto, UserResponseDto};
+use crate::infrastructure::auth::password::verify_password;
+use crate::infrastructure::auth::token::{generate_token, hash_token};
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::mail::{EmailMessage, Mailer};
+use crate::infrastructure::request_context::RequestContext;
+
+const CONFIRM_WINDOW_HOURS: i64 = 24;
+const REVERT_WINDOW_DAYS: i64 = 7;
+/// How recently an OAuth-only user must have signed in to change their email
+const REAUTH_WINDOW_MINUTES: i64 = 10;
+
+/// Request Email Change Use Case - sends a confirmation link to the new address
+/// and a revert link to the current one; `users.email` is left untouched
+pub struct RequestEmailChangeUseCase<R: UserRepository, E: EmailChangeRepository, S: SessionRepository, A: AuditRepository, M: Mailer> {
+    user_repository: Arc<R>,
+    email_change_repository: Arc<E>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+    mailer: Arc<M>,
+    base_url: String,
+}
+
+impl<R: UserRepository, E: EmailChangeRepository, S: SessionRepository, A: AuditRepository, M: Mailer> RequestEmailChangeUseCase<R, E, S, A, M> {
+    pub fn new(
+        user_repository: Arc<R>,
+        email_change_repository: Arc<E>,
+        session_repository: Arc<S>,
+        audit_repository: Arc<A>,
+        mailer: Arc<M>,
+        base_url: String,
+    ) -> Self {
+        Self {
+            user_repository,
+            email_change_repository,
+            session_repository,
+            audit_repository,
+            mailer,
+            base_url,
+        }
+    }
+
+    /// `current_session` is the one making the request
+    pub async fn execute(&self, user_id: Uuid, current_session: Option<Uuid>, dto: RequestEmailChangeDto, ctx: &RequestContext) -> Result<(), AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        // Password users must prove they know it; OAuth-only users, who have none,
+        // must have just signed in with their provider
+        match user.password_hash.as_ref() {
+            Some(password_hash) => {
+                let password = dto.current_password
+                    .ok_or_else(|| AppError::ValidationError("Current password is required".to_string()))?;
+                if !verify_password(password_hash, &password)? {
+                    return Err(AppError::InvalidCredentials);
+                }
+            }
+            None => self.ensure_recent_sign_in(user_id, current_session).await?,
+        }
+
+        let new_email = canonical_email(&dto.new_email);
+        if new_email == user.email {
+            return Err(AppError::ValidationError("New email must differ from the current one".to_string()));
+        }
+
+        // Early feedback only; uniqueness is enforced again on confirmation
+        if self.user_repository.find_by_email(&new_email).await?.is_some() {
+            return Err(AppError::EmailAlreadyExists);
+        }
+
+        self.email_change_repository.delete_pending_for_user(user_id).await?;
+
+        let confirm_token = generate_token();
+        let revert_token = generate_token();
+        let now = Utc::now();
+
+        let change = self.email_change_repository.create(&EmailChange {
+            id: Uuid::new_v4(),
+            user_id,
+            old_email: user.email.clone(),
+            new_email,
+            confirm_token_hash: hash_token(&confirm_token),
+            revert_token_hash: hash_token(&revert_token),
+            confirm_expires_at: now + Duration::hours(CONFIRM_WINDOW_HOURS),
+            revert_expires_at: now + Duration::days(REVERT_WINDOW_DAYS),
+            confirmed_at: None,
+            reverted_at: None,
+            created_at: None,
+        }).await?;
+
+        self.mailer.send(&EmailMessage {
+            to: change.new_email.clone(),
+            subject: "Confirm your new email address".to_string(),
+            body: format!(
+                "Hi {},\n\nConfirm this address for your account within {} hours:\n{}/api/v1/auth/email-change/confirm?token={}\n\nIf you did not request this, ignore this email.",
+                user.name, CONFIRM_WINDOW_HOURS, self.base_url, confirm_token
+            ),
+        }).await?;
+
+        self.mailer.send(&EmailMessage {
+            to: change.old_email.clone(),
+            subject: "Your account email is being changed".to_string(),
+            body: format!(
+                "Hi {},\n\nA request was made to change your account email to {}.\nIf this was not you, undo it within {} days:\n{}/api/v1/auth/email-change/revert?token={}",
+                user.name, change.new_email, REVERT_WINDOW_DAYS, self.base_url, revert_token
+            ),
+        }).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::EmailChangeRequested, Some(json!({ "new_email": change.new_email })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn ensure_recent_sign_in(&self, user_id: Uuid, current_session: Option<Uuid>) -> Result<(), AppError> {
+        let Some(session_id) = current_session else {
+            return Err(AppError::ReauthenticationRequired);
+        };
+        let signed_in_at = self.session_repository
+            .find_by_id(session_id, user_id)
+            .await?
+            .and_then(|session| session.created_at);
+        match signed_in_at {
+            Some(at) if at > Utc::now() - Duration::minutes(REAUTH_WINDOW_MINUTES) => Ok(()),
+            _ => Err(AppError::ReauthenticationRequired),
+        }
+    }
+}
+
+/// Confirm Email Change Use Case - swaps `users.email` to the new address
+pub struct ConfirmEmailChangeUseCase<R: UserRepository, E: EmailChangeRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    email_change_repository: Arc<E>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, E: EmailChangeRepository, A: AuditRepository> ConfirmEmailChangeUseCase<R, E, A> {
+    pub fn new(user_repository: Arc<R>, email_change_repository: Arc<E>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, email_change_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, token: &str, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
+        let change = self.email_change_repository
+            .find_confirmable(&hash_token(token))
+            .await?
+            .ok_or(AppError::InvalidToken)?;
+
+        let mut user = self.user_repository
+            .find_by_id(change.user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        // The account email moved on since the request was made (e.g. an admin edit)
+        if user.email != change.old_email {
+            return Err(AppError::InvalidToken);
+        }
+
+        if self.user_repository.find_by_email(&change.new_email).await?.is_some() {
+            return Err(AppError::EmailAlreadyExists);
+        }
+
+        let before = user.clone();
+        user.email = change.new_email.clone();
+        // Still maps a unique violation from a concurrent sign-up to EmailAlreadyExists
+        let updated_user = self.user_repository.update(user.id, &user).await?;
+        self.email_change_repository.mark_confirmed(change.id).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user.id), Some(user.id), AuditAction::EmailChanged, None)
+                .with_changes(Some(&before), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(updated_user))
+    }
+}
+
+/// Revert Email Change Use Case - cancels a pending change or restores the old
+/// address, and signs the account out everywhere in case it was taken over
+pub struct RevertEmailChangeUseCase<R: UserRepository, E: EmailChangeRepository, S: SessionRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    email_change_repository: Arc<E>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, E: EmailChangeRepository, S: SessionRepository, A: AuditRepository> RevertEmailChangeUseCase<R, E, S, A> {
+    pub fn new(user_repository: Arc<R>, email_change_repository: Arc<E>, session_repository: Arc<S>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, email_change_repository, session_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, token: &str, ctx: &RequestContext) -> Result<(), AppError> {
+        let change = self.email_change_repository
+            .find_revertable(&hash_token(token))
+            .await?
+            .ok_or(AppError::InvalidToken)?;
+
+        let mut user = self.user_repository
+            .find_by_id(change.user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+        let before = user.clone();
+
+        // Back to the address this change replaced, whatever changes followed it:
+        // a hijacker could otherwise chain a second change whose revert link only
+        // they receive
+        if change.confirmed_at.is_some() && user.email != change.old_email {
+            user.email = change.old_email.clone();
+            self.user_repository.update(user.id, &user).await?;
+        }
+        self.email_change_repository.mark_reverted_since(&change).await?;
+        let sessions_revoked = self.session_repository.delete_all_for_user(user.id, None).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user.id), Some(user.id), AuditAction::EmailChangeReverted, Some(json!({ "sessions_revoked": sessions_revoked })))
+                .with_changes(Some(&before), Some(&user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,11 @@
+pub mod audit;
 pub mod auth;
+pub mod avatar;
+pub mod email_change;
+pub mod data_privacy;
+pub mod phone;
+pub mod profile;
 pub mod users;
 pub mod user_management;
+pub mod provider_tokens;
+pub mod sessions;
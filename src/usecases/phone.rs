@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::phone_verification::PhoneVerification;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::phone_verification_repository::PhoneVerificationRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::domain::dtos::{ConfirmPhoneVerificationDto, PhoneVerificationSentDto, UserResponseDto};
+use crate::infrastructure::auth::token::{generate_otp, hash_token};
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::sms::{SmsMessage, SmsSender};
+
+const CODE_TTL_MINUTES: i64 = 10;
+const MAX_ATTEMPTS: i32 = 5;
+const RESEND_COOLDOWN_SECONDS: i64 = 60;
+
+/// Normalizes a phone about to be stored for `owner_id` (`None` for a new user).
+/// Blank input clears the phone. When the policy requires unique phones, a number
+/// already held by another active account is rejected.
+pub async fn prepare_phone<R: UserRepository>(
+    user_repository: &R,
+    policy: &PhonePolicy,
+    raw: &str,
+    owner_id: Option<Uuid>,
+) -> Result<Option<String>, AppError> {
+    if raw.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let phone = policy.normalize(raw)?;
+
+    if policy.requires_unique() {
+        if let Some(holder) = user_repository.find_by_phone(&phone).await? {
+            if Some(holder.id) != owner_id {
+                return Err(AppError::PhoneAlreadyExists);
+            }
+        }
+    }
+
+    Ok(Some(phone))
+}
+
+/// Request Phone Verification Use Case - texts a one-time code to the user's current phone
+pub struct RequestPhoneVerificationUseCase<R: UserRepository, P: PhoneVerificationRepository, A: AuditRepository, S: SmsSender> {
+    user_repository: Arc<R>,
+    phone_verification_repository: Arc<P>,
+    audit_repository: Arc<A>,
+    sms_sender: Arc<S>,
+}
+
+impl<R: UserRepository, P: PhoneVerificationRepository, A: AuditRepository, S: SmsSender> RequestPhoneVerificationUseCase<R, P, A, S> {
+    pub fn new(
+        user_repository: Arc<R>,
+        phone_verification_repository: Arc<P>,
+        audit_repository: Arc<A>,
+        sms_sender: Arc<S>,
+    ) -> Self {
+        Self {
+            user_repository,
+            phone_verification_repository,
+            audit_repository,
+            sms_sender,
+        }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, ctx: &RequestContext) -> Result<PhoneVerificationSentDto, AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let phone = user.phone
+            .ok_or_else(|| AppError::ValidationError("Account has no phone number".to_string()))?;
+        if user.phone_verified_at.is_some() {
+            return Err(AppError::ValidationError("Phone number is already verified".to_string()));
+        }
+
+        // Someone else proved ownership first; fail now rather than after the code is typed in
+        if let Some(holder) = self.user_repository.find_by_phone(&phone).await? {
+            if holder.id != user_id && holder.phone_verified_at.is_some() {
+                return Err(AppError::PhoneAlreadyExists);
+            }
+        }
+
+        let now = Utc::now();
+        if let Some(pending) = self.phone_verification_repository.find_latest_pending(user_id).await? {
+            let resend_after = pending.created_at.unwrap_or(now) + Duration::seconds(RESEND_COOLDOWN_SECONDS);
+            if pending.phone == phone && resend_after > now {
+                return Err(AppError::TooManyRequests(format!(
+                    "A code was just sent, try again in {} seconds",
+                    (resend_after - now).num_seconds().max(1)
+                )));
+            }
+        }
+
+        self.phone_verification_repository.delete_pending_for_user(user_id).await?;
+
+        let code = generate_otp();
+        let verification = self.phone_verification_repository.create(&PhoneVerification {
+            id: Uuid::new_v4(),
+            user_id,
+            phone: phone.clone(),
+            code_hash: hash_token(&code),
+            attempts: 0,
+            expires_at: now + Duration::minutes(CODE_TTL_MINUTES),
+            verified_at: None,
+            created_at: None,
+        }).await?;
+
+        self.sms_sender.send(&SmsMessage {
+            to: phone.clone(),
+            body: format!("Your verification code is {}. It expires in {} minutes.", code, CODE_TTL_MINUTES),
+        }).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::PhoneVerificationRequested, Some(json!({ "phone": phone })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(PhoneVerificationSentDto {
+            phone,
+            expires_at: verification.expires_at,
+            resend_after: now + Duration::seconds(RESEND_COOLDOWN_SECONDS),
+        })
+    }
+}
+
+/// Confirm Phone Verification Use Case - checks the code and marks the phone verified
+pub struct ConfirmPhoneVerificationUseCase<R: UserRepository, P: PhoneVerificationRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    phone_verification_repository: Arc<P>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, P: PhoneVerificationRepository, A: AuditRepository> ConfirmPhoneVerificationUseCase<R, P, A> {
+    pub fn new(user_repository: Arc<R>, phone_verification_repository: Arc<P>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, phone_verification_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, dto: ConfirmPhoneVerificationDto, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
+        let verification = self.phone_verification_repository
+            .find_latest_pending(user_id)
+            .await?
+            .ok_or(AppError::InvalidVerificationCode)?;
+
+        if verification.expires_at < Utc::now() {
+            return Err(AppError::InvalidVerificationCode);
+        }
+
+        // Every guess, right or wrong, uses up an attempt before the code is compared
+        if !self.phone_verification_repository.increment_attempts(verification.id, MAX_ATTEMPTS).await? {
+            return Err(AppError::InvalidVerificationCode);
+        }
+
+        if hash_token(dto.code.trim()) != verification.code_hash {
+            return Err(AppError::InvalidVerificationCode);
+        }
+
+        // Fails if the phone was changed after the code was sent
+        let user = self.user_repository.mark_phone_verified(user_id, &verification.phone).await?;
+        self.phone_verification_repository.mark_verified(verification.id).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::PhoneVerified, Some(json!({ "phone": verification.phone })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(user))
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::user::Role;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::domain::dtos::{ChangePasswordDto, UpdateProfileDto, UserResponseDto};
+use crate::infrastructure::auth::password::{hash_password, verify_password};
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::request_context::RequestContext;
+use crate::usecases::phone::prepare_phone;
+
+/// Get Profile Use Case - any authenticated user, own account only
+pub struct GetProfileUseCase<R: UserRepository> {
+    user_repository: Arc<R>,
+}
+
+impl<R: UserRepository> GetProfileUseCase<R> {
+    pub fn new(user_repository: Arc<R>) -> Self {
+        Self { user_repository }
+    }
+
+    pub async fn execute(&self, user_id: Uuid) -> Result<UserResponseDto, AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(UserResponseDto::from(user))
+    }
+}
+
+/// Update Profile Use Case - name and phone only; email and role stay admin-managed
+pub struct UpdateProfileUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    phone_policy: Arc<PhonePolicy>,
+}
+
+impl<R: UserRepository, A: AuditRepository> UpdateProfileUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, phone_policy: Arc<PhonePolicy>) -> Self {
+        Self { user_repository, audit_repository, phone_policy }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, dto: UpdateProfileDto, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
+        let mut user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+        let before = user.clone();
+
+        if let Some(name) = dto.name {
+            user.name = name;
+        }
+        if let Some(phone) = dto.phone {
+            // A changed number loses its verification (see `UserRepository::update`)
+            user.phone = prepare_phone(self.user_repository.as_ref(), &self.phone_policy, &phone, Some(user_id)).await?;
+        }
+
+        let updated_user = self.user_repository.update(user_id, &user).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::ProfileUpdated, None)
+                .with_changes(Some(&before), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(updated_user))
+    }
+}
+
+/// Change Password Use Case - requires the current password, and signs every
+/// other session out so a stolen one does not outlive the old password
+pub struct ChangePasswordUseCase<R: UserRepository, S: SessionRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, S: SessionRepository, A: AuditRepository> ChangePasswordUseCase<R, S, A> {
+    pub fn new(user_repository: Arc<R>, session_repository: Arc<S>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, session_repository, audit_repository }
+    }
+
+    /// `current_session` is the one making the request, which stays signed in
+    pub async fn execute(&self, user_id: Uuid, current_session: Option<Uuid>, dto: ChangePasswordDto, ctx: &RequestContext) -> Result<(), AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        // OAuth-only users have no password to verify against
+        let password_hash = user.password_hash.as_ref()
+            .ok_or_else(|| AppError::ValidationError("Account has no password set".to_string()))?;
+
+        if !verify_password(password_hash, &dto.current_password)? {
+            return Err(AppError::InvalidCredentials);
+        }
+
+        let new_hash = hash_password(&dto.new_password)?;
+        self.user_repository.update_password(user_id, &new_hash).await?;
+        let sessions_revoked = self.session_repository.delete_all_for_user(user_id, current_session).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::PasswordChanged, Some(json!({ "sessions_revoked": sessions_revoked })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
+
+/// Delete Account Use Case - password users must confirm with their password
+pub struct DeleteAccountUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, A: AuditRepository> DeleteAccountUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, user_id: Uuid, password: Option<String>, ctx: &RequestContext) -> Result<(), AppError> {
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        // Same rule as the admin endpoint: a SuperAdmin cannot remove themselves
+        if user.role == Role::SuperAdmin {
+            return Err(AppError::CannotDeleteSelf);
+        }
+
+        if let Some(password_hash) = user.password_hash.as_ref() {
+            let password = password
+                .ok_or_else(|| AppError::ValidationError("Password is required".to_string()))?;
+            if !verify_password(password_hash, &password)? {
+                return Err(AppError::InvalidCredentials);
+            }
+        }
+
+        self.user_repository.delete(user_id).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::AccountDeleted, None)
+                .with_changes(Some(&user), None)
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use crate::domain::entities::provider_token::ProviderToken;
+use crate::domain::entities::user::AuthProvider;
+use crate::domain::repositories::provider_token_repository::ProviderTokenRepository;
+use crate::infrastructure::auth::oauth::{OAuthProviderRegistry, OAuthTokens};
+use crate::infrastructure::crypto::EnvelopeCipher;
+use crate::infrastructure::errors::AppError;
+
+/// Access tokens this close to expiry are refreshed before use
+const REFRESH_MARGIN_SECONDS: i64 = 60;
+const ROTATION_BATCH_SIZE: i64 = 100;
+
+/// Keeps users' provider tokens, encrypted, so features can call the
+/// providers' APIs on their behalf. `access_token` is the internal API for that.
+pub struct ProviderTokenStore<P: ProviderTokenRepository> {
+    provider_token_repository: Arc<P>,
+    cipher: Arc<EnvelopeCipher>,
+    oauth_providers: Arc<OAuthProviderRegistry>,
+}
+
+/// Binds the ciphertexts to their row, so they cannot be copied to another user
+fn context(user_id: Uuid, provider: &str) -> String {
+    format!("provider_tokens:{}:{}", user_id, provider)
+}
+
+impl<P: ProviderTokenRepository> ProviderTokenStore<P> {
+    pub fn new(provider_token_repository: Arc<P>, cipher: Arc<EnvelopeCipher>, oauth_providers: Arc<OAuthProviderRegistry>) -> Self {
+        Self { provider_token_repository, cipher, oauth_providers }
+    }
+
+    /// Saves the tokens from a sign-in or refresh. Providers often return a refresh
+    /// token only the first time, so a missing one keeps the stored one.
+    pub async fn store(&self, user_id: Uuid, provider: AuthProvider, tokens: &OAuthTokens) -> Result<(), AppError> {
+        let refresh_token = match &tokens.refresh_token {
+            Some(refresh_token) => Some(refresh_token.clone()),
+            None => match self.provider_token_repository.find(user_id, provider).await? {
+                Some(stored) => self.open(&stored)?.1,
+                None => None,
+            },
+        };
+
+        let context = context(user_id, provider.as_str());
+        let mut values = vec![tokens.access_token.as_str()];
+        values.extend(refresh_token.as_deref());
+        let sealed = self.cipher.seal(&context, &values)?;
+        let mut ciphertexts = sealed.ciphertexts.into_iter();
+
+        self.provider_token_repository.upsert(&ProviderToken {
+            user_id,
+            provider: provider.as_str().to_string(),
+            key_id: sealed.key_id,
+            wrapped_data_key: sealed.wrapped_data_key,
+            access_token: ciphertexts.next().expect("the access token is always sealed"),
+            refresh_token: ciphertexts.next(),
+            expires_at: tokens.expires_in.map(|secs| Utc::now() + Duration::seconds(secs as i64)),
+        }).await
+    }
+
+    /// A currently valid access token for calling `provider` as the user,
+    /// refreshed first when it is about to expire
+    pub async fn access_token(&self, user_id: Uuid, provider: AuthProvider) -> Result<String, AppError> {
+        let oauth_provider = self.oauth_providers.get(provider.as_str());
+        let not_connected = || {
+            let name = oauth_provider.as_ref().map_or(provider.as_str(), |oauth_provider| oauth_provider.display_name());
+            AppError::ProviderNotConnected(name.to_string())
+        };
+
+        let stored = self.provider_token_repository
+            .find(user_id, provider)
+            .await?
+            .ok_or_else(not_connected)?;
+        let (access_token, refresh_token) = self.open(&stored)?;
+
+        let expiring = stored.expires_at
+            .is_some_and(|expires_at| expires_at - Duration::seconds(REFRESH_MARGIN_SECONDS) <= Utc::now());
+        if !expiring {
+            return Ok(access_token);
+        }
+
+        let refresh_token = refresh_token.ok_or_else(not_connected)?;
+        let oauth_provider = oauth_provider.as_ref().ok_or_else(not_connected)?;
+
+        let tokens = match oauth_provider.refresh_tokens(&refresh_token).await {
+            Ok(tokens) => tokens,
+            // The user revoked access or the token aged out; only signing in again helps
+            Err(AppError::OAuthInvalidCode) => {
+                self.provider_token_repository.delete(user_id, provider).await?;
+                return Err(not_connected());
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.store(user_id, provider, &tokens).await?;
+        Ok(tokens.access_token)
+    }
+
+    /// Re-wraps data keys still wrapped by an older master key; returns how many
+    pub async fn rotate_keys(&self) -> Result<u64, AppError> {
+        let active_key_id = self.cipher.active_key_id();
+        let mut rotated = 0;
+
+        loop {
+            let batch = self.provider_token_repository
+                .find_not_wrapped_by(active_key_id, ROTATION_BATCH_SIZE)
+                .await?;
+            if batch.is_empty() {
+                return Ok(rotated);
+            }
+
+            for token in &batch {
+                let wrapped = self.cipher.rewrap(&context(token.user_id, &token.provider), &token.key_id, &token.wrapped_data_key)?;
+                // A row rewritten meanwhile was sealed with the active key anyway
+                if self.provider_token_repository.update_wrapped_key(token, active_key_id, &wrapped).await? {
+                    rotated += 1;
+                }
+            }
+        }
+    }
+
+    fn open(&self, stored: &ProviderToken) -> Result<(String, Option<String>), AppError> {
+        let mut ciphertexts = vec![stored.access_token.as_slice()];
+        ciphertexts.extend(stored.refresh_token.as_deref());
+
+        let mut values = self.cipher
+            .open(&context(stored.user_id, &stored.provider), &stored.key_id, &stored.wrapped_data_key, &ciphertexts)?
+            .into_iter();
+
+        Ok((values.next().unwrap_or_default(), values.next()))
+    }
+}
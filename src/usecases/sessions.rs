@@ -0,0 +1,180 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::dtos::{AuthResponseDto, SessionDto};
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::entities::session::Session;
+use crate::domain::entities::user::{Role, User};
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::session_repository::SessionRepository;
+use crate::domain::repositories::user_repository::UserRepository;
+use crate::infrastructure::auth::jwt::JwtService;
+use crate::infrastructure::errors::AppError;
+use crate::infrastructure::request_context::RequestContext;
+
+/// Session Issuer - starts a session for each sign-in and keeps it fresh on
+/// refresh; every token pair names its session
+pub struct SessionIssuer<S: SessionRepository> {
+    session_repository: Arc<S>,
+    jwt_service: Arc<JwtService>,
+}
+
+impl<S: SessionRepository> SessionIssuer<S> {
+    pub fn new(session_repository: Arc<S>, jwt_service: Arc<JwtService>) -> Self {
+        Self { session_repository, jwt_service }
+    }
+
+    /// Records a sign-in with `auth_method` (`AuthProvider::as_str`) and issues its tokens
+    pub async fn start(&self, user: &User, auth_method: &str, ctx: &RequestContext) -> Result<AuthResponseDto, AppError> {
+        // Every sign-in adds a row, so the user's dead ones are cleared here
+        self.session_repository.delete_stale_for_user(user.id, stale_before(&self.jwt_service)).await?;
+
+        let session = self.session_repository.create(&Session {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            auth_method: auth_method.to_string(),
+            user_agent: ctx.user_agent.clone(),
+            ip_address: ctx.ip_address.clone(),
+            created_at: None,
+            last_seen_at: Utc::now(),
+        }).await?;
+
+        self.issue(user, session.id)
+    }
+
+    /// Issues new tokens for a session on refresh; a revoked session gets `InvalidToken`
+    pub async fn resume(&self, user: &User, session_id: Uuid, ctx: &RequestContext) -> Result<AuthResponseDto, AppError> {
+        let active = self.session_repository
+            .touch(session_id, user.id, ctx.ip_address.as_deref(), ctx.user_agent.as_deref())
+            .await?;
+        if !active {
+            return Err(AppError::InvalidToken);
+        }
+
+        self.issue(user, session_id)
+    }
+
+    fn issue(&self, user: &User, session_id: Uuid) -> Result<AuthResponseDto, AppError> {
+        let (access_token, refresh_token) = self.jwt_service.generate_tokens(user, session_id)?;
+
+        Ok(AuthResponseDto {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.jwt_service.access_token_ttl_secs(),
+        })
+    }
+}
+
+/// A session is over once the refresh token of its last refresh has expired
+fn stale_before(jwt_service: &JwtService) -> DateTime<Utc> {
+    Utc::now() - Duration::seconds(jwt_service.refresh_token_ttl_secs() as i64)
+}
+
+/// Admins manage anyone's sessions but a SuperAdmin's; SuperAdmins manage all
+async fn ensure_can_manage_sessions<R: UserRepository>(user_repository: &R, requester_role: Role, user_id: Uuid) -> Result<(), AppError> {
+    match requester_role {
+        Role::Admin | Role::SuperAdmin => {},
+        _ => return Err(AppError::Forbidden),
+    }
+
+    let user = user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or(AppError::UserNotFound)?;
+    if user.role == Role::SuperAdmin && requester_role != Role::SuperAdmin {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok(())
+}
+
+/// Get Sessions Use Case - where a user is signed in
+pub struct GetSessionsUseCase<R: UserRepository, S: SessionRepository> {
+    user_repository: Arc<R>,
+    session_repository: Arc<S>,
+    jwt_service: Arc<JwtService>,
+}
+
+impl<R: UserRepository, S: SessionRepository> GetSessionsUseCase<R, S> {
+    pub fn new(user_repository: Arc<R>, session_repository: Arc<S>, jwt_service: Arc<JwtService>) -> Self {
+        Self { user_repository, session_repository, jwt_service }
+    }
+
+    /// The user's own sessions; `current_session` is the one making the request
+    pub async fn execute(&self, user_id: Uuid, current_session: Option<Uuid>) -> Result<Vec<SessionDto>, AppError> {
+        let sessions = self.session_repository
+            .find_active_by_user(user_id, stale_before(&self.jwt_service))
+            .await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionDto {
+                current: Some(session.id) == current_session,
+                id: session.id,
+                auth_method: session.auth_method,
+                user_agent: session.user_agent,
+                ip_address: session.ip_address,
+                created_at: session.created_at,
+                last_seen_at: session.last_seen_at,
+            })
+            .collect())
+    }
+
+    /// Another user's sessions (Admin + SuperAdmin)
+    pub async fn execute_for_user(&self, requester_role: Role, user_id: Uuid) -> Result<Vec<SessionDto>, AppError> {
+        ensure_can_manage_sessions(self.user_repository.as_ref(), requester_role, user_id).await?;
+        self.execute(user_id, None).await
+    }
+}
+
+/// Revoke Session Use Case - signs a device out. Its refresh token stops working
+/// at once; access tokens already issued expire within their (short) lifetime.
+pub struct RevokeSessionUseCase<R: UserRepository, S: SessionRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    session_repository: Arc<S>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, S: SessionRepository, A: AuditRepository> RevokeSessionUseCase<R, S, A> {
+    pub fn new(user_repository: Arc<R>, session_repository: Arc<S>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, session_repository, audit_repository }
+    }
+
+    /// Revokes one of the user's own sessions
+    pub async fn execute(&self, user_id: Uuid, session_id: Uuid, ctx: &RequestContext) -> Result<(), AppError> {
+        self.revoke(user_id, user_id, session_id, ctx).await
+    }
+
+    /// Revokes all of the user's sessions but `current_session`
+    pub async fn execute_others(&self, user_id: Uuid, current_session: Option<Uuid>, ctx: &RequestContext) -> Result<(), AppError> {
+        let sessions_revoked = self.session_repository.delete_all_for_user(user_id, current_session).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(user_id), Some(user_id), AuditAction::SessionRevoked, Some(json!({ "sessions_revoked": sessions_revoked })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+
+    /// Revokes another user's session (Admin + SuperAdmin)
+    pub async fn execute_for_user(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, session_id: Uuid, ctx: &RequestContext) -> Result<(), AppError> {
+        ensure_can_manage_sessions(self.user_repository.as_ref(), requester_role, user_id).await?;
+        self.revoke(requester_id, user_id, session_id, ctx).await
+    }
+
+    async fn revoke(&self, actor_id: Uuid, user_id: Uuid, session_id: Uuid, ctx: &RequestContext) -> Result<(), AppError> {
+        if !self.session_repository.delete(session_id, user_id).await? {
+            return Err(AppError::SessionNotFound);
+        }
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(actor_id), Some(user_id), AuditAction::SessionRevoked, Some(json!({ "session_id": session_id })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(())
+    }
+}
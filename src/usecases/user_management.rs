@@ -1,68 +1,217 @@
+use std::collections::HashSet;
 use std::sync::Arc;
+use chrono::{Duration, Utc};
 use uuid::Uuid;
-use crate::domain::entities::user::{User, Role, UserStatus};
-use crate::domain::repositories::user_repository::UserRepository;
-use crate::domain::dtos::{CreateUserDto, UpdateUserDto, UpdateUserStatusDto, UserResponseDto};
+use serde_json::json;
+use crate::domain::entities::user::{canonical_email, User, Role};
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
+use crate::domain::repositories::user_repository::{ImportMode, PurgeMode, UserRepository};
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::data_export_repository::DataExportRepository;
+use crate::domain::repositories::email_change_repository::EmailChangeRepository;
+use crate::domain::dtos::{
+    AdminUserDto, CreateUserDto, UpdateUserDto, UpdateUserStatusDto, UserResponseDto, ImpersonationResponseDto,
+    ImportUserRowDto, ImportReportDto, ImportRowResultDto, ImportRowStatus,
+};
+use crate::infrastructure::auth::jwt::JwtService;
 use crate::infrastructure::auth::password::hash_password;
 use crate::infrastructure::errors::AppError;
+use crate::infrastructure::phone::PhonePolicy;
+use crate::infrastructure::request_context::RequestContext;
+use crate::infrastructure::storage::BlobStore;
+use crate::usecases::avatar::avatar_dir;
+use crate::usecases::data_privacy::remove_archive;
+use crate::usecases::phone::prepare_phone;
 
 /// Create User Use Case - Admin + SuperAdmin only
-pub struct CreateUserUseCase<R: UserRepository> {
+pub struct CreateUserUseCase<R: UserRepository, A: AuditRepository> {
     user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    phone_policy: Arc<PhonePolicy>,
 }
 
-impl<R: UserRepository> CreateUserUseCase<R> {
-    pub fn new(user_repository: Arc<R>) -> Self {
-        Self { user_repository }
+impl<R: UserRepository, A: AuditRepository> CreateUserUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, phone_policy: Arc<PhonePolicy>) -> Self {
+        Self { user_repository, audit_repository, phone_policy }
     }
 
-    pub async fn execute(&self, requester_role: Role, dto: CreateUserDto) -> Result<UserResponseDto, AppError> {
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, dto: CreateUserDto, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
         // Check permissions: Only Admin and SuperAdmin can create users
         match requester_role {
             Role::Admin | Role::SuperAdmin => {},
             _ => return Err(AppError::Forbidden),
         }
 
+        let phone = match dto.phone.as_deref() {
+            Some(raw) => prepare_phone(self.user_repository.as_ref(), &self.phone_policy, raw, None).await?,
+            None => None,
+        };
         let password_hash = hash_password(&dto.password)?;
 
         let user = User {
-            id: Uuid::new_v4(),
-            name: dto.name,
-            phone: dto.phone,
-            email: dto.email,
+            phone,
             password_hash: Some(password_hash),
-            role: dto.role,
-            status: UserStatus::default(),
-            github_id: None,
-            google_id: None,
-            avatar_url: None,
-            created_at: None,
-            updated_at: None,
+            ..User::new(dto.name, &dto.email, dto.role)
         };
 
         let created_user = self.user_repository.create(&user).await?;
 
-        Ok(UserResponseDto {
-            name: created_user.name,
-            phone: created_user.phone,
-            email: created_user.email,
-            role: created_user.role,
-            avatar_url: created_user.avatar_url,
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), Some(created_user.id), AuditAction::UserCreated, None)
+                .with_changes(None, Some(&created_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(created_user))
+    }
+}
+
+/// Upper bound on rows per import request
+pub const MAX_IMPORT_ROWS: usize = 1000;
+
+/// Import Users Use Case - Admin + SuperAdmin, same rules as creating users one by one
+pub struct ImportUsersUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    phone_policy: Arc<PhonePolicy>,
+}
+
+impl<R: UserRepository, A: AuditRepository> ImportUsersUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, phone_policy: Arc<PhonePolicy>) -> Self {
+        Self { user_repository, audit_repository, phone_policy }
+    }
+
+    pub async fn execute(
+        &self,
+        requester_id: Uuid,
+        requester_role: Role,
+        mut rows: Vec<ImportUserRowDto>,
+        mode: ImportMode,
+        dry_run: bool,
+        ctx: &RequestContext,
+    ) -> Result<ImportReportDto, AppError> {
+        match requester_role {
+            Role::Admin | Role::SuperAdmin => {},
+            _ => return Err(AppError::Forbidden),
+        }
+
+        if rows.is_empty() {
+            return Err(AppError::ValidationError("Import file has no rows".to_string()));
+        }
+        if rows.len() > MAX_IMPORT_ROWS {
+            return Err(AppError::ValidationError(format!("Import is limited to {} rows", MAX_IMPORT_ROWS)));
+        }
+
+        // Phones go through the same policy as single creates; with unique phones
+        // enforced, a number may also appear only once within the file
+        let mut seen_phones = HashSet::new();
+        for row in rows.iter_mut() {
+            let Ok(dto) = &mut row.user else { continue };
+            let Some(raw) = dto.phone.take() else { continue };
+            match prepare_phone(self.user_repository.as_ref(), &self.phone_policy, &raw, None).await {
+                Ok(Some(phone)) if self.phone_policy.requires_unique() && !seen_phones.insert(phone.clone()) => {
+                    row.user = Err(AppError::PhoneAlreadyExists.to_string());
+                }
+                Ok(phone) => dto.phone = phone,
+                Err(AppError::DatabaseError(e)) => return Err(AppError::DatabaseError(e)),
+                Err(e) => row.user = Err(e.to_string()),
+            }
+        }
+
+        // Nothing is kept on a dry run, so skip the expensive hashing. Argon2 is
+        // CPU-bound, so a whole file of it runs on the blocking pool, not the runtime.
+        let mut password_hashes = if dry_run {
+            Vec::new()
+        } else {
+            let passwords: Vec<String> = rows.iter()
+                .filter_map(|row| row.user.as_ref().ok().map(|dto| dto.password.clone()))
+                .collect();
+            tokio::task::spawn_blocking(move || passwords.iter().map(|password| hash_password(password)).collect::<Result<Vec<_>, _>>())
+                .await
+                .map_err(|_| AppError::InternalServerError)??
+        }
+        .into_iter();
+
+        let mut users = Vec::new();
+        let mut any_invalid = false;
+        for row in &rows {
+            match &row.user {
+                Ok(dto) => {
+                    let password_hash = password_hashes.next();
+                    users.push(User {
+                        phone: dto.phone.clone(),
+                        password_hash,
+                        ..User::new(dto.name.clone(), &dto.email, dto.role.clone())
+                    });
+                }
+                Err(_) => any_invalid = true,
+            }
+        }
+
+        // Invalid rows never reach the database, but still sink an all-or-nothing batch
+        let rollback = dry_run || (mode == ImportMode::AllOrNothing && any_invalid);
+        let mut inserted = self.user_repository.create_many(&users, mode, rollback).await?.into_iter();
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            let outcome = match row.user {
+                Ok(_) => inserted.next().ok_or(AppError::InternalServerError)?.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            results.push((row.line, row.email, outcome));
+        }
+
+        let any_failed = results.iter().any(|(_, _, outcome)| outcome.is_err());
+        let committed = !rollback && (mode == ImportMode::BestEffort || !any_failed);
+
+        let mut rows = Vec::with_capacity(results.len());
+        for (line, email, outcome) in results {
+            let row = match outcome {
+                Ok(user) => {
+                    let status = if committed {
+                        self.audit_repository.create(
+                            &AuditEvent::new(Some(requester_id), Some(user.id), AuditAction::UserCreated, Some(json!({ "source": "import" })))
+                                .with_changes(None, Some(&user))
+                                .with_context(ctx),
+                        ).await?;
+                        ImportRowStatus::Created
+                    } else if dry_run {
+                        ImportRowStatus::Valid
+                    } else {
+                        ImportRowStatus::RolledBack
+                    };
+                    ImportRowResultDto { line, email, status, id: committed.then_some(user.id), error: None }
+                }
+                Err(error) => ImportRowResultDto { line, email, status: ImportRowStatus::Failed, id: None, error: Some(error) },
+            };
+            rows.push(row);
+        }
+
+        Ok(ImportReportDto {
+            dry_run,
+            mode,
+            committed: committed && rows.iter().any(|row| row.status == ImportRowStatus::Created),
+            total: rows.len(),
+            created: rows.iter().filter(|row| row.status == ImportRowStatus::Created).count(),
+            failed: rows.iter().filter(|row| row.status == ImportRowStatus::Failed).count(),
+            rows,
         })
     }
 }
 
 /// Update User Use Case - SuperAdmin only
-pub struct UpdateUserUseCase<R: UserRepository> {
+pub struct UpdateUserUseCase<R: UserRepository, A: AuditRepository> {
     user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    phone_policy: Arc<PhonePolicy>,
 }
 
-impl<R: UserRepository> UpdateUserUseCase<R> {
-    pub fn new(user_repository: Arc<R>) -> Self {
-        Self { user_repository }
+impl<R: UserRepository, A: AuditRepository> UpdateUserUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, phone_policy: Arc<PhonePolicy>) -> Self {
+        Self { user_repository, audit_repository, phone_policy }
     }
 
-    pub async fn execute(&self, requester_role: Role, user_id: Uuid, dto: UpdateUserDto) -> Result<UserResponseDto, AppError> {
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, dto: UpdateUserDto, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
         // Check permissions: Only SuperAdmin can update users
         if requester_role != Role::SuperAdmin {
             return Err(AppError::Forbidden);
@@ -73,16 +222,17 @@ impl<R: UserRepository> UpdateUserUseCase<R> {
             .find_by_id(user_id)
             .await?
             .ok_or(AppError::UserNotFound)?;
+        let before = user.clone();
 
         // Update fields if provided
         if let Some(name) = dto.name {
             user.name = name;
         }
         if let Some(phone) = dto.phone {
-            user.phone = Some(phone);
+            user.phone = prepare_phone(self.user_repository.as_ref(), &self.phone_policy, &phone, Some(user_id)).await?;
         }
         if let Some(email) = dto.email {
-            user.email = email;
+            user.email = canonical_email(&email);
         }
         if let Some(role) = dto.role {
             user.role = role;
@@ -90,27 +240,29 @@ impl<R: UserRepository> UpdateUserUseCase<R> {
 
         let updated_user = self.user_repository.update(user_id, &user).await?;
 
-        Ok(UserResponseDto {
-            name: updated_user.name,
-            phone: updated_user.phone,
-            email: updated_user.email,
-            role: updated_user.role,
-            avatar_url: updated_user.avatar_url,
-        })
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), Some(user_id), AuditAction::UserUpdated, None)
+                .with_changes(Some(&before), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(updated_user))
     }
 }
 
-/// Delete User Use Case - SuperAdmin only, cannot delete self
-pub struct DeleteUserUseCase<R: UserRepository> {
+/// Delete User Use Case - SuperAdmin only, cannot delete self.
+/// Soft delete: the account can be restored until the purge job runs.
+pub struct DeleteUserUseCase<R: UserRepository, A: AuditRepository> {
     user_repository: Arc<R>,
+    audit_repository: Arc<A>,
 }
 
-impl<R: UserRepository> DeleteUserUseCase<R> {
-    pub fn new(user_repository: Arc<R>) -> Self {
-        Self { user_repository }
+impl<R: UserRepository, A: AuditRepository> DeleteUserUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, audit_repository }
     }
 
-    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid) -> Result<(), AppError> {
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, ctx: &RequestContext) -> Result<(), AppError> {
         // Check permissions: Only SuperAdmin can delete users
         if requester_role != Role::SuperAdmin {
             return Err(AppError::Forbidden);
@@ -122,42 +274,212 @@ impl<R: UserRepository> DeleteUserUseCase<R> {
         }
 
         // Verify user exists before deleting
-        self.user_repository
+        let user = self.user_repository
             .find_by_id(user_id)
             .await?
             .ok_or(AppError::UserNotFound)?;
 
         self.user_repository.delete(user_id).await?;
 
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), Some(user_id), AuditAction::UserDeleted, None)
+                .with_changes(Some(&user), None)
+                .with_context(ctx),
+        ).await?;
+
         Ok(())
     }
 }
 
+/// Restore User Use Case - SuperAdmin only, undoes a soft delete before it is purged
+pub struct RestoreUserUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, A: AuditRepository> RestoreUserUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, audit_repository }
+    }
+
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, ctx: &RequestContext) -> Result<AdminUserDto, AppError> {
+        // Check permissions: Only SuperAdmin can restore users
+        if requester_role != Role::SuperAdmin {
+            return Err(AppError::Forbidden);
+        }
+
+        let deleted_user = self.user_repository
+            .find_deleted_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let restored_user = self.user_repository.restore(user_id).await?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), Some(user_id), AuditAction::UserRestored, None)
+                .with_changes(Some(&deleted_user), Some(&restored_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(AdminUserDto::from(restored_user))
+    }
+}
+
+/// Purge Deleted Users Use Case - run by the background job, not exposed over HTTP.
+/// Like an erasure, it also drops the purged users' email history, exports and avatars.
+pub struct PurgeDeletedUsersUseCase<R, E, C, A, B>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    A: AuditRepository,
+    B: BlobStore,
+{
+    user_repository: Arc<R>,
+    data_export_repository: Arc<E>,
+    email_change_repository: Arc<C>,
+    audit_repository: Arc<A>,
+    blob_store: Arc<B>,
+}
+
+impl<R, E, C, A, B> PurgeDeletedUsersUseCase<R, E, C, A, B>
+where
+    R: UserRepository,
+    E: DataExportRepository,
+    C: EmailChangeRepository,
+    A: AuditRepository,
+    B: BlobStore,
+{
+    pub fn new(
+        user_repository: Arc<R>,
+        data_export_repository: Arc<E>,
+        email_change_repository: Arc<C>,
+        audit_repository: Arc<A>,
+        blob_store: Arc<B>,
+    ) -> Self {
+        Self { user_repository, data_export_repository, email_change_repository, audit_repository, blob_store }
+    }
+
+    pub async fn execute(&self, retention_days: i64, mode: PurgeMode) -> Result<usize, AppError> {
+        let cutoff = Utc::now() - Duration::days(retention_days);
+        let purged_ids = self.user_repository.purge_deleted_before(cutoff, mode).await?;
+        let purged = purged_ids.len();
+
+        for user_id in purged_ids {
+            if let Err(e) = self.email_change_repository.delete_all_for_user(user_id).await {
+                tracing::error!("Failed to remove email changes of purged user {}: {:?}", user_id, e);
+            }
+            match self.data_export_repository.delete_for_user(user_id).await {
+                Ok(exports) => {
+                    for file_path in exports.iter().filter_map(|export| export.file_path.as_deref()) {
+                        remove_archive(file_path).await;
+                    }
+                }
+                Err(e) => tracing::error!("Failed to remove data exports of purged user {}: {:?}", user_id, e),
+            }
+            if let Err(e) = self.blob_store.delete_prefix(&avatar_dir(user_id)).await {
+                tracing::error!("Failed to remove avatars of purged user {}: {:?}", user_id, e);
+            }
+        }
+
+        if purged > 0 {
+            self.audit_repository.create(&AuditEvent::new(
+                None,
+                None,
+                AuditAction::UsersPurged,
+                Some(json!({ "count": purged, "mode": mode, "cutoff": cutoff })),
+            )).await?;
+        }
+
+        Ok(purged)
+    }
+}
+
 /// Suspend/Activate User Use Case - Admin + SuperAdmin
-pub struct UpdateUserStatusUseCase<R: UserRepository> {
+pub struct UpdateUserStatusUseCase<R: UserRepository, A: AuditRepository> {
     user_repository: Arc<R>,
+    audit_repository: Arc<A>,
 }
 
-impl<R: UserRepository> UpdateUserStatusUseCase<R> {
-    pub fn new(user_repository: Arc<R>) -> Self {
-        Self { user_repository }
+impl<R: UserRepository, A: AuditRepository> UpdateUserStatusUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, audit_repository }
     }
 
-    pub async fn execute(&self, requester_role: Role, user_id: Uuid, dto: UpdateUserStatusDto) -> Result<UserResponseDto, AppError> {
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, dto: UpdateUserStatusDto, ctx: &RequestContext) -> Result<UserResponseDto, AppError> {
         // Check permissions: Admin and SuperAdmin can suspend users
         match requester_role {
             Role::Admin | Role::SuperAdmin => {},
             _ => return Err(AppError::Forbidden),
         }
 
+        let before = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
         let updated_user = self.user_repository.update_status(user_id, dto.status).await?;
 
-        Ok(UserResponseDto {
-            name: updated_user.name,
-            phone: updated_user.phone,
-            email: updated_user.email,
-            role: updated_user.role,
-            avatar_url: updated_user.avatar_url,
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), Some(user_id), AuditAction::UserStatusChanged, None)
+                .with_changes(Some(&before), Some(&updated_user))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(UserResponseDto::from(updated_user))
+    }
+}
+
+/// Impersonate User Use Case - SuperAdmin only, cannot impersonate self or other SuperAdmins
+pub struct ImpersonateUserUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+    jwt_service: Arc<JwtService>,
+}
+
+impl<R: UserRepository, A: AuditRepository> ImpersonateUserUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>, jwt_service: Arc<JwtService>) -> Self {
+        Self { user_repository, audit_repository, jwt_service }
+    }
+
+    pub async fn execute(&self, requester_id: Uuid, requester_role: Role, user_id: Uuid, ctx: &RequestContext) -> Result<ImpersonationResponseDto, AppError> {
+        // Check permissions: Only SuperAdmin can impersonate users
+        if requester_role != Role::SuperAdmin {
+            return Err(AppError::Forbidden);
+        }
+
+        if requester_id == user_id {
+            return Err(AppError::CannotImpersonateSelf);
+        }
+
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        // SuperAdmins cannot borrow each other's identity
+        if user.role == Role::SuperAdmin {
+            return Err(AppError::Forbidden);
+        }
+
+        let access_token = self.jwt_service.generate_impersonation_token(&user, requester_id)?;
+
+        self.audit_repository.create(
+            &AuditEvent::new(
+                Some(requester_id),
+                Some(user_id),
+                AuditAction::ImpersonationStarted,
+                Some(json!({ "expires_in": self.jwt_service.impersonation_token_ttl_secs() })),
+            )
+            .with_context(ctx),
+        ).await?;
+
+        Ok(ImpersonationResponseDto {
+            access_token,
+            token_type: "Bearer".to_string(),
+            expires_in: self.jwt_service.impersonation_token_ttl_secs(),
+            impersonated_user_id: user_id,
+            actor_id: requester_id,
         })
     }
 }
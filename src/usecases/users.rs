@@ -1,8 +1,15 @@
 use std::sync::Arc;
+use futures_util::stream::{BoxStream, StreamExt};
+use serde_json::json;
+use uuid::Uuid;
+use crate::domain::entities::audit_event::{AuditAction, AuditEvent};
 use crate::domain::entities::user::Role;
-use crate::domain::repositories::user_repository::UserRepository;
-use crate::domain::dtos::UserResponseDto;
+use crate::domain::repositories::audit_repository::AuditRepository;
+use crate::domain::repositories::user_repository::{UserFilter, UserRepository, UserSort};
+use crate::domain::repositories::pagination::{Cursor, KeysetPage, PageParams};
+use crate::domain::dtos::{AdminUserDto, UserSearchResultDto, UserSearchHighlightsDto};
 use crate::infrastructure::errors::AppError;
+use crate::infrastructure::request_context::RequestContext;
 
 pub struct GetUsersUseCase<R: UserRepository> {
     user_repository: Arc<R>,
@@ -13,23 +20,151 @@ impl<R: UserRepository> GetUsersUseCase<R> {
         Self { user_repository }
     }
 
-    pub async fn execute(&self, role: Role) -> Result<Vec<UserResponseDto>, AppError> {
+    /// Returns one page of users plus the total number of matches
+    pub async fn execute(
+        &self,
+        role: Role,
+        filter: UserFilter,
+        sort: UserSort,
+        page: PageParams,
+    ) -> Result<(Vec<AdminUserDto>, i64), AppError> {
         // Authorize: Only Admin and SuperAdmin can get all users
         match role {
             Role::Admin | Role::SuperAdmin => {},
-            _ => return Err(AppError::InvalidToken), // Or create a Forbidden error
+            _ => return Err(AppError::Forbidden),
         }
 
-        let users = self.user_repository.find_all().await?;
+        let users = self.user_repository.find_page(&filter, sort, page).await?;
+        let total = self.user_repository.count(&filter).await?;
 
-        let user_dtos = users.into_iter().map(|u| UserResponseDto {
-            name: u.name,
-            phone: u.phone,
-            email: u.email,
-            role: u.role,
-            avatar_url: u.avatar_url,
-        }).collect();
+        let user_dtos = users.into_iter().map(AdminUserDto::from).collect();
 
-        Ok(user_dtos)
+        Ok((user_dtos, total))
+    }
+
+    /// Returns one keyset page of users; `cursor` is the opaque value from a previous page
+    pub async fn execute_keyset(
+        &self,
+        role: Role,
+        filter: UserFilter,
+        sort: UserSort,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<KeysetPage<AdminUserDto>, AppError> {
+        match role {
+            Role::Admin | Role::SuperAdmin => {},
+            _ => return Err(AppError::Forbidden),
+        }
+
+        let cursor = Cursor::decode_for(cursor, sort.field.as_str(), sort.direction)?;
+        let page = self.user_repository.find_keyset(&filter, sort, cursor.as_ref(), limit).await?;
+
+        Ok(KeysetPage {
+            items: page.items.into_iter().map(AdminUserDto::from).collect(),
+            next_cursor: page.next_cursor,
+            prev_cursor: page.prev_cursor,
+        })
+    }
+}
+
+/// Export Users Use Case - Admin + SuperAdmin; same filters as the list, without paging
+pub struct ExportUsersUseCase<R: UserRepository, A: AuditRepository> {
+    user_repository: Arc<R>,
+    audit_repository: Arc<A>,
+}
+
+impl<R: UserRepository, A: AuditRepository> ExportUsersUseCase<R, A> {
+    pub fn new(user_repository: Arc<R>, audit_repository: Arc<A>) -> Self {
+        Self { user_repository, audit_repository }
+    }
+
+    pub async fn execute(
+        &self,
+        requester_id: Uuid,
+        role: Role,
+        filter: UserFilter,
+        sort: UserSort,
+        format: &str,
+        ctx: &RequestContext,
+    ) -> Result<BoxStream<'static, Result<AdminUserDto, AppError>>, AppError> {
+        match role {
+            Role::Admin | Role::SuperAdmin => {},
+            _ => return Err(AppError::Forbidden),
+        }
+
+        // Recorded up front: the stream may be cut short by the client at any point
+        self.audit_repository.create(
+            &AuditEvent::new(Some(requester_id), None, AuditAction::UsersExported, Some(json!({ "format": format, "filter": filter })))
+                .with_context(ctx),
+        ).await?;
+
+        Ok(self.user_repository
+            .stream(filter, sort)
+            .map(|row| row.map(AdminUserDto::from))
+            .boxed())
+    }
+}
+
+/// Get User Use Case - Admin + SuperAdmin
+pub struct GetUserUseCase<R: UserRepository> {
+    user_repository: Arc<R>,
+}
+
+impl<R: UserRepository> GetUserUseCase<R> {
+    pub fn new(user_repository: Arc<R>) -> Self {
+        Self { user_repository }
+    }
+
+    pub async fn execute(&self, role: Role, user_id: Uuid) -> Result<AdminUserDto, AppError> {
+        match role {
+            Role::Admin | Role::SuperAdmin => {},
+            _ => return Err(AppError::Forbidden),
+        }
+
+        let user = self.user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        Ok(AdminUserDto::from(user))
+    }
+}
+
+/// Search Users Use Case - Admin + SuperAdmin
+pub struct SearchUsersUseCase<R: UserRepository> {
+    user_repository: Arc<R>,
+}
+
+impl<R: UserRepository> SearchUsersUseCase<R> {
+    pub fn new(user_repository: Arc<R>) -> Self {
+        Self { user_repository }
+    }
+
+    pub async fn execute(&self, role: Role, query: &str, limit: i64) -> Result<Vec<UserSearchResultDto>, AppError> {
+        match role {
+            Role::Admin | Role::SuperAdmin => {},
+            _ => return Err(AppError::Forbidden),
+        }
+
+        if query.trim().is_empty() {
+            return Err(AppError::ValidationError("Search query is required".to_string()));
+        }
+
+        let hits = self.user_repository.search(query, limit).await?;
+
+        Ok(hits.into_iter().map(|hit| UserSearchResultDto {
+            id: hit.user.id,
+            name: hit.user.name,
+            phone: hit.user.phone,
+            email: hit.user.email,
+            role: hit.user.role,
+            avatar_url: hit.user.avatar_url,
+            score: hit.rank,
+            highlights: UserSearchHighlightsDto {
+                name: hit.name_highlight,
+                email: hit.email_highlight,
+                phone: hit.phone_highlight,
+            },
+        }).collect())
     }
 }
@@ -0,0 +1,79 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+
+/// Value of the cookie `name` sent with the request
+pub fn get<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Which cross-site requests a cookie is sent with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSite {
+    /// Only requests from our own site
+    Strict,
+    /// Top-level navigations from other sites, but not their POSTs
+    Lax,
+    /// Every cross-site request, e.g. a provider POSTing back to us; requires `Secure`
+    None,
+}
+
+/// `Set-Cookie` value for an HttpOnly cookie. `Secure` is added when `secure` is
+/// set (callers pass whether `APP_BASE_URL` is https), and always for
+/// `SameSite::None` (browsers accept it on http://localhost). A `max_age_secs`
+/// of 0 deletes the cookie.
+pub fn set(name: &str, value: &str, path: &str, max_age_secs: i64, secure: bool, same_site: SameSite) -> HeaderValue {
+    build(name, value, path, max_age_secs, secure, same_site, true)
+}
+
+/// Like `set`, but readable from JavaScript; only for values that are not secrets
+/// on their own, such as CSRF tokens
+pub fn set_readable(name: &str, value: &str, path: &str, max_age_secs: i64, secure: bool, same_site: SameSite) -> HeaderValue {
+    build(name, value, path, max_age_secs, secure, same_site, false)
+}
+
+fn build(name: &str, value: &str, path: &str, max_age_secs: i64, secure: bool, same_site: SameSite, http_only: bool) -> HeaderValue {
+    let same_site_value = match same_site {
+        SameSite::Strict => "Strict",
+        SameSite::Lax => "Lax",
+        SameSite::None => "None",
+    };
+    let mut cookie = format!("{}={}; Path={}; Max-Age={}", name, value, path, max_age_secs);
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    cookie.push_str(&format!("; SameSite={}", same_site_value));
+    if secure || same_site == SameSite::None {
+        cookie.push_str("; Secure");
+    }
+    HeaderValue::from_str(&cookie).expect("cookie names and values are URL-safe")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secure_only_when_asked_or_same_site_none() {
+        let http = set("session", "value", "/", 60, false, SameSite::Lax);
+        let https = set("session", "value", "/", 60, true, SameSite::Lax);
+        let cross_site = set("session", "value", "/", 60, false, SameSite::None);
+
+        assert_eq!(http, "session=value; Path=/; Max-Age=60; HttpOnly; SameSite=Lax");
+        assert_eq!(https, "session=value; Path=/; Max-Age=60; HttpOnly; SameSite=Lax; Secure");
+        assert_eq!(cross_site, "session=value; Path=/; Max-Age=60; HttpOnly; SameSite=None; Secure");
+    }
+
+    #[test]
+    fn readable_cookies_are_not_httponly() {
+        let cookie = set_readable("csrf", "token", "/", 0, true, SameSite::Strict);
+
+        assert_eq!(cookie, "csrf=token; Path=/; Max-Age=0; SameSite=Strict; Secure");
+    }
+}
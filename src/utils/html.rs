@@ -0,0 +1,9 @@
+/// Escapes text for use in HTML content and quoted attribute values
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
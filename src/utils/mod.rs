@@ -1,2 +1,4 @@
+pub mod cookie;
+pub mod html;
 pub mod response;
 pub mod validation;
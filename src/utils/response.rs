@@ -1,5 +1,6 @@
-use axum::{response::IntoResponse, Json};
+use axum::{http::Uri, response::IntoResponse, Json};
 use serde::Serialize;
+use crate::domain::repositories::pagination::{KeysetPage, PageParams};
 
 #[derive(Serialize)]
 pub struct ApiResponse<T: Serialize> {
@@ -11,6 +12,110 @@ pub struct ApiResponse<T: Serialize> {
 pub struct Meta {
     pub status: String,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+/// Pagination metadata: page numbers for offset listings, opaque cursors for keyset ones
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Pagination {
+    Page(PagePagination),
+    Cursor(CursorPagination),
+}
+
+#[derive(Serialize)]
+pub struct PagePagination {
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub total_pages: i64,
+    pub links: PaginationLinks,
+}
+
+#[derive(Serialize)]
+pub struct CursorPagination {
+    pub per_page: i64,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub links: CursorLinks,
+}
+
+#[derive(Serialize)]
+pub struct PaginationLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub first: String,
+    pub last: String,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CursorLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+impl Pagination {
+    /// Builds offset metadata with links that reuse the request's own query string
+    pub fn new(total: i64, params: PageParams, uri: &Uri) -> Self {
+        let total_pages = ((total + params.per_page - 1) / params.per_page).max(1);
+        let link = |page: i64| {
+            with_query(uri, &["page", "per_page"], &[("page", page.to_string()), ("per_page", params.per_page.to_string())])
+        };
+
+        Pagination::Page(PagePagination {
+            total,
+            page: params.page,
+            per_page: params.per_page,
+            total_pages,
+            links: PaginationLinks {
+                current: link(params.page),
+                first: link(1),
+                last: link(total_pages),
+                next: (params.page < total_pages).then(|| link(params.page + 1)),
+                prev: (params.page > 1).then(|| link((params.page - 1).min(total_pages))),
+            },
+        })
+    }
+
+    /// Builds keyset metadata; links carry the next/prev cursor in place of the current one
+    pub fn cursor<T>(page: &KeysetPage<T>, per_page: i64, uri: &Uri) -> Self {
+        let link = |cursor: &String| {
+            with_query(uri, &["cursor", "per_page"], &[("cursor", cursor.clone()), ("per_page", per_page.to_string())])
+        };
+
+        Pagination::Cursor(CursorPagination {
+            per_page,
+            next_cursor: page.next_cursor.clone(),
+            prev_cursor: page.prev_cursor.clone(),
+            links: CursorLinks {
+                current: uri.to_string(),
+                next: page.next_cursor.as_ref().map(link),
+                prev: page.prev_cursor.as_ref().map(link),
+            },
+        })
+    }
+}
+
+/// Rebuilds `uri` with the `replace` keys removed and `params` appended
+fn with_query(uri: &Uri, replace: &[&str], params: &[(&str, String)]) -> String {
+    let mut query: Vec<String> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && !replace.contains(&key)
+        })
+        .map(|pair| pair.to_string())
+        .collect();
+    query.extend(params.iter().map(|(key, value)| format!("{}={}", key, urlencoding::encode(value))));
+
+    format!("{}?{}", uri.path(), query.join("&"))
 }
 
 pub fn success_response<T: Serialize>(data: T, message: impl Into<String>) -> impl IntoResponse {
@@ -18,6 +123,18 @@ pub fn success_response<T: Serialize>(data: T, message: impl Into<String>) -> im
         meta: Meta {
             status: "success".to_string(),
             message: message.into(),
+            pagination: None,
+        },
+        results: data,
+    })
+}
+
+pub fn paginated_response<T: Serialize>(data: Vec<T>, message: impl Into<String>, pagination: Pagination) -> impl IntoResponse {
+    Json(ApiResponse {
+        meta: Meta {
+            status: "success".to_string(),
+            message: message.into(),
+            pagination: Some(pagination),
         },
         results: data,
     })
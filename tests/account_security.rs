@@ -0,0 +1,171 @@
+// Self-service account changes that must end other sign-ins
+
+mod support;
+
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::dtos::RequestEmailChangeDto;
+use rust_axum::infrastructure::errors::AppError;
+use rust_axum::infrastructure::mail::{EmailMessage, Mailer};
+use rust_axum::infrastructure::request_context::RequestContext;
+use rust_axum::usecases::email_change::RequestEmailChangeUseCase;
+use support::{results, TestApp};
+
+fn token<'a>(tokens: &'a Value, name: &str) -> &'a str {
+    tokens[name].as_str().expect("token")
+}
+
+async fn refresh_status(app: &TestApp, tokens: &Value) -> StatusCode {
+    app.post_json("/auth/refresh", json!({ "refresh_token": token(tokens, "refresh_token") }))
+        .await
+        .status()
+}
+
+#[sqlx::test]
+async fn changing_the_password_signs_out_every_other_session(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let this_device = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let stolen = app.sign_in("owner@example.com", "correct-horse-battery").await;
+
+    let response = app.client
+        .put(app.url("/me/password"))
+        .bearer_auth(token(&this_device, "access_token"))
+        .json(&json!({ "current_password": "correct-horse-battery", "new_password": "new-horse-battery" }))
+        .send()
+        .await
+        .expect("PUT /me/password");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(refresh_status(&app, &stolen).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(refresh_status(&app, &this_device).await, StatusCode::OK);
+}
+
+/// Keeps outgoing email so tests can follow the links in it
+#[derive(Default)]
+struct RecordingMailer {
+    sent: Mutex<Vec<EmailMessage>>,
+}
+
+#[async_trait]
+impl Mailer for RecordingMailer {
+    async fn send(&self, message: &EmailMessage) -> Result<(), AppError> {
+        self.sent.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+impl RecordingMailer {
+    /// Token of the last link emailed to `to`
+    fn token_sent_to(&self, to: &str) -> String {
+        let sent = self.sent.lock().unwrap();
+        let body = &sent.iter().rev().find(|message| message.to == to).expect("email sent").body;
+        let start = body.find("token=").expect("link in email") + "token=".len();
+        body[start..].split_whitespace().next().expect("token").to_string()
+    }
+}
+
+async fn user_id(app: &TestApp, email: &str) -> Uuid {
+    sqlx::query_scalar("SELECT id FROM users WHERE email = $1").bind(email).fetch_one(&app.pool).await.expect("user row")
+}
+
+async fn email_of(app: &TestApp, id: Uuid) -> String {
+    sqlx::query_scalar("SELECT email FROM users WHERE id = $1").bind(id).fetch_one(&app.pool).await.expect("user row")
+}
+
+/// Starts a change to `new_email` the way `POST /me/email` does, keeping the emails
+async fn request_change(app: &TestApp, mailer: &Arc<RecordingMailer>, id: Uuid, new_email: &str, password: &str) {
+    let usecase = RequestEmailChangeUseCase::new(
+        app.state.user_repository.clone(),
+        app.state.email_change_repository.clone(),
+        app.state.session_repository.clone(),
+        app.state.audit_repository.clone(),
+        mailer.clone(),
+        app.state.app_base_url.clone(),
+    );
+    let dto = RequestEmailChangeDto { new_email: new_email.to_string(), current_password: Some(password.to_string()) };
+    usecase.execute(id, None, dto, &RequestContext::default()).await.expect("request email change");
+}
+
+/// Presses the button on the page behind an emailed link
+async fn press(app: &TestApp, action: &str, token: &str) -> StatusCode {
+    app.client
+        .post(app.url(&format!("/auth/email-change/{}", action)))
+        .form(&[("token", token)])
+        .send()
+        .await
+        .expect("POST email change link")
+        .status()
+}
+
+#[sqlx::test]
+async fn opening_an_emailed_link_changes_nothing_until_its_button_is_pressed(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let id = user_id(&app, "owner@example.com").await;
+    let mailer = Arc::new(RecordingMailer::default());
+    request_change(&app, &mailer, id, "new@example.com", "correct-horse-battery").await;
+    let token = mailer.token_sent_to("new@example.com");
+
+    let page = app.client
+        .get(app.url(&format!("/auth/email-change/confirm?token={}", token)))
+        .send()
+        .await
+        .expect("GET confirm link");
+    assert_eq!(page.status(), StatusCode::OK);
+    assert!(page.text().await.expect("page").contains("method=\"post\""));
+    assert_eq!(email_of(&app, id).await, "owner@example.com");
+
+    assert_eq!(press(&app, "confirm", &token).await, StatusCode::OK);
+    assert_eq!(email_of(&app, id).await, "new@example.com");
+}
+
+#[sqlx::test]
+async fn reverting_restores_the_original_address_after_chained_changes(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let hijacked = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let id = user_id(&app, "owner@example.com").await;
+    let mailer = Arc::new(RecordingMailer::default());
+
+    // Someone with the account changes the email twice, so the second revert link goes to them
+    request_change(&app, &mailer, id, "attacker-1@example.com", "correct-horse-battery").await;
+    assert_eq!(press(&app, "confirm", &mailer.token_sent_to("attacker-1@example.com")).await, StatusCode::OK);
+    request_change(&app, &mailer, id, "attacker-2@example.com", "correct-horse-battery").await;
+    assert_eq!(press(&app, "confirm", &mailer.token_sent_to("attacker-2@example.com")).await, StatusCode::OK);
+    let attacker_revert = mailer.token_sent_to("attacker-1@example.com");
+
+    assert_eq!(press(&app, "revert", &mailer.token_sent_to("owner@example.com")).await, StatusCode::OK);
+
+    assert_eq!(email_of(&app, id).await, "owner@example.com");
+    assert_eq!(refresh_status(&app, &hijacked).await, StatusCode::UNAUTHORIZED);
+    // The later change's revert link cannot take the address back
+    assert_eq!(press(&app, "revert", &attacker_revert).await, StatusCode::BAD_REQUEST);
+    assert_eq!(email_of(&app, id).await, "owner@example.com");
+}
+
+#[sqlx::test]
+async fn users_without_a_password_must_have_signed_in_recently_to_change_email(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = results(app.oauth_sign_in("github", "octocat").await).await;
+    let change_email = || async {
+        app.client
+            .post(app.url("/me/email"))
+            .bearer_auth(token(&tokens, "access_token"))
+            .json(&json!({ "new_email": "new-octocat@example.com" }))
+            .send()
+            .await
+            .expect("POST /me/email")
+            .status()
+    };
+
+    assert_eq!(change_email().await, StatusCode::OK);
+
+    sqlx::query("UPDATE sessions SET created_at = NOW() - INTERVAL '1 hour'")
+        .execute(&app.pool)
+        .await
+        .expect("age the session");
+    assert_eq!(change_email().await, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,158 @@
+// The audit log: append-only in the database, readable by SuperAdmins only,
+// filtered and paged through GET /audit-events
+
+mod support;
+
+use chrono::Utc;
+use reqwest::{Response, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use support::TestApp;
+
+const PASSWORD: &str = "correct-horse-battery";
+
+fn token(tokens: &Value) -> &str {
+    tokens["access_token"].as_str().expect("access token")
+}
+
+async fn user_with_role(app: &TestApp, email: &str, role: &str) -> Value {
+    app.register(role, email, PASSWORD).await;
+    app.set_role(email, role).await;
+    app.sign_in(email, PASSWORD).await
+}
+
+async fn get_events(app: &TestApp, tokens: &Value, query: &[(&str, String)]) -> Response {
+    app.client
+        .get(app.url("/audit-events"))
+        .query(query)
+        .bearer_auth(token(tokens))
+        .send()
+        .await
+        .expect("GET /audit-events")
+}
+
+/// The whole response body of a successful listing
+async fn events(app: &TestApp, tokens: &Value, query: &[(&str, String)]) -> Value {
+    let response = get_events(app, tokens, query).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.expect("JSON body")
+}
+
+fn ids(body: &Value) -> Vec<String> {
+    body["results"]
+        .as_array()
+        .expect("events")
+        .iter()
+        .map(|event| event["id"].as_str().expect("id").to_string())
+        .collect()
+}
+
+async fn fail_sign_in(app: &TestApp, email: &str) {
+    let response = app.post_json("/auth/sign-in", json!({ "email": email, "password": "wrong-password" })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test]
+async fn audit_events_cannot_be_changed_or_deleted(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Owner", "owner@example.com", PASSWORD).await;
+    let count = || async {
+        sqlx::query_scalar::<_, i64>("SELECT count(*) FROM audit_events").fetch_one(&app.pool).await.expect("count")
+    };
+    let before = count().await;
+    assert!(before > 0);
+
+    for statement in ["UPDATE audit_events SET action = 'user_deleted'", "DELETE FROM audit_events"] {
+        let error = sqlx::query(statement).execute(&app.pool).await.expect_err("audit_events was modified");
+        assert!(error.to_string().contains("audit_events is append-only"), "{}: {}", statement, error);
+    }
+
+    assert_eq!(count().await, before);
+    let changed: i64 = sqlx::query_scalar("SELECT count(*) FROM audit_events WHERE action = 'user_deleted'")
+        .fetch_one(&app.pool)
+        .await
+        .expect("count");
+    assert_eq!(changed, 0);
+}
+
+#[sqlx::test]
+async fn only_a_superadmin_reads_the_audit_log(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    for role in ["Admin", "Mentor", "User"] {
+        let tokens = user_with_role(&app, &format!("{}@example.com", role.to_lowercase()), role).await;
+        for query in [vec![], vec![("cursor", String::new())]] {
+            let response = get_events(&app, &tokens, &query).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} read the audit log", role);
+        }
+    }
+
+    let root = user_with_role(&app, "root@example.com", "SuperAdmin").await;
+    assert!(!ids(&events(&app, &root, &[]).await).is_empty());
+}
+
+#[sqlx::test]
+async fn filters_narrow_the_events(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let root = user_with_role(&app, "root@example.com", "SuperAdmin").await;
+    app.register("Alice", "alice@example.com", PASSWORD).await;
+    app.register("Bob", "bob@example.com", PASSWORD).await;
+    let (alice_id, bob_id) = (app.user_id("alice@example.com").await, app.user_id("bob@example.com").await);
+
+    fail_sign_in(&app, "alice@example.com").await;
+    fail_sign_in(&app, "alice@example.com").await;
+    let between = Utc::now();
+    fail_sign_in(&app, "bob@example.com").await;
+
+    let failed = ("action", "login_failed".to_string());
+    let alices_failures = events(&app, &root, &[failed.clone(), ("target_id", alice_id.to_string())]).await;
+    assert_eq!(alices_failures["meta"]["pagination"]["total"], 2);
+    for event in alices_failures["results"].as_array().expect("events") {
+        assert_eq!(event["action"], "login_failed");
+        assert_eq!(event["target_id"], alice_id.to_string());
+        assert!(event["actor_id"].is_null());
+    }
+
+    let later = events(&app, &root, &[failed.clone(), ("from", between.to_rfc3339())]).await;
+    assert_eq!(later["meta"]["pagination"]["total"], 1);
+    assert_eq!(later["results"][0]["target_id"], bob_id.to_string());
+    let earlier = events(&app, &root, &[failed.clone(), ("to", between.to_rfc3339())]).await;
+    assert_eq!(earlier["meta"]["pagination"]["total"], 2);
+
+    // Bob's own sign-in, not the failure, has him as the actor
+    let by_bob = events(&app, &root, &[("actor_id", bob_id.to_string())]).await;
+    let actions: Vec<&Value> = by_bob["results"].as_array().expect("events").iter().map(|event| &event["action"]).collect();
+    assert!(!actions.is_empty());
+    assert!(actions.iter().all(|action| *action != "login_failed"), "{:?}", actions);
+}
+
+#[sqlx::test]
+async fn the_cursor_walks_every_event_once(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let root = user_with_role(&app, "root@example.com", "SuperAdmin").await;
+    for _ in 0..3 {
+        app.sign_in("root@example.com", PASSWORD).await;
+        fail_sign_in(&app, "root@example.com").await;
+    }
+    let all = ids(&events(&app, &root, &[("per_page", "100".to_string())]).await);
+    assert!(all.len() > 6);
+
+    let mut walked = Vec::new();
+    let mut pages = Vec::new();
+    let mut cursor = String::new();
+    loop {
+        let page = events(&app, &root, &[("cursor", cursor.clone()), ("per_page", "2".to_string())]).await;
+        walked.extend(ids(&page));
+        pages.push(page.clone());
+        match page["meta"]["pagination"]["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(walked, all);
+
+    // Back from the second page to the first
+    let prev = pages[1]["meta"]["pagination"]["prev_cursor"].as_str().expect("prev cursor");
+    let first = events(&app, &root, &[("cursor", prev.to_string()), ("per_page", "2".to_string())]).await;
+    assert_eq!(ids(&first), ids(&pages[0]));
+}
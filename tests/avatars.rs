@@ -0,0 +1,67 @@
+// Where avatar URLs may come from
+
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::repositories::user_repository::UserRepository;
+use rust_axum::usecases::avatar::MirrorAvatarUseCase;
+use support::{results, TestApp};
+
+/// An HTTP listener standing in for an internal service; counts the connections it gets
+async fn internal_service() -> (String, Arc<AtomicUsize>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind internal service");
+    let url = format!("http://{}/admin/secrets", listener.local_addr().expect("address"));
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    tokio::spawn(async move {
+        while let Ok((_stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    (url, hits)
+}
+
+#[sqlx::test]
+async fn the_profile_endpoint_does_not_set_an_avatar_url(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+
+    let response = app.client
+        .patch(app.url("/me"))
+        .bearer_auth(tokens["access_token"].as_str().expect("access token"))
+        .json(&json!({ "name": "Renamed", "avatar_url": "http://169.254.169.254/latest/meta-data/" }))
+        .send()
+        .await
+        .expect("PATCH /me");
+    assert_eq!(response.status(), StatusCode::OK);
+    let profile = results(response).await;
+    assert_eq!(profile["name"], "Renamed");
+    assert!(profile["avatar_url"].is_null(), "{:#}", profile);
+}
+
+#[sqlx::test]
+async fn mirroring_only_fetches_the_picture_the_provider_just_returned(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let (internal_url, hits) = internal_service().await;
+    let user_id: Uuid = sqlx::query_scalar("UPDATE users SET avatar_url = $1 WHERE email = 'owner@example.com' RETURNING id")
+        .bind(&internal_url)
+        .fetch_one(&app.pool)
+        .await
+        .expect("store an avatar URL");
+    let user = app.state.user_repository.find_by_id(user_id).await.expect("find user").expect("user");
+
+    let mirror = MirrorAvatarUseCase::new(app.state.user_repository.clone(), app.state.blob_store.clone(), app.state.app_base_url.clone());
+    mirror.execute(&user, None).await.expect("nothing to mirror");
+    mirror.execute(&user, Some("https://avatars.example.com/u/1")).await.expect("not the user's avatar");
+
+    assert_eq!(hits.load(Ordering::SeqCst), 0, "the stored avatar URL was fetched");
+    let user = app.state.user_repository.find_by_id(user_id).await.expect("find user").expect("user");
+    assert_eq!(user.avatar_url.as_deref(), Some(internal_url.as_str()));
+    assert!(user.avatar_key.is_none());
+}
@@ -0,0 +1,83 @@
+// What a user's GDPR data export contains
+
+mod support;
+
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::repositories::data_export_repository::DataExportRepository;
+use rust_axum::usecases::data_privacy::GenerateDataExportUseCase;
+use support::TestApp;
+
+async fn user_id(app: &TestApp, email: &str) -> Uuid {
+    sqlx::query_scalar("SELECT id FROM users WHERE email = $1").bind(email).fetch_one(&app.pool).await.expect("user row")
+}
+
+/// Builds an export for `user_id` the way the background job does and returns the archive
+async fn export_of(app: &TestApp, user_id: Uuid) -> Value {
+    let export_dir = std::env::temp_dir().join(format!("rust-axum-test-exports-{}", Uuid::new_v4()));
+    let usecase = GenerateDataExportUseCase::new(
+        app.state.user_repository.clone(),
+        app.state.data_export_repository.clone(),
+        app.state.email_change_repository.clone(),
+        app.state.session_repository.clone(),
+        app.state.audit_repository.clone(),
+        export_dir.clone(),
+    );
+    let export = app.state.data_export_repository.create(user_id).await.expect("queue export");
+    assert!(usecase.execute_next().await.expect("build export"));
+
+    let archive = std::fs::read(export_dir.join(format!("{}.json", export.id))).expect("read archive");
+    std::fs::remove_dir_all(&export_dir).expect("remove export dir");
+    serde_json::from_slice(&archive).expect("archive is JSON")
+}
+
+#[sqlx::test]
+async fn a_super_admins_export_leaves_out_what_they_did_to_other_users(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Admin", "admin@example.com", "correct-horse-battery").await;
+    app.register("Member", "member@example.com", "correct-horse-battery").await;
+    let admin_id = user_id(&app, "admin@example.com").await;
+    let member_id = user_id(&app, "member@example.com").await;
+    sqlx::query("UPDATE users SET role = 'SuperAdmin' WHERE id = $1").bind(admin_id).execute(&app.pool).await.expect("promote");
+    let admin = app.sign_in("admin@example.com", "correct-horse-battery").await;
+
+    let response = app.client
+        .put(app.url(&format!("/users/{}", member_id)))
+        .bearer_auth(admin["access_token"].as_str().expect("access token"))
+        .json(&json!({ "name": "Member Renamed", "phone": "+6281234567890" }))
+        .send()
+        .await
+        .expect("PUT /users/{id}");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let admin_export = export_of(&app, admin_id).await;
+    let events = admin_export["audit_events"].as_array().expect("audit events");
+    assert!(!events.is_empty(), "the admin's own sign-ins are exported");
+    assert!(
+        events.iter().all(|event| event["target_id"] == json!(admin_id)),
+        "export holds events about other users: {:#}",
+        admin_export["audit_events"],
+    );
+    assert!(!admin_export.to_string().contains("+6281234567890"));
+
+    let member_export = export_of(&app, member_id).await;
+    let events = member_export["audit_events"].as_array().expect("audit events");
+    assert!(events.iter().any(|event| event["action"] == "user_updated"), "{:#}", member_export["audit_events"]);
+}
+
+#[sqlx::test]
+async fn the_export_lists_every_session_of_the_user(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    app.sign_in("owner@example.com", "correct-horse-battery").await;
+    app.register("Other", "other@example.com", "correct-horse-battery").await;
+    let owner_id = user_id(&app, "owner@example.com").await;
+
+    let export = export_of(&app, owner_id).await;
+    let sessions = export["sessions"].as_array().expect("sessions");
+    assert_eq!(sessions.len(), 2, "{:#}", export["sessions"]);
+    assert!(sessions.iter().all(|session| session["user_id"] == json!(owner_id)));
+    assert!(sessions.iter().all(|session| session["auth_method"] == "password" && session["ip_address"].is_string()));
+}
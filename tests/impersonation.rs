@@ -0,0 +1,176 @@
+// Impersonation: who may start it, what the borrowed session cannot do, and
+// the audit trail it leaves
+
+mod support;
+
+use reqwest::header::COOKIE;
+use reqwest::{Method, Response, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::repositories::user_repository::UserRepository;
+use support::{results, TestApp, TestOptions};
+
+const PASSWORD: &str = "correct-horse-battery";
+
+fn token(tokens: &Value) -> &str {
+    tokens["access_token"].as_str().expect("access token")
+}
+
+/// Signs up `email` with `role` and returns fresh tokens carrying it
+async fn user_with_role(app: &TestApp, email: &str, role: &str) -> Value {
+    app.register(role, email, PASSWORD).await;
+    app.set_role(email, role).await;
+    app.sign_in(email, PASSWORD).await
+}
+
+async fn impersonate(app: &TestApp, tokens: &Value, user_id: Uuid) -> Response {
+    app.client
+        .post(app.url(&format!("/users/{}/impersonate", user_id)))
+        .bearer_auth(token(tokens))
+        .send()
+        .await
+        .expect("POST /users/{id}/impersonate")
+}
+
+/// A SuperAdmin, a User, and an impersonation token for the User
+async fn impersonating(app: &TestApp) -> (Uuid, Uuid, Value) {
+    let root = user_with_role(app, "root@example.com", "SuperAdmin").await;
+    app.register("User", "user@example.com", PASSWORD).await;
+    let user_id = app.user_id("user@example.com").await;
+    let session = results(impersonate(app, &root, user_id).await).await;
+    (app.user_id("root@example.com").await, user_id, session)
+}
+
+async fn audit_count(app: &TestApp, action: &str, actor_id: Uuid, target_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM audit_events WHERE action = $1 AND actor_id = $2 AND target_id = $3")
+        .bind(action)
+        .bind(actor_id)
+        .bind(target_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("count audit events")
+}
+
+#[sqlx::test]
+async fn a_superadmin_acts_as_the_user(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let (root_id, user_id, session) = impersonating(&app).await;
+
+    assert_eq!(session["impersonated_user_id"], user_id.to_string());
+    assert_eq!(session["actor_id"], root_id.to_string());
+    assert!(session.get("refresh_token").is_none());
+    let response = app.client.get(app.url("/me")).bearer_auth(token(&session)).send().await.expect("GET /me");
+    assert_eq!(results(response).await["email"], "user@example.com");
+    assert_eq!(audit_count(&app, "impersonation_started", root_id, user_id).await, 1);
+}
+
+#[sqlx::test]
+async fn only_a_superadmin_can_impersonate(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("User", "user@example.com", PASSWORD).await;
+    let user_id = app.user_id("user@example.com").await;
+
+    for role in ["Admin", "Mentor", "User"] {
+        let requester = user_with_role(&app, &format!("{}-requester@example.com", role.to_lowercase()), role).await;
+        let response = impersonate(&app, &requester, user_id).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} impersonated", role);
+    }
+}
+
+#[sqlx::test]
+async fn a_superadmin_cannot_be_impersonated(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let root = user_with_role(&app, "root@example.com", "SuperAdmin").await;
+    user_with_role(&app, "other-root@example.com", "SuperAdmin").await;
+
+    let response = impersonate(&app, &root, app.user_id("other-root@example.com").await).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = impersonate(&app, &root, app.user_id("root@example.com").await).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn an_impersonated_session_cannot_change_credentials_or_delete_the_account(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let (_, user_id, session) = impersonating(&app).await;
+
+    let requests = [
+        (Method::PUT, "/me/password", json!({ "current_password": PASSWORD, "new_password": "new-horse-battery" })),
+        (Method::DELETE, "/me", json!({ "password": PASSWORD })),
+        (Method::POST, "/me/email", json!({ "new_email": "taken-over@example.com", "password": PASSWORD })),
+        (Method::POST, "/me/phone/verification", json!({})),
+        (Method::POST, "/me/phone/verification/confirm", json!({ "code": "123456" })),
+        (Method::POST, &format!("/users/{}/impersonate", user_id), json!({})),
+    ];
+    for (method, path, body) in requests {
+        let response = app.client
+            .request(method.clone(), app.url(path))
+            .bearer_auth(token(&session))
+            .json(&body)
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} {} was allowed", method, path);
+    }
+
+    // The account is untouched and the password still works
+    app.sign_in("user@example.com", PASSWORD).await;
+}
+
+#[sqlx::test]
+async fn every_impersonated_request_is_audited(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let (root_id, user_id, session) = impersonating(&app).await;
+
+    for _ in 0..2 {
+        app.client.get(app.url("/me")).bearer_auth(token(&session)).send().await.expect("GET /me");
+    }
+    app.client
+        .put(app.url("/me/password"))
+        .bearer_auth(token(&session))
+        .json(&json!({ "current_password": PASSWORD, "new_password": "new-horse-battery" }))
+        .send()
+        .await
+        .expect("PUT /me/password");
+
+    let events: Vec<Value> = sqlx::query_scalar(
+        "SELECT metadata FROM audit_events WHERE action = 'impersonated_request' AND actor_id = $1 AND target_id = $2 ORDER BY created_at",
+    )
+    .bind(root_id)
+    .bind(user_id)
+    .fetch_all(&app.pool)
+    .await
+    .expect("audit events");
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0], json!({ "method": "GET", "path": "/api/v1/me", "status": 200 }));
+    assert_eq!(events[2]["method"], "PUT");
+    assert_eq!(events[2]["status"], 403);
+
+    // The SuperAdmin's own requests are not
+    let root = app.sign_in("root@example.com", PASSWORD).await;
+    app.client.get(app.url("/me")).bearer_auth(token(&root)).send().await.expect("GET /me");
+    assert_eq!(audit_count(&app, "impersonated_request", root_id, user_id).await, 3);
+}
+
+#[sqlx::test]
+async fn impersonated_requests_with_the_session_cookie_are_audited(pool: PgPool) {
+    let app = TestApp::spawn_with(pool, TestOptions { session_cookies: true, ..Default::default() }).await;
+    // Sign-ins return no tokens in cookie mode, so the impersonation token is minted directly
+    app.register("Root", "root@example.com", PASSWORD).await;
+    app.register("User", "user@example.com", PASSWORD).await;
+    let (root_id, user_id) = (app.user_id("root@example.com").await, app.user_id("user@example.com").await);
+    let user = app.state.user_repository.find_by_id(user_id).await.expect("find user").expect("user");
+    let impersonation_token = app.state.jwt_service.generate_impersonation_token(&user, root_id).expect("token");
+
+    let response = app.client
+        .get(app.url("/me"))
+        .header(COOKIE, format!("access_token={}", impersonation_token))
+        .send()
+        .await
+        .expect("GET /me");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(audit_count(&app, "impersonated_request", root_id, user_id).await, 1);
+}
@@ -0,0 +1,165 @@
+// Keyset pagination against the real users table: walks stay stable while rows
+// are inserted between page fetches, and prev cursors lead back to the same pages.
+
+use std::collections::HashSet;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::entities::user::User;
+use rust_axum::domain::repositories::pagination::{Cursor, CursorKey, KeysetPage, SortDirection};
+use rust_axum::domain::repositories::user_repository::{UserFilter, UserRepository, UserSort, UserSortField};
+use rust_axum::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+
+const PAGE_SIZE: i64 = 4;
+
+async fn insert_user(pool: &PgPool, name: &str, created_at: DateTime<Utc>) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO users (name, email, password_hash, role, created_at) VALUES ($1, $2, 'x', 'User', $3) RETURNING id",
+    )
+    .bind(name)
+    .bind(format!("{}-{}@example.com", name, Uuid::new_v4()))
+    .bind(created_at)
+    .fetch_one(pool)
+    .await
+    .expect("insert user")
+}
+
+async fn page(repository: &PostgresUserRepository, sort: UserSort, cursor: Option<&str>) -> KeysetPage<User> {
+    let cursor = cursor.map(|value| Cursor::decode(value).expect("cursor from a previous page"));
+    repository
+        .find_keyset(&UserFilter::default(), sort, cursor.as_ref(), PAGE_SIZE)
+        .await
+        .expect("fetch page")
+}
+
+fn ids(page: &KeysetPage<User>) -> Vec<Uuid> {
+    page.items.iter().map(|user| user.id).collect()
+}
+
+/// Follows `next_cursor` to the end, calling `between` after every page
+async fn walk(
+    repository: &PostgresUserRepository,
+    sort: UserSort,
+    mut between: impl AsyncFnMut(usize),
+) -> Vec<User> {
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    for fetched in 1.. {
+        let page = page(repository, sort, cursor.as_deref()).await;
+        seen.extend(page.items);
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+        between(fetched).await;
+        assert!(fetched < 100, "pagination never ended");
+    }
+    seen
+}
+
+fn assert_no_duplicates(users: &[User]) {
+    let unique: HashSet<Uuid> = users.iter().map(|user| user.id).collect();
+    assert_eq!(unique.len(), users.len(), "a row was returned twice");
+}
+
+#[sqlx::test]
+async fn created_at_walk_is_stable_under_concurrent_inserts(pool: PgPool) {
+    let repository = PostgresUserRepository::new(pool.clone());
+    let base = Utc::now() - Duration::days(1);
+    let mut original = HashSet::new();
+    for i in 0..9 {
+        original.insert(insert_user(&pool, &format!("user{}", i), base + Duration::minutes(i)).await);
+    }
+    // Rows sharing a timestamp are told apart by id
+    for i in 0..6 {
+        original.insert(insert_user(&pool, &format!("tie{}", i), base + Duration::minutes(4)).await);
+    }
+    let sort = UserSort { field: UserSortField::CreatedAt, direction: SortDirection::Desc };
+
+    let seen = walk(&repository, sort, async |fetched| {
+        // Newer than anything listed so far, so behind the walk
+        insert_user(&pool, &format!("new{}", fetched), Utc::now()).await;
+    })
+    .await;
+
+    assert_no_duplicates(&seen);
+    let seen_ids: HashSet<Uuid> = seen.iter().map(|user| user.id).collect();
+    assert_eq!(seen_ids, original, "rows were skipped or new rows leaked into the walk");
+    let keys: Vec<(DateTime<Utc>, Uuid)> = seen.iter().map(|user| (user.created_at.expect("created_at"), user.id)).collect();
+    assert!(keys.windows(2).all(|pair| pair[0] > pair[1]), "rows out of (created_at, id) order");
+}
+
+#[sqlx::test]
+async fn name_walk_neither_skips_nor_repeats_rows_inserted_on_both_sides_of_the_cursor(pool: PgPool) {
+    let repository = PostgresUserRepository::new(pool.clone());
+    let mut original = HashSet::new();
+    for i in 0..14 {
+        original.insert(insert_user(&pool, &format!("m{:02}", i), Utc::now()).await);
+    }
+    let sort = UserSort { field: UserSortField::Name, direction: SortDirection::Asc };
+
+    let mut inserted_ahead = HashSet::new();
+    let seen = walk(&repository, sort, async |fetched| {
+        // One before everything already listed, one after the rest of the walk
+        insert_user(&pool, &format!("a{:02}", fetched), Utc::now()).await;
+        inserted_ahead.insert(insert_user(&pool, &format!("z{:02}", fetched), Utc::now()).await);
+    })
+    .await;
+
+    assert_no_duplicates(&seen);
+    let seen_ids: HashSet<Uuid> = seen.iter().map(|user| user.id).collect();
+    assert!(original.is_subset(&seen_ids), "rows that existed before the walk were skipped");
+    assert_eq!(&seen_ids - &original, inserted_ahead, "only rows inserted ahead of the cursor are picked up");
+    assert!(seen.iter().all(|user| !user.name.starts_with('a')), "rows inserted behind the cursor were picked up");
+}
+
+#[sqlx::test]
+async fn prev_cursors_lead_back_to_the_same_pages(pool: PgPool) {
+    let repository = PostgresUserRepository::new(pool.clone());
+    let base = Utc::now() - Duration::days(1);
+    for i in 0..10 {
+        insert_user(&pool, &format!("user{}", i), base + Duration::minutes(i % 3)).await;
+    }
+    let sort = UserSort { field: UserSortField::CreatedAt, direction: SortDirection::Asc };
+
+    let first = page(&repository, sort, None).await;
+    let second = page(&repository, sort, first.next_cursor.as_deref()).await;
+    let third = page(&repository, sort, second.next_cursor.as_deref()).await;
+    assert!(first.prev_cursor.is_none());
+    assert_eq!(third.items.len(), 2);
+    assert!(third.next_cursor.is_none());
+
+    let back_to_second = page(&repository, sort, third.prev_cursor.as_deref()).await;
+    assert_eq!(ids(&back_to_second), ids(&second));
+    let back_to_first = page(&repository, sort, back_to_second.prev_cursor.as_deref()).await;
+    assert_eq!(ids(&back_to_first), ids(&first));
+    assert!(back_to_first.prev_cursor.is_none(), "the first page has nothing before it");
+
+    // And forward again from a page reached backwards
+    let forward_again = page(&repository, sort, back_to_first.next_cursor.as_deref()).await;
+    assert_eq!(ids(&forward_again), ids(&second));
+}
+
+#[test]
+fn cursors_round_trip_and_are_tied_to_their_sort_order() {
+    let cursor = Cursor {
+        sort: "created_at".to_string(),
+        order: SortDirection::Desc,
+        key: CursorKey::Timestamp(Utc::now()),
+        id: Uuid::new_v4(),
+        backward: true,
+    };
+    let encoded = cursor.encode();
+
+    let decoded = Cursor::decode_for(Some(&encoded), "created_at", SortDirection::Desc)
+        .expect("matching sort order")
+        .expect("cursor present");
+    assert_eq!(decoded.key, cursor.key);
+    assert_eq!(decoded.id, cursor.id);
+    assert!(decoded.backward);
+
+    assert!(Cursor::decode_for(Some(&encoded), "name", SortDirection::Desc).is_err());
+    assert!(Cursor::decode_for(Some(&encoded), "created_at", SortDirection::Asc).is_err());
+    assert!(Cursor::decode("not-a-cursor").is_err());
+    assert!(Cursor::decode_for(Some(""), "created_at", SortDirection::Desc).expect("empty is no cursor").is_none());
+}
@@ -0,0 +1,335 @@
+// OAuth sign-ins end to end: the app redirects to the mock provider, the test
+// picks an account on its consent page and follows the callback back.
+
+mod support;
+
+use reqwest::header::LOCATION;
+use reqwest::StatusCode;
+use serde_json::Value;
+use sqlx::PgPool;
+use rust_axum::domain::entities::user::AuthProvider;
+use rust_axum::domain::repositories::user_repository::UserRepository;
+use rust_axum::infrastructure::errors::AppError;
+use support::mock_oauth_server::CONTOSO_TENANT_ID;
+use support::{header, results, TestApp, TestOptions};
+
+async fn me(app: &TestApp, tokens: &Value) -> Value {
+    let response = app.client
+        .get(app.url("/me"))
+        .bearer_auth(tokens["access_token"].as_str().expect("access token"))
+        .send()
+        .await
+        .expect("GET /me");
+    results(response).await
+}
+
+async fn identity_column(app: &TestApp, column: &str, email: &str) -> Option<String> {
+    sqlx::query_scalar(&format!("SELECT {}::text FROM users WHERE email = $1", column))
+        .bind(email)
+        .fetch_one(&app.pool)
+        .await
+        .expect("user row")
+}
+
+async fn signs_up_a_new_user(pool: PgPool, provider: &str, login: &str, email: &str, name: &str, column: &str) {
+    let app = TestApp::spawn(pool).await;
+
+    let tokens = results(app.oauth_sign_in(provider, login).await).await;
+
+    let user = me(&app, &tokens).await;
+    assert_eq!(user["email"], email);
+    assert_eq!(user["name"], name);
+    assert_eq!(app.user_count().await, 1);
+    assert!(identity_column(&app, column, email).await.is_some());
+}
+
+async fn signs_in_an_existing_user(pool: PgPool, provider: &str, login: &str, email: &str) {
+    let app = TestApp::spawn(pool).await;
+    results(app.oauth_sign_in(provider, login).await).await;
+
+    let tokens = results(app.oauth_sign_in(provider, login).await).await;
+
+    assert_eq!(me(&app, &tokens).await["email"], email);
+    assert_eq!(app.user_count().await, 1);
+}
+
+async fn links_a_password_account_by_email(pool: PgPool, provider: &str, login: &str, email: &str, column: &str) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Password User", email, "correct-horse-battery").await;
+    assert!(identity_column(&app, column, email).await.is_none());
+
+    let tokens = results(app.oauth_sign_in(provider, login).await).await;
+
+    let user = me(&app, &tokens).await;
+    assert_eq!(user["email"], email);
+    assert_eq!(user["name"], "Password User");
+    assert_eq!(app.user_count().await, 1);
+    assert!(identity_column(&app, column, email).await.is_some());
+    // The password still works after linking
+    app.sign_in(email, "correct-horse-battery").await;
+}
+
+#[sqlx::test]
+async fn github_signs_up_a_new_user(pool: PgPool) {
+    signs_up_a_new_user(pool, "github", "octocat", "octocat@example.com", "The Octocat", "github_id").await;
+}
+
+#[sqlx::test]
+async fn github_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "github", "octocat", "octocat@example.com").await;
+}
+
+#[sqlx::test]
+async fn github_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "github", "octocat", "octocat@example.com", "github_id").await;
+}
+
+#[sqlx::test]
+async fn github_uses_the_primary_email_when_the_profile_has_none(pool: PgPool) {
+    signs_up_a_new_user(pool, "github", "private-email", "private-email@example.com", "Private Email", "github_id").await;
+}
+
+#[sqlx::test]
+async fn google_signs_up_a_new_user(pool: PgPool) {
+    signs_up_a_new_user(pool, "google", "alice", "alice@example.com", "Alice Example", "google_id").await;
+}
+
+#[sqlx::test]
+async fn google_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "google", "alice", "alice@example.com").await;
+}
+
+#[sqlx::test]
+async fn google_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "google", "alice", "alice@example.com", "google_id").await;
+}
+
+#[sqlx::test]
+async fn does_not_link_a_password_account_on_an_unverified_email(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Password User", "unverified@example.com", "correct-horse-battery").await;
+
+    let response = app.oauth_sign_in("google", "unverified").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(identity_column(&app, "google_id", "unverified@example.com").await.is_none());
+    assert_eq!(app.user_count().await, 1);
+}
+
+async fn spawn_with_microsoft_tenant(pool: PgPool, tenant: &str) -> TestApp {
+    let mut options = TestOptions::default();
+    options.oauth.microsoft.tenant = Some(tenant.to_string());
+    TestApp::spawn_with(pool, options).await
+}
+
+#[sqlx::test]
+async fn gitlab_signs_up_a_new_user(pool: PgPool) {
+    signs_up_a_new_user(pool, "gitlab", "tanuki", "tanuki@example.com", "Tanuki Example", "gitlab_id").await;
+}
+
+#[sqlx::test]
+async fn gitlab_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "gitlab", "tanuki", "tanuki@example.com").await;
+}
+
+#[sqlx::test]
+async fn gitlab_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "gitlab", "tanuki", "tanuki@example.com", "gitlab_id").await;
+}
+
+#[sqlx::test]
+async fn gitlab_sends_the_browser_to_the_self_hosted_instance(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.client.get(app.url("/auth/gitlab")).send().await.expect("start sign-in");
+
+    assert!(response.status().is_redirection());
+    let location = header(&response, LOCATION);
+    assert!(location.starts_with(&format!("{}/oauth/authorize?", app.mock.gitlab_base_url())), "{}", location);
+}
+
+#[sqlx::test]
+async fn gitlab_does_not_link_a_password_account_on_an_unverified_email(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Password User", "gitlab-unverified@example.com", "correct-horse-battery").await;
+
+    let response = app.oauth_sign_in("gitlab", "gitlab-unverified").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(identity_column(&app, "gitlab_id", "gitlab-unverified@example.com").await.is_none());
+}
+
+#[sqlx::test]
+async fn microsoft_signs_up_a_new_user(pool: PgPool) {
+    signs_up_a_new_user(pool, "microsoft", "adele", "adele@contoso.example.com", "Adele Vance", "microsoft_id").await;
+}
+
+#[sqlx::test]
+async fn microsoft_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "microsoft", "adele", "adele@contoso.example.com").await;
+}
+
+#[sqlx::test]
+async fn microsoft_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "microsoft", "adele", "adele@contoso.example.com", "microsoft_id").await;
+}
+
+#[sqlx::test]
+async fn microsoft_does_not_link_an_email_on_a_domain_the_tenant_does_not_own(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Password User", "someone@example.com", "correct-horse-battery").await;
+
+    // Fabrikam's admin gave this account an example.com address without xms_edov
+    let response = app.oauth_sign_in("microsoft", "unowned-domain").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(identity_column(&app, "microsoft_id", "someone@example.com").await.is_none());
+    assert_eq!(app.user_count().await, 1);
+}
+
+#[sqlx::test]
+async fn microsoft_keys_accounts_by_tenant_and_object_id(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    results(app.oauth_sign_in("microsoft", "adele").await).await;
+
+    let microsoft_id = identity_column(&app, "microsoft_id", "adele@contoso.example.com").await;
+    assert_eq!(microsoft_id.as_deref(), Some(format!("{}:0b9e2f64-1c4d-4a8b-9e3f-5d6c7b8a9f01", CONTOSO_TENANT_ID).as_str()));
+}
+
+#[sqlx::test]
+async fn microsoft_pinned_to_a_tenant_refuses_other_tenants(pool: PgPool) {
+    let app = spawn_with_microsoft_tenant(pool, CONTOSO_TENANT_ID).await;
+
+    results(app.oauth_sign_in("microsoft", "adele").await).await;
+    assert_eq!(app.oauth_sign_in("microsoft", "personal").await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.oauth_sign_in("microsoft", "unowned-domain").await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.user_count().await, 1);
+}
+
+#[sqlx::test]
+async fn microsoft_organizations_refuses_personal_accounts(pool: PgPool) {
+    let app = spawn_with_microsoft_tenant(pool, "organizations").await;
+
+    results(app.oauth_sign_in("microsoft", "adele").await).await;
+    assert_eq!(app.oauth_sign_in("microsoft", "personal").await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.user_count().await, 1);
+}
+
+#[sqlx::test]
+async fn microsoft_consumers_refuses_work_accounts(pool: PgPool) {
+    let app = spawn_with_microsoft_tenant(pool, "consumers").await;
+
+    results(app.oauth_sign_in("microsoft", "personal").await).await;
+    assert_eq!(app.oauth_sign_in("microsoft", "adele").await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(app.user_count().await, 1);
+}
+
+#[sqlx::test]
+async fn discord_signs_up_a_new_user(pool: PgPool) {
+    signs_up_a_new_user(pool, "discord", "wumpus", "wumpus@example.com", "Wumpus", "discord_id").await;
+}
+
+#[sqlx::test]
+async fn discord_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "discord", "wumpus", "wumpus@example.com").await;
+}
+
+#[sqlx::test]
+async fn discord_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "discord", "wumpus", "wumpus@example.com", "discord_id").await;
+}
+
+#[sqlx::test]
+async fn discord_refuses_an_unverified_email_even_for_a_new_account(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.oauth_sign_in("discord", "discord-unverified").await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.text().await.expect("error body").contains("Verify your email address with Discord"));
+    assert_eq!(app.user_count().await, 0);
+}
+
+#[sqlx::test]
+async fn apple_signs_up_a_new_user_named_from_the_form_post(pool: PgPool) {
+    // The name is not in Apple's id_token; it only arrives in the posted `user` field
+    signs_up_a_new_user(pool, "apple", "appleseed", "appleseed@example.com", "Johnny Appleseed", "apple_id").await;
+}
+
+#[sqlx::test]
+async fn apple_signs_in_an_existing_user(pool: PgPool) {
+    signs_in_an_existing_user(pool, "apple", "appleseed", "appleseed@example.com").await;
+}
+
+#[sqlx::test]
+async fn apple_links_a_password_account_by_email(pool: PgPool) {
+    links_a_password_account_by_email(pool, "apple", "appleseed", "appleseed@example.com", "apple_id").await;
+}
+
+#[sqlx::test]
+async fn apple_private_relay_email_signs_up_a_separate_account(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Password User", "appleseed@example.com", "correct-horse-battery").await;
+
+    let tokens = results(app.oauth_sign_in("apple", "hide-my-email").await).await;
+
+    let user = me(&app, &tokens).await;
+    assert_eq!(user["email"], "x7k2p9qz4m@privaterelay.appleid.com");
+    assert_eq!(user["name"], "Hidden Email");
+    assert_eq!(app.user_count().await, 2);
+    assert!(identity_column(&app, "apple_id", "appleseed@example.com").await.is_none());
+}
+
+#[sqlx::test]
+async fn apple_callback_only_accepts_the_form_post(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.client
+        .get(app.url("/auth/apple/callback?code=mock-code-appleseed&state=forged"))
+        .send()
+        .await
+        .expect("GET callback");
+
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(app.user_count().await, 0);
+}
+
+#[sqlx::test]
+async fn a_deleted_account_does_not_block_its_provider_account(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    results(app.oauth_sign_in("github", "octocat").await).await;
+    let deleted_id = app.user_id("octocat@example.com").await;
+    sqlx::query("UPDATE users SET deleted_at = NOW() WHERE id = $1").bind(deleted_id).execute(&app.pool).await.expect("soft delete");
+
+    let tokens = results(app.oauth_sign_in("github", "octocat").await).await;
+
+    assert_ne!(me(&app, &tokens).await["id"], deleted_id.to_string());
+    assert_eq!(app.user_count().await, 2);
+
+    // Restoring the old account would link the provider account twice
+    app.register("Root", "root@example.com", "correct-horse-battery").await;
+    app.set_role("root@example.com", "SuperAdmin").await;
+    let root = app.sign_in("root@example.com", "correct-horse-battery").await;
+    let response = app.client
+        .post(app.url(&format!("/users/{}/restore", deleted_id)))
+        .bearer_auth(root["access_token"].as_str().expect("access token"))
+        .send()
+        .await
+        .expect("POST restore");
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test]
+async fn linking_a_provider_account_linked_elsewhere_conflicts(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    results(app.oauth_sign_in("google", "alice").await).await;
+    app.register("Password User", "bob@example.com", "correct-horse-battery").await;
+    let google_id = identity_column(&app, "google_id", "alice@example.com").await.expect("google id");
+
+    let linked = app.state.user_repository
+        .link_identity(app.user_id("bob@example.com").await, AuthProvider::Google, &google_id, None)
+        .await;
+
+    assert!(matches!(linked, Err(AppError::IdentityAlreadyLinked)));
+}
@@ -0,0 +1,157 @@
+// Phone verification: the texted one-time code, its expiry and attempt limit,
+// the resend cooldown, and a number only one account can verify
+
+mod support;
+
+use reqwest::{Response, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use support::{results, TestApp};
+
+const PHONE: &str = "+6281234567890";
+
+fn token(tokens: &Value) -> &str {
+    tokens["access_token"].as_str().expect("access token")
+}
+
+/// Signs up `email` with `PHONE` on the account and returns its tokens
+async fn user_with_phone(app: &TestApp, email: &str) -> Value {
+    let tokens = app.register("Owner", email, "correct-horse-battery").await;
+    let response = app.client
+        .patch(app.url("/me"))
+        .bearer_auth(token(&tokens))
+        .json(&json!({ "name": "Owner", "phone": PHONE }))
+        .send()
+        .await
+        .expect("PATCH /me");
+    assert_eq!(results(response).await["phone"], PHONE);
+    tokens
+}
+
+async fn request_code(app: &TestApp, tokens: &Value) -> Response {
+    app.client
+        .post(app.url("/me/phone/verification"))
+        .bearer_auth(token(tokens))
+        .send()
+        .await
+        .expect("POST /me/phone/verification")
+}
+
+async fn confirm(app: &TestApp, tokens: &Value, code: &str) -> Response {
+    app.client
+        .post(app.url("/me/phone/verification/confirm"))
+        .bearer_auth(token(tokens))
+        .json(&json!({ "code": code }))
+        .send()
+        .await
+        .expect("POST /me/phone/verification/confirm")
+}
+
+/// A code that is certainly not `code`
+fn wrong(code: &str) -> String {
+    code.chars().map(|digit| char::from_digit((digit.to_digit(10).expect("digit") + 1) % 10, 10).unwrap()).collect()
+}
+
+#[sqlx::test]
+async fn the_texted_code_verifies_the_phone(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = user_with_phone(&app, "owner@example.com").await;
+
+    assert_eq!(results(request_code(&app, &tokens).await).await["phone"], PHONE);
+    let code = app.last_sms_code(PHONE).await;
+
+    let user = results(confirm(&app, &tokens, &code).await).await;
+    assert_eq!(user["phone"], PHONE);
+    assert!(user["phone_verified_at"].is_string());
+
+    // The code is spent
+    let response = confirm(&app, &tokens, &code).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test]
+async fn an_expired_code_is_rejected(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = user_with_phone(&app, "owner@example.com").await;
+    results(request_code(&app, &tokens).await).await;
+    let code = app.last_sms_code(PHONE).await;
+
+    sqlx::query("UPDATE phone_verifications SET expires_at = now() - interval '1 second'")
+        .execute(&app.pool)
+        .await
+        .expect("expire code");
+
+    let response = confirm(&app, &tokens, &code).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let verified: Option<chrono::DateTime<chrono::Utc>> = sqlx::query_scalar("SELECT phone_verified_at FROM users")
+        .fetch_one(&app.pool)
+        .await
+        .expect("user row");
+    assert!(verified.is_none());
+}
+
+#[sqlx::test]
+async fn the_code_locks_after_five_wrong_guesses(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = user_with_phone(&app, "owner@example.com").await;
+    results(request_code(&app, &tokens).await).await;
+    let code = app.last_sms_code(PHONE).await;
+
+    for _ in 0..5 {
+        let response = confirm(&app, &tokens, &wrong(&code)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Even the right code no longer works, and the rejected try is not counted
+    let response = confirm(&app, &tokens, &code).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let attempts: i32 = sqlx::query_scalar("SELECT attempts FROM phone_verifications")
+        .fetch_one(&app.pool)
+        .await
+        .expect("verification row");
+    assert_eq!(attempts, 5);
+}
+
+#[sqlx::test]
+async fn a_new_code_waits_for_the_cooldown_and_replaces_the_old_one(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = user_with_phone(&app, "owner@example.com").await;
+    results(request_code(&app, &tokens).await).await;
+    let first_code = app.last_sms_code(PHONE).await;
+
+    let response = request_code(&app, &tokens).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    sqlx::query("UPDATE phone_verifications SET created_at = now() - interval '61 seconds'")
+        .execute(&app.pool)
+        .await
+        .expect("age code");
+    results(request_code(&app, &tokens).await).await;
+    let second_code = app.last_sms_code(PHONE).await;
+
+    if first_code != second_code {
+        let response = confirm(&app, &tokens, &first_code).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    results(confirm(&app, &tokens, &second_code).await).await;
+}
+
+#[sqlx::test]
+async fn only_one_account_can_verify_a_number(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    // Unverified numbers may be shared
+    let first = user_with_phone(&app, "first@example.com").await;
+    let second = user_with_phone(&app, "second@example.com").await;
+
+    results(request_code(&app, &second).await).await;
+    let second_code = app.last_sms_code(PHONE).await;
+    results(request_code(&app, &first).await).await;
+    let first_code = app.last_sms_code(PHONE).await;
+    results(confirm(&app, &first, &first_code).await).await;
+
+    // A code sent before the number was taken
+    let response = confirm(&app, &second, &second_code).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    let response = request_code(&app, &second).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
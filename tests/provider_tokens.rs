@@ -0,0 +1,135 @@
+// Stored provider tokens: encrypted at rest, refreshed on use, and re-wrapped
+// when the master key is rotated
+
+mod support;
+
+use std::sync::Arc;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::entities::user::AuthProvider;
+use rust_axum::infrastructure::auth::oauth::OAuthTokens;
+use rust_axum::infrastructure::crypto::EnvelopeCipher;
+use rust_axum::infrastructure::errors::AppError;
+use rust_axum::infrastructure::repositories::postgres_provider_token_repository::PostgresProviderTokenRepository;
+use rust_axum::jobs::provider_token_rotation;
+use rust_axum::usecases::provider_tokens::ProviderTokenStore;
+use support::{results, TestApp, TestOptions};
+
+fn key(id: &str, byte: u8) -> String {
+    format!("{}:{}", id, STANDARD.encode([byte; 32]))
+}
+
+async fn spawn(pool: PgPool, keys: &[String]) -> TestApp {
+    TestApp::spawn_with(pool, TestOptions { provider_token_keys: keys.to_vec(), ..Default::default() }).await
+}
+
+fn store(app: &TestApp, keys: &[String]) -> ProviderTokenStore<PostgresProviderTokenRepository> {
+    let cipher = EnvelopeCipher::new(keys).expect("valid keys");
+    ProviderTokenStore::new(app.state.provider_token_repository.clone(), Arc::new(cipher), app.state.oauth_providers.clone())
+}
+
+async fn stored(app: &TestApp, user_id: Uuid) -> (String, Vec<u8>, Option<DateTime<Utc>>) {
+    sqlx::query_as("SELECT key_id, access_token, expires_at FROM provider_tokens WHERE user_id = $1 AND provider = 'google'")
+        .bind(user_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("stored token")
+}
+
+async fn expire(app: &TestApp, user_id: Uuid) {
+    sqlx::query("UPDATE provider_tokens SET expires_at = now() WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&app.pool)
+        .await
+        .expect("expire token");
+}
+
+#[sqlx::test]
+async fn sign_in_stores_the_tokens_encrypted(pool: PgPool) {
+    let keys = [key("2024-01", 1)];
+    let app = spawn(pool, &keys).await;
+    results(app.oauth_sign_in("google", "alice").await).await;
+    let user_id = app.user_id("alice@example.com").await;
+
+    let (key_id, ciphertext, _) = stored(&app, user_id).await;
+    assert_eq!(key_id, "2024-01");
+    assert!(!String::from_utf8_lossy(&ciphertext).contains("mock-token-alice"));
+
+    let access_token = store(&app, &keys).access_token(user_id, AuthProvider::Google).await.expect("access token");
+    assert_eq!(access_token, "mock-token-alice");
+}
+
+#[sqlx::test]
+async fn an_expiring_access_token_is_refreshed_first(pool: PgPool) {
+    let keys = [key("2024-01", 1)];
+    let app = spawn(pool, &keys).await;
+    results(app.oauth_sign_in("google", "alice").await).await;
+    let user_id = app.user_id("alice@example.com").await;
+    expire(&app, user_id).await;
+
+    let access_token = store(&app, &keys).access_token(user_id, AuthProvider::Google).await.expect("access token");
+
+    assert_eq!(access_token, "mock-token-alice");
+    let (_, _, expires_at) = stored(&app, user_id).await;
+    assert!(expires_at.expect("expiry") > Utc::now() + Duration::minutes(30));
+    // Google sent no new refresh token, so the stored one still works
+    expire(&app, user_id).await;
+    assert!(store(&app, &keys).access_token(user_id, AuthProvider::Google).await.is_ok());
+}
+
+#[sqlx::test]
+async fn a_rejected_refresh_token_disconnects_the_provider(pool: PgPool) {
+    let keys = [key("2024-01", 1)];
+    let app = spawn(pool, &keys).await;
+    results(app.oauth_sign_in("google", "alice").await).await;
+    let user_id = app.user_id("alice@example.com").await;
+    let store = store(&app, &keys);
+    let revoked = OAuthTokens {
+        access_token: "mock-token-alice".to_string(),
+        refresh_token: Some("revoked-refresh-token".to_string()),
+        expires_in: Some(0),
+        id_token: None,
+    };
+    store.store(user_id, AuthProvider::Google, &revoked).await.expect("store tokens");
+
+    let result = store.access_token(user_id, AuthProvider::Google).await;
+
+    assert!(matches!(result, Err(AppError::ProviderNotConnected(name)) if name == "Google"));
+    let left: i64 = sqlx::query_scalar("SELECT count(*) FROM provider_tokens").fetch_one(&app.pool).await.expect("count");
+    assert_eq!(left, 0);
+}
+
+#[sqlx::test]
+async fn a_provider_never_connected_has_no_token(pool: PgPool) {
+    let keys = [key("2024-01", 1)];
+    let app = spawn(pool, &keys).await;
+    app.register("Password User", "user@example.com", "correct-horse-battery").await;
+    let user_id = app.user_id("user@example.com").await;
+
+    let result = store(&app, &keys).access_token(user_id, AuthProvider::Google).await;
+
+    assert!(matches!(result, Err(AppError::ProviderNotConnected(_))));
+}
+
+#[sqlx::test]
+async fn rotation_rewraps_rows_so_the_old_key_can_be_removed(pool: PgPool) {
+    let old_keys = [key("2024-01", 1)];
+    let app = spawn(pool, &old_keys).await;
+    results(app.oauth_sign_in("google", "alice").await).await;
+    let user_id = app.user_id("alice@example.com").await;
+    let (_, ciphertext_before, _) = stored(&app, user_id).await;
+
+    // What a restart with the new key first does
+    let rotated_keys = [key("2024-06", 2), key("2024-01", 1)];
+    provider_token_rotation::spawn(store(&app, &rotated_keys)).await.expect("rotation job");
+    assert_eq!(store(&app, &rotated_keys).rotate_keys().await.expect("rotate again"), 0);
+
+    let (key_id, ciphertext_after, _) = stored(&app, user_id).await;
+    assert_eq!(key_id, "2024-06");
+    // Only the data key was re-encrypted
+    assert_eq!(ciphertext_after, ciphertext_before);
+    let access_token = store(&app, &[key("2024-06", 2)]).access_token(user_id, AuthProvider::Google).await.expect("access token");
+    assert_eq!(access_token, "mock-token-alice");
+}
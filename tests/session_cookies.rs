@@ -0,0 +1,170 @@
+// Session cookie mode: tokens in HttpOnly cookies, and the CSRF token that
+// cookie-authenticated unsafe requests must echo in a header
+
+mod support;
+
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde_json::json;
+use sqlx::PgPool;
+use support::{cookie_pair, results, TestApp, TestOptions};
+
+const CSRF_HEADER: &str = "x-csrf-token";
+
+async fn spawn(pool: PgPool) -> TestApp {
+    TestApp::spawn_with(pool, TestOptions { session_cookies: true, ..Default::default() }).await
+}
+
+/// What a browser holds after signing in
+struct BrowserSession {
+    access_cookie: String,
+    refresh_cookie: String,
+    csrf_token: String,
+}
+
+impl BrowserSession {
+    /// The cookies a sign-in or refresh response sets; the body holds no tokens
+    async fn from_response(response: Response) -> Self {
+        let access_cookie = cookie_pair(&response, "access_token").expect("access_token cookie");
+        let refresh_cookie = cookie_pair(&response, "refresh_token").expect("refresh_token cookie");
+        let csrf_cookie = cookie_pair(&response, "csrf_token").expect("csrf_token cookie");
+        let body = results(response).await;
+        assert!(body.get("access_token").is_none() && body.get("refresh_token").is_none(), "tokens in the body: {}", body);
+        let csrf_token = body["csrf_token"].as_str().expect("csrf_token in body").to_string();
+        assert_eq!(csrf_cookie, format!("csrf_token={}", csrf_token));
+
+        Self { access_cookie, refresh_cookie, csrf_token }
+    }
+
+    /// The cookies sent to the auth routes, where the refresh token cookie is scoped
+    fn auth_cookies(&self) -> String {
+        format!("{}; {}", self.access_cookie, self.refresh_cookie)
+    }
+
+    fn access_token(&self) -> &str {
+        self.access_cookie.trim_start_matches("access_token=")
+    }
+}
+
+async fn sign_up_and_in(app: &TestApp, email: &str) -> BrowserSession {
+    let response = app.post_json("/auth/sign-up", json!({ "name": "Owner", "email": email, "password": "correct-horse-battery" })).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_json("/auth/sign-in", json!({ "email": email, "password": "correct-horse-battery" })).await;
+    BrowserSession::from_response(response).await
+}
+
+fn rename(app: &TestApp, session: &BrowserSession) -> RequestBuilder {
+    app.client
+        .patch(app.url("/me"))
+        .header(COOKIE, &session.access_cookie)
+        .json(&json!({ "name": "Renamed" }))
+}
+
+#[sqlx::test]
+async fn sign_in_puts_the_tokens_in_cookies(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+
+    let response = app.client.get(app.url("/me")).header(COOKIE, &session.access_cookie).send().await.expect("GET /me");
+    assert_eq!(results(response).await["email"], "owner@example.com");
+}
+
+#[sqlx::test]
+async fn session_cookies_are_httponly_except_the_csrf_token(pool: PgPool) {
+    let app = spawn(pool).await;
+    app.post_json("/auth/sign-up", json!({ "name": "Owner", "email": "owner@example.com", "password": "correct-horse-battery" })).await;
+    let response = app.post_json("/auth/sign-in", json!({ "email": "owner@example.com", "password": "correct-horse-battery" })).await;
+
+    let set_cookies: Vec<&str> = response.headers().get_all(SET_COOKIE).iter().map(|value| value.to_str().unwrap()).collect();
+    let cookie = |name: &str| *set_cookies.iter().find(|cookie| cookie.starts_with(&format!("{}=", name))).expect("cookie set");
+    assert!(cookie("access_token").contains("HttpOnly"));
+    assert!(cookie("refresh_token").contains("HttpOnly"));
+    assert!(cookie("refresh_token").contains("Path=/api/v1/auth"));
+    assert!(!cookie("csrf_token").contains("HttpOnly"));
+    // Served over http
+    assert!(set_cookies.iter().all(|cookie| !cookie.contains("Secure")));
+}
+
+#[sqlx::test]
+async fn unsafe_cookie_requests_need_the_csrf_header(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+
+    let response = rename(&app, &session).send().await.expect("PATCH /me");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = rename(&app, &session).header(CSRF_HEADER, &session.csrf_token).send().await.expect("PATCH /me");
+    assert_eq!(results(response).await["name"], "Renamed");
+}
+
+#[sqlx::test]
+async fn a_wrong_csrf_token_is_rejected(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+    // A valid token, but for another session
+    let other = sign_up_and_in(&app, "other@example.com").await;
+
+    for csrf_token in ["not-a-csrf-token", other.csrf_token.as_str()] {
+        let response = rename(&app, &session).header(CSRF_HEADER, csrf_token).send().await.expect("PATCH /me");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "accepted {}", csrf_token);
+    }
+}
+
+#[sqlx::test]
+async fn bearer_requests_need_no_csrf_token(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+
+    // The Authorization header wins over the cookie, and a cross-site page cannot set it
+    let response = rename(&app, &session).bearer_auth(session.access_token()).send().await.expect("PATCH /me");
+    assert_eq!(results(response).await["name"], "Renamed");
+
+    // So is a refresh token in the body, which gets tokens back in the body
+    let refresh_token = session.refresh_cookie.trim_start_matches("refresh_token=");
+    let response = app.post_json("/auth/refresh", json!({ "refresh_token": refresh_token })).await;
+    assert!(results(response).await["refresh_token"].is_string());
+}
+
+#[sqlx::test]
+async fn refresh_from_the_cookie_needs_the_csrf_header(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+    let refresh = || app.client.post(app.url("/auth/refresh")).header(COOKIE, session.auth_cookies());
+
+    let response = refresh().send().await.expect("refresh");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = refresh().header(CSRF_HEADER, "not-a-csrf-token").send().await.expect("refresh");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = refresh().header(CSRF_HEADER, &session.csrf_token).send().await.expect("refresh");
+    let refreshed = BrowserSession::from_response(response).await;
+
+    let response = app.client.get(app.url("/me")).header(COOKIE, &refreshed.access_cookie).send().await.expect("GET /me");
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn sign_out_from_the_cookie_needs_the_csrf_header_and_clears_the_cookies(pool: PgPool) {
+    let app = spawn(pool).await;
+    let session = sign_up_and_in(&app, "owner@example.com").await;
+    let sign_out = || app.client.post(app.url("/auth/sign-out")).header(COOKIE, session.auth_cookies());
+
+    let response = sign_out().send().await.expect("sign out");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = sign_out().header(CSRF_HEADER, &session.csrf_token).send().await.expect("sign out");
+    assert_eq!(response.status(), StatusCode::OK);
+    for name in ["access_token", "refresh_token", "csrf_token"] {
+        assert_eq!(cookie_pair(&response, name), Some(format!("{}=", name)), "{} not cleared", name);
+    }
+
+    // The session is over
+    let response = app.client
+        .post(app.url("/auth/refresh"))
+        .header(COOKIE, session.auth_cookies())
+        .header(CSRF_HEADER, &session.csrf_token)
+        .send()
+        .await
+        .expect("refresh");
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
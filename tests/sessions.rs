@@ -0,0 +1,197 @@
+// Sessions: listing and revoking them, for oneself and as an admin, and what
+// revoking does to refresh tokens
+
+mod support;
+
+use jsonwebtoken::{encode, EncodingKey, Header};
+use reqwest::{Response, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use support::{results, TestApp, JWT_SECRET};
+
+fn token<'a>(tokens: &'a Value, name: &str) -> &'a str {
+    tokens[name].as_str().expect("token")
+}
+
+async fn refresh_status(app: &TestApp, tokens: &Value) -> StatusCode {
+    app.post_json("/auth/refresh", json!({ "refresh_token": token(tokens, "refresh_token") }))
+        .await
+        .status()
+}
+
+/// The session id (`sid`) the tokens belong to
+fn session_of(app: &TestApp, tokens: &Value) -> Uuid {
+    let claims = app.state.jwt_service.verify_token(token(tokens, "access_token")).expect("valid token");
+    claims.claims.sid.expect("sid claim")
+}
+
+async fn get(app: &TestApp, tokens: &Value, path: &str) -> Response {
+    app.client.get(app.url(path)).bearer_auth(token(tokens, "access_token")).send().await.expect("GET")
+}
+
+async fn delete(app: &TestApp, tokens: &Value, path: &str) -> Response {
+    app.client.delete(app.url(path)).bearer_auth(token(tokens, "access_token")).send().await.expect("DELETE")
+}
+
+fn session_ids(sessions: &Value) -> Vec<Uuid> {
+    sessions
+        .as_array()
+        .expect("sessions")
+        .iter()
+        .map(|session| session["id"].as_str().expect("id").parse().expect("uuid"))
+        .collect()
+}
+
+#[sqlx::test]
+async fn lists_the_users_sessions_and_marks_the_current_one(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let laptop = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let phone = app.sign_in("owner@example.com", "correct-horse-battery").await;
+
+    let sessions = results(get(&app, &laptop, "/me/sessions").await).await;
+
+    let mut ids = session_ids(&sessions);
+    ids.sort();
+    let mut expected = vec![session_of(&app, &laptop), session_of(&app, &phone)];
+    expected.sort();
+    assert_eq!(ids, expected);
+    let current: Vec<&Value> = sessions.as_array().unwrap().iter().filter(|session| session["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["id"], session_of(&app, &laptop).to_string());
+    assert_eq!(current[0]["auth_method"], "password");
+}
+
+#[sqlx::test]
+async fn revoking_a_session_stops_its_refresh_token(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let laptop = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let phone = app.sign_in("owner@example.com", "correct-horse-battery").await;
+
+    let response = delete(&app, &laptop, &format!("/me/sessions/{}", session_of(&app, &phone))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(refresh_status(&app, &phone).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(refresh_status(&app, &laptop).await, StatusCode::OK);
+    assert_eq!(session_ids(&results(get(&app, &laptop, "/me/sessions").await).await), vec![session_of(&app, &laptop)]);
+
+    // Already gone
+    let response = delete(&app, &laptop, &format!("/me/sessions/{}", session_of(&app, &phone))).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test]
+async fn cannot_revoke_another_users_session_as_ones_own(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let other = app.register("Other", "other@example.com", "correct-horse-battery").await;
+
+    let response = delete(&app, &other, &format!("/me/sessions/{}", session_of(&app, &owner))).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(refresh_status(&app, &owner).await, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn revoking_all_sessions_keeps_the_current_one(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let laptop = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let phone = app.sign_in("owner@example.com", "correct-horse-battery").await;
+    let tablet = app.sign_in("owner@example.com", "correct-horse-battery").await;
+
+    let response = delete(&app, &laptop, "/me/sessions").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(refresh_status(&app, &phone).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(refresh_status(&app, &tablet).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(refresh_status(&app, &laptop).await, StatusCode::OK);
+
+    let revoked: i64 = sqlx::query_scalar(
+        "SELECT (metadata->>'sessions_revoked')::bigint FROM audit_events WHERE action = 'session_revoked' AND target_id = $1",
+    )
+    .bind(app.user_id("owner@example.com").await)
+    .fetch_one(&app.pool)
+    .await
+    .expect("audit event");
+    assert_eq!(revoked, 2);
+}
+
+#[sqlx::test]
+async fn refresh_tokens_without_a_session_are_rejected(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = app.register("Owner", "owner@example.com", "correct-horse-battery").await;
+    let sessions_before: i64 = sqlx::query_scalar("SELECT count(*) FROM sessions").fetch_one(&app.pool).await.expect("count");
+
+    // A refresh token as issued before sessions were recorded
+    let mut claims = app.state.jwt_service.verify_token(token(&tokens, "refresh_token")).expect("valid token").claims;
+    claims.sid = None;
+    let legacy = encode(&Header::default(), &claims, &EncodingKey::from_secret(JWT_SECRET.as_bytes())).expect("sign");
+
+    let response = app.post_json("/auth/refresh", json!({ "refresh_token": legacy })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let sessions_after: i64 = sqlx::query_scalar("SELECT count(*) FROM sessions").fetch_one(&app.pool).await.expect("count");
+    assert_eq!(sessions_after, sessions_before);
+}
+
+#[sqlx::test]
+async fn admins_list_and_revoke_a_users_sessions(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Admin", "admin@example.com", "correct-horse-battery").await;
+    app.set_role("admin@example.com", "Admin").await;
+    let admin = app.sign_in("admin@example.com", "correct-horse-battery").await;
+    let user = app.register("User", "user@example.com", "correct-horse-battery").await;
+    let user_id = app.user_id("user@example.com").await;
+
+    let sessions = results(get(&app, &admin, &format!("/users/{}/sessions", user_id)).await).await;
+    assert_eq!(session_ids(&sessions), vec![session_of(&app, &user)]);
+    assert_eq!(sessions[0]["current"], false);
+
+    let response = delete(&app, &admin, &format!("/users/{}/sessions/{}", user_id, session_of(&app, &user))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(refresh_status(&app, &user).await, StatusCode::UNAUTHORIZED);
+
+    let actor: Uuid = sqlx::query_scalar("SELECT actor_id FROM audit_events WHERE action = 'session_revoked' AND target_id = $1")
+        .bind(user_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("audit event");
+    assert_eq!(actor, app.user_id("admin@example.com").await);
+}
+
+#[sqlx::test]
+async fn only_admins_manage_other_users_sessions(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let mentor = app.register("Mentor", "mentor@example.com", "correct-horse-battery").await;
+    app.set_role("mentor@example.com", "Mentor").await;
+    let user = app.register("User", "user@example.com", "correct-horse-battery").await;
+    let user_id = app.user_id("user@example.com").await;
+
+    let response = get(&app, &mentor, &format!("/users/{}/sessions", user_id)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = delete(&app, &mentor, &format!("/users/{}/sessions/{}", user_id, session_of(&app, &user))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(refresh_status(&app, &user).await, StatusCode::OK);
+}
+
+#[sqlx::test]
+async fn admins_cannot_manage_a_superadmins_sessions(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Admin", "admin@example.com", "correct-horse-battery").await;
+    app.set_role("admin@example.com", "Admin").await;
+    let admin = app.sign_in("admin@example.com", "correct-horse-battery").await;
+    app.register("Root", "root@example.com", "correct-horse-battery").await;
+    app.set_role("root@example.com", "SuperAdmin").await;
+    let root = app.sign_in("root@example.com", "correct-horse-battery").await;
+    let root_id = app.user_id("root@example.com").await;
+
+    let response = get(&app, &admin, &format!("/users/{}/sessions", root_id)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = delete(&app, &admin, &format!("/users/{}/sessions/{}", root_id, session_of(&app, &root))).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(refresh_status(&app, &root).await, StatusCode::OK);
+
+    // A SuperAdmin can
+    app.set_role("admin@example.com", "SuperAdmin").await;
+    let superadmin = app.sign_in("admin@example.com", "correct-horse-battery").await;
+    let response = delete(&app, &superadmin, &format!("/users/{}/sessions/{}", root_id, session_of(&app, &root))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
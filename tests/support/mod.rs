@@ -0,0 +1,296 @@
+// Shared setup for the integration tests: the real router, served on a free
+// port against the per-test database `#[sqlx::test]` creates, with every OAuth
+// provider replaced by the mock server.
+//
+// The tests need a PostgreSQL server that DATABASE_URL points at; each test
+// gets its own throwaway database with the migrations applied.
+
+#![allow(dead_code)]
+
+// The mock lives with the dev_server example so the server binary can never
+// be built with it
+#[path = "../../examples/dev_server/mock_oauth_server.rs"]
+pub mod mock_oauth_server;
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use reqwest::header::{COOKIE, LOCATION, SET_COOKIE};
+use reqwest::{redirect, Client, Response, StatusCode};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tower_http::cors::CorsLayer;
+use mock_oauth_server::MockOAuthServer;
+use rust_axum::AppState;
+use rust_axum::config::{JwtConfig, OAuthConfig, OAuthHttpConfig, Secret};
+use rust_axum::infrastructure::auth::jwt::JwtService;
+use rust_axum::infrastructure::auth::oauth::OAuthHttpClient;
+use rust_axum::infrastructure::auth::session_cookies::SessionCookies;
+use rust_axum::infrastructure::crypto::EnvelopeCipher;
+use rust_axum::infrastructure::mail::log_mailer::LogMailer;
+use rust_axum::infrastructure::phone::PhonePolicy;
+use rust_axum::infrastructure::repositories::postgres_audit_repository::PostgresAuditRepository;
+use rust_axum::infrastructure::repositories::postgres_data_export_repository::PostgresDataExportRepository;
+use rust_axum::infrastructure::repositories::postgres_email_change_repository::PostgresEmailChangeRepository;
+use rust_axum::infrastructure::repositories::postgres_oauth_exchange_code_repository::PostgresOAuthExchangeCodeRepository;
+use rust_axum::infrastructure::repositories::postgres_phone_verification_repository::PostgresPhoneVerificationRepository;
+use rust_axum::infrastructure::repositories::postgres_provider_token_repository::PostgresProviderTokenRepository;
+use rust_axum::infrastructure::repositories::postgres_session_repository::PostgresSessionRepository;
+use rust_axum::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+use rust_axum::infrastructure::sms::log_sms_sender::LogSmsSender;
+use rust_axum::infrastructure::storage::local_blob_store::LocalBlobStore;
+use rust_axum::utils::cookie::SameSite;
+
+pub const JWT_SECRET: &str = "integration-test-secret-integration-test-secret";
+pub const FRONTEND_ORIGIN: &str = "http://frontend.test";
+
+/// How the app under test is set up
+#[derive(Default)]
+pub struct TestOptions {
+    pub session_cookies: bool,
+    /// Provider settings the mock honours: Google hosted domain, Microsoft tenant
+    pub oauth: OAuthConfig,
+    /// `PROVIDER_TOKEN_KEYS`; provider tokens are only stored when set
+    pub provider_token_keys: Vec<String>,
+}
+
+pub struct TestApp {
+    pub address: SocketAddr,
+    pub pool: PgPool,
+    pub state: AppState,
+    pub mock: MockOAuthServer,
+    /// Does not follow redirects, so each hop can be checked
+    pub client: Client,
+    /// Where the SMS sender appends each text it sends
+    pub sms_log: PathBuf,
+}
+
+pub fn jwt_service() -> JwtService {
+    JwtService::new(&JwtConfig {
+        secret: Secret::new(JWT_SECRET),
+        ..JwtConfig::default()
+    })
+}
+
+/// Application state backed by `pool`, without any OAuth providers
+pub fn state(pool: &PgPool) -> AppState {
+    let jwt_service = Arc::new(jwt_service());
+    AppState {
+        user_repository: Arc::new(PostgresUserRepository::new(pool.clone())),
+        audit_repository: Arc::new(PostgresAuditRepository::new(pool.clone())),
+        email_change_repository: Arc::new(PostgresEmailChangeRepository::new(pool.clone())),
+        data_export_repository: Arc::new(PostgresDataExportRepository::new(pool.clone())),
+        phone_verification_repository: Arc::new(PostgresPhoneVerificationRepository::new(pool.clone())),
+        provider_token_repository: Arc::new(PostgresProviderTokenRepository::new(pool.clone())),
+        oauth_exchange_code_repository: Arc::new(PostgresOAuthExchangeCodeRepository::new(pool.clone())),
+        session_repository: Arc::new(PostgresSessionRepository::new(pool.clone())),
+        oauth_providers: Arc::default(),
+        oauth_redirect_origins: Arc::new(vec![FRONTEND_ORIGIN.to_string()]),
+        session_cookies: None,
+        token_cipher: None,
+        mailer: Arc::new(LogMailer),
+        sms_sender: Arc::new(LogSmsSender::new(None)),
+        phone_policy: Arc::new(PhonePolicy::new("ID", false).expect("ID is a known region")),
+        blob_store: Arc::new(LocalBlobStore::new(std::env::temp_dir().join(format!("rust-axum-test-{}", uuid::Uuid::new_v4())))),
+        mirror_oauth_avatars: false,
+        app_base_url: "http://localhost".to_string(),
+        trusted_proxies: Arc::default(),
+        jwt_service,
+    }
+}
+
+impl TestApp {
+    pub async fn spawn(pool: PgPool) -> Self {
+        Self::spawn_with(pool, TestOptions::default()).await
+    }
+
+    pub async fn spawn_with(pool: PgPool, options: TestOptions) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind test server");
+        let address = listener.local_addr().expect("test server address");
+        let base_url = format!("http://{}", address);
+
+        let mock = mock_oauth_server::spawn().await.expect("start mock OAuth server");
+        let http = OAuthHttpClient::new(&OAuthHttpConfig::default());
+
+        let mut state = state(&pool);
+        state.app_base_url = base_url.clone();
+        state.oauth_providers = Arc::new(mock.providers(&base_url, &options.oauth, &http));
+        if options.session_cookies {
+            state.session_cookies = Some(SessionCookies::new(SameSite::Lax, state.jwt_service.refresh_token_ttl_secs(), false));
+        }
+        let sms_log = std::env::temp_dir().join(format!("rust-axum-test-sms-{}.jsonl", Uuid::new_v4()));
+        state.sms_sender = Arc::new(LogSmsSender::new(Some(sms_log.clone())));
+        if !options.provider_token_keys.is_empty() {
+            let cipher = EnvelopeCipher::new(&options.provider_token_keys).expect("valid provider token keys");
+            state.token_cipher = Some(Arc::new(cipher));
+        }
+
+        let app = rust_axum::app(state.clone(), CorsLayer::new());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .expect("test server failed");
+        });
+
+        let client = Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()
+            .expect("build test client");
+
+        Self { address, pool, state, mock, client, sms_log }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}/api/v1{}", self.address, path)
+    }
+
+    pub async fn post_json(&self, path: &str, body: Value) -> Response {
+        self.client.post(self.url(path)).json(&body).send().await.expect("request failed")
+    }
+
+    /// Registers a password account and returns its tokens
+    pub async fn register(&self, name: &str, email: &str, password: &str) -> Value {
+        let response = self.post_json("/auth/sign-up", json!({ "name": name, "email": email, "password": password })).await;
+        assert_eq!(response.status(), StatusCode::OK, "sign-up failed");
+        self.sign_in(email, password).await
+    }
+
+    /// Signs in with a password and returns `results` (the tokens)
+    pub async fn sign_in(&self, email: &str, password: &str) -> Value {
+        let response = self.post_json("/auth/sign-in", json!({ "email": email, "password": password })).await;
+        assert_eq!(response.status(), StatusCode::OK, "sign-in failed");
+        results(response).await
+    }
+
+    /// Goes through `/auth/{provider}` and the mock consent page as a browser
+    /// would, picking the mock account `login`, and returns the callback response
+    pub async fn oauth_sign_in(&self, provider: &str, login: &str) -> Response {
+        self.oauth_sign_in_from(&format!("/auth/{}", provider), provider, login).await
+    }
+
+    /// Like `oauth_sign_in`, starting at `start` (e.g. with `?redirect_to=`)
+    pub async fn oauth_sign_in_from(&self, start: &str, provider: &str, login: &str) -> Response {
+        let response = self.client.get(self.url(start)).send().await.expect("start sign-in");
+        assert!(response.status().is_redirection(), "sign-in did not redirect: {}", response.status());
+        let nonce_cookie = cookie_pair(&response, "oauth_nonce").expect("nonce cookie");
+        let consent_url = header(&response, LOCATION);
+
+        let page = self.client.get(consent_url).send().await.expect("consent page").text().await.expect("consent page body");
+        let code = format!("mock-code-{}", login);
+
+        if page.contains("method=\"post\"") {
+            let form = page
+                .split("<form")
+                .find(|form| form.contains(&code))
+                .unwrap_or_else(|| panic!("no {} account {} on the consent page", provider, login));
+            let action = unescape_html(attribute(form, "action"));
+            let fields: Vec<(String, String)> = form
+                .split("<input")
+                .skip(1)
+                .map(|input| (unescape_html(attribute(input, "name")), unescape_html(attribute(input, "value"))))
+                .collect();
+            return self.client
+                .post(action)
+                .header(COOKIE, nonce_cookie)
+                .form(&fields)
+                .send()
+                .await
+                .expect("callback");
+        }
+
+        let href = page
+            .split("href=\"")
+            .skip(1)
+            .map(|rest| unescape_html(&rest[..rest.find('"').expect("closing quote")]))
+            .find(|href| {
+                reqwest::Url::parse(href).is_ok_and(|url| {
+                    url.query_pairs()
+                        .any(|(name, value)| name == "code" && (value == code || value.starts_with(&format!("{}.", code))))
+                })
+            })
+            .unwrap_or_else(|| panic!("no {} account {} on the consent page", provider, login));
+        self.client.get(href).header(COOKIE, nonce_cookie).send().await.expect("callback")
+    }
+
+    pub async fn user_id(&self, email: &str) -> Uuid {
+        sqlx::query_scalar("SELECT id FROM users WHERE email = $1").bind(email).fetch_one(&self.pool).await.expect("user row")
+    }
+
+    /// Gives the user `role`; tokens issued before keep the old one
+    pub async fn set_role(&self, email: &str, role: &str) {
+        sqlx::query("UPDATE users SET role = $1 WHERE email = $2")
+            .bind(role)
+            .bind(email)
+            .execute(&self.pool)
+            .await
+            .expect("set role");
+    }
+
+    /// The one-time code in the last text sent to `phone`
+    pub async fn last_sms_code(&self, phone: &str) -> String {
+        let log = tokio::fs::read_to_string(&self.sms_log).await.expect("SMS log");
+        let body = log
+            .lines()
+            .rev()
+            .map(|line| serde_json::from_str::<Value>(line).expect("SMS log line"))
+            .find(|sms| sms["to"] == phone)
+            .unwrap_or_else(|| panic!("no SMS sent to {}", phone))["body"]
+            .as_str()
+            .expect("SMS body")
+            .to_string();
+        body.split_whitespace()
+            .map(|word| word.trim_end_matches('.'))
+            .find(|word| word.len() == 6 && word.chars().all(|c| c.is_ascii_digit()))
+            .expect("code in SMS")
+            .to_string()
+    }
+
+    pub async fn user_count(&self) -> i64 {
+        sqlx::query_scalar("SELECT count(*) FROM users").fetch_one(&self.pool).await.expect("count users")
+    }
+}
+
+/// `results` of a successful JSON response
+pub async fn results(response: Response) -> Value {
+    let status = response.status();
+    let body: Value = response.json().await.expect("JSON body");
+    assert!(status.is_success(), "unexpected {}: {}", status, body);
+    body["results"].clone()
+}
+
+/// `name=value` of a cookie the response sets
+pub fn cookie_pair(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .find(|pair| pair.starts_with(&format!("{}=", name)))
+        .map(str::to_string)
+}
+
+pub fn header(response: &Response, name: reqwest::header::HeaderName) -> String {
+    response
+        .headers()
+        .get(&name)
+        .unwrap_or_else(|| panic!("missing {} header", name))
+        .to_str()
+        .expect("ASCII header")
+        .to_string()
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> &'a str {
+    let start = tag.find(&format!("{}=\"", name)).map(|i| i + name.len() + 2).unwrap_or(tag.len());
+    let end = tag[start..].find('"').map(|i| start + i).unwrap_or(start);
+    &tag[start..end]
+}
+
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,41 @@
+// Bulk user import
+
+mod support;
+
+use reqwest::{header, StatusCode};
+use serde_json::json;
+use sqlx::PgPool;
+use support::{results, TestApp};
+
+#[sqlx::test]
+async fn imported_users_sign_in_with_the_password_on_their_own_row(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    app.register("Admin", "admin@example.com", "correct-horse-battery").await;
+    sqlx::query("UPDATE users SET role = 'Admin' WHERE email = 'admin@example.com'").execute(&app.pool).await.expect("promote");
+    let admin = app.sign_in("admin@example.com", "correct-horse-battery").await;
+
+    let body = [
+        json!({ "name": "First", "email": "first@example.com", "role": "User", "password": "first-password" }),
+        json!({ "name": "Broken", "email": "not-an-email", "role": "User", "password": "broken-password" }),
+        json!({ "name": "Third", "email": "third@example.com", "role": "Mentor", "password": "third-password" }),
+    ]
+    .map(|row| row.to_string())
+    .join("\n");
+    let response = app.client
+        .post(app.url("/users/import?mode=best_effort"))
+        .bearer_auth(admin["access_token"].as_str().expect("access token"))
+        .header(header::CONTENT_TYPE, "application/x-ndjson")
+        .body(body)
+        .send()
+        .await
+        .expect("POST /users/import");
+    assert_eq!(response.status(), StatusCode::OK);
+    let report = results(response).await;
+    assert_eq!((report["created"].as_u64(), report["failed"].as_u64()), (Some(2), Some(1)), "{:#}", report);
+
+    // The invalid row in between must not shift the hashes onto the wrong users
+    app.sign_in("first@example.com", "first-password").await;
+    app.sign_in("third@example.com", "third-password").await;
+    let response = app.post_json("/auth/sign-in", json!({ "email": "third@example.com", "password": "first-password" })).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
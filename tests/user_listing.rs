@@ -0,0 +1,113 @@
+// The admin user list and its CSV / NDJSON export: who may read them and
+// what the export contains
+
+mod support;
+
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+use sqlx::PgPool;
+use support::{header, results, TestApp};
+
+const PASSWORD: &str = "correct-horse-battery";
+
+fn token(tokens: &Value) -> &str {
+    tokens["access_token"].as_str().expect("access token")
+}
+
+async fn admin(app: &TestApp) -> Value {
+    app.register("Admin", "admin@example.com", PASSWORD).await;
+    app.set_role("admin@example.com", "Admin").await;
+    app.sign_in("admin@example.com", PASSWORD).await
+}
+
+async fn export(app: &TestApp, tokens: &Value, query: &str) -> Response {
+    let response = app.client
+        .get(app.url(&format!("/users/export?{}", query)))
+        .bearer_auth(token(tokens))
+        .send()
+        .await
+        .expect("GET /users/export");
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+}
+
+#[sqlx::test]
+async fn only_admins_list_users(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    for role in ["Admin", "SuperAdmin"] {
+        let email = format!("{}@example.com", role.to_lowercase());
+        app.register(role, &email, PASSWORD).await;
+        app.set_role(&email, role).await;
+        let tokens = app.sign_in(&email, PASSWORD).await;
+        let response = app.client.get(app.url("/users")).bearer_auth(token(&tokens)).send().await.expect("GET /users");
+        assert!(results(response).await.is_array());
+    }
+
+    for role in ["Mentor", "User"] {
+        let email = format!("{}@example.com", role.to_lowercase());
+        app.register(role, &email, PASSWORD).await;
+        app.set_role(&email, role).await;
+        let tokens = app.sign_in(&email, PASSWORD).await;
+        // The token is valid, so this is 403 rather than 401
+        for path in ["/users", "/users?cursor=anything"] {
+            let response = app.client.get(app.url(path)).bearer_auth(token(&tokens)).send().await.expect("GET /users");
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} listed {}", role, path);
+        }
+    }
+}
+
+#[sqlx::test]
+async fn the_csv_export_quotes_formulas(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = admin(&app).await;
+    app.register("=HYPERLINK(\"http://evil.test\")", "mallory@example.com", PASSWORD).await;
+
+    let response = export(&app, &tokens, "format=csv").await;
+    assert_eq!(header(&response, CONTENT_TYPE), "text/csv; charset=utf-8");
+    assert!(header(&response, CONTENT_DISPOSITION).ends_with(".csv\""));
+    let body = response.text().await.expect("CSV body");
+
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let columns: Vec<String> = reader.headers().expect("header row").iter().map(str::to_string).collect();
+    assert_eq!(columns[..4], ["id", "name", "email", "phone"]);
+    let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().expect("CSV rows");
+    assert_eq!(rows.len(), 2);
+    let row = |email: &str| rows.iter().find(|row| &row[2] == email).expect("row for the user");
+    assert_eq!(&row("mallory@example.com")[1], "'=HYPERLINK(\"http://evil.test\")");
+    assert_eq!(&row("admin@example.com")[1], "Admin");
+    assert_eq!(&row("admin@example.com")[6], "password");
+}
+
+#[sqlx::test]
+async fn the_ndjson_export_has_one_user_per_line(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = admin(&app).await;
+    app.register("=1+1", "mallory@example.com", PASSWORD).await;
+    app.register("Bob", "bob@example.com", PASSWORD).await;
+
+    let response = export(&app, &tokens, "format=ndjson&q=mallory").await;
+    assert_eq!(header(&response, CONTENT_TYPE), "application/x-ndjson");
+    let body = response.text().await.expect("NDJSON body");
+
+    let users: Vec<Value> = body.lines().map(|line| serde_json::from_str(line).expect("JSON line")).collect();
+    assert_eq!(users.len(), 1);
+    // Only CSV cells are quoted
+    assert_eq!(users[0]["name"], "=1+1");
+    assert_eq!(users[0]["email"], "mallory@example.com");
+}
+
+#[sqlx::test]
+async fn only_admins_export_users(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = app.register("User", "user@example.com", PASSWORD).await;
+
+    let response = app.client
+        .get(app.url("/users/export?format=csv"))
+        .bearer_auth(token(&tokens))
+        .send()
+        .await
+        .expect("GET /users/export");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,76 @@
+// The background purge of soft-deleted users
+
+mod support;
+
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_axum::domain::repositories::data_export_repository::DataExportRepository;
+use rust_axum::domain::repositories::user_repository::PurgeMode;
+use rust_axum::usecases::user_management::PurgeDeletedUsersUseCase;
+use support::TestApp;
+
+async fn rows_for(app: &TestApp, table: &str, user_id: Uuid) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE user_id = $1", table))
+        .bind(user_id)
+        .fetch_one(&app.pool)
+        .await
+        .expect("count rows")
+}
+
+#[sqlx::test]
+async fn anonymizing_a_purged_user_removes_email_history_and_export_archives(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let tokens = app.register("Leaver", "leaver@example.com", "correct-horse-battery").await;
+    let access_token = tokens["access_token"].as_str().expect("access token");
+    let user_id: Uuid = sqlx::query_scalar("SELECT id FROM users WHERE email = 'leaver@example.com'")
+        .fetch_one(&app.pool)
+        .await
+        .expect("user row");
+
+    let response = app.client
+        .post(app.url("/me/email"))
+        .bearer_auth(access_token)
+        .json(&json!({ "new_email": "leaver@example.org", "current_password": "correct-horse-battery" }))
+        .send()
+        .await
+        .expect("POST /me/email");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let archive = std::env::temp_dir().join(format!("rust-axum-test-export-{}.json", Uuid::new_v4()));
+    std::fs::write(&archive, "{}").expect("write archive");
+    let export = app.state.data_export_repository.create(user_id).await.expect("create export");
+    app.state.data_export_repository
+        .mark_ready(export.id, &archive.to_string_lossy(), chrono::Utc::now() + chrono::Duration::days(7))
+        .await
+        .expect("mark export ready");
+
+    let response = app.client
+        .delete(app.url("/me"))
+        .bearer_auth(access_token)
+        .json(&json!({ "password": "correct-horse-battery" }))
+        .send()
+        .await
+        .expect("DELETE /me");
+    assert_eq!(response.status(), StatusCode::OK);
+    sqlx::query("UPDATE users SET deleted_at = NOW() - INTERVAL '31 days' WHERE id = $1")
+        .bind(user_id)
+        .execute(&app.pool)
+        .await
+        .expect("age the deletion");
+
+    let usecase = PurgeDeletedUsersUseCase::new(
+        app.state.user_repository.clone(),
+        app.state.data_export_repository.clone(),
+        app.state.email_change_repository.clone(),
+        app.state.audit_repository.clone(),
+        app.state.blob_store.clone(),
+    );
+    assert_eq!(usecase.execute(30, PurgeMode::Anonymize).await.expect("purge"), 1);
+
+    assert_eq!(rows_for(&app, "email_changes", user_id).await, 0);
+    assert_eq!(rows_for(&app, "data_exports", user_id).await, 0);
+    assert_eq!(rows_for(&app, "sessions", user_id).await, 0);
+    assert!(!archive.exists(), "export archive left on disk");
+}
@@ -0,0 +1,21 @@
+// Ranked user search against the real users table
+
+use sqlx::PgPool;
+use rust_axum::domain::repositories::user_repository::UserRepository;
+use rust_axum::infrastructure::repositories::postgres_user_repository::PostgresUserRepository;
+
+#[sqlx::test]
+async fn highlights_escape_user_data_and_only_mark_matches(pool: PgPool) {
+    sqlx::query("INSERT INTO users (name, email, password_hash, role) VALUES ($1, 'bob@example.com', 'x', 'User')")
+        .bind("<img src=x onerror=alert(1)> Bob \u{1}sneaky\u{2}")
+        .execute(&pool)
+        .await
+        .expect("insert user");
+    let repository = PostgresUserRepository::new(pool);
+
+    let hits = repository.search("bob", 10).await.expect("search");
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].name_highlight, "&lt;img src=x onerror=alert(1)&gt; <mark>Bob</mark> sneaky");
+    assert_eq!(hits[0].email_highlight, "<mark>bob@example.com</mark>");
+}